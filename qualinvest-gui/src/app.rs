@@ -1,7 +1,11 @@
 use crate::account_view::AccountsTable;
 use crate::auth::User;
+use crate::import_view::ImportPage;
+use crate::integrity::IntegrityCheck;
+use crate::jobs::{JobsTable, ScheduledRuns};
 use crate::position_view::PositionTable;
 use crate::quote_graph::QuotesGraph;
+use crate::replay_view::ReplayPanel;
 use crate::settings_view::UsersTable;
 use crate::simulation_view::SimulationPage;
 use crate::transaction_view::TransactionsTable;
@@ -66,6 +70,7 @@ pub fn App() -> impl IntoView {
                     <Route path=StaticSegment("assets") view=|| { view!{ <ProtectedRoute><Assets/></ProtectedRoute> } }/>
                     <Route path=StaticSegment("settings") view=|| { view!{ <AdminRoute><Settings/></AdminRoute> } }/>
                     <Route path=StaticSegment("accounts") view=|| { view!{ <ProtectedRoute><Accounts/></ProtectedRoute> } }/>
+                    <Route path=StaticSegment("import") view=|| { view!{ <ProtectedRoute><Import/></ProtectedRoute> } }/>
                 </Routes>
             </main>
         </Router>
@@ -341,6 +346,8 @@ fn Simulation() -> impl IntoView {
         <div class="center">
             <h1>Simulation</h1>
             <SimulationPage />
+            <div class="divider"></div>
+            <ReplayPanel />
         </div>
     }
 }
@@ -465,6 +472,9 @@ fn Settings() -> impl IntoView {
         <div class="center">
             <h1>Settings</h1>
             <UsersTable />
+            <ScheduledRuns />
+            <JobsTable />
+            <IntegrityCheck />
         </div>
     }
 }
@@ -479,6 +489,16 @@ fn Accounts() -> impl IntoView {
     }
 }
 
+#[component]
+fn Import() -> impl IntoView {
+    view! {
+        <div class="center">
+            <h1>Import Transactions</h1>
+            <ImportPage />
+        </div>
+    }
+}
+
 #[component]
 fn Nav() -> impl IntoView {
     let nav_menu = RwSignal::new(false);
@@ -495,6 +515,7 @@ fn Nav() -> impl IntoView {
                     <li class={move || if nav_menu.get() { "show" } else { "" } }><A href="/simulation">Simulation</A></li>
                     <li class={move || if nav_menu.get() { "show" } else { "" } }><A href="/assets">Assets</A></li>
                     <li class={move || if nav_menu.get() { "show" } else { "" } }><A href="/accounts">Accounts</A></li>
+                    <li class={move || if nav_menu.get() { "show" } else { "" } }><A href="/import">Import</A></li>
                     <Suspense fallback=|| view! { <li></li> }>
                         {move || {
                             user.get().map(|user_data| {
@@ -20,8 +20,10 @@ cfg_if! {
         use finql::datatypes::AssetHandler;
 
         pub async fn get_assets_ssr(db: PostgresDB) -> Vec<AssetView> {
+            // Price index series are stored as assets, but can't be invested in
+            let cpi_assets = qualinvest_core::inflation::get_cpi_asset_ids(&db).await;
             if let Ok(assets) = db.get_asset_list().await {
-                assets.into_iter().map(|a| AssetView {
+                assets.into_iter().filter(|a| !cpi_assets.contains(&a.id)).map(|a| AssetView {
                     id: a.id,
                     name: a.name,
                     class: a.class,
@@ -25,5 +25,14 @@ cfg_if! {
                 })
         }
 
+        pub fn get_jobs() -> Result<crate::jobs::JobQueue, ServerFnError> {
+            debug!("Request job queue from context.");
+            use_context::<crate::jobs::JobQueue>()
+                .ok_or_else(|| {
+                    error!("Job queue is missing in context.");
+                    ServerFnError::ServerError("Job queue is missing.".into())
+                })
+        }
+
     }
 }
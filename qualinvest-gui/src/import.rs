@@ -0,0 +1,294 @@
+use cfg_if::cfg_if;
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Stored mapping profile, see `qualinvest_core::csv_import::MappingProfile`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportProfileView {
+    pub name: String,
+    /// Profile in pretty printed JSON format
+    pub json: String,
+}
+
+/// Statement parser for exports of brokers and banks, see
+/// `qualinvest_core::statement_parser::StatementParser`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatementParserView {
+    pub name: String,
+    pub description: String,
+}
+
+/// Parsed row of an import file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportRowView {
+    pub line: usize,
+    pub date: String,
+    pub kind: String,
+    pub asset: Option<String>,
+    /// True if the asset is not yet in the database
+    pub unknown_asset: bool,
+    /// True if the row has been imported into the account before
+    pub duplicate: bool,
+    pub position: Option<f64>,
+    pub amount: f64,
+    pub currency: String,
+    pub fee: f64,
+    pub tax: f64,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImportPreviewView {
+    pub rows: Vec<ImportRowView>,
+    pub skipped: usize,
+    pub errors: Vec<String>,
+}
+
+/// Transactions of an account suspected to be duplicates of each other
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateGroupView {
+    pub date: String,
+    pub transaction_type: String,
+    pub asset: Option<String>,
+    pub position: Option<f64>,
+    pub amount: f64,
+    pub currency: String,
+    pub transaction_ids: Vec<i32>,
+}
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use crate::auth::User;
+        use finql::postgres::PostgresDB;
+        use qualinvest_core::csv_import::{ImportPreview, MappingProfile};
+
+        async fn check_account_right(db: &PostgresDB, user: &User, account_id: i32) -> Result<(), ServerFnError> {
+            use qualinvest_core::user::UserHandler;
+
+            if user.is_admin {
+                return Ok(());
+            }
+            let valid = db
+                .valid_accounts(user.id, &[account_id])
+                .await
+                .map_err(|e| ServerFnError::new(format!("Failed to check account rights: {}", e)))?;
+            if valid.is_empty() {
+                return Err(ServerFnError::new("No access to this account"));
+            }
+            Ok(())
+        }
+
+        /// Parse the file with the statement parser of the given name or, if there is none, with
+        /// the stored mapping profile
+        async fn parse_import(profile: &str, csv: &str) -> Result<ImportPreview, ServerFnError> {
+            use qualinvest_core::csv_import::get_import_profile;
+            use qualinvest_core::statement_parser::{ProfileParser, StatementParserRegistry};
+
+            let mut registry = StatementParserRegistry::default();
+            if registry.get(profile).is_none() {
+                let db = crate::db::get_db()?;
+                let profile = get_import_profile(&db, profile)
+                    .await
+                    .map_err(|e| ServerFnError::new(e.to_string()))?;
+                registry.register(Box::new(ProfileParser(profile)));
+            }
+            registry
+                .get(profile)
+                .ok_or_else(|| ServerFnError::new("Unknown profile"))?
+                .parse(csv)
+                .map_err(|e| ServerFnError::new(format!("Failed to parse file: {}", e)))
+        }
+    }
+}
+
+#[server(GetStatementParsers, "/api")]
+pub async fn get_statement_parsers() -> Result<Vec<StatementParserView>, ServerFnError> {
+    use qualinvest_core::statement_parser::StatementParserRegistry;
+
+    Ok(StatementParserRegistry::default()
+        .parsers()
+        .iter()
+        .map(|parser| StatementParserView {
+            name: parser.name().to_string(),
+            description: parser.description().to_string(),
+        })
+        .collect())
+}
+
+#[server(GetImportProfiles, "/api")]
+pub async fn get_import_profiles() -> Result<Vec<ImportProfileView>, ServerFnError> {
+    use crate::auth::PostgresBackend;
+    use axum_login::AuthSession;
+
+    let auth: AuthSession<PostgresBackend> = expect_context();
+    let _user = auth
+        .user
+        .ok_or_else(|| ServerFnError::new("Unauthorized"))?;
+
+    let db = crate::db::get_db()?;
+    qualinvest_core::csv_import::get_import_profiles(&db)
+        .await
+        .into_iter()
+        .map(|profile| {
+            let json = serde_json::to_string_pretty(&profile)
+                .map_err(|e| ServerFnError::new(format!("Failed to serialize profile: {}", e)))?;
+            Ok(ImportProfileView {
+                name: profile.name,
+                json,
+            })
+        })
+        .collect()
+}
+
+/// Store a mapping profile given in JSON format, replacing any profile of the same name
+#[server(SaveImportProfile, "/api")]
+pub async fn save_import_profile(json: String) -> Result<(), ServerFnError> {
+    use crate::auth::PostgresBackend;
+    use axum_login::AuthSession;
+
+    let auth: AuthSession<PostgresBackend> = expect_context();
+    let user = auth
+        .user
+        .ok_or_else(|| ServerFnError::new("Unauthorized"))?;
+
+    // Profiles are shared by all users
+    if !user.is_admin {
+        return Err(ServerFnError::new("Admin access required"));
+    }
+
+    let profile: MappingProfile = serde_json::from_str(&json)
+        .map_err(|e| ServerFnError::new(format!("Invalid mapping profile: {}", e)))?;
+    let db = crate::db::get_db()?;
+    qualinvest_core::csv_import::save_import_profile(&db, &profile)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Failed to save mapping profile: {}", e)))
+}
+
+/// Parse the CSV file with the given profile without writing anything to the database.
+/// If an account is given, rows imported into it before are flagged as duplicates.
+#[server(PreviewImport, "/api")]
+pub async fn preview_import(
+    profile: String,
+    csv: String,
+    account_id: Option<i32>,
+) -> Result<ImportPreviewView, ServerFnError> {
+    use crate::auth::PostgresBackend;
+    use axum_login::AuthSession;
+    use qualinvest_core::csv_import::{find_duplicate_rows, resolve_asset};
+
+    let auth: AuthSession<PostgresBackend> = expect_context();
+    let user = auth
+        .user
+        .ok_or_else(|| ServerFnError::new("Unauthorized"))?;
+
+    let preview = parse_import(&profile, &csv).await?;
+    let db = crate::db::get_db()?;
+    let duplicates = match account_id {
+        Some(account_id) => {
+            check_account_right(&db, &user, account_id).await?;
+            find_duplicate_rows(&db, account_id, &profile, &preview.rows)
+                .await
+                .map_err(|e| ServerFnError::new(format!("Failed to check for duplicates: {}", e)))?
+        }
+        None => vec![false; preview.rows.len()],
+    };
+    let mut rows = Vec::with_capacity(preview.rows.len());
+    for (row, duplicate) in preview.rows.iter().zip(duplicates) {
+        let unknown_asset = row.kind.needs_asset() && resolve_asset(&db, row).await.is_none();
+        rows.push(ImportRowView {
+            line: row.line,
+            date: row.date.to_string(),
+            kind: row.kind.to_string(),
+            asset: row.asset_description(),
+            unknown_asset,
+            duplicate,
+            position: row.position,
+            amount: row.amount,
+            currency: row.currency.clone(),
+            fee: row.fee,
+            tax: row.tax,
+            note: row.note.clone(),
+        });
+    }
+    Ok(ImportPreviewView {
+        rows,
+        skipped: preview.skipped,
+        errors: preview.errors.iter().map(|e| e.to_string()).collect(),
+    })
+}
+
+/// Import all valid rows of the CSV file into the account, returns the number of stored
+/// transactions including related fees and taxes
+#[server(RunImport, "/api")]
+pub async fn run_import(
+    profile: String,
+    account_id: i32,
+    csv: String,
+    create_assets: bool,
+    skip_duplicates: bool,
+) -> Result<usize, ServerFnError> {
+    use crate::auth::PostgresBackend;
+    use axum_login::AuthSession;
+    use qualinvest_core::csv_import::{import_rows, ImportOptions};
+
+    let auth: AuthSession<PostgresBackend> = expect_context();
+    let user = auth
+        .user
+        .ok_or_else(|| ServerFnError::new("Unauthorized"))?;
+
+    let db = crate::db::get_db()?;
+    check_account_right(&db, &user, account_id).await?;
+
+    let preview = parse_import(&profile, &csv).await?;
+    let market = crate::db::get_market()?;
+    let options = ImportOptions {
+        source: profile,
+        create_missing_assets: create_assets,
+        skip_duplicates,
+    };
+    let report = import_rows(&db, &market, &preview.rows, account_id, &options)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Failed to import transactions: {}", e)))?;
+    Ok(report.transaction_ids.len())
+}
+
+/// List transactions of the account which agree in date, type, asset, position and amount
+#[server(GetDuplicateTransactions, "/api")]
+pub async fn get_duplicate_transactions(
+    account_id: i32,
+) -> Result<Vec<DuplicateGroupView>, ServerFnError> {
+    use crate::auth::PostgresBackend;
+    use axum_login::AuthSession;
+    use finql::datatypes::AssetHandler;
+    use qualinvest_core::duplicates::FingerprintHandler;
+
+    let auth: AuthSession<PostgresBackend> = expect_context();
+    let user = auth
+        .user
+        .ok_or_else(|| ServerFnError::new("Unauthorized"))?;
+
+    let db = crate::db::get_db()?;
+    check_account_right(&db, &user, account_id).await?;
+    let groups = db
+        .find_duplicate_transactions(&[account_id])
+        .await
+        .map_err(|e| ServerFnError::new(format!("Failed to find duplicates: {}", e)))?;
+    let assets = db
+        .get_asset_list()
+        .await
+        .map_err(|e| ServerFnError::new(format!("Failed to get assets: {}", e)))?;
+    Ok(groups
+        .into_iter()
+        .map(|group| DuplicateGroupView {
+            date: group.date.to_string(),
+            transaction_type: group.transaction_type,
+            asset: group
+                .asset_id
+                .and_then(|id| assets.iter().find(|a| a.id == id).map(|a| a.name.clone())),
+            position: group.position,
+            amount: group.amount,
+            currency: group.currency,
+            transaction_ids: group.transaction_ids,
+        })
+        .collect())
+}
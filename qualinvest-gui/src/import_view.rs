@@ -0,0 +1,450 @@
+use crate::account::get_accounts;
+use crate::auth::User;
+use crate::import::{
+    get_duplicate_transactions, get_import_profiles, get_statement_parsers, preview_import,
+    run_import, save_import_profile, ImportPreviewView,
+};
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+use leptos::wasm_bindgen::JsCast;
+
+/// Upload of CSV files with preview of the parsed transactions before they are imported
+#[component]
+pub fn ImportPage() -> impl IntoView {
+    let user = expect_context::<Resource<Option<User>>>();
+    let (profiles_version, set_profiles_version) = signal(0);
+    let profiles = Resource::new(move || profiles_version.get(), |_| get_import_profiles());
+    let parsers = Resource::new(|| (), |_| get_statement_parsers());
+    let accounts = Resource::new(|| (), |_| get_accounts());
+
+    let (profile, set_profile) = signal(String::new());
+    let (account_id, set_account_id) = signal::<Option<i32>>(None);
+    let (csv, set_csv) = signal(String::new());
+    let (create_assets, set_create_assets) = signal(false);
+    let (import_duplicates, set_import_duplicates) = signal(false);
+    let (preview, set_preview) = signal::<Option<ImportPreviewView>>(None);
+    let (message, set_message) = signal::<Option<Result<String, String>>>(None);
+
+    let load_file = move |ev: leptos::ev::Event| {
+        let file = ev
+            .target()
+            .and_then(|t| t.dyn_into::<web_sys::HtmlInputElement>().ok())
+            .and_then(|input| input.files())
+            .and_then(|files| files.get(0));
+        if let Some(file) = file {
+            spawn_local(async move {
+                match wasm_bindgen_futures::JsFuture::from(file.text()).await {
+                    Ok(text) => {
+                        set_csv.set(text.as_string().unwrap_or_default());
+                        set_preview.set(None);
+                    }
+                    Err(_) => set_message.set(Some(Err("Failed to read file".to_string()))),
+                }
+            });
+        }
+    };
+
+    let show_preview = move |_| {
+        set_message.set(None);
+        let (profile, csv) = (profile.get_untracked(), csv.get_untracked());
+        if profile.is_empty() || csv.is_empty() {
+            set_message.set(Some(Err("Select a profile and a file first".to_string())));
+            return;
+        }
+        let account_id = account_id.get_untracked();
+        spawn_local(async move {
+            match preview_import(profile, csv, account_id).await {
+                Ok(result) => set_preview.set(Some(result)),
+                Err(e) => {
+                    set_preview.set(None);
+                    set_message.set(Some(Err(e.to_string())));
+                }
+            }
+        });
+    };
+
+    let import = move |_| {
+        let Some(account_id) = account_id.get_untracked() else {
+            set_message.set(Some(Err("Select an account first".to_string())));
+            return;
+        };
+        let (profile, csv) = (profile.get_untracked(), csv.get_untracked());
+        let create_assets = create_assets.get_untracked();
+        let skip_duplicates = !import_duplicates.get_untracked();
+        spawn_local(async move {
+            match run_import(profile, account_id, csv, create_assets, skip_duplicates).await {
+                Ok(count) => {
+                    set_preview.set(None);
+                    set_message.set(Some(Ok(format!("Imported {} transactions", count))));
+                }
+                Err(e) => set_message.set(Some(Err(e.to_string()))),
+            }
+        });
+    };
+
+    view! {
+        <fieldset class="strategy-fieldset">
+            <legend>"Broker or Bank Export"</legend>
+            <div class="strategy-row">
+                <div class="form-group">
+                    <label>"Format or Mapping Profile"</label>
+                    <Suspense fallback=|| view! { <p>"Loading profiles..."</p> }>
+                        <select
+                            prop:value=move || profile.get()
+                            on:change=move |ev| {
+                                set_profile.set(event_target_value(&ev));
+                                set_preview.set(None);
+                            }
+                        >
+                            <option value="">"-- select --"</option>
+                            {move || {
+                                parsers
+                                    .get()
+                                    .and_then(|r| r.ok())
+                                    .unwrap_or_default()
+                                    .into_iter()
+                                    .map(|p| view! { <option value=p.name.clone()>{p.description}</option> })
+                                    .collect_view()
+                            }}
+                            {move || {
+                                profiles
+                                    .get()
+                                    .and_then(|r| r.ok())
+                                    .unwrap_or_default()
+                                    .into_iter()
+                                    .map(|p| view! { <option value=p.name.clone()>{p.name}</option> })
+                                    .collect_view()
+                            }}
+                        </select>
+                    </Suspense>
+                </div>
+                <div class="form-group">
+                    <label>"Account"</label>
+                    <Suspense fallback=|| view! { <p>"Loading accounts..."</p> }>
+                        <select on:change=move |ev| {
+                            set_account_id.set(event_target_value(&ev).parse::<i32>().ok());
+                            set_preview.set(None);
+                        }>
+                            <option value="">"-- select --"</option>
+                            {move || {
+                                accounts
+                                    .get()
+                                    .and_then(|r| r.ok())
+                                    .unwrap_or_default()
+                                    .into_iter()
+                                    .map(|a| {
+                                        view! { <option value=a.id.to_string()>{a.display_name()}</option> }
+                                    })
+                                    .collect_view()
+                            }}
+                        </select>
+                    </Suspense>
+                </div>
+                <div class="form-group">
+                    <label>"File"</label>
+                    <input type="file" accept=".csv,.txt,.xml,.sta,.mt940" on:change=load_file />
+                </div>
+            </div>
+            <div class="form-group">
+                <label>"Content"</label>
+                <textarea
+                    rows=8
+                    prop:value=move || csv.get()
+                    on:input=move |ev| {
+                        set_csv.set(event_target_value(&ev));
+                        set_preview.set(None);
+                    }
+                ></textarea>
+            </div>
+            <button class="button" on:click=show_preview>
+                "Preview"
+            </button>
+        </fieldset>
+        {move || {
+            message.get().map(|message| match message {
+                Ok(msg) => view! { <p>{msg}</p> }.into_any(),
+                Err(e) => view! { <p class="error">{e}</p> }.into_any(),
+            })
+        }}
+        {move || {
+            preview.get().map(|preview| {
+                let unknown_assets = preview.rows.iter().any(|r| r.unknown_asset);
+                let duplicates = preview.rows.iter().filter(|r| r.duplicate).count();
+                view! {
+                    <ImportPreviewTable preview=preview.clone() />
+                    {unknown_assets.then(|| view! {
+                        <div class="form-group form-group--inline">
+                            <label>
+                                <input
+                                    type="checkbox"
+                                    prop:checked=move || create_assets.get()
+                                    on:change=move |ev| {
+                                        let checked = ev
+                                            .target()
+                                            .and_then(|t| t.dyn_into::<web_sys::HtmlInputElement>().ok())
+                                            .map(|el| el.checked())
+                                            .unwrap_or(false);
+                                        set_create_assets.set(checked);
+                                    }
+                                />
+                                " Create unknown assets"
+                            </label>
+                        </div>
+                    })}
+                    {(duplicates > 0).then(|| view! {
+                        <div class="form-group form-group--inline">
+                            <label>
+                                <input
+                                    type="checkbox"
+                                    prop:checked=move || import_duplicates.get()
+                                    on:change=move |ev| {
+                                        let checked = ev
+                                            .target()
+                                            .and_then(|t| t.dyn_into::<web_sys::HtmlInputElement>().ok())
+                                            .map(|el| el.checked())
+                                            .unwrap_or(false);
+                                        set_import_duplicates.set(checked);
+                                    }
+                                />
+                                {format!(" Import {} rows already imported before", duplicates)}
+                            </label>
+                        </div>
+                    })}
+                    {(!preview.rows.is_empty()).then(|| view! {
+                        <button class="button" on:click=import>
+                            {format!("Import {} rows", preview.rows.len())}
+                        </button>
+                    })}
+                }
+            })
+        }}
+        {move || account_id.get().map(|account_id| view! { <DuplicateReport account_id=account_id /> })}
+        <Suspense fallback=|| ()>
+            {move || {
+                user.get().flatten().filter(|u| u.is_admin).map(|_| {
+                    view! {
+                        <ProfileEditor
+                            profiles=profiles
+                            on_saved=move || set_profiles_version.update(|v| *v += 1)
+                        />
+                    }
+                })
+            }}
+        </Suspense>
+    }
+}
+
+#[component]
+fn ImportPreviewTable(preview: ImportPreviewView) -> impl IntoView {
+    let errors = preview.errors.clone();
+    view! {
+        <h3>
+            {format!(
+                "{} rows to import, {} skipped, {} errors",
+                preview.rows.len(),
+                preview.skipped,
+                preview.errors.len(),
+            )}
+        </h3>
+        {(!errors.is_empty()).then(|| view! {
+            <ul class="error">
+                {errors.into_iter().map(|e| view! { <li>{e}</li> }).collect_view()}
+            </ul>
+        })}
+        <table>
+            <thead>
+                <tr>
+                    <th>"Line"</th>
+                    <th>"Date"</th>
+                    <th>"Type"</th>
+                    <th>"Asset"</th>
+                    <th>"Position"</th>
+                    <th>"Amount"</th>
+                    <th>"Currency"</th>
+                    <th>"Fee"</th>
+                    <th>"Tax"</th>
+                    <th>"Note"</th>
+                    <th>"Status"</th>
+                </tr>
+            </thead>
+            <tbody>
+                {preview
+                    .rows
+                    .into_iter()
+                    .map(|row| {
+                        let asset = match (row.asset, row.unknown_asset) {
+                            (Some(asset), true) => format!("{} (new)", asset),
+                            (asset, _) => asset.unwrap_or_default(),
+                        };
+                        view! {
+                            <tr>
+                                <td>{row.line}</td>
+                                <td>{row.date}</td>
+                                <td>{row.kind}</td>
+                                <td>{asset}</td>
+                                <td>{row.position.map(|p| p.to_string()).unwrap_or_default()}</td>
+                                <td>{format!("{:.2}", row.amount)}</td>
+                                <td>{row.currency}</td>
+                                <td>{format!("{:.2}", row.fee)}</td>
+                                <td>{format!("{:.2}", row.tax)}</td>
+                                <td>{row.note.unwrap_or_default()}</td>
+                                <td>{if row.duplicate { "already imported" } else { "" }}</td>
+                            </tr>
+                        }
+                    })
+                    .collect_view()}
+            </tbody>
+        </table>
+    }
+}
+
+/// Suspected duplicates among the transactions already stored for the account
+#[component]
+fn DuplicateReport(account_id: i32) -> impl IntoView {
+    let (show, set_show) = signal(false);
+    let groups = Resource::new(
+        move || show.get(),
+        move |show| async move {
+            if show {
+                get_duplicate_transactions(account_id).await.map(Some)
+            } else {
+                Ok(None)
+            }
+        },
+    );
+
+    view! {
+        <fieldset class="strategy-fieldset">
+            <legend>"Suspected Duplicates"</legend>
+            <button class="button" on:click=move |_| set_show.set(true)>
+                "Check Account"
+            </button>
+            <Suspense fallback=|| view! { <p>"Searching duplicates..."</p> }>
+                {move || {
+                    groups.get().map(|result| match result {
+                        Ok(None) => ().into_any(),
+                        Ok(Some(groups)) if groups.is_empty() => {
+                            view! { <p>"No suspected duplicates found"</p> }.into_any()
+                        }
+                        Ok(Some(groups)) => view! {
+                            <table>
+                                <thead>
+                                    <tr>
+                                        <th>"Date"</th>
+                                        <th>"Type"</th>
+                                        <th>"Asset"</th>
+                                        <th>"Position"</th>
+                                        <th>"Amount"</th>
+                                        <th>"Currency"</th>
+                                        <th>"Transaction Ids"</th>
+                                    </tr>
+                                </thead>
+                                <tbody>
+                                    {groups
+                                        .into_iter()
+                                        .map(|group| {
+                                            let ids: Vec<String> = group
+                                                .transaction_ids
+                                                .iter()
+                                                .map(|id| id.to_string())
+                                                .collect();
+                                            view! {
+                                                <tr>
+                                                    <td>{group.date}</td>
+                                                    <td>{group.transaction_type}</td>
+                                                    <td>{group.asset.unwrap_or_default()}</td>
+                                                    <td>{group.position.map(|p| p.to_string()).unwrap_or_default()}</td>
+                                                    <td>{format!("{:.2}", group.amount)}</td>
+                                                    <td>{group.currency}</td>
+                                                    <td>{ids.join(", ")}</td>
+                                                </tr>
+                                            }
+                                        })
+                                        .collect_view()}
+                                </tbody>
+                            </table>
+                        }
+                        .into_any(),
+                        Err(e) => view! { <p class="error">{e.to_string()}</p> }.into_any(),
+                    })
+                }}
+            </Suspense>
+        </fieldset>
+    }
+}
+
+/// Edit mapping profiles as JSON, selecting an existing profile loads it as template
+#[component]
+fn ProfileEditor<F>(
+    profiles: Resource<Result<Vec<crate::import::ImportProfileView>, ServerFnError>>,
+    on_saved: F,
+) -> impl IntoView
+where
+    F: Fn() + 'static + Clone + Send + Sync,
+{
+    let (json, set_json) = signal(String::new());
+    let (message, set_message) = signal::<Option<Result<String, String>>>(None);
+
+    let load = move |ev: leptos::ev::Event| {
+        let name = event_target_value(&ev);
+        if let Some(profile) = profiles
+            .get_untracked()
+            .and_then(|r| r.ok())
+            .and_then(|profiles| profiles.into_iter().find(|p| p.name == name))
+        {
+            set_json.set(profile.json);
+        }
+    };
+
+    let save = move |_| {
+        let on_saved = on_saved.clone();
+        let json = json.get_untracked();
+        spawn_local(async move {
+            match save_import_profile(json).await {
+                Ok(()) => {
+                    set_message.set(Some(Ok("Profile saved".to_string())));
+                    on_saved();
+                }
+                Err(e) => set_message.set(Some(Err(e.to_string()))),
+            }
+        });
+    };
+
+    view! {
+        <fieldset class="strategy-fieldset">
+            <legend>"Mapping Profiles"</legend>
+            <div class="form-group">
+                <label>"Template"</label>
+                <select on:change=load>
+                    <option value="">"-- new profile --"</option>
+                    {move || {
+                        profiles
+                            .get()
+                            .and_then(|r| r.ok())
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|p| view! { <option value=p.name.clone()>{p.name}</option> })
+                            .collect_view()
+                    }}
+                </select>
+            </div>
+            <div class="form-group">
+                <label>"Profile (JSON)"</label>
+                <textarea
+                    rows=16
+                    prop:value=move || json.get()
+                    on:input=move |ev| set_json.set(event_target_value(&ev))
+                ></textarea>
+            </div>
+            <button class="button" on:click=save>
+                "Save Profile"
+            </button>
+            {move || {
+                message.get().map(|message| match message {
+                    Ok(msg) => view! { <p>{msg}</p> }.into_any(),
+                    Err(e) => view! { <p class="error">{e}</p> }.into_any(),
+                })
+            }}
+        </fieldset>
+    }
+}
@@ -0,0 +1,138 @@
+use cfg_if::cfg_if;
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Show values in real terms of the base year, deflated by the given price index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InflationParams {
+    pub cpi_ticker_id: i32,
+    pub base_year: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CpiSeriesView {
+    pub ticker_id: i32,
+    pub name: String,
+}
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use finql::postgres::PostgresDB;
+        use qualinvest_core::inflation::Deflator;
+
+        /// Load the deflator for the requested price index, if real values are requested at all
+        pub async fn get_deflator(
+            db: &PostgresDB,
+            inflation: Option<InflationParams>,
+        ) -> Result<Option<Deflator>, ServerFnError> {
+            match inflation {
+                None => Ok(None),
+                Some(params) => Deflator::from_ticker(db, params.cpi_ticker_id, params.base_year)
+                    .await
+                    .map(Some)
+                    .map_err(|e| ServerFnError::new(format!("Failed to load price index: {}", e))),
+            }
+        }
+    }
+}
+
+#[server(GetCpiSeries, "/api")]
+pub async fn get_cpi_series() -> Result<Vec<CpiSeriesView>, ServerFnError> {
+    use crate::auth::PostgresBackend;
+    use axum_login::AuthSession;
+
+    let auth: AuthSession<PostgresBackend> = expect_context();
+    let _user = auth
+        .user
+        .ok_or_else(|| ServerFnError::new("Unauthorized"))?;
+
+    let db = crate::db::get_db()?;
+    Ok(qualinvest_core::inflation::get_cpi_series(&db)
+        .await
+        .into_iter()
+        .map(|s| CpiSeriesView {
+            ticker_id: s.ticker_id,
+            name: s.name,
+        })
+        .collect())
+}
+
+/// Toggle between nominal values and real values in terms of a chosen base year
+#[component]
+pub fn InflationSelector(set_selected: WriteSignal<Option<InflationParams>>) -> impl IntoView {
+    let (enabled, set_enabled) = signal(false);
+    let (cpi_ticker_id, set_cpi_ticker_id) = signal::<Option<i32>>(None);
+    let (base_year, set_base_year) = signal(2015_i32);
+
+    let update_selection = move || {
+        let selection = match (enabled.get_untracked(), cpi_ticker_id.get_untracked()) {
+            (true, Some(cpi_ticker_id)) => Some(InflationParams {
+                cpi_ticker_id,
+                base_year: base_year.get_untracked(),
+            }),
+            _ => None,
+        };
+        set_selected.set(selection);
+    };
+
+    view! {
+        <div class="inflation-selector">
+            <div class="form-group form-group--inline">
+                <label>
+                    <input
+                        type="checkbox"
+                        prop:checked=move || enabled.get()
+                        on:change=move |ev| {
+                            set_enabled.set(event_target_checked(&ev));
+                            update_selection();
+                        }
+                    />
+                    " Real values (inflation adjusted)"
+                </label>
+            </div>
+            <Show when=move || enabled.get()>
+                <div class="form-group">
+                    <label>"Price Index"</label>
+                    <Suspense fallback=|| view! { <span>"Loading price indices…"</span> }>
+                        <Await future=get_cpi_series() let:series>
+                            {
+                                let series = series.clone().unwrap_or_default();
+                                view! {
+                                    <select
+                                        prop:value=move || {
+                                            cpi_ticker_id.get().map(|id| id.to_string()).unwrap_or_default()
+                                        }
+                                        on:change=move |ev| {
+                                            set_cpi_ticker_id.set(event_target_value(&ev).parse::<i32>().ok());
+                                            update_selection();
+                                        }
+                                    >
+                                        <option value="">"— select price index —"</option>
+                                        {series
+                                            .into_iter()
+                                            .map(|s| view! { <option value=s.ticker_id>{s.name}</option> })
+                                            .collect::<Vec<_>>()}
+                                    </select>
+                                }
+                            }
+                        </Await>
+                    </Suspense>
+                </div>
+                <div class="form-group">
+                    <label>"Base Year"</label>
+                    <input
+                        type="number"
+                        step="1"
+                        prop:value=move || base_year.get().to_string()
+                        on:change=move |ev| {
+                            if let Ok(year) = event_target_value(&ev).parse::<i32>() {
+                                set_base_year.set(year);
+                                update_selection();
+                            }
+                        }
+                    />
+                </div>
+            </Show>
+        </div>
+    }
+}
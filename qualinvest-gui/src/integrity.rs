@@ -0,0 +1,187 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Inconsistency found in the database
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IssueView {
+    pub kind: String,
+    pub message: String,
+    /// How to fix the issue
+    pub hint: String,
+    /// Description of the automatic fix, if any
+    pub fix: Option<String>,
+    /// Serialized automatic fix, passed back to `apply_integrity_fix`
+    pub fix_data: Option<String>,
+    /// The fix deletes data
+    pub deletes_data: bool,
+    /// The fix moves or deletes data and is therefore only applied individually
+    pub requires_confirmation: bool,
+}
+
+/// Scan the database for inconsistencies, admin only
+#[server(CheckIntegrity, "/api")]
+pub async fn check_integrity() -> Result<Vec<IssueView>, ServerFnError> {
+    use crate::auth::PostgresBackend;
+    use axum_login::AuthSession;
+
+    let auth: AuthSession<PostgresBackend> = expect_context();
+    let user = auth
+        .user
+        .ok_or_else(|| ServerFnError::new("Unauthorized"))?;
+    if !user.is_admin {
+        return Err(ServerFnError::new("Forbidden: Admin access required"));
+    }
+
+    let db = crate::db::get_db()?;
+    // todo: read base currency from db instead of using "EUR" hard coded
+    let issues = qualinvest_core::integrity::check_integrity(&db, "EUR")
+        .await
+        .map_err(|e| ServerFnError::new(format!("Failed to check database: {}", e)))?;
+    issues
+        .into_iter()
+        .map(|issue| {
+            let fix_data = issue
+                .fix
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()
+                .map_err(|e| ServerFnError::new(format!("Failed to serialize fix: {}", e)))?;
+            Ok(IssueView {
+                kind: issue.kind.to_string(),
+                message: issue.message,
+                hint: issue.hint,
+                deletes_data: issue.fix.as_ref().is_some_and(|fix| fix.deletes_data()),
+                requires_confirmation: issue
+                    .fix
+                    .as_ref()
+                    .is_some_and(|fix| fix.requires_confirmation()),
+                fix: issue.fix.map(|fix| fix.to_string()),
+                fix_data,
+            })
+        })
+        .collect()
+}
+
+/// Apply an automatic fix found by `check_integrity`, admin only
+#[server(ApplyIntegrityFix, "/api")]
+pub async fn apply_integrity_fix(fix_data: String) -> Result<(), ServerFnError> {
+    use crate::auth::PostgresBackend;
+    use axum_login::AuthSession;
+    use log::info;
+    use qualinvest_core::integrity::{apply_fix, Fix};
+
+    let auth: AuthSession<PostgresBackend> = expect_context();
+    let user = auth
+        .user
+        .ok_or_else(|| ServerFnError::new("Unauthorized"))?;
+    if !user.is_admin {
+        return Err(ServerFnError::new("Forbidden: Admin access required"));
+    }
+
+    let fix: Fix = serde_json::from_str(&fix_data)
+        .map_err(|e| ServerFnError::new(format!("Invalid fix: {}", e)))?;
+    let db = crate::db::get_db()?;
+    apply_fix(&db, &fix)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Failed to apply fix: {}", e)))?;
+    info!("User {} applied fix: {}", user.name, fix);
+    Ok(())
+}
+
+/// Runs the integrity check on demand and offers the automatic fixes of the issues found
+#[component]
+pub fn IntegrityCheck() -> impl IntoView {
+    // The check scans all transactions and quotes, therefore it is only run on request
+    let (run, set_run) = signal(0_u32);
+    let issues = Resource::new(
+        move || run.get(),
+        |run| async move {
+            if run == 0 {
+                Ok(None)
+            } else {
+                check_integrity().await.map(Some)
+            }
+        },
+    );
+
+    let apply = move |fixes: Vec<String>| {
+        leptos::task::spawn_local(async move {
+            for fix_data in fixes {
+                if let Err(e) = apply_integrity_fix(fix_data).await {
+                    log::error!("Failed to apply fix: {}", e);
+                }
+            }
+            set_run.update(|n| *n += 1);
+        });
+    };
+
+    view! {
+        <h2>"Database Integrity"</h2>
+        <div class="job-actions">
+            <button class="button" on:click=move |_| set_run.update(|n| *n += 1)>
+                "Run Check"
+            </button>
+        </div>
+        <Transition fallback=|| view! { <p>"Checking database..."</p> }>
+            {move || {
+                issues.get().map(|issues| match issues {
+                    Err(e) => view! { <p class="error">{e.to_string()}</p> }.into_any(),
+                    Ok(None) => ().into_any(),
+                    Ok(Some(issues)) if issues.is_empty() => {
+                        view! { <p>"No issues found."</p> }.into_any()
+                    }
+                    Ok(Some(issues)) => {
+                        let fixable = issues.iter().filter(|issue| issue.fix_data.is_some()).count();
+                        let all_fixes: Vec<String> = issues
+                            .iter()
+                            .filter(|issue| !issue.requires_confirmation)
+                            .filter_map(|issue| issue.fix_data.clone())
+                            .collect();
+                        view! {
+                            <p>{format!("{} issues found, {} can be fixed automatically", issues.len(), fixable)}</p>
+                            {(!all_fixes.is_empty()).then(|| view! {
+                                <button class="button" on:click=move |_| apply(all_fixes.clone())>
+                                    "Apply All Fixes"
+                                </button>
+                            })}
+                            <table class="table">
+                                <thead>
+                                    <tr>
+                                        <th class="cell">"Issue"</th>
+                                        <th class="cell">"Description"</th>
+                                        <th class="cell">"How to Fix"</th>
+                                        <th class="cell"></th>
+                                    </tr>
+                                </thead>
+                                <tbody>
+                                    {issues
+                                        .into_iter()
+                                        .map(|issue| view! {
+                                            <tr>
+                                                <td class="cell">{issue.kind}</td>
+                                                <td class="cell">{issue.message}</td>
+                                                <td class="cell">{issue.hint}</td>
+                                                <td class="cell">
+                                                    {issue.fix_data.map(|fix_data| view! {
+                                                        <button
+                                                            class="button"
+                                                            title=issue.fix.unwrap_or_default()
+                                                            on:click=move |_| apply(vec![fix_data.clone()])
+                                                        >
+                                                            {if issue.deletes_data { "Delete" } else { "Fix" }}
+                                                        </button>
+                                                    })}
+                                                </td>
+                                            </tr>
+                                        })
+                                        .collect::<Vec<_>>()}
+                                </tbody>
+                            </table>
+                        }
+                        .into_any()
+                    }
+                })
+            }}
+        </Transition>
+    }
+}
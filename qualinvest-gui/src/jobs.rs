@@ -0,0 +1,489 @@
+use cfg_if::cfg_if;
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// State of a background job as seen by the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JobState {
+    Queued,
+    Running,
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn is_done(&self) -> bool {
+        matches!(
+            self,
+            JobState::Finished | JobState::Failed | JobState::Cancelled
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobView {
+    pub id: i32,
+    pub kind: String,
+    pub state: JobState,
+    /// Fraction of work done, in [0, 1]
+    pub progress: f64,
+    pub message: Option<String>,
+    /// Result of a finished job, e.g. an SVG graph
+    pub result: Option<String>,
+    pub created: String,
+}
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use std::collections::HashMap;
+        use std::future::Future;
+        use std::sync::{Arc, Mutex};
+
+        use finql::postgres::PostgresDB;
+        use log::{error, warn};
+        use qualinvest_core::jobs::{Job, JobHandler, JobStatus};
+        use tokio::sync::Semaphore;
+        use tokio::task::AbortHandle;
+
+        impl From<Job> for JobView {
+            fn from(job: Job) -> Self {
+                let state = match job.status {
+                    JobStatus::Queued => JobState::Queued,
+                    JobStatus::Running => JobState::Running,
+                    JobStatus::Finished => JobState::Finished,
+                    JobStatus::Failed => JobState::Failed,
+                    JobStatus::Cancelled => JobState::Cancelled,
+                };
+                JobView {
+                    id: job.id,
+                    kind: job.kind,
+                    state,
+                    progress: job.progress,
+                    message: job.message,
+                    result: job.result,
+                    created: job.created.to_string(),
+                }
+            }
+        }
+
+        /// Handle passed to a running job to report its progress
+        #[derive(Clone)]
+        pub struct JobProgress {
+            job_id: i32,
+            db: Arc<PostgresDB>,
+        }
+
+        impl JobProgress {
+            /// Store the fraction of work done, in [0, 1], and a description of the current step
+            pub async fn report(&self, progress: f64, message: &str) {
+                if let Err(e) = self
+                    .db
+                    .set_job_progress(self.job_id, progress, Some(message))
+                    .await
+                {
+                    warn!("Failed to store progress of job {}: {}", self.job_id, e);
+                }
+            }
+        }
+
+        /// Runs jobs on a limited number of tokio workers and keeps track of them
+        /// in order to be able to cancel them
+        #[derive(Clone)]
+        pub struct JobQueue {
+            db: Arc<PostgresDB>,
+            workers: Arc<Semaphore>,
+            running: Arc<Mutex<HashMap<i32, AbortHandle>>>,
+        }
+
+        impl JobQueue {
+            pub fn new(db: PostgresDB, workers: usize) -> Self {
+                Self {
+                    db: Arc::new(db),
+                    workers: Arc::new(Semaphore::new(workers.max(1))),
+                    running: Arc::new(Mutex::new(HashMap::new())),
+                }
+            }
+
+            /// Queue a new job and return its id. The job is started as soon as a worker
+            /// is available; its result or error message is stored in the database.
+            pub async fn submit<F, Fut>(
+                &self,
+                kind: &str,
+                user_id: Option<i32>,
+                job: F,
+            ) -> Result<i32, ServerFnError>
+            where
+                F: FnOnce(JobProgress) -> Fut + Send + 'static,
+                Fut: Future<Output = Result<String, ServerFnError>> + Send + 'static,
+            {
+                let job_id = self
+                    .db
+                    .insert_job(kind, user_id)
+                    .await
+                    .map_err(|e| ServerFnError::new(format!("Failed to queue job: {}", e)))?;
+
+                let db = self.db.clone();
+                let workers = self.workers.clone();
+                let running = self.running.clone();
+                // Hold the lock until the handle is stored, such that a quickly finished
+                // job can't try to remove itself before it has been registered
+                let mut running_jobs = self.running.lock().unwrap();
+                let handle = tokio::spawn(async move {
+                    let _permit = workers.acquire_owned().await;
+                    if let Err(e) = db.set_job_running(job_id).await {
+                        error!("Failed to start job {}: {}", job_id, e);
+                    }
+                    let progress = JobProgress {
+                        job_id,
+                        db: db.clone(),
+                    };
+                    let outcome = job(progress).await;
+                    let stored = match outcome {
+                        Ok(result) => {
+                            db.finish_job(job_id, JobStatus::Finished, Some(&result), None)
+                                .await
+                        }
+                        Err(e) => {
+                            db.finish_job(job_id, JobStatus::Failed, None, Some(&e.to_string()))
+                                .await
+                        }
+                    };
+                    if let Err(e) = stored {
+                        error!("Failed to store result of job {}: {}", job_id, e);
+                    }
+                    running.lock().unwrap().remove(&job_id);
+                });
+                running_jobs.insert(job_id, handle.abort_handle());
+                Ok(job_id)
+            }
+
+            /// Abort a queued or running job; returns false if the job is not running anymore
+            pub async fn cancel(&self, job_id: i32) -> Result<bool, ServerFnError> {
+                let handle = self.running.lock().unwrap().remove(&job_id);
+                match handle {
+                    Some(handle) => {
+                        handle.abort();
+                        self.db
+                            .finish_job(job_id, JobStatus::Cancelled, None, Some("cancelled by user"))
+                            .await
+                            .map_err(|e| {
+                                ServerFnError::new(format!("Failed to cancel job: {}", e))
+                            })?;
+                        Ok(true)
+                    }
+                    None => Ok(false),
+                }
+            }
+        }
+
+        /// Load the job and make sure the current user may access it
+        async fn get_authorized_job(job_id: i32) -> Result<Job, ServerFnError> {
+            use crate::auth::PostgresBackend;
+            use axum_login::AuthSession;
+
+            let auth: AuthSession<PostgresBackend> = expect_context();
+            let user = auth
+                .user
+                .ok_or_else(|| ServerFnError::new("Unauthorized"))?;
+
+            let db = crate::db::get_db()?;
+            let job = db
+                .get_job(job_id)
+                .await
+                .map_err(|e| ServerFnError::new(format!("Failed to get job: {}", e)))?;
+            if !user.is_admin && job.user_id != Some(user.id) {
+                return Err(ServerFnError::new(format!(
+                    "Forbidden: Cannot access job {}",
+                    job_id
+                )));
+            }
+            Ok(job)
+        }
+    }
+}
+
+#[server(GetJob, "/api")]
+pub async fn get_job(job_id: i32) -> Result<JobView, ServerFnError> {
+    let job = get_authorized_job(job_id).await?;
+    Ok(job.into())
+}
+
+#[server(CancelJob, "/api")]
+pub async fn cancel_job(job_id: i32) -> Result<bool, ServerFnError> {
+    let _job = get_authorized_job(job_id).await?;
+    let jobs = crate::db::get_jobs()?;
+    jobs.cancel(job_id).await
+}
+
+/// Most recent jobs of all users, admin only
+#[server(GetRecentJobs, "/api")]
+pub async fn get_recent_jobs() -> Result<Vec<JobView>, ServerFnError> {
+    use crate::auth::PostgresBackend;
+    use axum_login::AuthSession;
+    use qualinvest_core::jobs::JobHandler;
+
+    let auth: AuthSession<PostgresBackend> = expect_context();
+    let user = auth
+        .user
+        .ok_or_else(|| ServerFnError::new("Unauthorized"))?;
+    if !user.is_admin {
+        return Err(ServerFnError::new("Forbidden: Admin access required"));
+    }
+
+    let db = crate::db::get_db()?;
+    let jobs = db
+        .get_jobs(None, 50)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Failed to get jobs: {}", e)))?;
+    Ok(jobs.into_iter().map(JobView::from).collect())
+}
+
+/// Last run of each task scheduled by `qualinvest daemon`, admin only
+#[server(GetScheduledRuns, "/api")]
+pub async fn get_scheduled_runs() -> Result<Vec<JobView>, ServerFnError> {
+    use crate::auth::PostgresBackend;
+    use axum_login::AuthSession;
+    use qualinvest_core::jobs::JobHandler;
+    use qualinvest_core::scheduler::SCHEDULED_JOB_PREFIX;
+
+    let auth: AuthSession<PostgresBackend> = expect_context();
+    let user = auth
+        .user
+        .ok_or_else(|| ServerFnError::new("Unauthorized"))?;
+    if !user.is_admin {
+        return Err(ServerFnError::new("Forbidden: Admin access required"));
+    }
+
+    let db = crate::db::get_db()?;
+    let jobs = db
+        .get_latest_jobs(SCHEDULED_JOB_PREFIX)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Failed to get scheduled runs: {}", e)))?;
+    Ok(jobs.into_iter().map(JobView::from).collect())
+}
+
+/// Fill gaps in the quote histories of all tickers as background job, admin only
+#[server(SubmitFillQuoteGaps, "/api")]
+pub async fn submit_fill_quote_gaps(min_size: usize) -> Result<i32, ServerFnError> {
+    use crate::auth::PostgresBackend;
+    use axum_login::AuthSession;
+
+    let auth: AuthSession<PostgresBackend> = expect_context();
+    let user = auth
+        .user
+        .ok_or_else(|| ServerFnError::new("Unauthorized"))?;
+    if !user.is_admin {
+        return Err(ServerFnError::new("Forbidden: Admin access required"));
+    }
+
+    let mut market = crate::db::get_market()?;
+    let jobs = crate::db::get_jobs()?;
+    jobs.submit(
+        "fill quote gaps",
+        Some(user.id),
+        move |progress| async move {
+            let handle = tokio::runtime::Handle::current();
+            qualinvest_core::fill_quote_gaps_with_progress(
+                &mut market,
+                min_size,
+                |count, total| {
+                    if total > 0 {
+                        let progress = progress.clone();
+                        let message = format!("{} of {} tickers checked", count, total);
+                        handle.spawn(async move {
+                            progress.report(count as f64 / total as f64, &message).await;
+                        });
+                    }
+                },
+            )
+            .await
+            .map_err(|e| ServerFnError::new(format!("Failed to fill quote gaps: {}", e)))?;
+            Ok("Gaps in quote histories filled".to_string())
+        },
+    )
+    .await
+}
+
+/// Shows the progress of a background job and polls its state until it is done;
+/// the result or error message of the job is then passed to `set_outcome`
+#[component]
+pub fn JobMonitor(
+    job_id: ReadSignal<Option<i32>>,
+    set_outcome: WriteSignal<Option<Result<String, String>>>,
+) -> impl IntoView {
+    let (poll, set_poll) = signal(0_u32);
+
+    let job = Resource::new(
+        move || (job_id.get(), poll.get()),
+        move |(job_id, _)| async move {
+            match job_id {
+                Some(job_id) => Some(get_job(job_id).await),
+                None => None,
+            }
+        },
+    );
+
+    // Effects only run in the browser, so polling is never started during server side rendering
+    Effect::new(move |_| {
+        if let Some(Some(Ok(job))) = job.get() {
+            if job.state.is_done() {
+                let outcome = match job.state {
+                    JobState::Finished => Ok(job.result.unwrap_or_default()),
+                    JobState::Cancelled => Err("Job has been cancelled".to_string()),
+                    _ => Err(job.message.unwrap_or_else(|| "Job failed".to_string())),
+                };
+                set_outcome.set(Some(outcome));
+            } else {
+                set_timeout(move || set_poll.update(|n| *n += 1), Duration::from_secs(1));
+            }
+        }
+    });
+
+    view! {
+        <Transition fallback=|| ()>
+            {move || {
+                job.get().flatten().map(|job| match job {
+                    Err(e) => view! { <p class="error">{e.to_string()}</p> }.into_any(),
+                    Ok(job) if job.state.is_done() => ().into_any(),
+                    Ok(job) => {
+                        let job_id = job.id;
+                        let percent = (job.progress * 100.0).round();
+                        view! {
+                            <div class="job-monitor">
+                                <span class="job-state">{format!("{:?}", job.state)}</span>
+                                <progress max="100" value=percent></progress>
+                                <span class="job-message">{job.message.unwrap_or_default()}</span>
+                                <button
+                                    class="button"
+                                    on:click=move |_| {
+                                        leptos::task::spawn_local(async move {
+                                            if let Err(e) = cancel_job(job_id).await {
+                                                log::error!("Failed to cancel job: {}", e);
+                                            }
+                                        });
+                                    }
+                                >
+                                    "Cancel"
+                                </button>
+                            </div>
+                        }
+                        .into_any()
+                    }
+                })
+            }}
+        </Transition>
+    }
+}
+
+/// List of the most recent background jobs with a button to fill gaps in quote histories
+#[component]
+pub fn JobsTable() -> impl IntoView {
+    let (refresh, set_refresh) = signal(0_u32);
+    let jobs = Resource::new(move || refresh.get(), |_| get_recent_jobs());
+
+    let fill_gaps = move |_| {
+        leptos::task::spawn_local(async move {
+            match submit_fill_quote_gaps(1).await {
+                Ok(job_id) => log::info!("Started job {} to fill quote gaps", job_id),
+                Err(e) => log::error!("Failed to start job: {}", e),
+            }
+            set_refresh.update(|n| *n += 1);
+        });
+    };
+
+    view! {
+        <h2>"Background Jobs"</h2>
+        <div class="job-actions">
+            <button class="button" on:click=fill_gaps>"Fill Quote Gaps"</button>
+            <button class="button" on:click=move |_| set_refresh.update(|n| *n += 1)>
+                "Refresh"
+            </button>
+        </div>
+        <Transition fallback=|| view! { <p>"Loading jobs..."</p> }>
+            {move || {
+                jobs.get().map(|jobs| match jobs {
+                    Err(e) => view! { <p class="error">{e.to_string()}</p> }.into_any(),
+                    Ok(jobs) => view! {
+                        <table class="table">
+                            <thead>
+                                <tr>
+                                    <th class="cell">"Id"</th>
+                                    <th class="cell">"Job"</th>
+                                    <th class="cell">"Created"</th>
+                                    <th class="cell">"State"</th>
+                                    <th class="cell">"Progress"</th>
+                                    <th class="cell">"Message"</th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {jobs
+                                    .into_iter()
+                                    .map(|job| view! {
+                                        <tr>
+                                            <td class="cell">{job.id}</td>
+                                            <td class="cell">{job.kind}</td>
+                                            <td class="cell">{job.created}</td>
+                                            <td class="cell">{format!("{:?}", job.state)}</td>
+                                            <td class="cell">{format!("{:.0}%", job.progress * 100.0)}</td>
+                                            <td class="cell">{job.message.unwrap_or_default()}</td>
+                                        </tr>
+                                    })
+                                    .collect::<Vec<_>>()}
+                            </tbody>
+                        </table>
+                    }
+                    .into_any(),
+                })
+            }}
+        </Transition>
+    }
+}
+
+/// Status of the last run of each task scheduled by `qualinvest daemon`
+#[component]
+pub fn ScheduledRuns() -> impl IntoView {
+    let runs = Resource::new(|| (), |_| get_scheduled_runs());
+
+    view! {
+        <h2>"Scheduled Updates"</h2>
+        <Transition fallback=|| view! { <p>"Loading scheduled runs..."</p> }>
+            {move || {
+                runs.get().map(|runs| match runs {
+                    Err(e) => view! { <p class="error">{e.to_string()}</p> }.into_any(),
+                    Ok(runs) if runs.is_empty() => view! {
+                        <p>"No scheduled runs yet, they are started by the command qualinvest daemon."</p>
+                    }
+                    .into_any(),
+                    Ok(runs) => view! {
+                        <table class="table">
+                            <thead>
+                                <tr>
+                                    <th class="cell">"Task"</th>
+                                    <th class="cell">"Last Run"</th>
+                                    <th class="cell">"State"</th>
+                                    <th class="cell">"Outcome"</th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {runs
+                                    .into_iter()
+                                    .map(|run| view! {
+                                        <tr>
+                                            <td class="cell">{run.kind}</td>
+                                            <td class="cell">{run.created}</td>
+                                            <td class="cell">{format!("{:?}", run.state)}</td>
+                                            <td class="cell">{run.message.unwrap_or_default()}</td>
+                                        </tr>
+                                    })
+                                    .collect::<Vec<_>>()}
+                            </tbody>
+                        </table>
+                    }
+                    .into_any(),
+                })
+            }}
+        </Transition>
+    }
+}
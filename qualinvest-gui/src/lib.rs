@@ -8,15 +8,23 @@ pub mod db;
 pub mod error;
 pub mod error_template;
 pub mod global_settings;
+pub mod import;
+pub mod import_view;
+pub mod inflation;
+pub mod integrity;
+pub mod jobs;
 pub mod position;
 pub mod position_view;
 pub mod quote_graph;
 pub mod quote_view;
 pub mod quotes;
+pub mod replay;
+pub mod replay_view;
 pub mod settings;
 pub mod settings_view;
 pub mod simulation;
 pub mod simulation_view;
+pub mod synthetic;
 pub mod ticker;
 pub mod ticker_view;
 pub mod time_range;
@@ -27,14 +27,17 @@ cfg_if! {
         //use http::{HeaderMap, Request};
         use leptos::prelude::*;
         use leptos_axum::{generate_route_list, handle_server_fns_with_context, LeptosRoutes};
-        use log::{debug, info, error};
+        use log::{debug, info, error, warn};
         use serde::{Deserialize, Serialize};
         use std::path::PathBuf;
         use std::net::{IpAddr, Ipv4Addr, SocketAddr};
         use qualinvest_gui::{
             error_template::{AppError, ErrorTemplate},
             global_settings::GlobalSettings,
+            jobs::JobQueue,
         };
+        use qualinvest_core::jobs::JobHandler;
+        use qualinvest_core::user::UserHandler;
         use tower::ServiceExt;
         use tower_http::services::ServeDir;
         use axum_login::AuthManagerLayerBuilder;
@@ -103,6 +106,7 @@ cfg_if! {
             pub market: Market,
             pub leptos_options: LeptosOptions,
             pub global_settings: GlobalSettings,
+            pub jobs: JobQueue,
         }
 
         #[derive(Parser)]
@@ -116,6 +120,9 @@ cfg_if! {
         pub struct Configuration {
             pub port: u16,
             pub database_url: String,
+            /// Number of background jobs (e.g. simulations) run in parallel
+            #[serde(default)]
+            pub job_workers: Option<usize>,
         }
 
         async fn server_fn_handler(
@@ -133,6 +140,7 @@ cfg_if! {
                     provide_context(app_state.db.clone());
                     provide_context(app_state.market.clone());
                     provide_context(app_state.global_settings.clone());
+                    provide_context(app_state.jobs.clone());
                     provide_context(auth_session.clone());
                 },
                 request,
@@ -170,6 +178,11 @@ cfg_if! {
                        let db = PostgresDB::new(&config.database_url)
                             .await
                             .expect("failed to open database");
+                        // Create missing tables or upgrade the schema of an existing database
+                        qualinvest_core::schema::migrate(&db).await?;
+                        if db.get_all_users().await.is_empty() {
+                            warn!("No users defined, create an administrator with 'qualinvest user add <name> --admin'");
+                        }
                         let mut leptos_options = get_configuration(None)
                             .expect("failed to load leptos options")
                             .leptos_options;
@@ -188,6 +201,13 @@ cfg_if! {
 
                         let market = create_market(&db, global_settings.inception_date.date()).await?;
 
+                        // Jobs of a previous run can't be continued
+                        let interrupted = db.fail_interrupted_jobs().await?;
+                        if interrupted > 0 {
+                            info!("{} interrupted jobs marked as failed", interrupted);
+                        }
+                        let jobs = JobQueue::new(db.clone(), config.job_workers.unwrap_or(2));
+
                         // Session layer
                         //
                         // This uses `tower-sessions`to establish a layer that will provide the
@@ -224,6 +244,7 @@ cfg_if! {
                             market,
                             leptos_options,
                             global_settings,
+                            jobs,
                         };
 
                         let routes = generate_route_list(|| view! { <App/> });
@@ -2,6 +2,7 @@ use cfg_if::cfg_if;
 use leptos::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use crate::inflation::InflationParams;
 use crate::time_range::TimeRange;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,7 +45,7 @@ cfg_if! {
         use finql::time_series::TimeSeries;
         use crate::time_range::{TimeRangePoint, CustomTimeRange};
 
-        fn time_range_to_period_dates(time_range: TimeRange) -> (PeriodDate, PeriodDate) {
+        pub fn time_range_to_period_dates(time_range: TimeRange) -> (PeriodDate, PeriodDate) {
             match time_range {
                 TimeRange::All => (PeriodDate::Inception, PeriodDate::Today),
                 TimeRange::Latest => (PeriodDate::Today, PeriodDate::Today),
@@ -70,10 +71,16 @@ cfg_if! {
 pub async fn get_positions(
     account_ids: Vec<i32>,
     time_range: TimeRange,
+    inflation: Option<InflationParams>,
 ) -> Result<PositionData, ServerFnError> {
     use crate::auth::PostgresBackend;
+    use crate::inflation::get_deflator;
     use axum_login::AuthSession;
     use log::debug;
+    use qualinvest_core::position::{
+        calculate_position_for_period_for_accounts,
+        calculate_real_position_for_period_for_accounts, PositionSummary,
+    };
     use qualinvest_core::user::UserHandler;
 
     debug!("get positions called for accounts {account_ids:?}");
@@ -110,9 +117,22 @@ pub async fn get_positions(
         .map_err(|e| ServerFnError::new(format!("Failed to get currency: {}", e)))?;
 
     let (start, end) = time_range_to_period_dates(time_range);
+    let deflator = get_deflator(&db, inflation).await?;
 
-    let (portfolio, _totals) =
-        qualinvest_core::position::calculate_position_for_period_for_accounts(
+    // In real terms, each cash flow is deflated at its own date
+    let portfolio = match deflator {
+        Some(deflator) => {
+            calculate_real_position_for_period_for_accounts(
+                currency,
+                &account_ids,
+                start,
+                end,
+                &deflator,
+                Arc::new(db),
+            )
+            .await
+        }
+        None => calculate_position_for_period_for_accounts(
             currency,
             &account_ids,
             start,
@@ -120,7 +140,9 @@ pub async fn get_positions(
             Arc::new(db),
         )
         .await
-        .map_err(|e| ServerFnError::new(format!("Failed to calculate positions: {}", e)))?;
+        .map(|(portfolio, _totals)| portfolio),
+    }
+    .map_err(|e| ServerFnError::new(format!("Failed to calculate positions: {}", e)))?;
 
     let cash = PositionRow {
         name: "Cash".to_string(),
@@ -135,7 +157,7 @@ pub async fn get_positions(
         last_quote: portfolio.cash.last_quote,
     };
 
-    let assets = portfolio
+    let assets: Vec<PositionRow> = portfolio
         .assets
         .values()
         .map(|pos| PositionRow {
@@ -153,29 +175,16 @@ pub async fn get_positions(
         .collect();
 
     // Compute totals from portfolio data (PositionTotals has private fields)
-    let mut totals_view = PositionTotalsView {
-        value: portfolio.cash.position,
-        trading_pnl: portfolio.cash.trading_pnl,
-        unrealized_pnl: 0.0,
-        dividend: portfolio.cash.dividend,
-        interest: portfolio.cash.interest,
-        tax: portfolio.cash.tax,
-        fees: portfolio.cash.fees,
+    let totals = PositionSummary::from_position(&portfolio);
+    let totals_view = PositionTotalsView {
+        value: totals.value,
+        trading_pnl: totals.trading_pnl,
+        unrealized_pnl: totals.unrealized_pnl,
+        dividend: totals.dividend,
+        interest: totals.interest,
+        tax: totals.tax,
+        fees: totals.fees,
     };
-    for pos in portfolio.assets.values() {
-        let pos_value = if let Some(quote) = pos.last_quote {
-            pos.position * quote
-        } else {
-            -pos.purchase_value
-        };
-        totals_view.value += pos_value;
-        totals_view.trading_pnl += pos.trading_pnl;
-        totals_view.unrealized_pnl += pos_value + pos.purchase_value;
-        totals_view.dividend += pos.dividend;
-        totals_view.interest += pos.interest;
-        totals_view.tax += pos.tax;
-        totals_view.fees += pos.fees;
-    }
 
     Ok(PositionData {
         cash,
@@ -184,18 +193,17 @@ pub async fn get_positions(
     })
 }
 
+/// Queue the calculation of the performance graph as background job and return the job id.
+/// The result of the job is the SVG graph of the portfolio value.
 #[server(GetPerformanceGraph, "/api")]
 pub async fn get_performance_graph(
     account_ids: Vec<i32>,
     time_range: TimeRange,
-) -> Result<String, ServerFnError> {
+    inflation: Option<InflationParams>,
+) -> Result<i32, ServerFnError> {
     use crate::auth::PostgresBackend;
     use axum_login::AuthSession;
-    use finql::Market;
     use log::debug;
-    use qualinvest_core::accounts::AccountHandler;
-    use qualinvest_core::performance::calc_performance;
-    use qualinvest_core::plot::make_plot;
     use qualinvest_core::user::UserHandler;
 
     debug!("get performance graph called for accounts {account_ids:?}");
@@ -223,6 +231,28 @@ pub async fn get_performance_graph(
         }
     }
 
+    let jobs = crate::db::get_jobs()?;
+    jobs.submit("performance graph", Some(user.id), move |progress| {
+        performance_graph(account_ids, time_range, inflation, db, progress)
+    })
+    .await
+}
+
+#[cfg(feature = "ssr")]
+async fn performance_graph(
+    account_ids: Vec<i32>,
+    time_range: TimeRange,
+    inflation: Option<InflationParams>,
+    db: finql::postgres::PostgresDB,
+    progress: crate::jobs::JobProgress,
+) -> Result<String, ServerFnError> {
+    use crate::inflation::get_deflator;
+    use finql::Market;
+    use qualinvest_core::accounts::AccountHandler;
+    use qualinvest_core::performance::calc_performance;
+    use qualinvest_core::plot::make_plot;
+
+    progress.report(0.0, "Loading transactions").await;
     let (start_pd, end_pd) = time_range_to_period_dates(time_range);
 
     let end = end_pd
@@ -236,6 +266,9 @@ pub async fn get_performance_graph(
         .date_from_trades(&transactions)
         .map_err(|e| ServerFnError::new(format!("Failed to resolve start date: {}", e)))?;
 
+    let deflator = get_deflator(&db, inflation).await?;
+
+    progress.report(0.2, "Loading quotes").await;
     let market = Market::new_with_date_range(Arc::new(db), start, end)
         .await
         .map_err(|e| ServerFnError::new(format!("Failed to create market: {}", e)))?;
@@ -245,15 +278,23 @@ pub async fn get_performance_graph(
         .await
         .map_err(|e| ServerFnError::new(format!("Failed to get currency: {}", e)))?;
 
+    progress.report(0.4, "Calculating performance").await;
     let performance = calc_performance(currency, &transactions, start, end, &market, "TARGET")
         .await
         .map_err(|e| ServerFnError::new(format!("Failed to calculate performance: {}", e)))?;
 
-    let time_series = TimeSeries {
-        title: "Portfolio Value".to_string(),
-        series: performance,
+    let time_series = match deflator {
+        Some(deflator) => TimeSeries {
+            title: "Portfolio Value (real)".to_string(),
+            series: deflator.deflate_series(&performance),
+        },
+        None => TimeSeries {
+            title: "Portfolio Value".to_string(),
+            series: performance,
+        },
     };
 
+    progress.report(0.9, "Drawing graph").await;
     make_plot("Performance", &[time_series])
         .map_err(|e| ServerFnError::new(format!("Failed to generate plot: {}", e)))
 }
@@ -1,4 +1,6 @@
 use crate::account::{get_accounts, AccountOption};
+use crate::inflation::{InflationParams, InflationSelector};
+use crate::jobs::JobMonitor;
 use crate::position::{get_performance_graph, get_positions, PositionData, PositionRow};
 use crate::time_range::{TimeRange, TimeRangeSelector};
 use leptos::prelude::*;
@@ -8,28 +10,45 @@ use leptos::wasm_bindgen::JsCast;
 pub fn PositionTable() -> impl IntoView {
     let (selected_account_ids, set_selected_account_ids) = signal::<Vec<i32>>(Vec::new());
     let (selected_time_range, set_selected_time_range) = signal(TimeRange::All);
+    let (inflation, set_inflation) = signal::<Option<InflationParams>>(None);
 
     let position_resource = Resource::new(
-        move || (selected_account_ids.get(), selected_time_range.get()),
-        move |(account_ids, time_range)| async move {
+        move || {
+            (
+                selected_account_ids.get(),
+                selected_time_range.get(),
+                inflation.get(),
+            )
+        },
+        move |(account_ids, time_range, inflation)| async move {
             if account_ids.is_empty() {
                 Err(ServerFnError::new("No account selected".to_string()))
             } else {
-                get_positions(account_ids, time_range).await
+                get_positions(account_ids, time_range, inflation).await
             }
         },
     );
 
-    let performance_graph = Resource::new(
-        move || (selected_account_ids.get(), selected_time_range.get()),
-        move |(account_ids, time_range)| async move {
-            if account_ids.is_empty() {
-                None
-            } else {
-                get_performance_graph(account_ids, time_range).await.ok()
+    // The performance graph is calculated as background job, restarted on each change of the
+    // selection; the monitor reports the outcome once the job is done.
+    let (graph_job_id, set_graph_job_id) = signal::<Option<i32>>(None);
+    let (graph, set_graph) = signal::<Option<Result<String, String>>>(None);
+    Effect::new(move |_| {
+        let account_ids = selected_account_ids.get();
+        let time_range = selected_time_range.get();
+        let inflation = inflation.get();
+        set_graph_job_id.set(None);
+        set_graph.set(None);
+        if account_ids.is_empty() {
+            return;
+        }
+        leptos::task::spawn_local(async move {
+            match get_performance_graph(account_ids, time_range, inflation).await {
+                Ok(job_id) => set_graph_job_id.set(Some(job_id)),
+                Err(e) => set_graph.set(Some(Err(e.to_string()))),
             }
-        },
-    );
+        });
+    });
 
     view! {
         <div class="account-selector">
@@ -78,13 +97,14 @@ pub fn PositionTable() -> impl IntoView {
         <div class="time-range-wrapper">
             <TimeRangeSelector set_selected=set_selected_time_range />
         </div>
-        <Suspense fallback=|| view! { <p>"Loading performance graph..."</p> }>
-            {move || {
-                performance_graph.get().flatten().map(|svg| {
-                    view! { <div class="performance-graph" inner_html=svg></div> }
-                })
-            }}
-        </Suspense>
+        <InflationSelector set_selected=set_inflation />
+        <JobMonitor job_id=graph_job_id set_outcome=set_graph />
+        {move || {
+            graph.get().map(|graph| match graph {
+                Ok(svg) => view! { <div class="performance-graph" inner_html=svg></div> }.into_any(),
+                Err(e) => view! { <p class="error">{e}</p> }.into_any(),
+            })
+        }}
         <Suspense fallback=|| view! { <p>"Loading positions..."</p> }>
             {move || {
                 position_resource.get().map(|result| {
@@ -18,6 +18,16 @@ pub struct QuoteView {
     pub volume: Option<f64>,
 }
 
+/// Layout of an uploaded quote file, empty fields keep the defaults of a yahoo download
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QuoteFileLayout {
+    pub delimiter: String,
+    pub date_column: String,
+    pub price_column: String,
+    pub date_format: String,
+    pub decimal_separator: String,
+}
+
 cfg_if! {
     if #[cfg(feature = "ssr")] {
         use finql::postgres::PostgresDB;
@@ -226,3 +236,69 @@ pub async fn delete_quotes(ticker_id: i32, time_range: TimeRange) -> Result<(),
     debug!("quotes deleted successfully");
     Ok(())
 }
+
+/// Import quotes of a ticker from the content of a CSV file, returns the number of new quotes
+#[server(ImportQuoteCsv, "/api")]
+pub async fn import_quote_csv(
+    ticker_id: i32,
+    layout: QuoteFileLayout,
+    csv: String,
+) -> Result<usize, ServerFnError> {
+    use crate::auth::PostgresBackend;
+    use axum_login::AuthSession;
+    use log::info;
+    use qualinvest_core::csv_import::Column;
+    use qualinvest_core::quote_import::{import_quotes, parse_quote_csv, QuoteCsvFormat};
+
+    let auth: AuthSession<PostgresBackend> = expect_context();
+    let user = auth
+        .user
+        .ok_or_else(|| ServerFnError::new("Unauthorized"))?;
+
+    // Security Note: Only admin users can import quotes
+    if !user.is_admin {
+        return Err(ServerFnError::new("Forbidden: Admin access required"));
+    }
+
+    let mut format = QuoteCsvFormat::default();
+    let column = |text: &str| match text.trim().parse() {
+        Ok(index) => Column::Index(index),
+        Err(_) => Column::Name(text.trim().to_string()),
+    };
+    if let Some(delimiter) = layout.delimiter.chars().next() {
+        format.delimiter = delimiter;
+    }
+    if !layout.date_column.trim().is_empty() {
+        format.date = column(&layout.date_column);
+    }
+    if !layout.price_column.trim().is_empty() {
+        format.price = column(&layout.price_column);
+    }
+    if !layout.date_format.trim().is_empty() {
+        format.date_format = layout.date_format.trim().to_string();
+    }
+    if let Some(decimal_separator) = layout.decimal_separator.chars().next() {
+        format.decimal_separator = decimal_separator;
+    }
+
+    let preview = parse_quote_csv(&format, &csv).map_err(ServerFnError::new)?;
+    if preview.quotes.is_empty() {
+        return Err(ServerFnError::new(match preview.errors.first() {
+            Some(error) => format!("No valid quotes found, {}", error),
+            None => "No quotes found".to_string(),
+        }));
+    }
+    let db = crate::db::get_db()?;
+    let ticker = db
+        .get_ticker_by_id(ticker_id)
+        .await
+        .map_err(ServerFnError::new)?;
+    let count = import_quotes(&db, &ticker, &preview.quotes)
+        .await
+        .map_err(ServerFnError::new)?;
+    info!(
+        "User {} imported {} quotes for ticker {}",
+        user.name, count, ticker_id
+    );
+    Ok(count)
+}
@@ -0,0 +1,139 @@
+use crate::inflation::InflationParams;
+use crate::time_range::TimeRange;
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// ── serialisable replay parameters (sent from client to server) ───────────────
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllocationParam {
+    pub asset_id: i32,
+    /// As a fraction in [0, 1]
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlternativeParams {
+    /// Human-readable label shown in the graph legend.
+    pub label: String,
+    /// Allocation of each deposit; the remainder stays in cash
+    pub allocations: Vec<AllocationParam>,
+    // --- transaction costs ---
+    pub min_fee: f64,
+    /// None means no upper cap on fees
+    pub max_fee: Option<f64>,
+    /// As a fraction in [0, 1]
+    pub proportional_fee: f64,
+    /// As a fraction in [0, 1]
+    pub tax_rate: f64,
+}
+
+// ── server function ───────────────────────────────────────────────────────────
+
+/// Replay the external cash flows of the given accounts into each alternative and plot
+/// the alternatives next to the real portfolio value.
+#[server(RunReplay, "/api")]
+pub async fn run_replay(
+    account_ids: Vec<i32>,
+    alternatives: Vec<AlternativeParams>,
+    time_range: TimeRange,
+    inflation: Option<InflationParams>,
+) -> Result<String, ServerFnError> {
+    use crate::auth::PostgresBackend;
+    use crate::inflation::get_deflator;
+    use crate::position::time_range_to_period_dates;
+    use axum_login::AuthSession;
+    use finql::datatypes::CurrencyISOCode;
+    use finql::strategy::{StockTransactionCosts, StockTransactionFee};
+    use finql::time_series::TimeSeries;
+    use log::debug;
+    use qualinvest_core::plot::make_plot;
+    use qualinvest_core::replay::{what_if_replay, Alternative, TargetWeight};
+    use qualinvest_core::user::UserHandler;
+    use std::sync::Arc;
+
+    debug!(
+        "run_replay called for accounts {account_ids:?} with {} alternatives",
+        alternatives.len()
+    );
+
+    let auth: AuthSession<PostgresBackend> = expect_context();
+    let user = auth
+        .user
+        .ok_or_else(|| ServerFnError::new("Unauthorized"))?;
+
+    if account_ids.is_empty() {
+        return Err(ServerFnError::new("No account selected"));
+    }
+
+    let db = crate::db::get_db()?;
+
+    if !user.is_admin {
+        let user_accounts = db
+            .get_user_accounts(user.id)
+            .await
+            .map_err(|e| ServerFnError::new(format!("Failed to get user accounts: {}", e)))?;
+        let user_account_ids: Vec<i32> = user_accounts.iter().filter_map(|a| a.id).collect();
+        for account_id in &account_ids {
+            if !user_account_ids.contains(account_id) {
+                return Err(ServerFnError::new(format!(
+                    "Forbidden: Cannot access account {}",
+                    account_id
+                )));
+            }
+        }
+    }
+
+    let market = crate::db::get_market()?;
+    // todo: read user's base currency from db instead of using "EUR" hard coded
+    let currency = market
+        .get_currency(CurrencyISOCode::new("EUR")?)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Failed to get currency: {}", e)))?;
+
+    let alternatives: Vec<Alternative> = alternatives
+        .into_iter()
+        .map(|a| Alternative {
+            label: a.label,
+            targets: a
+                .allocations
+                .into_iter()
+                .map(|t| TargetWeight {
+                    asset_id: t.asset_id,
+                    weight: t.weight,
+                })
+                .collect(),
+            costs: StockTransactionCosts {
+                fee: StockTransactionFee::new(a.min_fee, a.max_fee, a.proportional_fee),
+                tax_rate: a.tax_rate,
+            },
+        })
+        .collect();
+
+    let deflator = get_deflator(&db, inflation).await?;
+    let (start, end) = time_range_to_period_dates(time_range);
+    let all_series = what_if_replay(
+        currency,
+        &account_ids,
+        start,
+        end,
+        &alternatives,
+        Arc::new(db),
+    )
+    .await
+    .map_err(|e| ServerFnError::new(format!("Failed to replay cash flows: {}", e)))?;
+
+    let all_series: Vec<TimeSeries> = match deflator {
+        Some(deflator) => all_series
+            .into_iter()
+            .map(|ts| TimeSeries {
+                title: ts.title,
+                series: deflator.deflate_series(&ts.series),
+            })
+            .collect(),
+        None => all_series,
+    };
+
+    make_plot("What-if Replay", &all_series)
+        .map_err(|e| ServerFnError::new(format!("Failed to generate plot: {}", e)))
+}
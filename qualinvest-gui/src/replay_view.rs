@@ -0,0 +1,453 @@
+use crate::account::{get_accounts, AccountOption};
+use crate::assets::{get_assets, AssetView};
+use crate::inflation::{InflationParams, InflationSelector};
+use crate::replay::{run_replay, AllocationParam, AlternativeParams};
+use crate::time_range::{TimeRange, TimeRangeSelector};
+use leptos::prelude::*;
+use leptos::wasm_bindgen::JsCast;
+use std::sync::Arc;
+
+// ── per-alternative form state ────────────────────────────────────────────────
+
+/// A flat, fully owned representation of one replay alternative in the form.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlternativeEntry {
+    /// Unique client-side key (incrementing counter).
+    pub key: u32,
+    pub label: String,
+    // allocations: list of (asset_id, weight in %) rows
+    pub allocations: Vec<(Option<i32>, f64)>,
+    // transaction costs
+    pub min_fee: f64,
+    pub proportional_fee: f64,
+    pub tax_rate: f64,
+}
+
+impl AlternativeEntry {
+    fn new(key: u32) -> Self {
+        Self {
+            key,
+            label: format!("Alternative {key}"),
+            allocations: vec![(None, 100.0)],
+            min_fee: 0.0,
+            proportional_fee: 0.0,
+            tax_rate: 0.0,
+        }
+    }
+}
+
+/// An allocation row together with its index, used as item type for `<For>`.
+#[derive(Clone, PartialEq)]
+struct IndexedAllocation {
+    idx: usize,
+    asset_id: Option<i32>,
+    weight: f64,
+}
+
+// ── replay panel ──────────────────────────────────────────────────────────────
+
+/// Replay the real deposits and withdrawals of selected accounts into passive alternatives
+#[component]
+pub fn ReplayPanel() -> impl IntoView {
+    let (selected_account_ids, set_selected_account_ids) = signal::<Vec<i32>>(Vec::new());
+    let (selected_time_range, set_selected_time_range) = signal(TimeRange::All);
+    let (inflation, set_inflation) = signal::<Option<InflationParams>>(None);
+    let (next_key, set_next_key) = signal(2_u32);
+    let (alternatives, set_alternatives) = signal(vec![AlternativeEntry::new(1)]);
+
+    // Incremented each time the user clicks "Start Replay"; 0 means never run.
+    let (run_trigger, set_run_trigger) = signal(0_u32);
+
+    let add_alternative = move |_| {
+        let key = next_key.get_untracked();
+        set_next_key.set(key + 1);
+        set_alternatives.update(|v| v.push(AlternativeEntry::new(key)));
+    };
+
+    let replay_result = Resource::new(
+        move || run_trigger.get(),
+        move |trigger| {
+            let account_ids = selected_account_ids.get_untracked();
+            let entries = alternatives.get_untracked();
+            let time_range = selected_time_range.get_untracked();
+            let inflation = inflation.get_untracked();
+            async move {
+                if trigger == 0 {
+                    return None;
+                }
+                let params: Vec<AlternativeParams> = entries
+                    .into_iter()
+                    .map(|e| AlternativeParams {
+                        label: e.label,
+                        allocations: e
+                            .allocations
+                            .into_iter()
+                            .filter_map(|(asset_id, weight)| {
+                                Some(AllocationParam {
+                                    asset_id: asset_id?,
+                                    weight: weight / 100.0,
+                                })
+                            })
+                            .collect(),
+                        min_fee: e.min_fee,
+                        max_fee: None,
+                        proportional_fee: e.proportional_fee,
+                        tax_rate: e.tax_rate,
+                    })
+                    .collect();
+                Some(run_replay(account_ids, params, time_range, inflation).await)
+            }
+        },
+    );
+
+    view! {
+        <div class="simulation-strategies">
+            <h2>"What-if replay"</h2>
+            <p>
+                "Replays the real deposits and withdrawals of the selected accounts into \
+                passive alternatives and compares them with the real portfolio value."
+            </p>
+            <div class="account-selector">
+                <label for="replay-account-select">"Select Account: "</label>
+                <Suspense fallback=|| view! { <p>"Loading accounts..."</p> }>
+                    <Await future=get_accounts() let:accounts>
+                        {
+                            let account_list = accounts.clone();
+                            view! {
+                                <select
+                                    id="replay-account-select"
+                                    multiple
+                                    on:change=move |ev| {
+                                        let select = event_target::<web_sys::HtmlSelectElement>(&ev);
+                                        let options = select.selected_options();
+                                        let mut ids = Vec::new();
+                                        for i in 0..options.length() {
+                                            if let Some(opt) = options.item(i) {
+                                                if let Ok(opt) = opt.dyn_into::<web_sys::HtmlOptionElement>() {
+                                                    if let Ok(id) = opt.value().parse::<i32>() {
+                                                        ids.push(id);
+                                                    }
+                                                }
+                                            }
+                                        }
+                                        set_selected_account_ids.set(ids);
+                                    }
+                                >
+                                    <For
+                                        each=move || account_list.clone().unwrap_or_default()
+                                        key=|account| account.id
+                                        children=move |account: AccountOption| {
+                                            view! {
+                                                <option value=account.id>{account.display_name()}</option>
+                                            }
+                                        }
+                                    />
+                                </select>
+                            }
+                        }
+                    </Await>
+                </Suspense>
+            </div>
+
+            <Suspense fallback=|| view! { <p>"Loading assets…"</p> }>
+                <Await future=get_assets() let:assets_result>
+                    {
+                        let assets: Vec<AssetView> = assets_result
+                            .as_ref()
+                            .map(|s| s.get())
+                            .unwrap_or_default();
+
+                        view! {
+                            <For
+                                each=move || alternatives.get()
+                                key=|a| a.key
+                                children={
+                                    let assets = assets.clone();
+                                    move |entry| {
+                                        let key = entry.key;
+                                        let assets = assets.clone();
+                                        view! {
+                                            <AlternativeCard
+                                                entry=entry
+                                                assets=assets
+                                                on_change=move |updated| {
+                                                    set_alternatives.update(|v| {
+                                                        if let Some(slot) = v.iter_mut().find(|e| e.key == key) {
+                                                            *slot = updated;
+                                                        }
+                                                    });
+                                                }
+                                                on_remove=move || {
+                                                    set_alternatives.update(|v| v.retain(|e| e.key != key));
+                                                }
+                                            />
+                                        }
+                                    }
+                                }
+                            />
+                        }
+                    }
+                </Await>
+            </Suspense>
+
+            <button class="button" on:click=add_alternative>
+                "+ Add Alternative"
+            </button>
+            <div class="simulation-run-section">
+                <div class="time-range-wrapper">
+                    <TimeRangeSelector set_selected=set_selected_time_range />
+                </div>
+                <InflationSelector set_selected=set_inflation />
+                <button
+                    class="button"
+                    on:click=move |_| set_run_trigger.update(|n| *n += 1)
+                >
+                    "Start Replay"
+                </button>
+            </div>
+
+            <Suspense fallback=|| view! { <p class="simulation-running">"Running replay…"</p> }>
+                {move || {
+                    replay_result.get().flatten().map(|outcome| match outcome {
+                        Err(e) => view! {
+                            <p class="error">"Replay failed: " {e.to_string()}</p>
+                        }.into_any(),
+                        Ok(svg) => view! {
+                            <div class="simulation-graph" inner_html=svg />
+                        }.into_any(),
+                    })
+                }}
+            </Suspense>
+        </div>
+    }
+}
+
+// ── single alternative card ───────────────────────────────────────────────────
+
+#[component]
+fn AlternativeCard<FChange, FRemove>(
+    entry: AlternativeEntry,
+    assets: Vec<AssetView>,
+    on_change: FChange,
+    on_remove: FRemove,
+) -> impl IntoView
+where
+    FChange: Fn(AlternativeEntry) + 'static + Clone + Send + Sync,
+    FRemove: Fn() + 'static + Send + Sync,
+{
+    let (label, set_label) = signal(entry.label.clone());
+    let (allocations, set_allocations) = signal(entry.allocations.clone());
+    let (min_fee, set_min_fee) = signal(entry.min_fee);
+    let (proportional_fee, set_proportional_fee) = signal(entry.proportional_fee);
+    let (tax_rate, set_tax_rate) = signal(entry.tax_rate);
+
+    let key = entry.key;
+
+    // Collect current state and notify the parent.
+    let emit: Arc<dyn Fn() + Send + Sync> = Arc::new(move || {
+        on_change(AlternativeEntry {
+            key,
+            label: label.get_untracked(),
+            allocations: allocations.get_untracked(),
+            min_fee: min_fee.get_untracked(),
+            proportional_fee: proportional_fee.get_untracked(),
+            tax_rate: tax_rate.get_untracked(),
+        });
+    });
+
+    let emit_allocations_for = emit.clone();
+    let emit_add_allocation = emit.clone();
+
+    view! {
+        <div class="strategy-card">
+            <div class="strategy-card-header">
+                <div class="form-group">
+                    <label>"Label"</label>
+                    <input
+                        type="text"
+                        prop:value=move || label.get()
+                        on:input={
+                            let emit = emit.clone();
+                            move |ev| {
+                                set_label.set(event_target_value(&ev));
+                                emit();
+                            }
+                        }
+                    />
+                </div>
+                <button
+                    class="button strategy-remove-btn"
+                    on:click=move |_| on_remove()
+                >
+                    "Remove"
+                </button>
+            </div>
+
+            <fieldset class="strategy-fieldset">
+                <legend>"Allocation"</legend>
+                <div class="dividends-header">
+                    <span class="dividend-col-label">"Asset"</span>
+                    <span class="dividend-col-label">"Weight (%)"</span>
+                </div>
+                <For
+                    each=move || {
+                        allocations
+                            .get()
+                            .into_iter()
+                            .enumerate()
+                            .map(|(idx, (asset_id, weight))| IndexedAllocation { idx, asset_id, weight })
+                            .collect::<Vec<IndexedAllocation>>()
+                    }
+                    key=|item| item.idx
+                    children={
+                        let emit = emit_allocations_for.clone();
+                        let assets = assets.clone();
+                        move |item| {
+                            let idx = item.idx;
+                            let emit = emit.clone();
+                            view! {
+                                <div class="dividend-row">
+                                    <select
+                                        prop:value=item.asset_id.map(|id| id.to_string()).unwrap_or_default()
+                                        on:change={
+                                            let emit = emit.clone();
+                                            move |ev| {
+                                                let asset_id = event_target_value(&ev).parse::<i32>().ok();
+                                                set_allocations.update(|rows| {
+                                                    if let Some(row) = rows.get_mut(idx) {
+                                                        row.0 = asset_id;
+                                                    }
+                                                });
+                                                emit();
+                                            }
+                                        }
+                                    >
+                                        <option value="">"— select asset —"</option>
+                                        {assets
+                                            .iter()
+                                            .map(|a| {
+                                                let id_str = a.id.to_string();
+                                                let name = a.name.clone();
+                                                view! { <option value=id_str>{name}</option> }
+                                            })
+                                            .collect::<Vec<_>>()}
+                                    </select>
+                                    <input
+                                        type="number"
+                                        step="any"
+                                        min="0"
+                                        max="100"
+                                        prop:value=item.weight.to_string()
+                                        on:input={
+                                            let emit = emit.clone();
+                                            move |ev| {
+                                                if let Ok(v) = event_target_value(&ev).parse::<f64>() {
+                                                    set_allocations.update(|rows| {
+                                                        if let Some(row) = rows.get_mut(idx) {
+                                                            row.1 = v;
+                                                        }
+                                                    });
+                                                    emit();
+                                                }
+                                            }
+                                        }
+                                    />
+                                    <button
+                                        class="button"
+                                        on:click={
+                                            let emit = emit.clone();
+                                            move |_| {
+                                                set_allocations.update(|rows| {
+                                                    if rows.len() > 1 {
+                                                        rows.remove(idx);
+                                                    }
+                                                });
+                                                emit();
+                                            }
+                                        }
+                                    >
+                                        "−"
+                                    </button>
+                                </div>
+                            }
+                        }
+                    }
+                />
+                <button
+                    class="button"
+                    on:click={
+                        let emit = emit_add_allocation.clone();
+                        move |_| {
+                            set_allocations.update(|rows| rows.push((None, 0.0)));
+                            emit();
+                        }
+                    }
+                >
+                    "+ Add Asset"
+                </button>
+            </fieldset>
+
+            <fieldset class="strategy-fieldset">
+                <legend>"Transaction Costs"</legend>
+                <div class="strategy-row">
+                    <div class="form-group">
+                        <label>"Min Fee"</label>
+                        <input
+                            type="number"
+                            step="any"
+                            min="0"
+                            prop:value=move || min_fee.get().to_string()
+                            on:input={
+                                let emit = emit.clone();
+                                move |ev| {
+                                    if let Ok(v) = event_target_value(&ev).parse::<f64>() {
+                                        set_min_fee.set(v);
+                                        emit();
+                                    }
+                                }
+                            }
+                        />
+                    </div>
+                    <div class="form-group">
+                        <label>"Proportional Fee (%)"</label>
+                        <input
+                            type="number"
+                            step="0.0001"
+                            min="0"
+                            max="100"
+                            prop:value=move || format!("{:.4}", proportional_fee.get() * 100.0)
+                            on:input={
+                                let emit = emit.clone();
+                                move |ev| {
+                                    if let Ok(v) = event_target_value(&ev).parse::<f64>() {
+                                        set_proportional_fee.set(v / 100.0);
+                                        emit();
+                                    }
+                                }
+                            }
+                        />
+                    </div>
+                    <div class="form-group">
+                        <label>"Tax Rate (%)"</label>
+                        <input
+                            type="number"
+                            step="0.0001"
+                            min="0"
+                            max="100"
+                            prop:value=move || format!("{:.4}", tax_rate.get() * 100.0)
+                            on:input={
+                                let emit = emit.clone();
+                                move |ev| {
+                                    if let Ok(v) = event_target_value(&ev).parse::<f64>() {
+                                        set_tax_rate.set(v / 100.0);
+                                        emit();
+                                    }
+                                }
+                            }
+                        />
+                    </div>
+                </div>
+            </fieldset>
+        </div>
+    }
+}
@@ -1,3 +1,5 @@
+use crate::inflation::InflationParams;
+use crate::replay::AllocationParam;
 use crate::time_range::TimeRange;
 use cfg_if::cfg_if;
 use leptos::prelude::*;
@@ -13,19 +15,136 @@ pub struct DividendParam {
     pub amount: f64,
 }
 
+/// Type of a strategy parameter, see `qualinvest_core::strategy::ParamKind`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ParamKindView {
+    Asset,
+    Ticker,
+    Integer {
+        min: Option<i64>,
+        max: Option<i64>,
+    },
+    Number {
+        min: Option<f64>,
+        max: Option<f64>,
+    },
+    /// Entered in percent, bounds are given as fractions
+    Percent {
+        min: Option<f64>,
+        max: Option<f64>,
+    },
+    Choice(Vec<String>),
+    TargetWeights,
+    CashFlows,
+}
+
+impl ParamKindView {
+    /// Parameters entered in a single input field; all others have their own form sections
+    pub fn is_scalar(&self) -> bool {
+        matches!(
+            self,
+            ParamKindView::Integer { .. }
+                | ParamKindView::Number { .. }
+                | ParamKindView::Percent { .. }
+                | ParamKindView::Choice(_)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParamSpecView {
+    pub name: String,
+    pub label: String,
+    pub description: String,
+    pub kind: ParamKindView,
+    pub required: bool,
+    /// Default value in its text representation
+    pub default: Option<String>,
+}
+
+/// Strategy available for simulations, as registered in the strategy registry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StrategyTypeView {
+    pub name: String,
+    pub description: String,
+    pub params: Vec<ParamSpecView>,
+}
+
+impl StrategyTypeView {
+    pub fn has_param_kind(&self, kind: &ParamKindView) -> bool {
+        self.params.iter().any(|p| &p.kind == kind)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetTaxParam {
+    pub asset_id: i32,
+    /// "Stock" | "EquityFund" | "MixedFund" | "RealEstateFund" | "Other"
+    pub class: String,
+    /// Accumulating funds are subject to the yearly Vorabpauschale
+    pub accumulating: bool,
+}
+
+/// Parameters of the German capital gains tax model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GermanTaxParam {
+    /// As a fraction in [0, 1], including solidarity surcharge and church tax
+    pub tax_rate: f64,
+    /// Sparerpauschbetrag per year
+    pub allowance: f64,
+    /// Assets not listed are taxed as stocks
+    pub assets: Vec<AssetTaxParam>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateStepParam {
+    /// ISO-8601 date string the rate is valid from
+    pub date: String,
+    /// As a fraction
+    pub rate: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterestRateParam {
+    /// "Fixed" | "Schedule" | "Reference"
+    pub kind: String,
+    /// Fixed rate as a fraction
+    pub rate: f64,
+    pub schedule: Vec<RateStepParam>,
+    /// Asset with quotes of the reference rate in percent, e.g. €STR
+    pub reference_asset_id: Option<i32>,
+    /// Spread over the reference rate as a fraction
+    pub spread: f64,
+}
+
+/// Interest on positive and negative cash balances
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CashInterestParam {
+    pub deposit: InterestRateParam,
+    pub borrowing: InterestRateParam,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StrategyParams {
     /// Human-readable label shown in the graph legend.
     pub label: String,
-    /// "StaticInSingleStock" | "ReInvestInSingleStock"
+    /// Name of the strategy in the strategy registry
     pub strategy_type: String,
-    pub asset_id: i32,
-    /// Only required for ReInvestInSingleStock
+    /// Value of the asset parameter, if the strategy has one
+    pub asset_id: Option<i32>,
+    /// Value of the ticker parameter, if the strategy has one
     pub ticker_id: Option<i32>,
+    /// If not empty, the simulation starts from the real holdings of these accounts
+    /// and the initial position and cash below are ignored
+    pub start_account_ids: Vec<i32>,
     /// Initial number of shares
     pub initial_position: f64,
     /// Initial cash in the portfolio
     pub initial_cash: f64,
+    /// Value of the target weights parameter, if the strategy has one
+    pub targets: Vec<AllocationParam>,
+    /// Values of all other strategy parameters in their text representation, by name
+    pub params: Vec<(String, String)>,
     /// ISO-4217 currency code, e.g. "EUR"
     pub currency: String,
     // --- transaction costs ---
@@ -36,9 +155,30 @@ pub struct StrategyParams {
     pub proportional_fee: f64,
     /// As a fraction in [0, 1]
     pub tax_rate: f64,
+    /// If set, German capital gains tax is applied instead of the flat tax rate
+    pub german_tax: Option<GermanTaxParam>,
+    /// If set, interest is booked on the cash balance
+    pub cash_interest: Option<CashInterestParam>,
+    /// Value of the dividends parameter, if the strategy has one
     pub dividends: Vec<DividendParam>,
 }
 
+/// Transactions generated by a simulated strategy
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerView {
+    pub title: String,
+    /// The ledger in CSV format, see `qualinvest_core::strategy::ledger`
+    pub csv: String,
+}
+
+/// Result of a simulation job, stored as JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulationOutput {
+    /// Graph of the simulated portfolio values
+    pub svg: String,
+    pub ledgers: Vec<LedgerView>,
+}
+
 // ── SSR-only implementation ───────────────────────────────────────────────────
 
 cfg_if! {
@@ -51,16 +191,23 @@ cfg_if! {
             date_time_helper::date_to_offset_date_time,
         };
         use finql::period_date::PeriodDate;
-        use finql::strategy::{
-            ReInvestInSingleStock, StaticInSingleStock, StockTransactionCosts, StockTransactionFee,
-        };
-        use finql::strategy::calc_strategy;
+        use finql::postgres::PostgresDB;
+        use finql::strategy::{StockTransactionCosts, StockTransactionFee, Strategy};
         use finql::time_series::TimeSeries;
         use finql::Market;
         use qualinvest_core::plot::make_plot;
+        use qualinvest_core::position::{
+            calculate_position_for_period_for_accounts, start_transactions_from_position,
+        };
+        use qualinvest_core::strategy::{
+            AssetTaxInfo, CashInterest, CashInterestStrategy, GermanTaxParams, GermanTaxStrategy,
+            InterestRate, ParamKind, ParamSpec, ParamValue, ParamValues, StrategyContext,
+            StrategyRegistry, TargetWeight, TaxClass, calc_strategy_with_ledger,
+        };
         use time::Date;
         use time::macros::format_description;
         use crate::time_range::{TimeRangePoint, CustomTimeRange};
+        use crate::jobs::JobProgress;
 
         fn time_range_to_dates(time_range: TimeRange) -> Result<(PeriodDate, PeriodDate), ServerFnError> {
             let (start, end) = match time_range {
@@ -89,11 +236,109 @@ cfg_if! {
                 .map_err(|e| ServerFnError::new(format!("Invalid date '{}': {}", s, e)))
         }
 
-        /// Build the two initial transactions (cash deposit + asset purchase) that
-        /// `calc_strategy` expects as its `start_transactions` argument.
+        fn interest_rate(params: &InterestRateParam) -> Result<InterestRate, ServerFnError> {
+            match params.kind.as_str() {
+                "Fixed" => Ok(InterestRate::Fixed(params.rate)),
+                "Schedule" => {
+                    let steps = params
+                        .schedule
+                        .iter()
+                        .filter(|s| !s.date.is_empty())
+                        .map(|s| Ok((parse_date(&s.date)?, s.rate)))
+                        .collect::<Result<Vec<_>, ServerFnError>>()?;
+                    Ok(InterestRate::Schedule(steps))
+                }
+                "Reference" => {
+                    let asset_id = params.reference_asset_id.ok_or_else(|| {
+                        ServerFnError::new("Reference rate requires an asset with quotes")
+                    })?;
+                    Ok(InterestRate::Reference {
+                        asset_id,
+                        spread: params.spread,
+                    })
+                }
+                other => Err(ServerFnError::new(format!(
+                    "Unknown interest rate type '{}'",
+                    other
+                ))),
+            }
+        }
+
+        impl From<&ParamSpec> for ParamSpecView {
+            fn from(spec: &ParamSpec) -> Self {
+                let kind = match &spec.kind {
+                    ParamKind::Asset => ParamKindView::Asset,
+                    ParamKind::Ticker => ParamKindView::Ticker,
+                    ParamKind::Integer { min, max } => ParamKindView::Integer { min: *min, max: *max },
+                    ParamKind::Number { min, max } => ParamKindView::Number { min: *min, max: *max },
+                    ParamKind::Percent { min, max } => ParamKindView::Percent { min: *min, max: *max },
+                    ParamKind::Choice(choices) => {
+                        ParamKindView::Choice(choices.iter().map(|c| c.to_string()).collect())
+                    }
+                    ParamKind::TargetWeights => ParamKindView::TargetWeights,
+                    ParamKind::CashFlows => ParamKindView::CashFlows,
+                };
+                ParamSpecView {
+                    name: spec.name.to_string(),
+                    label: spec.label.to_string(),
+                    description: spec.description.to_string(),
+                    kind,
+                    required: spec.required,
+                    default: spec.default.map(|d| d.to_string()),
+                }
+            }
+        }
+
+        /// Collect the parameter values from the form fields dedicated to a parameter kind
+        /// and from the generic parameter list
+        fn strategy_param_values(
+            params: &StrategyParams,
+            specs: &[ParamSpec],
+        ) -> Result<ParamValues, ServerFnError> {
+            let mut values = ParamValues::new();
+            for spec in specs {
+                let value = match spec.kind {
+                    ParamKind::Asset => params.asset_id.map(ParamValue::Id),
+                    ParamKind::Ticker => params.ticker_id.map(ParamValue::Id),
+                    ParamKind::TargetWeights => Some(ParamValue::Weights(
+                        params
+                            .targets
+                            .iter()
+                            .map(|t| TargetWeight {
+                                asset_id: t.asset_id,
+                                weight: t.weight,
+                            })
+                            .collect(),
+                    )),
+                    ParamKind::CashFlows => Some(ParamValue::CashFlows(
+                        params
+                            .dividends
+                            .iter()
+                            .filter(|d| !d.date.is_empty() && d.amount != 0.0)
+                            .map(|d| Ok((parse_date(&d.date)?, d.amount)))
+                            .collect::<Result<Vec<_>, ServerFnError>>()?,
+                    )),
+                    _ => match params.params.iter().find(|(name, _)| name == spec.name) {
+                        Some((_, text)) => {
+                            spec.parse(text).map_err(|e| ServerFnError::new(e.to_string()))?
+                        }
+                        None => None,
+                    },
+                };
+                if let Some(value) = value {
+                    values.insert(spec.name, value);
+                }
+            }
+            Ok(values)
+        }
+
+        /// Build the initial transactions that `calc_strategy` expects as its
+        /// `start_transactions` argument: either the real holdings of the selected
+        /// accounts at the start date, or a cash deposit plus an asset purchase.
         async fn build_start_transactions(
             params: &StrategyParams,
             market: &Market,
+            db: Arc<PostgresDB>,
             start: Date,
         ) -> Result<Vec<Transaction>, ServerFnError> {
             let currency = market
@@ -104,6 +349,19 @@ cfg_if! {
                 .await
                 .map_err(|e| ServerFnError::new(format!("Failed to resolve currency: {}", e)))?;
 
+            if !params.start_account_ids.is_empty() {
+                let (position, _totals) = calculate_position_for_period_for_accounts(
+                    currency,
+                    &params.start_account_ids,
+                    PeriodDate::Inception,
+                    PeriodDate::FixedDate(start),
+                    db,
+                )
+                .await
+                .map_err(|e| ServerFnError::new(format!("Failed to calculate start position: {}", e)))?;
+                return Ok(start_transactions_from_position(&position, start));
+            }
+
             let mut txns = Vec::new();
 
             // Cash deposit
@@ -117,18 +375,21 @@ cfg_if! {
             // Initial stock position (valued at purchase price = 0 cost basis here;
             // the position count is what matters for strategy calculations)
             if params.initial_position != 0.0 {
+                let asset_id = params
+                    .asset_id
+                    .ok_or_else(|| ServerFnError::new("An initial position requires an asset"))?;
                 let start_time = date_to_offset_date_time(&start, 20, None)
                     .map_err(|e| ServerFnError::new(format!("Date conversion error: {}", e)))?;
-                debug!("get asset price for asset_id={}, currency={currency}, start_time={start_time:?}", params.asset_id);
+                debug!("get asset price for asset_id={asset_id}, currency={currency}, start_time={start_time:?}");
                 let price = market
-                    .get_asset_price(params.asset_id, currency, start_time)
+                    .get_asset_price(asset_id, currency, start_time)
                     .await
                     .map_err(|e| ServerFnError::new(format!("Failed to get asset price: {}", e)))?;
 
                 txns.push(Transaction {
                     id: None,
                     transaction_type: TransactionType::Asset {
-                        asset_id: params.asset_id,
+                        asset_id,
                         position: params.initial_position,
                     },
                     cash_flow: CashFlow::new(
@@ -143,16 +404,17 @@ cfg_if! {
             Ok(txns)
         }
 
-        /// Run a single strategy and return its time series.  Errors are turned into
-        /// an empty series with the error message so one bad strategy doesn't abort
-        /// the whole run.
+        /// Run a single strategy and return its time series together with its ledger.
+        /// Errors are turned into an empty series with the error message so one bad
+        /// strategy doesn't abort the whole run.
         async fn run_one(
             params: StrategyParams,
             market: Market,
+            db: Arc<PostgresDB>,
             start: Date,
             end: Date,
-        ) -> TimeSeries {
-            let result: Result<TimeSeries, ServerFnError> = async {
+        ) -> (TimeSeries, Vec<Transaction>) {
+            let result: Result<(TimeSeries, Vec<Transaction>), ServerFnError> = async {
                 let currency = market
                     .get_currency(
                         CurrencyISOCode::new(&params.currency)
@@ -161,90 +423,153 @@ cfg_if! {
                     .await
                     .map_err(|e| ServerFnError::new(format!("Currency error: {}", e)))?;
 
-                // Parse dividends
-                let dividends: Vec<CashFlow> = params
-                    .dividends
-                    .iter()
-                    .filter(|d| !d.date.is_empty() && d.amount != 0.0)
-                    .map(|d| {
-                        let date = parse_date(&d.date)?;
-                        Ok(CashFlow::new(d.amount, currency, date))
-                    })
-                    .collect::<Result<Vec<_>, ServerFnError>>()?;
-
                 let costs = StockTransactionCosts {
                     fee: StockTransactionFee::new(
                         params.min_fee,
                         params.max_fee,
                         params.proportional_fee,
                     ),
-                    tax_rate: params.tax_rate,
+                    // The German tax model replaces the flat tax rate
+                    tax_rate: if params.german_tax.is_some() {
+                        0.0
+                    } else {
+                        params.tax_rate
+                    },
                 };
 
                 let start_txns =
-                    build_start_transactions(&params, &market, start).await?;
-
-                let series = match params.strategy_type.as_str() {
-                    "StaticInSingleStock" => {
-                        let strategy =
-                            StaticInSingleStock::new(params.asset_id, dividends, costs);
-                        calc_strategy(currency, &start_txns, &strategy, start, end, market.clone())
-                            .await
-                    }
-                    "ReInvestInSingleStock" => {
-                        let ticker_id = params.ticker_id.ok_or_else(|| {
-                            ServerFnError::new("ReInvestInSingleStock requires a ticker_id")
-                        })?;
-                        let strategy = ReInvestInSingleStock::new(
-                            params.asset_id,
-                            ticker_id,
-                            market.clone(),
-                            dividends,
-                            costs,
-                        );
-                        calc_strategy(currency, &start_txns, &strategy, start, end, market.clone())
-                            .await
+                    build_start_transactions(&params, &market, db, start).await?;
+
+                let registry = StrategyRegistry::default();
+                let spec = registry.get(&params.strategy_type).ok_or_else(|| {
+                    ServerFnError::new(format!("Unknown strategy type '{}'", params.strategy_type))
+                })?;
+                let values = strategy_param_values(&params, &spec.params)?;
+                let context = StrategyContext {
+                    currency,
+                    costs,
+                    market: market.clone(),
+                };
+                let strategy = registry
+                    .build(&params.strategy_type, &values, context)
+                    .map_err(|e| ServerFnError::new(e.to_string()))?;
+
+                let strategy: Box<dyn Strategy> = match &params.cash_interest {
+                    Some(interest) => {
+                        let interest = CashInterest {
+                            deposit: interest_rate(&interest.deposit)?,
+                            borrowing: interest_rate(&interest.borrowing)?,
+                        };
+                        Box::new(
+                            CashInterestStrategy::new(
+                                strategy,
+                                interest,
+                                currency,
+                                market.clone(),
+                                end,
+                            )
+                            .map_err(|e| ServerFnError::new(e.to_string()))?,
+                        )
                     }
-                    other => {
-                        return Err(ServerFnError::new(format!(
-                            "Unknown strategy type '{}'",
-                            other
-                        )))
+                    None => strategy,
+                };
+
+                // Applied last to tax the interest on cash as well
+                let strategy: Box<dyn Strategy> = match &params.german_tax {
+                    Some(tax) => {
+                        let assets = tax
+                            .assets
+                            .iter()
+                            .map(|a| {
+                                let class: TaxClass = a.class.parse().map_err(ServerFnError::new)?;
+                                Ok((
+                                    a.asset_id,
+                                    AssetTaxInfo {
+                                        class,
+                                        accumulating: a.accumulating,
+                                    },
+                                ))
+                            })
+                            .collect::<Result<_, ServerFnError>>()?;
+                        let tax_params = GermanTaxParams {
+                            tax_rate: tax.tax_rate,
+                            allowance: tax.allowance,
+                            ..GermanTaxParams::new(assets)
+                        };
+                        Box::new(GermanTaxStrategy::new(strategy, tax_params, market.clone()))
                     }
+                    None => strategy,
                 };
 
-                Ok(TimeSeries {
-                    title: params.label.clone(),
-                    series,
-                })
+                let (series, ledger) = calc_strategy_with_ledger(
+                    currency,
+                    &start_txns,
+                    strategy,
+                    start,
+                    end,
+                    market.clone(),
+                )
+                .await;
+
+                Ok((
+                    TimeSeries {
+                        title: params.label.clone(),
+                        series,
+                    },
+                    ledger,
+                ))
             }
             .await;
 
             debug!("Result: {result:?}");
-            result.unwrap_or_else(|e| TimeSeries {
-                title: format!("{} (error: {})", params.label, e),
-                series: Vec::new(),
+            result.unwrap_or_else(|e| {
+                (
+                    TimeSeries {
+                        title: format!("{} (error: {})", params.label, e),
+                        series: Vec::new(),
+                    },
+                    Vec::new(),
+                )
             })
         }
     }
 }
 
-// ── server function ───────────────────────────────────────────────────────────
+// ── server functions ──────────────────────────────────────────────────────────
+
+/// All strategies available for simulations together with their parameters
+#[server(GetStrategyTypes, "/api")]
+pub async fn get_strategy_types() -> Result<Vec<StrategyTypeView>, ServerFnError> {
+    use qualinvest_core::strategy::StrategyRegistry;
+
+    Ok(StrategyRegistry::default()
+        .strategies()
+        .iter()
+        .map(|s| StrategyTypeView {
+            name: s.name.to_string(),
+            description: s.description.to_string(),
+            params: s.params.iter().map(ParamSpecView::from).collect(),
+        })
+        .collect())
+}
 
+/// Queue the simulation of all strategies as background job and return the job id.
+/// The result of the job is a `SimulationOutput` in JSON format.
 #[server(RunStrategies, "/api")]
 pub async fn run_strategies(
     strategies: Vec<StrategyParams>,
     time_range: TimeRange,
-) -> Result<String, ServerFnError> {
+    inflation: Option<InflationParams>,
+) -> Result<i32, ServerFnError> {
     use crate::auth::PostgresBackend;
     use axum_login::AuthSession;
-    use futures::future::join_all;
     use log::debug;
+    use qualinvest_core::user::UserHandler;
 
     debug!("run_strategies called with {} strategies", strategies.len());
 
     let auth: AuthSession<PostgresBackend> = expect_context();
-    let _user = auth
+    let user = auth
         .user
         .ok_or_else(|| ServerFnError::new("Unauthorized"))?;
 
@@ -254,6 +579,48 @@ pub async fn run_strategies(
 
     let db = crate::db::get_db()?;
 
+    if !user.is_admin {
+        let user_accounts = db
+            .get_user_accounts(user.id)
+            .await
+            .map_err(|e| ServerFnError::new(format!("Failed to get user accounts: {}", e)))?;
+        let user_account_ids: Vec<i32> = user_accounts.iter().filter_map(|a| a.id).collect();
+        for account_id in strategies.iter().flat_map(|s| s.start_account_ids.iter()) {
+            if !user_account_ids.contains(account_id) {
+                return Err(ServerFnError::new(format!(
+                    "Forbidden: Cannot access account {}",
+                    account_id
+                )));
+            }
+        }
+    }
+
+    let jobs = crate::db::get_jobs()?;
+    jobs.submit("simulation", Some(user.id), move |progress| {
+        simulate(strategies, time_range, inflation, db, progress)
+    })
+    .await
+}
+
+#[cfg(feature = "ssr")]
+async fn simulate(
+    strategies: Vec<StrategyParams>,
+    time_range: TimeRange,
+    inflation: Option<InflationParams>,
+    db: PostgresDB,
+    progress: JobProgress,
+) -> Result<String, ServerFnError> {
+    use crate::inflation::get_deflator;
+    use finql::datatypes::AssetHandler;
+    use futures::future::join_all;
+    use log::debug;
+    use qualinvest_core::strategy::ledger::{ledger_entries, write_ledger_csv};
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let deflator = get_deflator(&db, inflation).await?;
+    let db = Arc::new(db);
+
     let (start_pd, end_pd) = time_range_to_dates(time_range)?;
 
     // Resolve the end date first (no transactions needed for that)
@@ -279,33 +646,110 @@ pub async fn run_strategies(
     };
     debug!("Time range: start={start:?}, end={end:?}");
 
-    let market = Market::new_with_date_range(Arc::new(db), start, end)
+    let market = Market::new_with_date_range(db.clone(), start, end)
         .await
         .map_err(|e| ServerFnError::new(format!("Failed to create market: {}", e)))?;
 
     debug!("market created");
 
-    debug!("running {} strategies", strategies.len());
+    let total = strategies.len();
+    debug!("running {} strategies", total);
+    progress
+        .report(0.0, &format!("0 of {} strategies simulated", total))
+        .await;
 
     // Run all strategies in parallel
+    let done = AtomicUsize::new(0);
     let futures: Vec<_> = strategies
         .into_iter()
-        .map(|p| run_one(p, market.clone(), start, end))
+        .map(|p| async {
+            let run = run_one(p, market.clone(), db.clone(), start, end).await;
+            let count = done.fetch_add(1, Ordering::SeqCst) + 1;
+            progress
+                .report(
+                    count as f64 / total as f64,
+                    &format!("{} of {} strategies simulated", count, total),
+                )
+                .await;
+            run
+        })
         .collect();
 
-    let all_series: Vec<_> = join_all(futures)
+    let runs: Vec<_> = join_all(futures)
         .await
         .into_iter()
-        .filter(|ts| !ts.series.is_empty())
+        .filter(|(ts, _)| !ts.series.is_empty())
         .collect();
 
-    if all_series.is_empty() {
+    if runs.is_empty() {
         return Err(ServerFnError::new(
             "All strategies produced empty time series. \
              Check your parameters and date range.",
         ));
     }
 
-    make_plot("Strategy Simulation", &all_series)
-        .map_err(|e| ServerFnError::new(format!("Failed to generate plot: {}", e)))
+    let asset_names: HashMap<i32, String> = db
+        .get_asset_list()
+        .await
+        .map_err(|e| ServerFnError::new(format!("Failed to get assets: {}", e)))?
+        .into_iter()
+        .map(|a| (a.id, a.name))
+        .collect();
+    let mut ledgers = Vec::new();
+    let mut all_series = Vec::new();
+    for (ts, transactions) in runs {
+        let mut csv = Vec::new();
+        write_ledger_csv(&mut csv, &ledger_entries(&transactions, &asset_names))
+            .map_err(|e| ServerFnError::new(format!("Failed to write ledger: {}", e)))?;
+        ledgers.push(LedgerView {
+            title: ts.title.clone(),
+            csv: String::from_utf8_lossy(&csv).into_owned(),
+        });
+        all_series.push(match &deflator {
+            Some(deflator) => TimeSeries {
+                title: ts.title,
+                series: deflator.deflate_series(&ts.series),
+            },
+            None => ts,
+        });
+    }
+
+    let svg = make_plot("Strategy Simulation", &all_series)
+        .map_err(|e| ServerFnError::new(format!("Failed to generate plot: {}", e)))?;
+    serde_json::to_string(&SimulationOutput { svg, ledgers })
+        .map_err(|e| ServerFnError::new(format!("Failed to store simulation result: {}", e)))
+}
+
+/// Store the ledger of a simulated strategy as transactions of a new sandbox account
+/// the user has access to, and return the id of the new account
+#[server(ImportSimulationLedger, "/api")]
+pub async fn import_simulation_ledger(
+    csv: String,
+    account_name: String,
+) -> Result<i32, ServerFnError> {
+    use crate::auth::PostgresBackend;
+    use axum_login::AuthSession;
+    use qualinvest_core::strategy::ledger::{import_ledger, read_ledger_csv};
+    use qualinvest_core::user::UserHandler;
+
+    let auth: AuthSession<PostgresBackend> = expect_context();
+    let user = auth
+        .user
+        .ok_or_else(|| ServerFnError::new("Unauthorized"))?;
+
+    if account_name.trim().is_empty() {
+        return Err(ServerFnError::new("Account name is required"));
+    }
+    let entries = read_ledger_csv(csv.as_bytes())
+        .map_err(|e| ServerFnError::new(format!("Failed to read ledger: {}", e)))?;
+
+    let db = crate::db::get_db()?;
+    let market = crate::db::get_market()?;
+    let account_id = import_ledger(&db, &market, account_name.trim(), &entries)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Failed to import ledger: {}", e)))?;
+    db.add_account_right(user.id, account_id)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Failed to grant account access: {}", e)))?;
+    Ok(account_id)
 }
@@ -1,8 +1,18 @@
+use crate::account::{get_accounts, AccountOption};
 use crate::assets::{get_assets, AssetView};
-use crate::simulation::{run_strategies, DividendParam, StrategyParams};
+use crate::inflation::{InflationParams, InflationSelector};
+use crate::jobs::JobMonitor;
+use crate::replay::AllocationParam;
+use crate::simulation::{
+    get_strategy_types, import_simulation_ledger, run_strategies, AssetTaxParam, CashInterestParam,
+    DividendParam, GermanTaxParam, InterestRateParam, LedgerView, ParamKindView, ParamSpecView,
+    RateStepParam, SimulationOutput, StrategyParams, StrategyTypeView,
+};
+use crate::synthetic::SyntheticPathForm;
 use crate::ticker::{get_tickers, TickerFilter, TickerView};
 use crate::time_range::{TimeRange, TimeRangeSelector};
 use leptos::prelude::*;
+use leptos::wasm_bindgen::JsCast;
 use std::sync::Arc;
 
 // ── helper types ──────────────────────────────────────────────────────────────
@@ -16,17 +26,87 @@ struct IndexedDividend {
     amount: String,
 }
 
+/// A target weight row together with its index, used as item type for `<For>`.
+#[derive(Clone, PartialEq)]
+struct IndexedTarget {
+    idx: usize,
+    asset_id: Option<i32>,
+    weight: f64,
+}
+
+/// A tax classification row together with its index, used as item type for `<For>`.
+#[derive(Clone, PartialEq)]
+struct IndexedTaxAsset {
+    idx: usize,
+    asset_id: Option<i32>,
+    class: String,
+    accumulating: bool,
+}
+
+/// A rate schedule row together with its index, used as item type for `<For>`.
+#[derive(Clone, PartialEq)]
+struct IndexedRateStep {
+    idx: usize,
+    date: String,
+    rate: f64,
+}
+
 // ── per-strategy form state ───────────────────────────────────────────────────
 
+/// Form state of an interest rate on cash, all rates in %
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateEntry {
+    /// "Fixed" | "Schedule" | "Reference"
+    pub kind: String,
+    pub rate: f64,
+    // schedule: list of (date_str, rate) rows
+    pub schedule: Vec<(String, f64)>,
+    pub reference_asset_id: Option<i32>,
+    pub spread: f64,
+}
+
+impl RateEntry {
+    fn new(rate: f64) -> Self {
+        Self {
+            kind: "Fixed".to_string(),
+            rate,
+            schedule: vec![("".to_string(), rate)],
+            reference_asset_id: None,
+            spread: 0.0,
+        }
+    }
+
+    fn to_params(&self) -> InterestRateParam {
+        InterestRateParam {
+            kind: self.kind.clone(),
+            rate: self.rate / 100.0,
+            schedule: self
+                .schedule
+                .iter()
+                .filter(|(date, _)| !date.is_empty())
+                .map(|(date, rate)| RateStepParam {
+                    date: date.clone(),
+                    rate: rate / 100.0,
+                })
+                .collect(),
+            reference_asset_id: self.reference_asset_id,
+            spread: self.spread / 100.0,
+        }
+    }
+}
+
 /// A flat, fully owned representation of one strategy entry in the form.
 #[derive(Debug, Clone, PartialEq)]
 pub struct StrategyEntry {
     /// Unique client-side key (incrementing counter).
     pub key: u32,
-    /// "StaticInSingleStock" | "ReInvestInSingleStock"
+    /// Name of the strategy in the strategy registry
     pub strategy_type: String,
     // -- shared fields --
     pub asset_id: Option<i32>,
+    /// Start from the real holdings of the selected accounts instead of the initial position
+    pub start_from_accounts: bool,
+    pub start_account_ids: Vec<i32>,
     pub initial_position: f64,
     pub initial_cash: f64,
     pub currency: String,
@@ -36,10 +116,24 @@ pub struct StrategyEntry {
     pub max_fee: f64,
     pub proportional_fee: f64,
     pub tax_rate: f64,
+    /// "Flat" | "German"
+    pub tax_model: String,
+    /// Sparerpauschbetrag, German tax model only
+    pub allowance: f64,
+    // tax classification of assets: list of (asset_id, class, accumulating) rows
+    pub tax_assets: Vec<(Option<i32>, String, bool)>,
+    // interest on cash
+    pub interest_enabled: bool,
+    pub deposit_rate: RateEntry,
+    pub borrowing_rate: RateEntry,
     // dividends: list of (date_str, amount_str) rows
     pub dividends: Vec<(String, String)>,
-    // -- ReInvestInSingleStock only --
+    // -- strategies with a ticker parameter only --
     pub ticker_id: Option<i32>,
+    // -- strategies with target weights only: list of (asset_id, weight in %) rows --
+    pub targets: Vec<(Option<i32>, f64)>,
+    /// Text values of the other strategy parameters by name, percentages in %
+    pub params: Vec<(String, String)>,
 }
 
 impl StrategyEntry {
@@ -48,6 +142,8 @@ impl StrategyEntry {
             key,
             strategy_type: "StaticInSingleStock".to_string(),
             asset_id: None,
+            start_from_accounts: false,
+            start_account_ids: Vec::new(),
             initial_position: 0.0,
             initial_cash: 0.0,
             currency: "EUR".to_string(),
@@ -56,8 +152,16 @@ impl StrategyEntry {
             max_fee: 0.0,
             proportional_fee: 0.0,
             tax_rate: 0.0,
+            tax_model: "Flat".to_string(),
+            allowance: 1000.0,
+            tax_assets: Vec::new(),
+            interest_enabled: false,
+            deposit_rate: RateEntry::new(0.0),
+            borrowing_rate: RateEntry::new(8.0),
             dividends: vec![("".to_string(), "".to_string())],
             ticker_id: None,
+            targets: vec![(None, 100.0)],
+            params: Vec::new(),
         }
     }
 }
@@ -69,9 +173,15 @@ pub fn SimulationPage() -> impl IntoView {
     let (next_key, set_next_key) = signal(1_u32);
     let (strategies, set_strategies) = signal(vec![StrategyEntry::new(0)]);
     let (selected_time_range, set_selected_time_range) = signal(TimeRange::All);
+    let (inflation, set_inflation) = signal::<Option<InflationParams>>(None);
+
+    // Simulations run as background job; the monitor reports the outcome once it is done.
+    let (job_id, set_job_id) = signal::<Option<i32>>(None);
+    let (outcome, set_outcome) = signal::<Option<Result<String, String>>>(None);
 
-    // Incremented each time the user clicks "Start Simulation"; 0 means never run.
-    let (run_trigger, set_run_trigger) = signal(0_u32);
+    // Reloaded whenever a synthetic price path has been added as new asset
+    let (assets_version, set_assets_version) = signal(0_u32);
+    let assets_resource = Resource::new(move || assets_version.get(), |_| get_assets());
 
     let add_strategy = move |_| {
         let key = next_key.get_untracked();
@@ -79,136 +189,259 @@ pub fn SimulationPage() -> impl IntoView {
         set_strategies.update(|v| v.push(StrategyEntry::new(key)));
     };
 
-    // Convert StrategyEntry list to StrategyParams and call the server function.
-    let simulation_result = Resource::new(
-        move || run_trigger.get(),
-        move |trigger| {
-            let entries = strategies.get_untracked();
-            let time_range = selected_time_range.get_untracked();
-            async move {
-                if trigger == 0 {
-                    return None;
-                }
-                let params: Vec<StrategyParams> = entries
-                    .into_iter()
-                    .enumerate()
-                    .filter_map(|(i, e)| {
-                        let asset_id = e.asset_id?;
-                        Some(StrategyParams {
-                            label: format!("Strategy {i}: {}", e.strategy_type),
-                            strategy_type: e.strategy_type,
-                            asset_id,
-                            ticker_id: e.ticker_id,
-                            initial_position: e.initial_position,
-                            initial_cash: e.initial_cash,
-                            currency: e.currency,
-                            min_fee: e.min_fee,
-                            max_fee: if e.max_fee_enabled {
-                                Some(e.max_fee)
-                            } else {
-                                None
-                            },
-                            proportional_fee: e.proportional_fee,
+    // Convert StrategyEntry list to StrategyParams and submit the simulation job.
+    let start_simulation = move |_| {
+        let entries = strategies.get_untracked();
+        let time_range = selected_time_range.get_untracked();
+        let inflation = inflation.get_untracked();
+        set_job_id.set(None);
+        set_outcome.set(None);
+        leptos::task::spawn_local(async move {
+            let params: Vec<StrategyParams> = entries
+                .into_iter()
+                .enumerate()
+                .filter_map(|(i, e)| {
+                    let start_account_ids = if e.start_from_accounts {
+                        if e.start_account_ids.is_empty() {
+                            return None;
+                        }
+                        e.start_account_ids
+                    } else {
+                        Vec::new()
+                    };
+                    Some(StrategyParams {
+                        label: format!("Strategy {i}: {}", e.strategy_type),
+                        strategy_type: e.strategy_type,
+                        asset_id: e.asset_id,
+                        ticker_id: e.ticker_id,
+                        start_account_ids,
+                        initial_position: e.initial_position,
+                        initial_cash: e.initial_cash,
+                        targets: e
+                            .targets
+                            .into_iter()
+                            .filter_map(|(asset_id, weight)| {
+                                Some(AllocationParam {
+                                    asset_id: asset_id?,
+                                    weight: weight / 100.0,
+                                })
+                            })
+                            .collect(),
+                        params: e.params,
+                        currency: e.currency,
+                        min_fee: e.min_fee,
+                        max_fee: if e.max_fee_enabled {
+                            Some(e.max_fee)
+                        } else {
+                            None
+                        },
+                        proportional_fee: e.proportional_fee,
+                        tax_rate: e.tax_rate,
+                        german_tax: (e.tax_model == "German").then(|| GermanTaxParam {
                             tax_rate: e.tax_rate,
-                            dividends: e
-                                .dividends
-                                .into_iter()
-                                .filter(|(d, a)| !d.is_empty() && !a.is_empty())
-                                .filter_map(|(d, a)| {
-                                    let amount = a.parse::<f64>().ok()?;
-                                    Some(DividendParam { date: d, amount })
+                            allowance: e.allowance,
+                            assets: e
+                                .tax_assets
+                                .iter()
+                                .filter_map(|(asset_id, class, accumulating)| {
+                                    Some(AssetTaxParam {
+                                        asset_id: (*asset_id)?,
+                                        class: class.clone(),
+                                        accumulating: *accumulating,
+                                    })
                                 })
                                 .collect(),
-                        })
+                        }),
+                        cash_interest: e.interest_enabled.then(|| CashInterestParam {
+                            deposit: e.deposit_rate.to_params(),
+                            borrowing: e.borrowing_rate.to_params(),
+                        }),
+                        dividends: e
+                            .dividends
+                            .into_iter()
+                            .filter(|(d, a)| !d.is_empty() && !a.is_empty())
+                            .filter_map(|(d, a)| {
+                                let amount = a.parse::<f64>().ok()?;
+                                Some(DividendParam { date: d, amount })
+                            })
+                            .collect(),
                     })
-                    .collect();
-                Some(run_strategies(params, time_range).await)
+                })
+                .collect();
+            match run_strategies(params, time_range, inflation).await {
+                Ok(id) => set_job_id.set(Some(id)),
+                Err(e) => set_outcome.set(Some(Err(e.to_string()))),
             }
-        },
-    );
+        });
+    };
 
     view! {
         <div class="simulation-strategies">
             <Suspense fallback=|| view! { <p>"Loading assets…"</p> }>
-                <Await future=get_assets() let:assets_result>
-                    {
+                {move || assets_resource.get().map(|assets_result| {
                         let assets: Vec<AssetView> = assets_result
-                            .as_ref()
                             .map(|s| s.get())
                             .unwrap_or_default();
 
                         view! {
-                            <For
-                                each=move || strategies.get()
-                                key=|s| s.key
-                                children={
+                            <Await future=get_strategy_types() let:types_result>
+                                {
+                                    let strategy_types: Vec<StrategyTypeView> = types_result
+                                        .clone()
+                                        .unwrap_or_default();
                                     let assets = assets.clone();
-                                    move |entry| {
-                                        let key = entry.key;
-                                        let assets = assets.clone();
-                                        view! {
-                                            <StrategyCard
-                                                entry=entry
-                                                assets=assets
-                                                on_change=move |updated| {
-                                                    set_strategies.update(|v| {
-                                                        if let Some(slot) = v.iter_mut().find(|e| e.key == key) {
-                                                            *slot = updated;
-                                                        }
-                                                    });
-                                                }
-                                                on_remove=move || {
-                                                    set_strategies.update(|v| v.retain(|e| e.key != key));
+
+                                    view! {
+                                        <For
+                                            each=move || strategies.get()
+                                            key=|s| s.key
+                                            children={
+                                                let assets = assets.clone();
+                                                move |entry| {
+                                                    let key = entry.key;
+                                                    let assets = assets.clone();
+                                                    let strategy_types = strategy_types.clone();
+                                                    view! {
+                                                        <StrategyCard
+                                                            entry=entry
+                                                            assets=assets
+                                                            strategy_types=strategy_types
+                                                            on_change=move |updated| {
+                                                                set_strategies.update(|v| {
+                                                                    if let Some(slot) = v.iter_mut().find(|e| e.key == key) {
+                                                                        *slot = updated;
+                                                                    }
+                                                                });
+                                                            }
+                                                            on_remove=move || {
+                                                                set_strategies.update(|v| v.retain(|e| e.key != key));
+                                                            }
+                                                        />
+                                                    }
                                                 }
-                                            />
-                                        }
+                                            }
+                                        />
                                     }
                                 }
-                            />
+                            </Await>
                         }
-                    }
-                </Await>
+                })}
             </Suspense>
 
             <button class="button" on:click=add_strategy>
                 "+ Add Strategy"
             </button>
+            <SyntheticPathForm on_created=move |_| set_assets_version.update(|v| *v += 1) />
             <div class="divider"></div>
             <div class="simulation-run-section">
                 <h2>"Simulate strategies"</h2>
                 <div class="time-range-wrapper">
                     <TimeRangeSelector set_selected=set_selected_time_range />
                 </div>
-                <button
-                    class="button"
-                    on:click=move |_| set_run_trigger.update(|n| *n += 1)
-                >
+                <InflationSelector set_selected=set_inflation />
+                <button class="button" on:click=start_simulation>
                     "Start Simulation"
                 </button>
             </div>
 
-            <Suspense fallback=|| view! { <p class="simulation-running">"Running simulation…"</p> }>
-                {move || {
-                    simulation_result.get().flatten().map(|outcome| match outcome {
-                        Err(e) => view! {
-                            <p class="error">"Simulation failed: " {e.to_string()}</p>
+            <JobMonitor job_id=job_id set_outcome=set_outcome />
+            {move || {
+                outcome.get().map(|outcome| match outcome {
+                    Err(e) => view! {
+                        <p class="error">"Simulation failed: " {e}</p>
+                    }.into_any(),
+                    Ok(result) => match serde_json::from_str::<SimulationOutput>(&result) {
+                        Ok(output) => view! {
+                            <div class="simulation-graph" inner_html=output.svg />
+                            <LedgerExport ledgers=output.ledgers />
                         }.into_any(),
-                        Ok(svg) => view! {
-                            <div class="simulation-graph" inner_html=svg />
+                        Err(e) => view! {
+                            <p class="error">"Invalid simulation result: " {e.to_string()}</p>
                         }.into_any(),
-                    })
-                }}
-            </Suspense>
+                    },
+                })
+            }}
         </div>
     }
 }
 
+// ── simulated transactions ────────────────────────────────────────────────────
+
+/// Data URL to download the CSV text without another round trip to the server
+fn csv_data_url(csv: &str) -> String {
+    let mut url = String::from("data:text/csv;charset=utf-8,");
+    for byte in csv.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+            url.push(byte as char);
+        } else {
+            url.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    url
+}
+
+/// Download links for the ledgers of all simulated strategies and import into sandbox accounts
+#[component]
+fn LedgerExport(ledgers: Vec<LedgerView>) -> impl IntoView {
+    view! {
+        <fieldset class="strategy-fieldset">
+            <legend>"Simulated Transactions"</legend>
+            {ledgers
+                .into_iter()
+                .enumerate()
+                .map(|(i, ledger)| {
+                    let (account_name, set_account_name) = signal(format!("Sandbox: {}", ledger.title));
+                    let (message, set_message) = signal::<Option<Result<String, String>>>(None);
+                    let csv = ledger.csv.clone();
+                    let import = move |_| {
+                        let csv = csv.clone();
+                        let name = account_name.get_untracked();
+                        leptos::task::spawn_local(async move {
+                            match import_simulation_ledger(csv, name.clone()).await {
+                                Ok(_) => set_message.set(Some(Ok(format!("Created account '{}'", name)))),
+                                Err(e) => set_message.set(Some(Err(e.to_string()))),
+                            }
+                        });
+                    };
+                    view! {
+                        <div class="strategy-row">
+                            <span>{ledger.title.clone()}</span>
+                            <a
+                                class="button"
+                                href=csv_data_url(&ledger.csv)
+                                download=format!("simulation_ledger_{}.csv", i)
+                            >
+                                "Download CSV"
+                            </a>
+                            <input
+                                type="text"
+                                prop:value=move || account_name.get()
+                                on:input=move |ev| set_account_name.set(event_target_value(&ev))
+                            />
+                            <button class="button" on:click=import>
+                                "Import into Sandbox Account"
+                            </button>
+                            {move || {
+                                message.get().map(|message| match message {
+                                    Ok(msg) => view! { <span>{msg}</span> }.into_any(),
+                                    Err(e) => view! { <span class="error">{e}</span> }.into_any(),
+                                })
+                            }}
+                        </div>
+                    }
+                })
+                .collect::<Vec<_>>()}
+        </fieldset>
+    }
+}
+
 // ── single strategy card ──────────────────────────────────────────────────────
 
 #[component]
 fn StrategyCard<FChange, FRemove>(
     entry: StrategyEntry,
     assets: Vec<AssetView>,
+    strategy_types: Vec<StrategyTypeView>,
     on_change: FChange,
     on_remove: FRemove,
 ) -> impl IntoView
@@ -218,6 +451,8 @@ where
 {
     let (strategy_type, set_strategy_type) = signal(entry.strategy_type.clone());
     let (asset_id, set_asset_id) = signal(entry.asset_id);
+    let (start_from_accounts, set_start_from_accounts) = signal(entry.start_from_accounts);
+    let (start_account_ids, set_start_account_ids) = signal(entry.start_account_ids.clone());
     let (initial_position, set_initial_position) = signal(entry.initial_position);
     let (initial_cash, set_initial_cash) = signal(entry.initial_cash);
     let (currency, set_currency) = signal(entry.currency.clone());
@@ -226,8 +461,16 @@ where
     let (max_fee, set_max_fee) = signal(entry.max_fee);
     let (proportional_fee, set_proportional_fee) = signal(entry.proportional_fee);
     let (tax_rate, set_tax_rate) = signal(entry.tax_rate);
+    let (tax_model, set_tax_model) = signal(entry.tax_model.clone());
+    let (allowance, set_allowance) = signal(entry.allowance);
+    let (tax_assets, set_tax_assets) = signal(entry.tax_assets.clone());
+    let (interest_enabled, set_interest_enabled) = signal(entry.interest_enabled);
+    let (deposit_rate, set_deposit_rate) = signal(entry.deposit_rate.clone());
+    let (borrowing_rate, set_borrowing_rate) = signal(entry.borrowing_rate.clone());
     let (dividends, set_dividends) = signal(entry.dividends.clone());
     let (ticker_id, set_ticker_id) = signal(entry.ticker_id);
+    let (targets, set_targets) = signal(entry.targets.clone());
+    let (params, set_params) = signal(entry.params.clone());
 
     let key = entry.key;
 
@@ -240,6 +483,8 @@ where
                 key,
                 strategy_type: strategy_type.get_untracked(),
                 asset_id: asset_id.get_untracked(),
+                start_from_accounts: start_from_accounts.get_untracked(),
+                start_account_ids: start_account_ids.get_untracked(),
                 initial_position: initial_position.get_untracked(),
                 initial_cash: initial_cash.get_untracked(),
                 currency: currency.get_untracked(),
@@ -248,18 +493,35 @@ where
                 max_fee: max_fee.get_untracked(),
                 proportional_fee: proportional_fee.get_untracked(),
                 tax_rate: tax_rate.get_untracked(),
+                tax_model: tax_model.get_untracked(),
+                allowance: allowance.get_untracked(),
+                tax_assets: tax_assets.get_untracked(),
+                interest_enabled: interest_enabled.get_untracked(),
+                deposit_rate: deposit_rate.get_untracked(),
+                borrowing_rate: borrowing_rate.get_untracked(),
                 dividends: dividends.get_untracked(),
                 ticker_id: ticker_id.get_untracked(),
+                targets: targets.get_untracked(),
+                params: params.get_untracked(),
             });
         }
     });
 
-    // Tickers are only fetched when the type is ReInvestInSingleStock and an
+    // The form sections shown depend on the parameters declared by the selected strategy
+    let strategy_types = StoredValue::new(strategy_types);
+    let selected_type = move || {
+        let name = strategy_type.get();
+        strategy_types.with_value(|types| types.iter().find(|t| t.name == name).cloned())
+    };
+    let has_param_kind =
+        move |kind: ParamKindView| selected_type().is_some_and(|t| t.has_param_kind(&kind));
+
+    // Tickers are only fetched when the strategy has a ticker parameter and an
     // asset has been selected.
     let ticker_resource = Resource::new(
-        move || (strategy_type.get(), asset_id.get()),
-        move |(stype, aid)| async move {
-            if stype == "ReInvestInSingleStock" {
+        move || (has_param_kind(ParamKindView::Ticker), asset_id.get()),
+        move |(needs_ticker, aid)| async move {
+            if needs_ticker {
                 if let Some(id) = aid {
                     return get_tickers(TickerFilter { asset_id: id })
                         .await
@@ -274,7 +536,16 @@ where
     let assets_for_select = assets.clone();
 
     // Pre-clone emit for each move closure that consumes it inside view!
+    let emit_asset = emit.clone();
     let emit_type = emit.clone();
+    let emit_start = emit.clone();
+    let emit_targets = emit.clone();
+    let emit_params = emit.clone();
+    let emit_allowance = emit.clone();
+    let emit_tax_assets = emit.clone();
+    let assets_for_tax = assets.clone();
+    let emit_interest = emit.clone();
+    let assets_for_interest = assets.clone();
     let emit_max_fee = emit.clone();
     let emit_dividends_for = emit.clone();
     let emit_add_dividend = emit.clone();
@@ -293,12 +564,19 @@ where
                             move |ev| {
                                 set_strategy_type.set(event_target_value(&ev));
                                 set_ticker_id.set(None);
+                                set_params.set(Vec::new());
                                 emit();
                             }
                         }
                     >
-                        <option value="StaticInSingleStock">"StaticInSingleStock"</option>
-                        <option value="ReInvestInSingleStock">"ReInvestInSingleStock"</option>
+                        {strategy_types
+                            .get_value()
+                            .into_iter()
+                            .map(|t| {
+                                let name = t.name.clone();
+                                view! { <option value=name.clone()>{name}</option> }
+                            })
+                            .collect::<Vec<_>>()}
                     </select>
                 </div>
                 <button
@@ -308,37 +586,50 @@ where
                     "Remove"
                 </button>
             </div>
+            {move || {
+                selected_type().map(|t| view! { <p class="disabled-note">{t.description}</p> })
+            }}
 
-            // ── asset selector ────────────────────────────────────────────────
-            <div class="form-group">
-                <label>"Asset"</label>
-                <select
-                    prop:value=move || asset_id.get().map(|id| id.to_string()).unwrap_or_default()
-                    on:change={
-                        let emit = emit.clone();
-                        move |ev| {
-                            let val = event_target_value(&ev);
-                            set_asset_id.set(val.parse::<i32>().ok());
-                            set_ticker_id.set(None);
-                            emit();
-                        }
-                    }
-                >
-                    <option value="">"— select asset —"</option>
-                    {assets_for_select
-                        .iter()
-                        .map(|a| {
-                            let id_str = a.id.to_string();
-                            let name = a.name.clone();
-                            view! { <option value=id_str>{name}</option> }
-                        })
-                        .collect::<Vec<_>>()}
-                </select>
-            </div>
+            // ── asset selector (single asset strategies only) ─────────────────
+            {move || {
+                if has_param_kind(ParamKindView::Asset) {
+                    let emit = emit_asset.clone();
+                    let assets = assets_for_select.clone();
+                    view! {
+                        <div class="form-group">
+                            <label>"Asset"</label>
+                            <select
+                                prop:value=move || asset_id.get().map(|id| id.to_string()).unwrap_or_default()
+                                on:change={
+                                    let emit = emit.clone();
+                                    move |ev| {
+                                        let val = event_target_value(&ev);
+                                        set_asset_id.set(val.parse::<i32>().ok());
+                                        set_ticker_id.set(None);
+                                        emit();
+                                    }
+                                }
+                            >
+                                <option value="">"— select asset —"</option>
+                                {assets
+                                    .iter()
+                                    .map(|a| {
+                                        let id_str = a.id.to_string();
+                                        let name = a.name.clone();
+                                        view! { <option value=id_str>{name}</option> }
+                                    })
+                                    .collect::<Vec<_>>()}
+                            </select>
+                        </div>
+                    }.into_any()
+                } else {
+                    ().into_any()
+                }
+            }}
 
-            // ── ticker (ReInvestInSingleStock only) ───────────────────────────
+            // ── ticker (strategies reinvesting dividends only) ────────────────
             {move || {
-                if strategy_type.get() == "ReInvestInSingleStock" {
+                if has_param_kind(ParamKindView::Ticker) {
                     let emit = emit_type.clone();
                     view! {
                         <div class="form-group">
@@ -383,43 +674,119 @@ where
                 }
             }}
 
-            // ── initial position, cash, currency ──────────────────────────────
+            // ── start of the simulation: initial position or real holdings ────
             <div class="strategy-row">
                 <div class="form-group">
-                    <label>"Initial Position"</label>
-                    <input
-                        type="number"
-                        step="any"
-                        min="0"
-                        prop:value=move || initial_position.get().to_string()
-                        on:input={
-                            let emit = emit.clone();
-                            move |ev| {
-                                if let Ok(v) = event_target_value(&ev).parse::<f64>() {
-                                    set_initial_position.set(v);
-                                    emit();
-                                }
-                            }
-                        }
-                    />
-                </div>
-                <div class="form-group">
-                    <label>"Initial Cash"</label>
-                    <input
-                        type="number"
-                        step="any"
-                        prop:value=move || initial_cash.get().to_string()
-                        on:input={
+                    <label>"Start From"</label>
+                    <select
+                        prop:value=move || if start_from_accounts.get() { "accounts" } else { "initial" }
+                        on:change={
                             let emit = emit.clone();
                             move |ev| {
-                                if let Ok(v) = event_target_value(&ev).parse::<f64>() {
-                                    set_initial_cash.set(v);
-                                    emit();
-                                }
+                                set_start_from_accounts.set(event_target_value(&ev) == "accounts");
+                                emit();
                             }
                         }
-                    />
+                    >
+                        <option value="initial">"Initial Position"</option>
+                        <option value="accounts">"Real Account Holdings"</option>
+                    </select>
                 </div>
+            </div>
+
+            // ── initial position or accounts, cash, currency ──────────────────
+            <div class="strategy-row">
+                {move || {
+                    let emit = emit_start.clone();
+                    if start_from_accounts.get() {
+                        view! {
+                            <div class="form-group">
+                                <label>"Accounts"</label>
+                                <Suspense fallback=|| view! { <span>"Loading accounts…"</span> }>
+                                    <Await future=get_accounts() let:accounts>
+                                        {
+                                            let account_list = accounts.clone().unwrap_or_default();
+                                            let emit = emit.clone();
+                                            view! {
+                                                <select
+                                                    multiple
+                                                    on:change=move |ev| {
+                                                        let select = event_target::<web_sys::HtmlSelectElement>(&ev);
+                                                        let options = select.selected_options();
+                                                        let mut ids = Vec::new();
+                                                        for i in 0..options.length() {
+                                                            if let Some(opt) = options.item(i) {
+                                                                if let Ok(opt) = opt.dyn_into::<web_sys::HtmlOptionElement>() {
+                                                                    if let Ok(id) = opt.value().parse::<i32>() {
+                                                                        ids.push(id);
+                                                                    }
+                                                                }
+                                                            }
+                                                        }
+                                                        set_start_account_ids.set(ids);
+                                                        emit();
+                                                    }
+                                                >
+                                                    {account_list
+                                                        .into_iter()
+                                                        .map(|account: AccountOption| {
+                                                            let selected = start_account_ids
+                                                                .get_untracked()
+                                                                .contains(&account.id);
+                                                            view! {
+                                                                <option value=account.id selected=selected>
+                                                                    {account.display_name()}
+                                                                </option>
+                                                            }
+                                                        })
+                                                        .collect::<Vec<_>>()}
+                                                </select>
+                                            }
+                                        }
+                                    </Await>
+                                </Suspense>
+                            </div>
+                        }.into_any()
+                    } else {
+                        view! {
+                            <div class="form-group">
+                                <label>"Initial Position"</label>
+                                <input
+                                    type="number"
+                                    step="any"
+                                    min="0"
+                                    prop:value=move || initial_position.get().to_string()
+                                    on:input={
+                                        let emit = emit.clone();
+                                        move |ev| {
+                                            if let Ok(v) = event_target_value(&ev).parse::<f64>() {
+                                                set_initial_position.set(v);
+                                                emit();
+                                            }
+                                        }
+                                    }
+                                />
+                            </div>
+                            <div class="form-group">
+                                <label>"Initial Cash"</label>
+                                <input
+                                    type="number"
+                                    step="any"
+                                    prop:value=move || initial_cash.get().to_string()
+                                    on:input={
+                                        let emit = emit.clone();
+                                        move |ev| {
+                                            if let Ok(v) = event_target_value(&ev).parse::<f64>() {
+                                                set_initial_cash.set(v);
+                                                emit();
+                                            }
+                                        }
+                                    }
+                                />
+                            </div>
+                        }.into_any()
+                    }
+                }}
                 <div class="form-group">
                     <label>"Currency"</label>
                     <input
@@ -438,6 +805,168 @@ where
                 </div>
             </div>
 
+            // ── target weights (portfolio strategies only) ────────────────────
+            {move || {
+                if has_param_kind(ParamKindView::TargetWeights) {
+                    let emit = emit_targets.clone();
+                    let assets = assets.clone();
+                    view! {
+                        <fieldset class="strategy-fieldset">
+                            <legend>"Target Weights"</legend>
+                            <div class="dividends-header">
+                                <span class="dividend-col-label">"Asset"</span>
+                                <span class="dividend-col-label">"Weight (%)"</span>
+                            </div>
+                            <For
+                                each=move || {
+                                    targets
+                                        .get()
+                                        .into_iter()
+                                        .enumerate()
+                                        .map(|(idx, (asset_id, weight))| IndexedTarget { idx, asset_id, weight })
+                                        .collect::<Vec<IndexedTarget>>()
+                                }
+                                key=|item| item.idx
+                                children={
+                                    let emit = emit.clone();
+                                    let assets = assets.clone();
+                                    move |item| {
+                                        let idx = item.idx;
+                                        let emit = emit.clone();
+                                        view! {
+                                            <div class="dividend-row">
+                                                <select
+                                                    prop:value=item.asset_id.map(|id| id.to_string()).unwrap_or_default()
+                                                    on:change={
+                                                        let emit = emit.clone();
+                                                        move |ev| {
+                                                            let asset_id = event_target_value(&ev).parse::<i32>().ok();
+                                                            set_targets.update(|rows| {
+                                                                if let Some(row) = rows.get_mut(idx) {
+                                                                    row.0 = asset_id;
+                                                                }
+                                                            });
+                                                            emit();
+                                                        }
+                                                    }
+                                                >
+                                                    <option value="">"— select asset —"</option>
+                                                    {assets
+                                                        .iter()
+                                                        .map(|a| {
+                                                            let id_str = a.id.to_string();
+                                                            let name = a.name.clone();
+                                                            view! { <option value=id_str>{name}</option> }
+                                                        })
+                                                        .collect::<Vec<_>>()}
+                                                </select>
+                                                <input
+                                                    type="number"
+                                                    step="any"
+                                                    min="0"
+                                                    max="100"
+                                                    prop:value=item.weight.to_string()
+                                                    on:input={
+                                                        let emit = emit.clone();
+                                                        move |ev| {
+                                                            if let Ok(v) = event_target_value(&ev).parse::<f64>() {
+                                                                set_targets.update(|rows| {
+                                                                    if let Some(row) = rows.get_mut(idx) {
+                                                                        row.1 = v;
+                                                                    }
+                                                                });
+                                                                emit();
+                                                            }
+                                                        }
+                                                    }
+                                                />
+                                                <button
+                                                    class="button"
+                                                    on:click={
+                                                        let emit = emit.clone();
+                                                        move |_| {
+                                                            set_targets.update(|rows| {
+                                                                if rows.len() > 1 {
+                                                                    rows.remove(idx);
+                                                                }
+                                                            });
+                                                            emit();
+                                                        }
+                                                    }
+                                                >
+                                                    "−"
+                                                </button>
+                                            </div>
+                                        }
+                                    }
+                                }
+                            />
+                            <button
+                                class="button"
+                                on:click={
+                                    let emit = emit.clone();
+                                    move |_| {
+                                        set_targets.update(|rows| rows.push((None, 0.0)));
+                                        emit();
+                                    }
+                                }
+                            >
+                                "+ Add Asset"
+                            </button>
+                        </fieldset>
+                    }.into_any()
+                } else {
+                    ().into_any()
+                }
+            }}
+
+            // ── further parameters declared by the strategy ───────────────────
+            {move || {
+                let specs: Vec<ParamSpecView> = selected_type()
+                    .map(|t| t.params.into_iter().filter(|p| p.kind.is_scalar()).collect())
+                    .unwrap_or_default();
+                if specs.is_empty() {
+                    return ().into_any();
+                }
+                let current = params.get_untracked();
+                let emit = emit_params.clone();
+                view! {
+                    <fieldset class="strategy-fieldset">
+                        <legend>"Strategy Parameters"</legend>
+                        <div class="strategy-row">
+                            {specs
+                                .into_iter()
+                                .map(|spec| {
+                                    let name = spec.name.clone();
+                                    let value = current
+                                        .iter()
+                                        .find(|(n, _)| *n == name)
+                                        .map(|(_, v)| v.clone())
+                                        .or_else(|| spec.default.clone())
+                                        .unwrap_or_default();
+                                    let emit = emit.clone();
+                                    view! {
+                                        <ParamInput
+                                            spec=spec
+                                            value=value
+                                            on_change=move |v: String| {
+                                                set_params.update(|ps| {
+                                                    match ps.iter_mut().find(|(n, _)| *n == name) {
+                                                        Some(slot) => slot.1 = v,
+                                                        None => ps.push((name.clone(), v)),
+                                                    }
+                                                });
+                                                emit();
+                                            }
+                                        />
+                                    }
+                                })
+                                .collect::<Vec<_>>()}
+                        </div>
+                    </fieldset>
+                }.into_any()
+            }}
+
             // ── transaction costs ─────────────────────────────────────────────
             <fieldset class="strategy-fieldset">
                 <legend>"Transaction Costs"</legend>
@@ -556,7 +1085,238 @@ where
                 </div>
             </fieldset>
 
-            // ── dividends ─────────────────────────────────────────────────────
+            // ── tax model ─────────────────────────────────────────────────────
+            <fieldset class="strategy-fieldset">
+                <legend>"Tax Model"</legend>
+                <div class="strategy-row">
+                    <div class="form-group">
+                        <label>"Tax Model"</label>
+                        <select
+                            prop:value=move || tax_model.get()
+                            on:change={
+                                let emit = emit.clone();
+                                move |ev| {
+                                    set_tax_model.set(event_target_value(&ev));
+                                    emit();
+                                }
+                            }
+                        >
+                            <option value="Flat">"Flat tax rate"</option>
+                            <option value="German">"German capital gains tax"</option>
+                        </select>
+                    </div>
+                    {move || {
+                        if tax_model.get() == "German" {
+                            let emit = emit_allowance.clone();
+                            view! {
+                                <div class="form-group">
+                                    <label>"Allowance per Year"</label>
+                                    <input
+                                        type="number"
+                                        step="any"
+                                        min="0"
+                                        prop:value=move || allowance.get().to_string()
+                                        on:input=move |ev| {
+                                            if let Ok(v) = event_target_value(&ev).parse::<f64>() {
+                                                set_allowance.set(v);
+                                                emit();
+                                            }
+                                        }
+                                    />
+                                </div>
+                            }.into_any()
+                        } else {
+                            ().into_any()
+                        }
+                    }}
+                </div>
+                {move || {
+                    if tax_model.get() != "German" {
+                        return ().into_any();
+                    }
+                    let emit = emit_tax_assets.clone();
+                    let assets = assets_for_tax.clone();
+                    view! {
+                        <span class="disabled-note">
+                            "(assets not listed are taxed as stocks, the tax rate above is applied)"
+                        </span>
+                        <div class="dividends-header">
+                            <span class="dividend-col-label">"Asset"</span>
+                            <span class="dividend-col-label">"Tax Class"</span>
+                            <span class="dividend-col-label">"Accumulating"</span>
+                        </div>
+                        <For
+                            each=move || {
+                                tax_assets
+                                    .get()
+                                    .into_iter()
+                                    .enumerate()
+                                    .map(|(idx, (asset_id, class, accumulating))| IndexedTaxAsset {
+                                        idx,
+                                        asset_id,
+                                        class,
+                                        accumulating,
+                                    })
+                                    .collect::<Vec<IndexedTaxAsset>>()
+                            }
+                            key=|item| item.idx
+                            children={
+                                let emit = emit.clone();
+                                let assets = assets.clone();
+                                move |item| {
+                                    let idx = item.idx;
+                                    let emit = emit.clone();
+                                    view! {
+                                        <div class="dividend-row">
+                                            <select
+                                                prop:value=item.asset_id.map(|id| id.to_string()).unwrap_or_default()
+                                                on:change={
+                                                    let emit = emit.clone();
+                                                    move |ev| {
+                                                        let asset_id = event_target_value(&ev).parse::<i32>().ok();
+                                                        set_tax_assets.update(|rows| {
+                                                            if let Some(row) = rows.get_mut(idx) {
+                                                                row.0 = asset_id;
+                                                            }
+                                                        });
+                                                        emit();
+                                                    }
+                                                }
+                                            >
+                                                <option value="">"— select asset —"</option>
+                                                {assets
+                                                    .iter()
+                                                    .map(|a| {
+                                                        let id_str = a.id.to_string();
+                                                        let name = a.name.clone();
+                                                        view! { <option value=id_str>{name}</option> }
+                                                    })
+                                                    .collect::<Vec<_>>()}
+                                            </select>
+                                            <select
+                                                prop:value=item.class.clone()
+                                                on:change={
+                                                    let emit = emit.clone();
+                                                    move |ev| {
+                                                        let class = event_target_value(&ev);
+                                                        set_tax_assets.update(|rows| {
+                                                            if let Some(row) = rows.get_mut(idx) {
+                                                                row.1 = class;
+                                                            }
+                                                        });
+                                                        emit();
+                                                    }
+                                                }
+                                            >
+                                                <option value="Stock">"Stock"</option>
+                                                <option value="EquityFund">"Equity fund (30% exempt)"</option>
+                                                <option value="MixedFund">"Mixed fund (15% exempt)"</option>
+                                                <option value="RealEstateFund">"Real estate fund (60% exempt)"</option>
+                                                <option value="Other">"Other"</option>
+                                            </select>
+                                            <input
+                                                type="checkbox"
+                                                prop:checked=item.accumulating
+                                                on:change={
+                                                    let emit = emit.clone();
+                                                    move |ev| {
+                                                        let checked = event_target_checked(&ev);
+                                                        set_tax_assets.update(|rows| {
+                                                            if let Some(row) = rows.get_mut(idx) {
+                                                                row.2 = checked;
+                                                            }
+                                                        });
+                                                        emit();
+                                                    }
+                                                }
+                                            />
+                                            <button
+                                                class="button"
+                                                on:click={
+                                                    let emit = emit.clone();
+                                                    move |_| {
+                                                        set_tax_assets.update(|rows| {
+                                                            rows.remove(idx);
+                                                        });
+                                                        emit();
+                                                    }
+                                                }
+                                            >
+                                                "−"
+                                            </button>
+                                        </div>
+                                    }
+                                }
+                            }
+                        />
+                        <button
+                            class="button"
+                            on:click={
+                                let emit = emit.clone();
+                                move |_| {
+                                    set_tax_assets.update(|rows| {
+                                        rows.push((None, "EquityFund".to_string(), true));
+                                    });
+                                    emit();
+                                }
+                            }
+                        >
+                            "+ Add Asset"
+                        </button>
+                    }.into_any()
+                }}
+            </fieldset>
+
+            // ── interest on cash ──────────────────────────────────────────────
+            <fieldset class="strategy-fieldset">
+                <legend>"Cash Interest"</legend>
+                <div class="form-group form-group--inline">
+                    <label>
+                        <input
+                            type="checkbox"
+                            prop:checked=move || interest_enabled.get()
+                            on:change={
+                                let emit = emit.clone();
+                                move |ev| {
+                                    set_interest_enabled.set(event_target_checked(&ev));
+                                    emit();
+                                }
+                            }
+                        />
+                        " Book interest on cash balance"
+                    </label>
+                </div>
+                {move || {
+                    if !interest_enabled.get() {
+                        return ().into_any();
+                    }
+                    let emit_deposit = emit_interest.clone();
+                    let emit_borrowing = emit_interest.clone();
+                    view! {
+                        <InterestRateFields
+                            title="Deposit Rate"
+                            entry=deposit_rate.get_untracked()
+                            assets=assets_for_interest.clone()
+                            on_change=move |rate| {
+                                set_deposit_rate.set(rate);
+                                emit_deposit();
+                            }
+                        />
+                        <InterestRateFields
+                            title="Borrowing Rate"
+                            entry=borrowing_rate.get_untracked()
+                            assets=assets_for_interest.clone()
+                            on_change=move |rate| {
+                                set_borrowing_rate.set(rate);
+                                emit_borrowing();
+                            }
+                        />
+                    }.into_any()
+                }}
+            </fieldset>
+
+            // ── dividends (strategies with scripted dividends only) ───────────
+            <Show when=move || has_param_kind(ParamKindView::CashFlows)>
             <fieldset class="strategy-fieldset">
                 <legend>"Dividends"</legend>
                 <div class="dividends-header">
@@ -658,7 +1418,278 @@ where
                     "+ Add Dividend"
                 </button>
             </fieldset>
+            </Show>
 
         </div>
     }
 }
+
+// ── strategy parameter input ──────────────────────────────────────────────────
+
+/// Input field of a single scalar strategy parameter, as declared in the strategy registry
+#[component]
+fn ParamInput<F>(spec: ParamSpecView, value: String, on_change: F) -> impl IntoView
+where
+    F: Fn(String) + 'static + Send + Sync,
+{
+    let label = if spec.required {
+        spec.label.clone()
+    } else {
+        format!("{} (optional)", spec.label)
+    };
+    let input = match spec.kind {
+        ParamKindView::Choice(choices) => view! {
+            <select
+                prop:value=value
+                on:change=move |ev| on_change(event_target_value(&ev))
+            >
+                {choices
+                    .into_iter()
+                    .map(|c| view! { <option value=c.clone()>{c}</option> })
+                    .collect::<Vec<_>>()}
+            </select>
+        }
+        .into_any(),
+        kind => {
+            let bound = |b: Option<f64>| b.map(|b| b.to_string());
+            let (step, min, max) = match kind {
+                ParamKindView::Integer { min, max } => {
+                    ("1", min.map(|m| m.to_string()), max.map(|m| m.to_string()))
+                }
+                // Percentages are entered in %, but bounds are given as fractions
+                ParamKindView::Percent { min, max } => (
+                    "any",
+                    bound(min.map(|m| m * 100.0)),
+                    bound(max.map(|m| m * 100.0)),
+                ),
+                ParamKindView::Number { min, max } => ("any", bound(min), bound(max)),
+                _ => ("any", None, None),
+            };
+            view! {
+                <input
+                    type="number"
+                    step=step
+                    min=min
+                    max=max
+                    prop:value=value
+                    on:input=move |ev| on_change(event_target_value(&ev))
+                />
+            }
+            .into_any()
+        }
+    };
+    view! {
+        <div class="form-group" title=spec.description>
+            <label>{label}</label>
+            {input}
+        </div>
+    }
+}
+
+// ── interest rate inputs ──────────────────────────────────────────────────────
+
+/// Inputs for a fixed rate, a step schedule of rates or a reference rate plus spread
+#[component]
+fn InterestRateFields<FChange>(
+    title: &'static str,
+    entry: RateEntry,
+    assets: Vec<AssetView>,
+    on_change: FChange,
+) -> impl IntoView
+where
+    FChange: Fn(RateEntry) + 'static + Clone + Send + Sync,
+{
+    let (kind, set_kind) = signal(entry.kind.clone());
+    let (rate, set_rate) = signal(entry.rate);
+    let (schedule, set_schedule) = signal(entry.schedule.clone());
+    let (reference_asset_id, set_reference_asset_id) = signal(entry.reference_asset_id);
+    let (spread, set_spread) = signal(entry.spread);
+
+    let emit: Arc<dyn Fn() + Send + Sync> = Arc::new(move || {
+        on_change(RateEntry {
+            kind: kind.get_untracked(),
+            rate: rate.get_untracked(),
+            schedule: schedule.get_untracked(),
+            reference_asset_id: reference_asset_id.get_untracked(),
+            spread: spread.get_untracked(),
+        });
+    });
+    let emit_kind = emit.clone();
+
+    view! {
+        <div class="strategy-row">
+            <div class="form-group">
+                <label>{title}</label>
+                <select
+                    prop:value=move || kind.get()
+                    on:change={
+                        let emit = emit.clone();
+                        move |ev| {
+                            set_kind.set(event_target_value(&ev));
+                            emit();
+                        }
+                    }
+                >
+                    <option value="Fixed">"Fixed rate"</option>
+                    <option value="Schedule">"Rate schedule"</option>
+                    <option value="Reference">"Reference rate + spread"</option>
+                </select>
+            </div>
+        </div>
+        {move || {
+            let emit = emit_kind.clone();
+            match kind.get().as_str() {
+                "Schedule" => view! {
+                    <div class="dividends-header">
+                        <span class="dividend-col-label">"Valid From"</span>
+                        <span class="dividend-col-label">"Rate (%)"</span>
+                    </div>
+                    <For
+                        each=move || {
+                            schedule
+                                .get()
+                                .into_iter()
+                                .enumerate()
+                                .map(|(idx, (date, rate))| IndexedRateStep { idx, date, rate })
+                                .collect::<Vec<IndexedRateStep>>()
+                        }
+                        key=|item| item.idx
+                        children={
+                            let emit = emit.clone();
+                            move |item| {
+                                let idx = item.idx;
+                                let emit = emit.clone();
+                                view! {
+                                    <div class="dividend-row">
+                                        <input
+                                            type="date"
+                                            prop:value=item.date.clone()
+                                            on:input={
+                                                let emit = emit.clone();
+                                                move |ev| {
+                                                    let v = event_target_value(&ev);
+                                                    set_schedule.update(|rows| {
+                                                        if let Some(row) = rows.get_mut(idx) {
+                                                            row.0 = v;
+                                                        }
+                                                    });
+                                                    emit();
+                                                }
+                                            }
+                                        />
+                                        <input
+                                            type="number"
+                                            step="any"
+                                            prop:value=item.rate.to_string()
+                                            on:input={
+                                                let emit = emit.clone();
+                                                move |ev| {
+                                                    if let Ok(v) = event_target_value(&ev).parse::<f64>() {
+                                                        set_schedule.update(|rows| {
+                                                            if let Some(row) = rows.get_mut(idx) {
+                                                                row.1 = v;
+                                                            }
+                                                        });
+                                                        emit();
+                                                    }
+                                                }
+                                            }
+                                        />
+                                        <button
+                                            class="button"
+                                            on:click={
+                                                let emit = emit.clone();
+                                                move |_| {
+                                                    set_schedule.update(|rows| {
+                                                        if rows.len() > 1 {
+                                                            rows.remove(idx);
+                                                        }
+                                                    });
+                                                    emit();
+                                                }
+                                            }
+                                        >
+                                            "−"
+                                        </button>
+                                    </div>
+                                }
+                            }
+                        }
+                    />
+                    <button
+                        class="button"
+                        on:click=move |_| {
+                            set_schedule.update(|rows| {
+                                rows.push(("".to_string(), 0.0));
+                            });
+                            emit();
+                        }
+                    >
+                        "+ Add Rate"
+                    </button>
+                }.into_any(),
+                "Reference" => {
+                    let emit_spread = emit.clone();
+                    view! {
+                        <div class="strategy-row">
+                            <div class="form-group">
+                                <label>"Reference Rate Quotes (%)"</label>
+                                <select
+                                    prop:value=move || {
+                                        reference_asset_id.get().map(|id| id.to_string()).unwrap_or_default()
+                                    }
+                                    on:change=move |ev| {
+                                        set_reference_asset_id.set(event_target_value(&ev).parse::<i32>().ok());
+                                        emit();
+                                    }
+                                >
+                                    <option value="">"— select asset —"</option>
+                                    {assets
+                                        .iter()
+                                        .map(|a| {
+                                            let id_str = a.id.to_string();
+                                            let name = a.name.clone();
+                                            view! { <option value=id_str>{name}</option> }
+                                        })
+                                        .collect::<Vec<_>>()}
+                                </select>
+                            </div>
+                            <div class="form-group">
+                                <label>"Spread (%)"</label>
+                                <input
+                                    type="number"
+                                    step="any"
+                                    prop:value=move || spread.get().to_string()
+                                    on:input=move |ev| {
+                                        if let Ok(v) = event_target_value(&ev).parse::<f64>() {
+                                            set_spread.set(v);
+                                            emit_spread();
+                                        }
+                                    }
+                                />
+                            </div>
+                        </div>
+                    }.into_any()
+                }
+                _ => view! {
+                    <div class="strategy-row">
+                        <div class="form-group">
+                            <label>"Rate (%)"</label>
+                            <input
+                                type="number"
+                                step="any"
+                                prop:value=move || rate.get().to_string()
+                                on:input=move |ev| {
+                                    if let Ok(v) = event_target_value(&ev).parse::<f64>() {
+                                        set_rate.set(v);
+                                        emit();
+                                    }
+                                }
+                            />
+                        </div>
+                    </div>
+                }.into_any(),
+            }
+        }}
+    }
+}
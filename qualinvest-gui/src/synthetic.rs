@@ -0,0 +1,267 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Parameters of a synthetic price path, see `qualinvest_core::synthetic`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyntheticPathParams {
+    pub name: String,
+    /// "Gbm" | "JumpDiffusion" | "Scripted"
+    pub model: String,
+    /// Annual drift, as a fraction
+    pub drift: f64,
+    /// Annual volatility, as a fraction
+    pub volatility: f64,
+    /// Expected number of jumps per year
+    pub jump_intensity: f64,
+    /// Mean of the log jump size
+    pub jump_mean: f64,
+    /// Standard deviation of the log jump size
+    pub jump_volatility: f64,
+    /// Segments of a scripted path as 'years:return in %,...'
+    pub path: String,
+    pub start_price: f64,
+    /// As YYYY-MM-DD
+    pub start: String,
+    /// As YYYY-MM-DD
+    pub end: String,
+    pub seed: u64,
+    pub currency: String,
+}
+
+/// Generate a synthetic price path and store it as virtual asset, returns the new asset id
+#[server(CreateSyntheticPath, "/api")]
+pub async fn create_synthetic_path(params: SyntheticPathParams) -> Result<i32, ServerFnError> {
+    use crate::auth::PostgresBackend;
+    use axum_login::AuthSession;
+    use finql::datatypes::CurrencyISOCode;
+    use qualinvest_core::synthetic::{
+        parse_scripted_path, register_synthetic_path, PriceModel, SyntheticPath,
+    };
+    use time::{macros::format_description, Date};
+
+    let auth: AuthSession<PostgresBackend> = expect_context();
+    let user = auth
+        .user
+        .ok_or_else(|| ServerFnError::new("Unauthorized"))?;
+
+    // Virtual tickers are master data visible to all users
+    if !user.is_admin {
+        return Err(ServerFnError::new("Admin access required"));
+    }
+
+    let model = match params.model.as_str() {
+        "Gbm" => PriceModel::Gbm {
+            drift: params.drift,
+            volatility: params.volatility,
+        },
+        "JumpDiffusion" => PriceModel::JumpDiffusion {
+            drift: params.drift,
+            volatility: params.volatility,
+            jump_intensity: params.jump_intensity,
+            jump_mean: params.jump_mean,
+            jump_volatility: params.jump_volatility,
+        },
+        "Scripted" => PriceModel::Scripted(
+            parse_scripted_path(&params.path).map_err(|e| ServerFnError::new(e.to_string()))?,
+        ),
+        model => {
+            return Err(ServerFnError::new(format!(
+                "Unknown price model '{}'",
+                model
+            )))
+        }
+    };
+    let fmt = format_description!("[year]-[month]-[day]");
+    let parse_date = |s: &str| {
+        Date::parse(s, &fmt).map_err(|e| ServerFnError::new(format!("Invalid date '{}': {}", s, e)))
+    };
+    let path = SyntheticPath {
+        model,
+        start_price: params.start_price,
+        start: parse_date(&params.start)?,
+        end: parse_date(&params.end)?,
+        seed: params.seed,
+    };
+
+    let market = crate::db::get_market()?;
+    let currency = market
+        .get_currency(CurrencyISOCode::new(&params.currency).map_err(|e| {
+            ServerFnError::new(format!("Bad currency '{}': {}", params.currency, e))
+        })?)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Failed to resolve currency: {}", e)))?;
+    let db = crate::db::get_db()?;
+    let ticker = register_synthetic_path(&db, &params.name, currency, &path)
+        .await
+        .map_err(|e| ServerFnError::new(format!("Failed to create synthetic path: {}", e)))?;
+    Ok(ticker.asset_id)
+}
+
+/// Form to add a synthetic price path as virtual asset for simulations
+#[component]
+pub fn SyntheticPathForm<F>(on_created: F) -> impl IntoView
+where
+    F: Fn(i32) + 'static + Clone + Send + Sync,
+{
+    let (name, set_name) = signal(String::new());
+    let (model, set_model) = signal("Gbm".to_string());
+    // all rates in %
+    let (drift, set_drift) = signal(5.0);
+    let (volatility, set_volatility) = signal(20.0);
+    let (jump_intensity, set_jump_intensity) = signal(0.5);
+    let (jump_mean, set_jump_mean) = signal(-10.0);
+    let (jump_volatility, set_jump_volatility) = signal(10.0);
+    let (path, set_path) = signal("1:5,0.25:-40,2:60".to_string());
+    let (start_price, set_start_price) = signal(100.0);
+    let (start, set_start) = signal("2000-01-01".to_string());
+    let (end, set_end) = signal(String::new());
+    let (seed, set_seed) = signal(1_u64);
+    let (message, set_message) = signal::<Option<Result<String, String>>>(None);
+
+    let number_input =
+        move |label: &'static str, value: ReadSignal<f64>, set_value: WriteSignal<f64>| {
+            view! {
+                <div class="form-group">
+                    <label>{label}</label>
+                    <input
+                        type="number"
+                        step="any"
+                        prop:value=move || value.get().to_string()
+                        on:input=move |ev| {
+                            if let Ok(v) = event_target_value(&ev).parse::<f64>() {
+                                set_value.set(v);
+                            }
+                        }
+                    />
+                </div>
+            }
+        };
+
+    let create = move |_| {
+        let params = SyntheticPathParams {
+            name: name.get_untracked(),
+            model: model.get_untracked(),
+            drift: drift.get_untracked() / 100.0,
+            volatility: volatility.get_untracked() / 100.0,
+            jump_intensity: jump_intensity.get_untracked(),
+            jump_mean: jump_mean.get_untracked() / 100.0,
+            jump_volatility: jump_volatility.get_untracked() / 100.0,
+            path: path.get_untracked(),
+            start_price: start_price.get_untracked(),
+            start: start.get_untracked(),
+            end: end.get_untracked(),
+            seed: seed.get_untracked(),
+            currency: "EUR".to_string(),
+        };
+        if params.name.is_empty() || params.end.is_empty() {
+            set_message.set(Some(Err("Name and end date are required".to_string())));
+            return;
+        }
+        let on_created = on_created.clone();
+        leptos::task::spawn_local(async move {
+            let name = params.name.clone();
+            match create_synthetic_path(params).await {
+                Ok(asset_id) => {
+                    set_message.set(Some(Ok(format!("Added asset '{}'", name))));
+                    on_created(asset_id);
+                }
+                Err(e) => set_message.set(Some(Err(e.to_string()))),
+            }
+        });
+    };
+
+    view! {
+        <fieldset class="strategy-fieldset">
+            <legend>"Synthetic Price Path"</legend>
+            <div class="strategy-row">
+                <div class="form-group">
+                    <label>"Name"</label>
+                    <input
+                        type="text"
+                        prop:value=move || name.get()
+                        on:input=move |ev| set_name.set(event_target_value(&ev))
+                    />
+                </div>
+                <div class="form-group">
+                    <label>"Model"</label>
+                    <select
+                        prop:value=move || model.get()
+                        on:change=move |ev| set_model.set(event_target_value(&ev))
+                    >
+                        <option value="Gbm">"Geometric Brownian Motion"</option>
+                        <option value="JumpDiffusion">"Jump-Diffusion"</option>
+                        <option value="Scripted">"Scripted Path"</option>
+                    </select>
+                </div>
+                {number_input("Start Price", start_price, set_start_price)}
+                <div class="form-group">
+                    <label>"Start Date"</label>
+                    <input
+                        type="date"
+                        prop:value=move || start.get()
+                        on:input=move |ev| set_start.set(event_target_value(&ev))
+                    />
+                </div>
+                <div class="form-group">
+                    <label>"End Date"</label>
+                    <input
+                        type="date"
+                        prop:value=move || end.get()
+                        on:input=move |ev| set_end.set(event_target_value(&ev))
+                    />
+                </div>
+            </div>
+            {move || match model.get().as_str() {
+                "Scripted" => view! {
+                    <div class="strategy-row">
+                        <div class="form-group">
+                            <label>"Segments (years:return in %, …)"</label>
+                            <input
+                                type="text"
+                                prop:value=move || path.get()
+                                on:input=move |ev| set_path.set(event_target_value(&ev))
+                            />
+                        </div>
+                    </div>
+                }.into_any(),
+                kind => {
+                    let jumps = kind == "JumpDiffusion";
+                    view! {
+                        <div class="strategy-row">
+                            {number_input("Drift (% p.a.)", drift, set_drift)}
+                            {number_input("Volatility (% p.a.)", volatility, set_volatility)}
+                            {jumps.then(|| view! {
+                                {number_input("Jumps per Year", jump_intensity, set_jump_intensity)}
+                                {number_input("Mean Log Jump (%)", jump_mean, set_jump_mean)}
+                                {number_input("Jump Volatility (%)", jump_volatility, set_jump_volatility)}
+                            })}
+                            <div class="form-group">
+                                <label>"Seed"</label>
+                                <input
+                                    type="number"
+                                    step="1"
+                                    min="0"
+                                    prop:value=move || seed.get().to_string()
+                                    on:input=move |ev| {
+                                        if let Ok(v) = event_target_value(&ev).parse::<u64>() {
+                                            set_seed.set(v);
+                                        }
+                                    }
+                                />
+                            </div>
+                        </div>
+                    }.into_any()
+                }
+            }}
+            <button class="button" on:click=create>
+                "Add as Asset"
+            </button>
+            {move || {
+                message.get().map(|message| match message {
+                    Ok(msg) => view! { <p>{msg}</p> }.into_any(),
+                    Err(e) => view! { <p class="error">{e}</p> }.into_any(),
+                })
+            }}
+        </fieldset>
+    }
+}
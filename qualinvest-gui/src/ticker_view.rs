@@ -1,9 +1,10 @@
 use crate::auth::User;
-use crate::quotes::{delete_quotes, update_quotes};
+use crate::quotes::{delete_quotes, import_quote_csv, update_quotes, QuoteFileLayout};
 use crate::ticker::{delete_ticker, insert_ticker, update_ticker, TickerView};
 use crate::time_range::{TimeRange, TimeRangeSelector};
 use leptos::prelude::*;
 use leptos::task::spawn_local;
+use leptos::wasm_bindgen::JsCast;
 
 #[component]
 pub fn TickersTable(
@@ -102,6 +103,7 @@ pub fn TickersTable(
                                                 "Delete"
                                             </button>
                                         </div>
+                                        <QuoteUpload selected_ticker_info=selected_ticker_info />
                                     </div>
                                 }.into_any())
                             } else {
@@ -180,6 +182,82 @@ pub fn TickersTable(
     }
 }
 
+/// Upload of a CSV file with quotes of the selected ticker
+#[component]
+fn QuoteUpload(selected_ticker_info: ReadSignal<Option<(i32, String)>>) -> impl IntoView {
+    let (layout, set_layout) = signal(QuoteFileLayout::default());
+    let (message, set_message) = signal::<Option<Result<String, String>>>(None);
+
+    let load_file = move |ev: leptos::ev::Event| {
+        let file = ev
+            .target()
+            .and_then(|t| t.dyn_into::<web_sys::HtmlInputElement>().ok())
+            .and_then(|input| input.files())
+            .and_then(|files| files.get(0));
+        let ticker_id = selected_ticker_info.get_untracked().map(|(id, _)| id);
+        if let (Some(file), Some(ticker_id)) = (file, ticker_id) {
+            set_message.set(None);
+            let layout = layout.get_untracked();
+            spawn_local(async move {
+                let csv = match wasm_bindgen_futures::JsFuture::from(file.text()).await {
+                    Ok(text) => text.as_string().unwrap_or_default(),
+                    Err(_) => {
+                        set_message.set(Some(Err("Failed to read file".to_string())));
+                        return;
+                    }
+                };
+                match import_quote_csv(ticker_id, layout, csv).await {
+                    Ok(count) => {
+                        set_message.set(Some(Ok(format!("Imported {} new quotes", count))))
+                    }
+                    Err(e) => set_message.set(Some(Err(e.to_string()))),
+                }
+            });
+        }
+    };
+
+    let layout_input =
+        move |label: &'static str,
+              placeholder: &'static str,
+              field: fn(&mut QuoteFileLayout) -> &mut String| {
+            view! {
+                <input
+                    type="text"
+                    size=8
+                    title=label
+                    placeholder=placeholder
+                    on:change=move |ev| {
+                        let value = event_target_value(&ev);
+                        set_layout.update(|layout| *field(layout) = value);
+                    }
+                />
+            }
+        };
+
+    view! {
+        <div class="quote-upload">
+            <label>"Import CSV"</label>
+            {layout_input("Date column", "Date", |l| &mut l.date_column)}
+            {layout_input("Price column", "Close", |l| &mut l.price_column)}
+            {layout_input("Date format", "%Y-%m-%d", |l| &mut l.date_format)}
+            {layout_input("Delimiter", ",", |l| &mut l.delimiter)}
+            {layout_input("Decimal separator", ".", |l| &mut l.decimal_separator)}
+            <input
+                type="file"
+                accept=".csv,.txt"
+                on:change=load_file
+                disabled=move || selected_ticker_info.get().is_none()
+            />
+            {move || {
+                message.get().map(|message| match message {
+                    Ok(msg) => view! { <span>{msg}</span> }.into_any(),
+                    Err(e) => view! { <span class="error">{e}</span> }.into_any(),
+                })
+            }}
+        </div>
+    }
+}
+
 #[component]
 fn EditableTickerRow(
     row: TickerView,
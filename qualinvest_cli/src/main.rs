@@ -5,25 +5,63 @@
 //! For more information, see [qualinvest on github](https://github.com/xemwebe/qualinvest)
 //!
 
+use std::collections::HashMap;
 use std::fs;
-use std::io::{stdout, BufReader, Write};
-use std::path::PathBuf;
+use std::io::{stdin, stdout, BufReader, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
 
-use clap::{Args, Parser, Subcommand};
-use log::info;
-use time::OffsetDateTime;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use log::{error, info, warn};
+use serde::Serialize;
+use time::macros::format_description;
+use time::{Date, OffsetDateTime};
 
 use finql::datatypes::{
-    date_time_helper::offset_date_time_from_str_standard, Currency, QuoteHandler, Ticker,
-    TransactionHandler,
+    date_time_helper::{date_to_offset_date_time, offset_date_time_from_str_standard},
+    AssetHandler, CashFlow, QuoteHandler, Ticker, Transaction, TransactionHandler, TransactionType,
 };
+use finql::period_date::PeriodDate;
+use finql::portfolio::{calc_position, Position as PortfolioEntry};
 use finql::postgres::PostgresDB;
-use finql::{portfolio::calc_position, Market};
+use finql::strategy::{StockTransactionCosts, StockTransactionFee};
+use finql::time_series::TimeSeries;
+use finql::Market;
 
 use qualinvest_core::{
-    accounts::AccountHandler, performance::calc_performance, setup_market, Config,
+    accounts::{Account, AccountHandler, TransactionView},
+    backup::{export_backup, restore_backup, Backup as BackupData},
+    bank_statement::{default_statement_rules, StatementFormat, StatementRule},
+    csv_import::{
+        find_duplicate_rows, get_import_profile, get_import_profiles, import_rows,
+        save_import_profile, Column, ImportOptions, MappingProfile,
+    },
+    duplicates::FingerprintHandler,
+    inflation::{parse_cpi_csv, register_cpi_series, Deflator},
+    integrity::{apply_fix, check_integrity},
+    performance::{calc_performance, PerformanceSummary},
+    plain_text_accounting::{
+        import_journal, load_journal, parse_journal, write_journal, JournalFormat,
+    },
+    plot::{make_plot, make_png_plot},
+    portfolio_performance::{export_pp_xml, import_pp_client, parse_pp_xml},
+    position::{
+        asset_value, calculate_position_for_period_for_accounts, get_asset_classes, PositionSummary,
+    },
+    quote_import::{import_quotes, parse_quote_csv, QuoteCsvFormat, QuoteFileWatcher},
+    scheduler::Scheduler,
+    schema::{migrate, migration_status, reset_database},
+    setup_market,
+    statement_parser::{decode_input, BankStatementParser, ProfileParser, StatementParserRegistry},
+    strategy::{
+        calc_strategy_with_ledger,
+        ledger::{import_ledger, ledger_entries, write_ledger_csv},
+        ParamValues, StrategyContext, StrategyRegistry,
+    },
+    synthetic::{parse_scripted_path, register_synthetic_path, PriceModel, SyntheticPath},
+    user::UserHandler,
+    Config,
 };
 
 #[derive(Parser)]
@@ -51,17 +89,230 @@ struct Cli {
 #[derive(Subcommand)]
 #[command()]
 enum Command {
-    /// Clear all data in database
-    CleanDb,
+    CleanDb(CleanDb),
     Position(Position),
     Update(Update),
     Insert(Insert),
     FillGaps(FillGaps),
+    Check(Check),
+    /// Run the quote updates and gap filling configured in the section `scheduler` until stopped
+    Daemon,
     Performance(Performance),
+    Cpi(Cpi),
+    /// List the strategies available for simulations and their parameters
+    Strategies,
+    Simulate(Simulate),
+    Synthetic(Synthetic),
+    Import(Import),
+    ImportQuotes(ImportQuotes),
+    Duplicates(Duplicates),
+    PpImport(PpImport),
+    PpExport(PpExport),
+    JournalImport(JournalImport),
+    JournalExport(JournalExport),
+    Backup(Backup),
+    Db(Db),
+    User(UserCommand),
+    Rights(Rights),
+    Account(AccountCommand),
+    Transactions(Transactions),
     /// Create a bcrypt password hash
     HashPassword,
 }
 
+/// Clear all data in database
+#[derive(Args)]
+struct CleanDb {
+    /// Write a backup to the given file before cleaning, asked for interactively if not given
+    #[arg(long, value_name = "file")]
+    backup: Option<PathBuf>,
+    /// Clean without asking for a backup
+    #[arg(long)]
+    no_backup: bool,
+}
+
+/// Full backup of the database as versioned JSON file, which can be restored into another
+/// installation
+#[derive(Args)]
+struct Backup {
+    #[command(subcommand)]
+    action: BackupAction,
+}
+
+#[derive(Subcommand)]
+enum BackupAction {
+    /// Write all data of the database to a backup file
+    Export {
+        #[arg(index = 1)]
+        file: PathBuf,
+        /// Include the password hashes of users
+        #[arg(long)]
+        with_passwords: bool,
+    },
+    /// Restore a backup into a database without transactions, ids are reassigned
+    Import {
+        #[arg(index = 1)]
+        file: PathBuf,
+    },
+}
+
+/// Manage the database schema
+#[derive(Args)]
+struct Db {
+    #[command(subcommand)]
+    action: DbAction,
+}
+
+#[derive(Subcommand)]
+enum DbAction {
+    /// Create a new database or upgrade an existing one to the current schema
+    Migrate,
+    /// List all schema migrations and whether they have been applied
+    Status,
+}
+
+/// Manage users of the GUI
+#[derive(Args)]
+struct UserCommand {
+    #[command(subcommand)]
+    action: UserAction,
+}
+
+#[derive(Subcommand)]
+enum UserAction {
+    /// Add a new user, the password is asked for interactively
+    Add {
+        #[arg(index = 1)]
+        name: String,
+        /// Name displayed in the GUI, defaults to the user name
+        #[arg(long)]
+        display: Option<String>,
+        /// Grant administrator rights
+        #[arg(long)]
+        admin: bool,
+        /// Read the password from the first line of standard input, e.g. in scripts
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// List all users
+    List,
+    /// Set a new password, which is asked for interactively
+    SetPassword {
+        #[arg(index = 1)]
+        name: String,
+        /// Read the password from the first line of standard input, e.g. in scripts
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// Grant or withdraw administrator rights
+    SetAdmin {
+        #[arg(index = 1)]
+        name: String,
+        #[arg(index = 2, action = clap::ArgAction::Set)]
+        admin: bool,
+    },
+    /// Delete a user including its account rights and settings
+    Delete {
+        #[arg(index = 1)]
+        name: String,
+    },
+}
+
+/// Manage the accounts users have access to
+#[derive(Args)]
+struct Rights {
+    #[command(subcommand)]
+    action: RightsAction,
+}
+
+#[derive(Subcommand)]
+enum RightsAction {
+    /// Give a user access to accounts
+    Grant {
+        #[arg(index = 1)]
+        user: String,
+        /// Ids of the accounts
+        #[arg(index = 2, required = true)]
+        accounts: Vec<i32>,
+    },
+    /// Remove the access of a user to accounts
+    Revoke {
+        #[arg(index = 1)]
+        user: String,
+        /// Ids of the accounts
+        #[arg(index = 2, required = true)]
+        accounts: Vec<i32>,
+    },
+    /// List the accounts of the given user or of all users
+    List {
+        #[arg(index = 1)]
+        user: Option<String>,
+    },
+}
+
+/// Output format of lists
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum OutputFormat {
+    /// Aligned columns for reading in a terminal
+    Table,
+    Csv,
+    Json,
+}
+
+/// Manage accounts
+#[derive(Args)]
+struct AccountCommand {
+    #[command(subcommand)]
+    action: AccountAction,
+}
+
+#[derive(Subcommand)]
+enum AccountAction {
+    /// Add a new account
+    Add {
+        #[arg(index = 1)]
+        broker: String,
+        #[arg(index = 2)]
+        name: String,
+    },
+    /// List all accounts
+    List,
+    /// Change broker and/or name of an account
+    Rename {
+        #[arg(index = 1)]
+        id: i32,
+        #[arg(long)]
+        broker: Option<String>,
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Delete an account including the rights of users to access it, which fails if it still
+    /// contains transactions
+    Delete {
+        #[arg(index = 1)]
+        id: i32,
+    },
+}
+
+/// List transactions
+#[derive(Args)]
+struct Transactions {
+    /// Only transactions of the given accounts, may be repeated (default all accounts)
+    #[arg(short, long)]
+    account: Vec<i32>,
+    /// Only transactions of the given asset, given by id or name
+    #[arg(long)]
+    asset: Option<String>,
+    /// Only transactions with cash date on or after this date
+    #[arg(long)]
+    from: Option<String>,
+    /// Only transactions with cash date on or before this date
+    #[arg(long)]
+    to: Option<String>,
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+}
+
 #[derive(Args)]
 struct Hash {
     /// Input file of which to calculate hash from
@@ -72,17 +323,65 @@ struct Hash {
 /// Calculate the position per asset
 #[derive(Args)]
 struct Position {
-    /// Display output in JSON format (default is csv)
+    /// Accounts to include, may be repeated (default all accounts)
     #[arg(short, long)]
-    json: bool,
-    /// Calculate position for the given account only
+    account: Vec<i32>,
+    /// Calculate the position as of the given date (default today)
     #[arg(short, long)]
-    account: Option<i32>,
-    /// Include fields for latest quotes
+    date: Option<String>,
+    /// Reporting currency (default EUR)
+    #[arg(short, long)]
+    currency: Option<String>,
+    /// Group positions with subtotals
+    #[arg(short, long, value_enum)]
+    group_by: Option<PositionGrouping>,
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Csv)]
+    format: OutputFormat,
+    /// Display output in JSON format, same as `--format json`
+    #[arg(short, long, conflicts_with = "format")]
+    json: bool,
+    /// Include fields for latest quotes in the CSV output without grouping, the other outputs
+    /// always include them
     #[arg(short, long)]
     quote: bool,
 }
 
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum PositionGrouping {
+    Account,
+    AssetClass,
+}
+
+#[derive(Serialize)]
+struct PositionLine {
+    name: String,
+    position: f64,
+    purchase_value: f64,
+    last_quote: Option<f64>,
+    value: f64,
+    trading_pnl: f64,
+    dividend: f64,
+    interest: f64,
+    fees: f64,
+    tax: f64,
+    currency: String,
+}
+
+#[derive(Serialize)]
+struct PositionGroup {
+    name: String,
+    positions: Vec<PositionLine>,
+    totals: PositionSummary,
+}
+
+#[derive(Serialize)]
+struct PositionReport {
+    date: String,
+    currency: String,
+    groups: Vec<PositionGroup>,
+    totals: PositionSummary,
+}
+
 /// Update quotes for all tickers
 #[derive(Args)]
 struct Update {
@@ -116,12 +415,27 @@ struct FillGaps {
     min_size: Option<String>,
 }
 
+/// Check the database for inconsistencies and explain how to fix them
+#[derive(Args)]
+struct Check {
+    /// Base currency, cash transactions in other currencies require an FX ticker (default EUR)
+    #[arg(short, long)]
+    currency: Option<String>,
+    /// Apply all automatic fixes which only complete data, e.g. assign a transaction to an account
+    #[arg(long)]
+    fix: bool,
+    /// Also apply fixes which move or delete data, e.g. move transactions between accounts or
+    /// delete quotes outside the range of relevant dates
+    #[arg(long, requires = "fix")]
+    force: bool,
+}
+
 /// Calculate total performance of set of transactions
 #[derive(Args)]
 struct Performance {
-    /// Account id for performance graph
+    /// Accounts to include, may be repeated (default all accounts)
     #[arg(short, long)]
-    account: i32,
+    account: Vec<i32>,
     /// Start date for performance calculation (default 2000-01-01)
     #[arg(short, long)]
     start: Option<String>,
@@ -131,9 +445,311 @@ struct Performance {
     /// Base currency for performance calculation
     #[arg(short, long)]
     currency: Option<String>,
-    /// Output file
+    /// Output file of the time series (default total_performance.json)
     #[arg(short, long)]
     output: Option<String>,
+    /// Format of the output file (default derived from the file extension)
+    #[arg(short, long, value_enum)]
+    format: Option<ExportFormat>,
+    /// Draw a chart of the performance into an SVG or PNG file, depending on the extension
+    #[arg(long)]
+    plot: Option<PathBuf>,
+    /// Ticker id of a registered consumer price index to show the performance in real terms
+    #[arg(long)]
+    cpi: Option<i32>,
+    /// Base year of real values (default is the year of the end date)
+    #[arg(long)]
+    base_year: Option<i32>,
+}
+
+/// File format of exported time series
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum ExportFormat {
+    Json,
+    Csv,
+    /// Columns date and value, requires the feature `parquet`
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+#[derive(Serialize)]
+struct PerformanceRecord {
+    date: String,
+    value: f64,
+}
+
+/// Register a consumer price index series for inflation adjustment
+#[derive(Args)]
+struct Cpi {
+    /// Name of the price index, e.g. "HICP Euro area"
+    #[arg(required = true, index = 1)]
+    name: String,
+    /// CSV file with the index values, e.g. a Eurostat HICP download
+    #[arg(required = true, index = 2)]
+    file: PathBuf,
+    /// Currency of the price index (default EUR)
+    #[arg(short, long)]
+    currency: Option<String>,
+}
+
+/// Simulate a strategy starting with a cash deposit
+#[derive(Args)]
+struct Simulate {
+    /// Name of the strategy, see the 'strategies' command
+    #[arg(short, long)]
+    strategy: String,
+    /// Strategy parameter as 'name=value', may be given multiple times
+    #[arg(short, long, value_name = "name=value")]
+    param: Vec<String>,
+    /// Start date of the simulation (default 2000-01-01)
+    #[arg(long)]
+    start: Option<String>,
+    /// End date of the simulation (default today)
+    #[arg(long)]
+    end: Option<String>,
+    /// Initial cash deposit
+    #[arg(long, default_value_t = 10000.0)]
+    cash: f64,
+    /// Currency of the simulated portfolio (default EUR)
+    #[arg(long)]
+    currency: Option<String>,
+    /// Minimum fee per transaction
+    #[arg(long, default_value_t = 0.0)]
+    min_fee: f64,
+    /// Fee proportional to the transaction volume, as a fraction
+    #[arg(long, default_value_t = 0.0)]
+    proportional_fee: f64,
+    /// Write all simulated transactions as CSV to this file
+    #[arg(long, value_name = "file")]
+    ledger: Option<String>,
+    /// Import all simulated transactions into a new sandbox account of this name
+    #[arg(long, value_name = "account")]
+    sandbox: Option<String>,
+}
+
+/// Generate a synthetic price path and store it as virtual ticker
+#[derive(Args)]
+struct Synthetic {
+    /// Name of the virtual asset and ticker
+    #[arg(short, long)]
+    name: String,
+    /// Price model: 'gbm', 'jump-diffusion' or 'scripted'
+    #[arg(short, long, default_value = "gbm")]
+    model: String,
+    /// Annual drift, as a fraction
+    #[arg(long, default_value_t = 0.05)]
+    drift: f64,
+    /// Annual volatility, as a fraction
+    #[arg(long, default_value_t = 0.2)]
+    volatility: f64,
+    /// Expected number of jumps per year (jump-diffusion only)
+    #[arg(long, default_value_t = 0.5)]
+    jump_intensity: f64,
+    /// Mean of the log jump size (jump-diffusion only)
+    #[arg(long, default_value_t = -0.1)]
+    jump_mean: f64,
+    /// Standard deviation of the log jump size (jump-diffusion only)
+    #[arg(long, default_value_t = 0.1)]
+    jump_volatility: f64,
+    /// Segments of a scripted path as 'years:return in %,...', e.g. "1:5,0.25:-40,2:60"
+    #[arg(long)]
+    path: Option<String>,
+    /// Price at the start date
+    #[arg(long, default_value_t = 100.0)]
+    start_price: f64,
+    /// Start date of the path (default 2000-01-01)
+    #[arg(long)]
+    start: Option<String>,
+    /// End date of the path (default today)
+    #[arg(long)]
+    end: Option<String>,
+    /// Seed of the random number generator
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+    /// Currency of the virtual ticker (default EUR)
+    #[arg(long)]
+    currency: Option<String>,
+}
+
+/// Import transactions from a broker or bank export or from a CSV file using a column mapping
+/// profile
+#[derive(Args)]
+struct Import {
+    /// File to be imported
+    #[arg(index = 1)]
+    file: Option<PathBuf>,
+    /// Name of the statement parser or mapping profile describing the file layout, detected
+    /// from the content of the file if not given
+    #[arg(short, long)]
+    profile: Option<String>,
+    /// JSON file with a list of rules mapping bank statement entries to transaction types,
+    /// applied before the default rules
+    #[arg(long, value_name = "file")]
+    rules: Option<PathBuf>,
+    /// Id of the account the transactions are added to
+    #[arg(short, long)]
+    account: Option<i32>,
+    /// Only show the parsed transactions and errors, without writing to the database
+    #[arg(long)]
+    preview: bool,
+    /// Create assets not yet in the database instead of aborting the import
+    #[arg(long)]
+    create_assets: bool,
+    /// Import all valid rows even if other rows could not be parsed
+    #[arg(long)]
+    ignore_errors: bool,
+    /// Import rows even if they have been imported into the account before
+    #[arg(long)]
+    allow_duplicates: bool,
+    /// Store the mapping profile given as JSON file, replacing a profile of the same name
+    #[arg(long, value_name = "file")]
+    add_profile: Option<PathBuf>,
+    /// List all statement parsers and the names of all stored mapping profiles
+    #[arg(long)]
+    list_profiles: bool,
+}
+
+/// Import the quote history of a ticker from a CSV file. The layout of the file is taken from
+/// the section `market_data.quote_file_format` of the configuration, single settings can be
+/// overridden by the options below.
+#[derive(Args)]
+struct ImportQuotes {
+    /// Id of the ticker the quotes belong to
+    #[arg(index = 1)]
+    ticker: i32,
+    /// File to be imported
+    #[arg(index = 2)]
+    file: PathBuf,
+    /// JSON file describing the layout of the file, replaces the configured layout
+    #[arg(long, value_name = "file")]
+    format: Option<PathBuf>,
+    /// Field delimiter
+    #[arg(long)]
+    delimiter: Option<char>,
+    /// Number of lines to skip before the header line
+    #[arg(long)]
+    skip_lines: Option<usize>,
+    /// The file has no header line, columns must be given by position
+    #[arg(long)]
+    no_headers: bool,
+    /// Name or position (starting at 0) of the date column
+    #[arg(long)]
+    date_column: Option<String>,
+    /// Name or position (starting at 0) of the price column
+    #[arg(long)]
+    price_column: Option<String>,
+    /// Name or position (starting at 0) of the volume column
+    #[arg(long)]
+    volume_column: Option<String>,
+    /// Format of dates as used by `strftime`, e.g. "%d.%m.%Y"
+    #[arg(long)]
+    date_format: Option<String>,
+    #[arg(long)]
+    decimal_separator: Option<char>,
+    #[arg(long)]
+    thousands_separator: Option<char>,
+    /// Only show the parsed quotes and errors, without writing to the database
+    #[arg(long)]
+    preview: bool,
+}
+
+impl ImportQuotes {
+    /// Layout of the file given by the options
+    fn quote_format(&self, default: &QuoteCsvFormat) -> QuoteCsvFormat {
+        let mut format = match &self.format {
+            Some(file) => serde_json::from_str(&fs::read_to_string(file).unwrap()).unwrap(),
+            None => default.clone(),
+        };
+        let column = |text: &String| match text.parse() {
+            Ok(index) => Column::Index(index),
+            Err(_) => Column::Name(text.clone()),
+        };
+        if let Some(delimiter) = self.delimiter {
+            format.delimiter = delimiter;
+        }
+        if let Some(skip_lines) = self.skip_lines {
+            format.skip_lines = skip_lines;
+        }
+        if self.no_headers {
+            format.has_headers = false;
+        }
+        if let Some(date) = &self.date_column {
+            format.date = column(date);
+        }
+        if let Some(price) = &self.price_column {
+            format.price = column(price);
+        }
+        if let Some(volume) = &self.volume_column {
+            format.volume = Some(column(volume));
+        }
+        if let Some(date_format) = &self.date_format {
+            format.date_format = date_format.clone();
+        }
+        if let Some(decimal_separator) = self.decimal_separator {
+            format.decimal_separator = decimal_separator;
+        }
+        if self.thousands_separator.is_some() {
+            format.thousands_separator = self.thousands_separator;
+        }
+        format
+    }
+}
+
+/// List suspected duplicate transactions, i.e. transactions of the same account agreeing in
+/// date, type, asset, position and amount
+#[derive(Args)]
+struct Duplicates {
+    /// Check the given account only
+    #[arg(short, long)]
+    account: Option<i32>,
+}
+
+/// Import securities, prices, accounts and transactions from a Portfolio Performance XML file
+#[derive(Args)]
+struct PpImport {
+    /// Portfolio Performance file, saved as XML
+    #[arg(required = true, index = 1)]
+    file: PathBuf,
+}
+
+/// Export accounts with their transactions, assets and quotes as Portfolio Performance XML file
+#[derive(Args)]
+struct PpExport {
+    /// Output file
+    #[arg(required = true, index = 1)]
+    file: PathBuf,
+    /// Export the given accounts only (default is all accounts)
+    #[arg(short, long)]
+    account: Vec<i32>,
+    /// Base currency of the Portfolio Performance file
+    #[arg(short, long, default_value = "EUR")]
+    currency: String,
+}
+
+/// Import accounts, transactions, commodities and prices from a beancount or ledger journal
+/// written by `journal-export`
+#[derive(Args)]
+struct JournalImport {
+    /// Journal file
+    #[arg(required = true, index = 1)]
+    file: PathBuf,
+}
+
+/// Export accounts, transactions, assets and quotes as plain-text accounting journal
+#[derive(Args)]
+struct JournalExport {
+    /// Output file
+    #[arg(required = true, index = 1)]
+    file: PathBuf,
+    /// Journal format, either beancount or ledger (also readable by hledger)
+    #[arg(short, long, default_value = "beancount")]
+    format: JournalFormat,
+    /// Export the given accounts only (default is all accounts)
+    #[arg(short, long)]
+    account: Vec<i32>,
+    /// Operating currency of the journal
+    #[arg(short, long, default_value = "EUR")]
+    currency: String,
 }
 
 /// Upload missing pdf to database
@@ -171,47 +787,184 @@ async fn main() {
     let market = Market::new(db.clone()).await;
 
     match args.command {
-        Command::CleanDb => {
+        Command::CleanDb(args) => {
+            let backup_file = match args.backup {
+                Some(file) => Some(file),
+                None if args.no_backup => None,
+                None => {
+                    print!("All data will be deleted. Write a backup first to file (leave empty to skip): ");
+                    stdout().flush().unwrap();
+                    let mut file = String::new();
+                    stdin().read_line(&mut file).unwrap();
+                    Some(file.trim().to_string())
+                        .filter(|f| !f.is_empty())
+                        .map(PathBuf::from)
+                }
+            };
+            if let Some(file) = backup_file {
+                migrate(db.as_ref()).await.unwrap();
+                let backup = export_backup(db.as_ref(), true).await.unwrap();
+                fs::write(&file, serde_json::to_string(&backup).unwrap()).unwrap();
+                println!("Backup written to {}", file.display());
+            }
             print!("Cleaning database...");
-            db.clean_accounts().await.unwrap();
-            db.clean().await.unwrap();
-            db.init_accounts().await.unwrap();
+            reset_database(db.as_ref()).await.unwrap();
             println!("done");
         }
         Command::Position(args) => {
-            let currency = Currency::from_str("EUR").unwrap();
-            let account_id = args.account;
-            let transactions = match account_id {
-                Some(account_id) => db
-                    .get_all_transactions_with_account(account_id)
-                    .await
-                    .unwrap(),
-                None => db.get_all_transactions().await.unwrap(),
-            };
-            let market = Market::new(db).await;
-            let mut position = calc_position(currency, &transactions, None, market.clone())
-                .await
-                .unwrap();
-            position
-                .get_asset_names(market.db().into_arc_dispatch())
+            let currency = market
+                .get_currency_from_str(args.currency.as_deref().unwrap_or("EUR"))
                 .await
-                .unwrap();
-
-            if args.quote {
-                let time =
-                    OffsetDateTime::now_local().expect("Indeterminate local time zone offset");
-                position.add_quote(time, &market).await;
-            }
-
-            if args.json {
-                println!("{}", serde_json::to_string(&position).unwrap());
+                .expect("Currency not found");
+            let account_ids = if args.account.is_empty() {
+                db.get_all_account_ids().await.unwrap()
+            } else {
+                args.account
+            };
+            let now = OffsetDateTime::now_local().expect("Indeterminate local time zone offset");
+            let date = match &args.date {
+                Some(date) => offset_date_time_from_str_standard(date, 9, None)
+                    .unwrap()
+                    .date(),
+                None => now.date(),
+            };
+            let format = if args.json {
+                OutputFormat::Json
             } else {
+                args.format
+            };
+
+            // Without grouping, the CSV output lists the positions as they are, with quotes only
+            // on request
+            if args.group_by.is_none() && format == OutputFormat::Csv {
+                let transactions = db
+                    .get_transactions_before_time(&account_ids, date)
+                    .await
+                    .unwrap();
+                let mut position = calc_position(currency, &transactions, None, market.clone())
+                    .await
+                    .unwrap();
+                position
+                    .get_asset_names(market.db().into_arc_dispatch())
+                    .await
+                    .unwrap();
+                if args.quote {
+                    let time = match args.date {
+                        Some(_) => date_to_offset_date_time(&date, 20, None).unwrap(),
+                        None => now,
+                    };
+                    position.add_quote(time, &market).await;
+                }
                 let mut wtr = csv::Writer::from_writer(stdout());
                 wtr.serialize(position.cash).unwrap();
                 for (_, pos) in position.assets {
                     wtr.serialize(pos).unwrap();
                 }
                 wtr.flush().unwrap();
+                return;
+            }
+
+            // Accounts are calculated separately only if grouped by account
+            let account_groups: Vec<(String, Vec<i32>)> =
+                if args.group_by == Some(PositionGrouping::Account) {
+                    let accounts = db.get_all_accounts().await;
+                    account_ids
+                        .iter()
+                        .map(|id| {
+                            let name = accounts
+                                .iter()
+                                .find(|a| a.id == Some(*id))
+                                .map(|a| format!("{} {}", a.broker, a.account_name))
+                                .unwrap_or_else(|| format!("Account {}", id));
+                            (name, vec![*id])
+                        })
+                        .collect()
+                } else {
+                    vec![("All".to_string(), account_ids)]
+                };
+            let asset_classes = if args.group_by == Some(PositionGrouping::AssetClass) {
+                get_asset_classes(db.as_ref()).await.unwrap()
+            } else {
+                HashMap::new()
+            };
+
+            let mut groups: Vec<PositionGroup> = Vec::new();
+            for (name, ids) in account_groups {
+                let (portfolio, _) = calculate_position_for_period_for_accounts(
+                    currency,
+                    &ids,
+                    PeriodDate::Inception,
+                    PeriodDate::FixedDate(date),
+                    db.clone(),
+                )
+                .await
+                .unwrap();
+                let mut cash = PositionGroup {
+                    name: "cash".to_string(),
+                    positions: vec![position_line(
+                        "Cash",
+                        &portfolio.cash,
+                        portfolio.cash.position,
+                    )],
+                    totals: PositionSummary::default(),
+                };
+                cash.totals.add_cash(&portfolio.cash);
+                let mut class_groups: Vec<PositionGroup> = Vec::new();
+                for (asset_id, pos) in &portfolio.assets {
+                    let group_name = asset_classes
+                        .get(asset_id)
+                        .cloned()
+                        .unwrap_or_else(|| "other".to_string());
+                    let index = match class_groups.iter().position(|g| g.name == group_name) {
+                        Some(index) => index,
+                        None => {
+                            class_groups.push(PositionGroup {
+                                name: group_name,
+                                positions: Vec::new(),
+                                totals: PositionSummary::default(),
+                            });
+                            class_groups.len() - 1
+                        }
+                    };
+                    let group = &mut class_groups[index];
+                    group
+                        .positions
+                        .push(position_line(&pos.name, pos, asset_value(pos)));
+                    group.totals.add_asset(pos);
+                }
+                if args.group_by == Some(PositionGrouping::AssetClass) {
+                    groups.push(cash);
+                    groups.extend(class_groups);
+                } else {
+                    // Cash is listed first within the group of the account
+                    let mut group = PositionGroup {
+                        name,
+                        positions: cash.positions,
+                        totals: cash.totals,
+                    };
+                    for class_group in class_groups {
+                        group.positions.extend(class_group.positions);
+                        group.totals.add(&class_group.totals);
+                    }
+                    groups.push(group);
+                }
+            }
+            let mut totals = PositionSummary::default();
+            for group in &groups {
+                totals.add(&group.totals);
+            }
+            let report = PositionReport {
+                date: date.to_string(),
+                currency: currency.iso_code.to_string(),
+                groups,
+                totals,
+            };
+
+            match format {
+                OutputFormat::Json => {
+                    println!("{}", serde_json::to_string_pretty(&report).unwrap())
+                }
+                format => print_position_report(format, &report, args.group_by.is_some()),
             }
         }
         Command::Update(args) => {
@@ -255,12 +1008,99 @@ async fn main() {
                 1
             };
             let mut market = setup_market(db.clone(), &config.market_data).await;
-            qualinvest_core::fill_quote_gaps(&mut market, min_size)
+            qualinvest_core::fill_quote_gaps_with_progress(
+                &mut market,
+                min_size,
+                |count, total| println!("{} of {} tickers checked", count, total),
+            )
+            .await
+            .unwrap();
+        }
+        Command::Check(args) => {
+            let issues = check_integrity(db.as_ref(), args.currency.as_deref().unwrap_or("EUR"))
+                .await
+                .unwrap();
+            let mut pending = 0;
+            for issue in &issues {
+                println!("[{}] {}", issue.kind, issue.message);
+                println!("    {}", issue.hint);
+                if let Some(fix) = &issue.fix {
+                    if args.fix && (args.force || !fix.requires_confirmation()) {
+                        apply_fix(db.as_ref(), fix).await.unwrap();
+                        println!("    Fixed: {}", fix);
+                    } else {
+                        pending += 1;
+                        println!("    Automatic fix: {}", fix);
+                    }
+                }
+            }
+            println!("{} issues found", issues.len());
+            if pending > 0 {
+                println!(
+                    "{} of them can be fixed automatically with `check --fix`, \
+                    fixes moving or deleting data require `--force` in addition",
+                    pending
+                );
+            }
+        }
+        Command::Daemon => {
+            let market = setup_market(db.clone(), &config.market_data).await;
+            let mut scheduler = Scheduler::new(config.scheduler.clone(), db.clone(), market)
                 .await
                 .unwrap();
+            let mut watcher = config
+                .market_data
+                .quote_directory
+                .as_ref()
+                .map(|directory| {
+                    QuoteFileWatcher::new(
+                        directory.into(),
+                        config.market_data.quote_file_format.clone(),
+                    )
+                });
+            info!("Scheduler started");
+            loop {
+                if let Some(watcher) = &mut watcher {
+                    match watcher.scan(db.as_ref()).await {
+                        Ok(imports) => {
+                            for import in imports {
+                                info!(
+                                    "Imported {} new quotes for ticker '{}' from {}",
+                                    import.new_quotes,
+                                    import.ticker,
+                                    import.path.display()
+                                );
+                                for e in import.errors {
+                                    warn!("{}: {}", import.path.display(), e);
+                                }
+                            }
+                        }
+                        Err(e) => error!("Scanning quote files failed: {}", e),
+                    }
+                }
+                let now =
+                    OffsetDateTime::now_local().expect("Indeterminate local time zone offset");
+                match scheduler.run_due_tasks(now).await {
+                    Ok(job_ids) => {
+                        for job_id in job_ids {
+                            info!("Scheduled job {} done", job_id);
+                        }
+                    }
+                    Err(e) => error!("Scheduled run failed: {}", e),
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(scheduler.check_interval()) => {}
+                    _ = tokio::signal::ctrl_c() => break,
+                }
+            }
+            info!("Scheduler stopped");
         }
         Command::Performance(args) => {
-            let account_id = args.account;
+            let account_ids = if args.account.is_empty() {
+                db.get_all_account_ids().await.unwrap()
+            } else {
+                args.account
+            };
 
             let start_date = if let Some(start) = args.start {
                 offset_date_time_from_str_standard(&start, 9, None)
@@ -294,14 +1134,23 @@ async fn main() {
                     .await
                     .expect("Currency not found")
             };
-            let file_name = if let Some(ref output) = args.output {
-                output.to_string()
-            } else {
-                "total_performance.json".to_string()
-            };
+            let file_name = args
+                .output
+                .unwrap_or_else(|| "total_performance.json".to_string());
+            let format = args.format.unwrap_or_else(|| {
+                match Path::new(&file_name)
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                {
+                    Some("csv") => ExportFormat::Csv,
+                    #[cfg(feature = "parquet")]
+                    Some("parquet") => ExportFormat::Parquet,
+                    _ => ExportFormat::Json,
+                }
+            });
 
             let transactions = db
-                .get_all_transactions_with_account_before(account_id, end_date)
+                .get_transactions_before_time(&account_ids, end_date)
                 .await
                 .unwrap();
 
@@ -315,8 +1164,757 @@ async fn main() {
             )
             .await
             .unwrap();
-            let mut file = fs::File::create(file_name).unwrap();
-            write!(file, "{:?}", total_performance).unwrap();
+            let total_performance = if let Some(cpi) = args.cpi {
+                let base_year = args.base_year.unwrap_or(end_date.year());
+                let deflator = Deflator::from_ticker(db.as_ref(), cpi, base_year)
+                    .await
+                    .unwrap();
+                deflator.deflate_series(&total_performance)
+            } else {
+                total_performance
+            };
+
+            match PerformanceSummary::from_series(&total_performance) {
+                Some(summary) => {
+                    println!("Period:        {} - {}", summary.start, summary.end);
+                    println!("Start value:   {:>14.2}", summary.start_value);
+                    println!("End value:     {:>14.2}", summary.end_value);
+                    match summary.relative_change() {
+                        Some(relative) => println!(
+                            "Change:        {:>14.2} ({:.2}%)",
+                            summary.change(),
+                            relative * 100.0
+                        ),
+                        None => println!("Change:        {:>14.2}", summary.change()),
+                    }
+                    println!("Minimum value: {:>14.2}", summary.min_value);
+                    println!("Maximum value: {:>14.2}", summary.max_value);
+                    println!("Max. drawdown: {:>13.2}%", summary.max_drawdown * 100.0);
+                }
+                None => println!("No performance data in the given period"),
+            }
+
+            let file = fs::File::create(&file_name).unwrap();
+            match format {
+                ExportFormat::Json => {
+                    let records: Vec<PerformanceRecord> = total_performance
+                        .iter()
+                        .map(|tv| PerformanceRecord {
+                            date: tv.time.date().to_string(),
+                            value: tv.value,
+                        })
+                        .collect();
+                    serde_json::to_writer_pretty(file, &records).unwrap();
+                }
+                ExportFormat::Csv => {
+                    let mut writer = csv::Writer::from_writer(file);
+                    for tv in &total_performance {
+                        writer
+                            .serialize(PerformanceRecord {
+                                date: tv.time.date().to_string(),
+                                value: tv.value,
+                            })
+                            .unwrap();
+                    }
+                    writer.flush().unwrap();
+                }
+                #[cfg(feature = "parquet")]
+                ExportFormat::Parquet => write_parquet(file, &total_performance),
+            }
+
+            if let Some(plot) = args.plot {
+                let title = if args.cpi.is_some() {
+                    "Portfolio Value (real)"
+                } else {
+                    "Portfolio Value"
+                };
+                let time_series = [TimeSeries {
+                    title: title.to_string(),
+                    series: total_performance,
+                }];
+                if plot.extension().and_then(|ext| ext.to_str()) == Some("png") {
+                    make_png_plot(&plot, "Performance", &time_series).unwrap();
+                } else {
+                    fs::write(&plot, make_plot("Performance", &time_series).unwrap()).unwrap();
+                }
+            }
+        }
+        Command::Cpi(args) => {
+            let currency = market
+                .get_currency_from_str(args.currency.as_deref().unwrap_or("EUR"))
+                .await
+                .expect("Currency not found");
+            let file = fs::File::open(args.file).unwrap();
+            let values = parse_cpi_csv(BufReader::new(file)).unwrap();
+            let ticker_id = register_cpi_series(db.as_ref(), &args.name, currency, &values)
+                .await
+                .unwrap();
+            println!(
+                "Registered price index '{}' with {} values as ticker {}",
+                args.name,
+                values.len(),
+                ticker_id
+            );
+        }
+        Command::Strategies => {
+            for strategy in StrategyRegistry::default().strategies() {
+                println!("{}: {}", strategy.name, strategy.description);
+                for param in &strategy.params {
+                    let default = match param.default {
+                        Some(default) => format!(", default {}", default),
+                        None if param.required => ", required".to_string(),
+                        None => String::new(),
+                    };
+                    println!(
+                        "    {} ({:?}{}): {}",
+                        param.name, param.kind, default, param.description
+                    );
+                }
+            }
+        }
+        Command::Simulate(args) => {
+            let start_date = offset_date_time_from_str_standard(
+                args.start.as_deref().unwrap_or("2000-01-01"),
+                9,
+                None,
+            )
+            .unwrap()
+            .date();
+            let end_date = if let Some(end) = args.end {
+                offset_date_time_from_str_standard(&end, 9, None)
+                    .unwrap()
+                    .date()
+            } else {
+                OffsetDateTime::now_local()
+                    .expect("Indeterminate local time zone offset")
+                    .date()
+            };
+            let market = Market::new_with_date_range(db.clone(), start_date, end_date)
+                .await
+                .unwrap();
+            let currency = market
+                .get_currency_from_str(args.currency.as_deref().unwrap_or("EUR"))
+                .await
+                .expect("Currency not found");
+
+            let registry = StrategyRegistry::default();
+            let strategy = registry
+                .get(&args.strategy)
+                .unwrap_or_else(|| panic!("Unknown strategy '{}'", args.strategy));
+            let mut values = ParamValues::new();
+            for param in &args.param {
+                let (name, text) = param.split_once('=').unwrap_or_else(|| {
+                    panic!("Parameter '{}' is not of the form name=value", param)
+                });
+                let spec = strategy
+                    .params
+                    .iter()
+                    .find(|p| p.name == name.trim())
+                    .unwrap_or_else(|| panic!("Unknown parameter '{}'", name));
+                if let Some(value) = spec.parse(text.trim()).unwrap() {
+                    values.insert(spec.name, value);
+                }
+            }
+            let context = StrategyContext {
+                currency,
+                costs: StockTransactionCosts {
+                    fee: StockTransactionFee::new(args.min_fee, None, args.proportional_fee),
+                    tax_rate: 0.0,
+                },
+                market: market.clone(),
+            };
+            let strategy = registry.build(&args.strategy, &values, context).unwrap();
+
+            let deposit = Transaction {
+                id: None,
+                transaction_type: TransactionType::Cash,
+                cash_flow: CashFlow::new(args.cash, currency, start_date),
+                note: Some("initial cash".to_string()),
+            };
+            let (series, transactions) = calc_strategy_with_ledger(
+                currency,
+                &[deposit],
+                strategy,
+                start_date,
+                end_date,
+                market.clone(),
+            )
+            .await;
+            for item in series {
+                println!("{},{}", item.time.date(), item.value);
+            }
+
+            if args.ledger.is_some() || args.sandbox.is_some() {
+                let asset_names = db
+                    .get_asset_list()
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|a| (a.id, a.name))
+                    .collect();
+                let entries = ledger_entries(&transactions, &asset_names);
+                if let Some(file_name) = args.ledger {
+                    let file = fs::File::create(file_name).unwrap();
+                    write_ledger_csv(file, &entries).unwrap();
+                }
+                if let Some(account_name) = args.sandbox {
+                    let account_id = import_ledger(db.as_ref(), &market, &account_name, &entries)
+                        .await
+                        .unwrap();
+                    println!(
+                        "Imported {} transactions into account {}",
+                        entries.len(),
+                        account_id
+                    );
+                }
+            }
+        }
+        Command::Synthetic(args) => {
+            let model = match args.model.as_str() {
+                "gbm" => PriceModel::Gbm {
+                    drift: args.drift,
+                    volatility: args.volatility,
+                },
+                "jump-diffusion" => PriceModel::JumpDiffusion {
+                    drift: args.drift,
+                    volatility: args.volatility,
+                    jump_intensity: args.jump_intensity,
+                    jump_mean: args.jump_mean,
+                    jump_volatility: args.jump_volatility,
+                },
+                "scripted" => PriceModel::Scripted(
+                    parse_scripted_path(
+                        args.path
+                            .as_deref()
+                            .expect("A scripted path requires the path segments"),
+                    )
+                    .unwrap(),
+                ),
+                model => panic!("Unknown price model '{}'", model),
+            };
+            let start = offset_date_time_from_str_standard(
+                args.start.as_deref().unwrap_or("2000-01-01"),
+                9,
+                None,
+            )
+            .unwrap()
+            .date();
+            let end = if let Some(end) = args.end {
+                offset_date_time_from_str_standard(&end, 9, None)
+                    .unwrap()
+                    .date()
+            } else {
+                OffsetDateTime::now_local()
+                    .expect("Indeterminate local time zone offset")
+                    .date()
+            };
+            let currency = market
+                .get_currency_from_str(args.currency.as_deref().unwrap_or("EUR"))
+                .await
+                .expect("Currency not found");
+            let path = SyntheticPath {
+                model,
+                start_price: args.start_price,
+                start,
+                end,
+                seed: args.seed,
+            };
+            let ticker = register_synthetic_path(db.as_ref(), &args.name, currency, &path)
+                .await
+                .unwrap();
+            println!(
+                "Registered synthetic price path '{}' as asset {} with ticker {}",
+                ticker.name, ticker.asset_id, ticker.ticker_id
+            );
+        }
+        Command::Import(args) => {
+            if let Some(profile_file) = args.add_profile {
+                let profile: MappingProfile =
+                    serde_json::from_str(&fs::read_to_string(profile_file).unwrap()).unwrap();
+                save_import_profile(db.as_ref(), &profile).await.unwrap();
+                println!("Stored mapping profile '{}'", profile.name);
+            }
+            let mut registry = StatementParserRegistry::default();
+            if let Some(rules) = args.rules {
+                let mut rules: Vec<StatementRule> =
+                    serde_json::from_str(&fs::read_to_string(rules).unwrap()).unwrap();
+                rules.extend(default_statement_rules());
+                for format in [StatementFormat::Camt053, StatementFormat::Mt940].iter() {
+                    registry.register(Box::new(BankStatementParser::with_rules(
+                        *format,
+                        rules.clone(),
+                    )));
+                }
+            }
+            if args.list_profiles {
+                for parser in registry.parsers() {
+                    println!("{}: {}", parser.name(), parser.description());
+                }
+                for profile in get_import_profiles(db.as_ref()).await {
+                    println!("{}", profile.name);
+                }
+            }
+            if let Some(file) = args.file {
+                let text = decode_input(&fs::read(file).unwrap());
+                let parser = match args.profile {
+                    Some(name) => {
+                        if registry.get(&name).is_none() {
+                            let profile = get_import_profile(db.as_ref(), &name).await.unwrap();
+                            registry.register(Box::new(ProfileParser(profile)));
+                        }
+                        registry.get(&name).unwrap()
+                    }
+                    None => registry
+                        .detect(&text)
+                        .expect("Unknown file format, a mapping profile is required"),
+                };
+                println!("Reading file as {}", parser.name());
+                let preview = parser.parse(&text).unwrap();
+                let source = parser.name().to_string();
+                migrate(db.as_ref()).await.unwrap();
+                let duplicates = match args.account {
+                    Some(account_id) => {
+                        find_duplicate_rows(db.as_ref(), account_id, &source, &preview.rows)
+                            .await
+                            .unwrap()
+                    }
+                    None => vec![false; preview.rows.len()],
+                };
+                for (row, duplicate) in preview.rows.iter().zip(&duplicates) {
+                    println!(
+                        "{},{},{},{},{},{},{},{}{}",
+                        row.date,
+                        row.kind,
+                        row.asset_description().unwrap_or_default(),
+                        row.position.map(|p| p.to_string()).unwrap_or_default(),
+                        row.amount,
+                        row.currency,
+                        row.fee,
+                        row.tax,
+                        if *duplicate { ",duplicate" } else { "" }
+                    );
+                }
+                for error in &preview.errors {
+                    eprintln!("Error in {}", error);
+                }
+                println!(
+                    "{} rows parsed, {} skipped, {} errors, {} already imported",
+                    preview.rows.len(),
+                    preview.skipped,
+                    preview.errors.len(),
+                    duplicates.iter().filter(|d| **d).count()
+                );
+                if args.preview {
+                    return;
+                }
+                if !preview.errors.is_empty() && !args.ignore_errors {
+                    eprintln!("Nothing imported, fix the errors or use --ignore-errors");
+                    return;
+                }
+                let account_id = args.account.expect("An account id is required");
+                let options = ImportOptions {
+                    source,
+                    create_missing_assets: args.create_assets,
+                    skip_duplicates: !args.allow_duplicates,
+                };
+                let report = import_rows(db.as_ref(), &market, &preview.rows, account_id, &options)
+                    .await
+                    .unwrap();
+                for asset in &report.created_assets {
+                    println!("Created asset '{}'", asset);
+                }
+                for line in &report.duplicates {
+                    println!("Skipped line {}, already imported", line);
+                }
+                println!(
+                    "Imported {} transactions into account {}",
+                    report.transaction_ids.len(),
+                    account_id
+                );
+            }
+        }
+        Command::ImportQuotes(args) => {
+            let format = args.quote_format(&config.market_data.quote_file_format);
+            let text = decode_input(&fs::read(&args.file).unwrap());
+            let preview = parse_quote_csv(&format, &text).unwrap();
+            for error in &preview.errors {
+                println!("Skipped {}", error);
+            }
+            if args.preview {
+                for quote in &preview.quotes {
+                    match quote.volume {
+                        Some(volume) => println!("{}\t{}\t{}", quote.date, quote.price, volume),
+                        None => println!("{}\t{}", quote.date, quote.price),
+                    }
+                }
+            } else {
+                let ticker = db.get_ticker_by_id(args.ticker).await.unwrap();
+                let count = import_quotes(db.as_ref(), &ticker, &preview.quotes)
+                    .await
+                    .unwrap();
+                println!(
+                    "Imported {} new quotes for ticker '{}', {} already stored",
+                    count,
+                    ticker.name,
+                    preview.quotes.len() - count
+                );
+            }
+        }
+        Command::Duplicates(args) => {
+            let account_ids = match args.account {
+                Some(account_id) => vec![account_id],
+                None => db.get_all_account_ids().await.unwrap(),
+            };
+            let groups = db.find_duplicate_transactions(&account_ids).await.unwrap();
+            let mut account_id = None;
+            for group in &groups {
+                if account_id != Some(group.account_id) {
+                    account_id = Some(group.account_id);
+                    println!("Account {}:", group.account_id);
+                }
+                let ids: Vec<String> = group
+                    .transaction_ids
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect();
+                println!(
+                    "    {} {} asset {} position {} amount {} {}: transactions {}",
+                    group.date,
+                    group.transaction_type,
+                    group
+                        .asset_id
+                        .map(|id| id.to_string())
+                        .unwrap_or_else(|| "-".to_string()),
+                    group
+                        .position
+                        .map(|p| p.to_string())
+                        .unwrap_or_else(|| "-".to_string()),
+                    group.amount,
+                    group.currency,
+                    ids.join(", ")
+                );
+            }
+            println!("{} groups of suspected duplicates found", groups.len());
+        }
+        Command::PpImport(args) => {
+            let client = parse_pp_xml(&fs::read_to_string(args.file).unwrap()).unwrap();
+            migrate(db.as_ref()).await.unwrap();
+            let report = import_pp_client(db.as_ref(), &market, &client)
+                .await
+                .unwrap();
+            for asset in &report.created_assets {
+                println!("Created asset '{}'", asset);
+            }
+            println!("Imported {} quotes", report.quotes);
+            for (name, account_id, account_report) in &report.accounts {
+                println!(
+                    "Imported {} transactions into account '{}' ({}), skipped {} imported before",
+                    account_report.transaction_ids.len(),
+                    name,
+                    account_id,
+                    account_report.duplicates.len()
+                );
+            }
+        }
+        Command::PpExport(args) => {
+            let account_ids = if args.account.is_empty() {
+                db.get_all_account_ids().await.unwrap()
+            } else {
+                args.account
+            };
+            let xml = export_pp_xml(db.as_ref(), &account_ids, &args.currency)
+                .await
+                .unwrap();
+            fs::write(args.file, xml).unwrap();
+        }
+        Command::JournalImport(args) => {
+            let journal = parse_journal(&fs::read_to_string(args.file).unwrap()).unwrap();
+            migrate(db.as_ref()).await.unwrap();
+            let report = import_journal(db.as_ref(), &market, &journal)
+                .await
+                .unwrap();
+            for asset in &report.created_assets {
+                println!("Created asset '{}'", asset);
+            }
+            println!("Imported {} quotes", report.quotes);
+            for (name, account_id, account_report) in &report.accounts {
+                println!(
+                    "Imported {} transactions into account '{}' ({}), skipped {} imported before",
+                    account_report.transaction_ids.len(),
+                    name,
+                    account_id,
+                    account_report.duplicates.len()
+                );
+            }
+        }
+        Command::JournalExport(args) => {
+            let account_ids = if args.account.is_empty() {
+                db.get_all_account_ids().await.unwrap()
+            } else {
+                args.account
+            };
+            let journal = load_journal(db.as_ref(), &account_ids, &args.currency)
+                .await
+                .unwrap();
+            fs::write(args.file, write_journal(&journal, args.format)).unwrap();
+        }
+        Command::Backup(args) => match args.action {
+            BackupAction::Export {
+                file,
+                with_passwords,
+            } => {
+                migrate(db.as_ref()).await.unwrap();
+                let backup = export_backup(db.as_ref(), with_passwords).await.unwrap();
+                fs::write(&file, serde_json::to_string(&backup).unwrap()).unwrap();
+                println!(
+                    "Wrote {} assets, {} quotes, {} accounts, {} transactions and {} users to {}",
+                    backup.assets.len(),
+                    backup.quotes.len(),
+                    backup.accounts.len(),
+                    backup.transactions.len(),
+                    backup.users.len(),
+                    file.display()
+                );
+            }
+            BackupAction::Import { file } => {
+                let backup: BackupData =
+                    serde_json::from_str(&fs::read_to_string(file).unwrap()).unwrap();
+                migrate(db.as_ref()).await.unwrap();
+                let report = restore_backup(db.as_ref(), &backup).await.unwrap();
+                println!(
+                    "Restored {} assets, {} tickers, {} quotes, {} accounts, {} transactions and {} users",
+                    report.assets,
+                    report.tickers,
+                    report.quotes,
+                    report.accounts,
+                    report.transactions,
+                    report.users
+                );
+                for user in report.users_without_password {
+                    println!(
+                        "User '{}' has been restored without password, set a new one",
+                        user
+                    );
+                }
+                for conflict in report.conflicts {
+                    println!("{}", conflict);
+                }
+            }
+        },
+        Command::Db(args) => match args.action {
+            DbAction::Migrate => {
+                migrate(db.as_ref()).await.unwrap();
+                println!("Database schema is up to date");
+            }
+            DbAction::Status => {
+                for migration in migration_status(db.as_ref()).await.unwrap() {
+                    let state = match (migration.applied, migration.modified) {
+                        (true, false) => "applied",
+                        (true, true) => "applied, but modified since",
+                        (false, _) => "pending",
+                    };
+                    println!("{} {}: {}", migration.version, migration.description, state);
+                }
+            }
+        },
+        Command::User(args) => match args.action {
+            UserAction::Add {
+                name,
+                display,
+                admin,
+                password_stdin,
+            } => {
+                let password = read_password(password_stdin);
+                let user = qualinvest_core::user::User {
+                    id: None,
+                    display: Some(display.unwrap_or_else(|| name.clone())),
+                    name,
+                    is_admin: admin,
+                };
+                let id = db.insert_user(&user, &password).await.unwrap();
+                println!("Added user '{}' with id {}", user.name, id);
+            }
+            UserAction::List => {
+                for user in db.get_all_users().await {
+                    println!(
+                        "{}: {} ({}){}",
+                        user.id.unwrap_or_default(),
+                        user.name,
+                        user.display.unwrap_or_default(),
+                        if user.is_admin { ", admin" } else { "" }
+                    );
+                }
+            }
+            UserAction::SetPassword {
+                name,
+                password_stdin,
+            } => {
+                let user_id = get_user_id(db.as_ref(), &name).await;
+                let password = read_password(password_stdin);
+                db.update_password(user_id, &password).await.unwrap();
+                println!("Password of user '{}' changed", name);
+            }
+            UserAction::SetAdmin { name, admin } => {
+                let user_id = get_user_id(db.as_ref(), &name).await;
+                let mut user = db.get_user_by_id(user_id).await.unwrap();
+                user.is_admin = admin;
+                db.update_user(&user).await.unwrap();
+                println!(
+                    "User '{}' is {}an administrator",
+                    name,
+                    if admin { "" } else { "no longer " }
+                );
+            }
+            UserAction::Delete { name } => {
+                let user_id = get_user_id(db.as_ref(), &name).await;
+                db.delete_user(user_id).await.unwrap();
+                println!("Deleted user '{}'", name);
+            }
+        },
+        Command::Rights(args) => match args.action {
+            RightsAction::Grant { user, accounts } => {
+                let user_id = get_user_id(db.as_ref(), &user).await;
+                for account_id in accounts {
+                    db.add_account_right(user_id, account_id).await.unwrap();
+                    println!("User '{}' has access to account {}", user, account_id);
+                }
+            }
+            RightsAction::Revoke { user, accounts } => {
+                let user_id = get_user_id(db.as_ref(), &user).await;
+                for account_id in accounts {
+                    db.remove_account_right(user_id, account_id).await.unwrap();
+                    println!("User '{}' has no access to account {}", user, account_id);
+                }
+            }
+            RightsAction::List { user } => {
+                let users = match user {
+                    Some(name) => {
+                        let user_id = get_user_id(db.as_ref(), &name).await;
+                        vec![db.get_user_by_id(user_id).await.unwrap()]
+                    }
+                    None => db.get_all_users().await,
+                };
+                for user in users {
+                    println!("{}:", user.name);
+                    let user_id = user.id.unwrap_or_default();
+                    for account in db.get_user_accounts(user_id).await.unwrap() {
+                        println!(
+                            "    {}: {} {}",
+                            account.id.unwrap_or_default(),
+                            account.broker,
+                            account.account_name
+                        );
+                    }
+                }
+            }
+        },
+        Command::Account(args) => match args.action {
+            AccountAction::Add { broker, name } => {
+                let account = Account {
+                    id: None,
+                    broker,
+                    account_name: name,
+                };
+                let id = db.insert_account_if_new(&account).await.unwrap();
+                println!(
+                    "Account {} {} has id {}",
+                    account.broker, account.account_name, id
+                );
+            }
+            AccountAction::List => {
+                for account in db.get_all_accounts().await {
+                    println!(
+                        "{}: {} {}",
+                        account.id.unwrap_or_default(),
+                        account.broker,
+                        account.account_name
+                    );
+                }
+            }
+            AccountAction::Rename { id, broker, name } => {
+                let mut account = db
+                    .get_all_accounts()
+                    .await
+                    .into_iter()
+                    .find(|a| a.id == Some(id))
+                    .unwrap_or_else(|| panic!("Unknown account {}", id));
+                if let Some(broker) = broker {
+                    account.broker = broker;
+                }
+                if let Some(name) = name {
+                    account.account_name = name;
+                }
+                db.update_account(&account).await.unwrap();
+                println!(
+                    "Account {} renamed to {} {}",
+                    id, account.broker, account.account_name
+                );
+            }
+            AccountAction::Delete { id } => {
+                db.delete_account(id).await.unwrap();
+                println!("Deleted account {}", id);
+            }
+        },
+        Command::Transactions(args) => {
+            let account_ids = if args.account.is_empty() {
+                db.get_all_account_ids().await.unwrap()
+            } else {
+                args.account
+            };
+            let parse_date = |date: &str| {
+                offset_date_time_from_str_standard(date, 9, None)
+                    .unwrap()
+                    .date()
+            };
+            let from = args.from.as_deref().map(parse_date);
+            let to = args.to.as_deref().map(parse_date);
+            let in_range = |cash_date: &str| {
+                let date = Date::parse(cash_date, format_description!("[year]-[month]-[day]")).ok();
+                from.is_none_or(|from| date.is_some_and(|date| date >= from))
+                    && to.is_none_or(|to| date.is_some_and(|date| date <= to))
+            };
+            let asset = args.asset.map(|asset| asset.to_lowercase());
+            let transactions: Vec<TransactionView> = db
+                .get_transaction_view_for_accounts(&account_ids)
+                .await
+                .unwrap()
+                .into_iter()
+                .filter(|t| in_range(&t.cash_date))
+                .filter(|t| {
+                    asset.as_ref().is_none_or(|asset| {
+                        t.asset_id.map(|id| id.to_string()).as_ref() == Some(asset)
+                            || t.asset_name.as_ref().map(|n| n.to_lowercase()).as_ref()
+                                == Some(asset)
+                    })
+                })
+                .collect();
+            let headers = [
+                "id", "group", "date", "account", "type", "asset", "position", "amount",
+                "currency", "note",
+            ];
+            let rows: Vec<Vec<String>> = transactions
+                .iter()
+                .map(|t| {
+                    vec![
+                        t.id.to_string(),
+                        t.group_id.map(|id| id.to_string()).unwrap_or_default(),
+                        t.cash_date.clone(),
+                        t.account_id.to_string(),
+                        t.trans_type.clone(),
+                        t.asset_name.clone().unwrap_or_default(),
+                        t.position.map(|p| p.to_string()).unwrap_or_default(),
+                        format!("{:.2}", t.cash_amount),
+                        t.cash_currency.clone(),
+                        t.note.clone().unwrap_or_default(),
+                    ]
+                })
+                .collect();
+            match args.format {
+                OutputFormat::Json => {
+                    println!("{}", serde_json::to_string_pretty(&transactions).unwrap())
+                }
+                format => print_rows(format, &headers, &rows),
+            }
         }
         Command::HashPassword => {
             let password = rpassword::prompt_password("Enter password: ").unwrap();
@@ -326,3 +1924,210 @@ async fn main() {
         }
     }
 }
+
+/// Id of the user with the given name, aborts if there is no such user
+async fn get_user_id(db: &PostgresDB, name: &str) -> i32 {
+    db.get_user_id(name)
+        .await
+        .unwrap_or_else(|| panic!("Unknown user '{}'", name))
+}
+
+/// Read a new password either from standard input or interactively with confirmation
+fn read_password(from_stdin: bool) -> String {
+    let password = if from_stdin {
+        let mut password = String::new();
+        stdin().read_line(&mut password).unwrap();
+        password.trim_end_matches(&['\r', '\n'][..]).to_string()
+    } else {
+        let password = rpassword::prompt_password("Enter password: ").unwrap();
+        let confirmation = rpassword::prompt_password("Repeat password: ").unwrap();
+        if password != confirmation {
+            panic!("Passwords do not match");
+        }
+        password
+    };
+    if password.is_empty() {
+        panic!("The password must not be empty");
+    }
+    password
+}
+
+/// Print rows as CSV or as table with aligned columns, numbers are right aligned
+fn print_rows(format: OutputFormat, headers: &[&str], rows: &[Vec<String>]) {
+    if format == OutputFormat::Csv {
+        let mut writer = csv::Writer::from_writer(stdout());
+        writer.write_record(headers).unwrap();
+        for row in rows {
+            writer.write_record(row).unwrap();
+        }
+        writer.flush().unwrap();
+        return;
+    }
+    let widths: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(i, header)| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .chain(std::iter::once(header.len()))
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    let numeric: Vec<bool> = (0..headers.len())
+        .map(|i| {
+            rows.iter()
+                .all(|row| row[i].is_empty() || row[i].parse::<f64>().is_ok())
+        })
+        .collect();
+    let format_row = |row: &[String]| -> String {
+        row.iter()
+            .enumerate()
+            .map(|(i, value)| {
+                if numeric[i] {
+                    format!("{:>width$}", value, width = widths[i])
+                } else {
+                    format!("{:<width$}", value, width = widths[i])
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    let headers: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+    println!("{}", format_row(&headers));
+    println!(
+        "{}",
+        widths
+            .iter()
+            .map(|w| "-".repeat(*w))
+            .collect::<Vec<String>>()
+            .join("  ")
+    );
+    for row in rows {
+        println!("{}", format_row(row));
+    }
+}
+
+/// Write a time series as Parquet file with the columns date and value
+#[cfg(feature = "parquet")]
+fn write_parquet(file: fs::File, series: &[finql::time_series::TimeValue]) {
+    use arrow_array::{ArrayRef, Date32Array, Float64Array, RecordBatch};
+    use arrow_schema::{DataType, Field, Schema};
+    use parquet::arrow::ArrowWriter;
+
+    // Date32 counts the days since 1970-01-01, which is the Julian day 2440588
+    let dates: Vec<i32> = series
+        .iter()
+        .map(|tv| tv.time.date().to_julian_day() - 2_440_588)
+        .collect();
+    let values: Vec<f64> = series.iter().map(|tv| tv.value).collect();
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("date", DataType::Date32, false),
+        Field::new("value", DataType::Float64, false),
+    ]));
+    let batch = RecordBatch::try_new(
+        schema.clone(),
+        vec![
+            Arc::new(Date32Array::from(dates)) as ArrayRef,
+            Arc::new(Float64Array::from(values)),
+        ],
+    )
+    .unwrap();
+    let mut writer = ArrowWriter::try_new(file, schema, None).unwrap();
+    writer.write(&batch).unwrap();
+    writer.close().unwrap();
+}
+
+fn position_line(name: &str, pos: &PortfolioEntry, value: f64) -> PositionLine {
+    PositionLine {
+        name: name.to_string(),
+        position: pos.position,
+        purchase_value: pos.purchase_value,
+        last_quote: pos.last_quote,
+        value,
+        trading_pnl: pos.trading_pnl,
+        dividend: pos.dividend,
+        interest: pos.interest,
+        fees: pos.fees,
+        tax: pos.tax,
+        currency: pos.currency.iso_code.to_string(),
+    }
+}
+
+/// Print the positions with a total line per group and, if grouped, a line with the grand totals
+fn print_position_report(format: OutputFormat, report: &PositionReport, grouped: bool) {
+    let headers = [
+        "group",
+        "name",
+        "position",
+        "purchase value",
+        "last quote",
+        "value",
+        "unrealized pnl",
+        "trading pnl",
+        "dividend",
+        "interest",
+        "fees",
+        "tax",
+        "currency",
+    ];
+    let amount = |value: f64| format!("{:.2}", value);
+    let totals_row = |group: &str, name: &str, totals: &PositionSummary| {
+        vec![
+            group.to_string(),
+            name.to_string(),
+            String::new(),
+            String::new(),
+            String::new(),
+            amount(totals.value),
+            amount(totals.unrealized_pnl),
+            amount(totals.trading_pnl),
+            amount(totals.dividend),
+            amount(totals.interest),
+            amount(totals.fees),
+            amount(totals.tax),
+            report.currency.clone(),
+        ]
+    };
+    let mut rows = Vec::new();
+    for group in &report.groups {
+        for line in &group.positions {
+            rows.push(vec![
+                group.name.clone(),
+                line.name.clone(),
+                format!("{:.4}", line.position),
+                amount(line.purchase_value),
+                line.last_quote.map(amount).unwrap_or_default(),
+                amount(line.value),
+                if line.last_quote.is_some() {
+                    amount(line.value + line.purchase_value)
+                } else {
+                    String::new()
+                },
+                amount(line.trading_pnl),
+                amount(line.dividend),
+                amount(line.interest),
+                amount(line.fees),
+                amount(line.tax),
+                line.currency.clone(),
+            ]);
+        }
+        if grouped {
+            rows.push(totals_row(&group.name, "Subtotal", &group.totals));
+        }
+    }
+    rows.push(totals_row("", "Total", &report.totals));
+    if format == OutputFormat::Table {
+        println!("Position as of {} in {}", report.date, report.currency);
+        // The group column is redundant if there is only a single group
+        if !grouped {
+            let headers = &headers[1..];
+            let rows: Vec<Vec<String>> = rows.into_iter().map(|row| row[1..].to_vec()).collect();
+            print_rows(format, headers, &rows);
+            return;
+        }
+    }
+    print_rows(format, &headers, &rows);
+}
@@ -0,0 +1,14 @@
+-- Background jobs like imports and quote updates, with their progress and result
+
+CREATE TABLE IF NOT EXISTS jobs (
+    id SERIAL PRIMARY KEY,
+    kind TEXT NOT NULL,
+    user_id INTEGER,
+    status TEXT NOT NULL,
+    progress FLOAT8 NOT NULL DEFAULT 0,
+    message TEXT,
+    result TEXT,
+    created TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+    finished TIMESTAMP WITH TIME ZONE,
+    FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE SET NULL
+);
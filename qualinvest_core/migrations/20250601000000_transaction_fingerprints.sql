@@ -0,0 +1,12 @@
+-- Fingerprints of imported transactions used to detect duplicates
+
+CREATE TABLE IF NOT EXISTS transaction_fingerprints (
+    id SERIAL PRIMARY KEY,
+    account_id INTEGER NOT NULL,
+    transaction_id INTEGER NOT NULL,
+    source TEXT NOT NULL,
+    fingerprint TEXT NOT NULL,
+    UNIQUE (account_id, fingerprint),
+    FOREIGN KEY(account_id) REFERENCES accounts(id) ON DELETE CASCADE,
+    FOREIGN KEY(transaction_id) REFERENCES transactions(id) ON DELETE CASCADE
+);
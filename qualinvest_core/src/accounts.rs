@@ -34,20 +34,17 @@ pub struct TransactionView {
 /// Handler for asset depot accounts
 #[async_trait]
 pub trait AccountHandler: TransactionHandler {
-    /// Clean database by dropping all tables and than run init
+    /// Remove all accounts and their links to transactions, the tables are kept
     async fn clean_accounts(&self) -> Result<(), sqlx::Error>;
 
-    /// Set up new table for account management
-    async fn init_accounts(&self) -> Result<(), sqlx::Error>;
-
     /// Insert new account info in database, if it not yet exist
     async fn insert_account_if_new(&self, account: &Account) -> Result<i32, DataError>;
 
     /// Update an existing accounts name and/or broker
     async fn update_account(&self, account: &Account) -> Result<(), DataError>;
 
-    /// Remove account from database
-    /// Fails if it does not exist or is referenced by other tables.
+    /// Remove account from database including the rights of users to access it
+    /// Fails if it does not exist or still contains transactions.
     async fn delete_account(&self, account_id: i32) -> Result<(), DataError>;
 
     /// Get account id for given account
@@ -138,66 +135,11 @@ pub trait AccountHandler: TransactionHandler {
 
 #[async_trait]
 impl AccountHandler for PostgresDB {
-    /// Clean database by dropping all tables and than run init
+    /// Remove all accounts and their links to transactions
     async fn clean_accounts(&self) -> Result<(), sqlx::Error> {
-        sqlx::query!("DROP TABLE IF EXISTS account_transactions")
-            .execute(&self.pool)
-            .await?;
-        sqlx::query!("DROP TABLE IF EXISTS accounts")
-            .execute(&self.pool)
-            .await?;
-        sqlx::query!("DROP TABLE IF EXISTS documents")
-            .execute(&self.pool)
-            .await?;
-        sqlx::query!("DROP TABLE IF EXISTS users")
+        sqlx::query!("TRUNCATE account_transactions, documents, accounts RESTART IDENTITY CASCADE")
             .execute(&self.pool)
             .await?;
-        self.init_accounts().await?;
-        Ok(())
-    }
-
-    /// Set up new table for account management
-    async fn init_accounts(&self) -> Result<(), sqlx::Error> {
-        sqlx::query!(
-            "CREATE TABLE IF NOT EXISTS users (
-                id SERIAL PRIMARY KEY,
-                name TEXT NOT NULL,
-                display TEXT NOT NULL,
-                salt_hash TEXT NOT NULL,
-                is_admin BOOLEAN NOT NULL DEFAULT False,
-                UNIQUE (name))"
-        )
-        .execute(&self.pool)
-        .await?;
-        sqlx::query!(
-            "CREATE TABLE IF NOT EXISTS accounts (
-                id SERIAL PRIMARY KEY,
-                broker TEXT NOT NULL,
-                account_name TEXT NOT NULL,
-                UNIQUE (broker, account_name))"
-        )
-        .execute(&self.pool)
-        .await?;
-        sqlx::query!(
-            "CREATE TABLE IF NOT EXISTS account_transactions (
-                id SERIAL PRIMARY KEY,
-                account_id INTEGER NOT NULL,
-                transaction_id INTEGER NOT NULL,
-                FOREIGN KEY(account_id) REFERENCES accounts(id),
-                FOREIGN KEY(transaction_id) REFERENCES transactions(id))"
-        )
-        .execute(&self.pool)
-        .await?;
-        sqlx::query!(
-            "CREATE TABLE IF NOT EXISTS documents (
-                id SERIAL PRIMARY KEY,
-                transaction_id INTEGER NOT NULL,
-                hash TEXT NOT NULL,
-                path TEXT NOT NULL,
-                FOREIGN KEY(transaction_id) REFERENCES transactions(id))"
-        )
-        .execute(&self.pool)
-        .await?;
         Ok(())
     }
 
@@ -237,12 +179,17 @@ impl AccountHandler for PostgresDB {
         Ok(())
     }
 
-    /// Remove account from database
-    /// Fails if account is referenced by other tables.
+    /// Remove account and the rights to access it from database
+    /// Fails if account still contains transactions.
     async fn delete_account(&self, account_id: i32) -> Result<(), DataError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!("DELETE FROM account_rights WHERE account_id=$1", account_id)
+            .execute(&mut *tx)
+            .await?;
         sqlx::query!("DELETE FROM accounts WHERE id=($1)", account_id)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await?;
+        tx.commit().await?;
         Ok(())
     }
 
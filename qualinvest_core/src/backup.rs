@@ -0,0 +1,729 @@
+//! Full backup of the database in a portable JSON format
+//!
+//! A backup contains assets, tickers, quotes, accounts, transactions, fingerprints of imported
+//! transactions, users, account rights, user settings and stored objects. Ids are exported as
+//! they are, but are reassigned on restore: currencies, stocks, tickers, accounts and users
+//! already in the database are matched by their natural keys (ISO code, ISIN or WKN, ticker name
+//! and source, broker and account name, user name) and reused, all others are newly created.
+//! Stocks with neither ISIN nor WKN are matched by name. A new stock whose name is already taken
+//! by a stock with different ISIN or WKN is restored under a new name and reported as conflict.
+//! References between the records, including ids stored in user settings and objects, are mapped
+//! to the new ids.
+//!
+//! Password hashes are only exported on request. Users restored without hash get a random
+//! password, which must be reset before they can log in.
+use std::collections::HashMap;
+
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+use time::{Date, OffsetDateTime};
+
+use finql::datatypes::DataError;
+use finql::postgres::PostgresDB;
+
+/// Identifies backup files
+pub const BACKUP_FORMAT: &str = "qualinvest-backup";
+/// Version of the backup format written by this library
+pub const BACKUP_VERSION: u32 = 1;
+
+#[derive(Error, Debug)]
+pub enum BackupError {
+    #[error("Database access failed")]
+    DbError(#[from] sqlx::Error),
+    #[error("Database access failed")]
+    DataError(#[from] DataError),
+    #[error("Not a qualinvest backup")]
+    InvalidFormat,
+    #[error("Backup version {0} is not supported, upgrade qualinvest first")]
+    UnsupportedVersion(u32),
+    #[error("Database already contains transactions, restore into an empty database")]
+    NotEmpty,
+    #[error("Backup references unknown {0} {1}")]
+    MissingReference(&'static str, i32),
+    #[error("Stock '{0}' matches more than one stock in the database")]
+    AmbiguousStock(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupCurrency {
+    pub iso_code: String,
+    pub rounding_digits: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupStock {
+    pub name: String,
+    pub wkn: Option<String>,
+    pub isin: Option<String>,
+    pub note: Option<String>,
+}
+
+/// Asset with the details of its class
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupAsset {
+    pub id: i32,
+    pub asset_class: String,
+    pub currency: Option<BackupCurrency>,
+    pub stock: Option<BackupStock>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupTicker {
+    pub id: i32,
+    pub name: String,
+    pub asset_id: i32,
+    pub source: String,
+    pub priority: i32,
+    pub currency_id: i32,
+    pub factor: f64,
+    pub tz: Option<String>,
+    pub cal: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupQuote {
+    pub ticker_id: i32,
+    pub price: f64,
+    pub time: OffsetDateTime,
+    pub volume: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupAccount {
+    pub id: i32,
+    pub broker: String,
+    pub account_name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupTransaction {
+    pub id: i32,
+    pub trans_type: String,
+    pub asset_id: Option<i32>,
+    pub cash_amount: f64,
+    pub cash_currency_id: i32,
+    pub cash_date: Date,
+    pub related_trans: Option<i32>,
+    pub position: Option<f64>,
+    pub note: Option<String>,
+    /// Accounts the transaction belongs to, usually exactly one
+    pub account_ids: Vec<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupFingerprint {
+    pub account_id: i32,
+    pub transaction_id: i32,
+    pub source: String,
+    pub fingerprint: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupUser {
+    pub id: i32,
+    pub name: String,
+    pub display: String,
+    pub is_admin: bool,
+    /// Only exported on request
+    pub password_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupRight {
+    pub user_id: i32,
+    pub account_id: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupUserSettings {
+    pub user_id: i32,
+    pub settings: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupObject {
+    pub id: String,
+    pub object: Value,
+}
+
+/// Content of a backup file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Backup {
+    pub format: String,
+    pub version: u32,
+    pub created: OffsetDateTime,
+    pub assets: Vec<BackupAsset>,
+    pub tickers: Vec<BackupTicker>,
+    pub quotes: Vec<BackupQuote>,
+    pub accounts: Vec<BackupAccount>,
+    pub transactions: Vec<BackupTransaction>,
+    pub fingerprints: Vec<BackupFingerprint>,
+    pub users: Vec<BackupUser>,
+    pub rights: Vec<BackupRight>,
+    pub user_settings: Vec<BackupUserSettings>,
+    pub objects: Vec<BackupObject>,
+}
+
+/// Number of records restored
+#[derive(Debug, Default)]
+pub struct RestoreReport {
+    pub assets: usize,
+    pub tickers: usize,
+    pub quotes: usize,
+    pub accounts: usize,
+    pub transactions: usize,
+    pub users: usize,
+    /// Names of new users which got a random password
+    pub users_without_password: Vec<String>,
+    /// Stocks which could not be restored as they were, e.g. due to a name already taken
+    pub conflicts: Vec<String>,
+}
+
+/// Mapping of ids in the backup to ids in the database
+#[derive(Debug, Default)]
+pub struct IdMaps {
+    pub assets: HashMap<i32, i32>,
+    pub tickers: HashMap<i32, i32>,
+    pub accounts: HashMap<i32, i32>,
+    pub transactions: HashMap<i32, i32>,
+    pub users: HashMap<i32, i32>,
+}
+
+impl IdMaps {
+    fn map(map: &HashMap<i32, i32>, kind: &'static str, id: i32) -> Result<i32, BackupError> {
+        map.get(&id)
+            .copied()
+            .ok_or(BackupError::MissingReference(kind, id))
+    }
+
+    /// Map ids stored in JSON values. Only the keys known to hold ids are mapped, at any level:
+    /// `account_ids` of user settings and `asset_id` and `ticker_id` of the stored price index
+    /// series and synthetic price paths. Unknown ids are kept.
+    pub fn remap_json(&self, value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                for (key, value) in object.iter_mut() {
+                    let map = match key.as_str() {
+                        "account_ids" => Some(&self.accounts),
+                        "asset_id" => Some(&self.assets),
+                        "ticker_id" => Some(&self.tickers),
+                        _ => None,
+                    };
+                    match map {
+                        Some(map) => match value {
+                            Value::Array(ids) => {
+                                ids.iter_mut().for_each(|id| remap_number(map, id))
+                            }
+                            value => remap_number(map, value),
+                        },
+                        None => self.remap_json(value),
+                    }
+                }
+            }
+            Value::Array(values) => values.iter_mut().for_each(|v| self.remap_json(v)),
+            _ => {}
+        }
+    }
+}
+
+fn remap_number(map: &HashMap<i32, i32>, value: &mut Value) {
+    if let Some(new_id) = value.as_i64().and_then(|id| map.get(&(id as i32))) {
+        *value = Value::from(*new_id);
+    }
+}
+
+/// Read the complete database into a backup, the schema must be up to date, see
+/// `crate::schema::migrate`
+pub async fn export_backup(
+    db: &PostgresDB,
+    include_password_hashes: bool,
+) -> Result<Backup, BackupError> {
+    let assets = sqlx::query!(
+        r#"SELECT a.id, a.asset_class, c.iso_code AS "iso_code?",
+            c.rounding_digits AS "rounding_digits?", s.name AS "name?", s.wkn, s.isin, s.note
+        FROM assets a
+        LEFT JOIN currencies c ON c.id = a.id
+        LEFT JOIN stocks s ON s.id = a.id
+        ORDER BY a.id"#
+    )
+    .fetch_all(&db.pool)
+    .await?
+    .into_iter()
+    .map(|row| BackupAsset {
+        id: row.id,
+        asset_class: row.asset_class,
+        currency: row
+            .iso_code
+            .zip(row.rounding_digits)
+            .map(|(iso_code, rounding_digits)| BackupCurrency {
+                iso_code,
+                rounding_digits,
+            }),
+        stock: row.name.map(|name| BackupStock {
+            name,
+            wkn: row.wkn,
+            isin: row.isin,
+            note: row.note,
+        }),
+    })
+    .collect();
+
+    let tickers = sqlx::query_as!(
+        BackupTicker,
+        "SELECT id, name, asset_id, source, priority, currency_id, factor, tz, cal
+        FROM ticker ORDER BY id"
+    )
+    .fetch_all(&db.pool)
+    .await?;
+
+    let quotes = sqlx::query_as!(
+        BackupQuote,
+        "SELECT ticker_id, price, time, volume FROM quotes ORDER BY ticker_id, time"
+    )
+    .fetch_all(&db.pool)
+    .await?;
+
+    let accounts = sqlx::query_as!(
+        BackupAccount,
+        "SELECT id, broker, account_name FROM accounts ORDER BY id"
+    )
+    .fetch_all(&db.pool)
+    .await?;
+
+    let transactions = sqlx::query_as!(
+        BackupTransaction,
+        r#"SELECT t.id, t.trans_type, t.asset_id, t.cash_amount, t.cash_currency_id, t.cash_date,
+            t.related_trans, t.position, t.note,
+            ARRAY(SELECT at.account_id FROM account_transactions at
+                WHERE at.transaction_id = t.id ORDER BY at.account_id) AS "account_ids!"
+        FROM transactions t
+        ORDER BY t.id"#
+    )
+    .fetch_all(&db.pool)
+    .await?;
+
+    let fingerprints = sqlx::query_as!(
+        BackupFingerprint,
+        "SELECT account_id, transaction_id, source, fingerprint
+        FROM transaction_fingerprints ORDER BY id"
+    )
+    .fetch_all(&db.pool)
+    .await?;
+
+    let users =
+        sqlx::query!("SELECT id, name, display, salt_hash, is_admin FROM users ORDER BY id")
+            .fetch_all(&db.pool)
+            .await?
+            .into_iter()
+            .map(|row| BackupUser {
+                id: row.id,
+                name: row.name,
+                display: row.display,
+                is_admin: row.is_admin,
+                password_hash: if include_password_hashes {
+                    Some(row.salt_hash)
+                } else {
+                    None
+                },
+            })
+            .collect();
+
+    let rights = sqlx::query_as!(
+        BackupRight,
+        "SELECT user_id, account_id FROM account_rights ORDER BY id"
+    )
+    .fetch_all(&db.pool)
+    .await?;
+
+    let user_settings = sqlx::query_as!(
+        BackupUserSettings,
+        r#"SELECT user_id AS "user_id!", settings FROM user_settings
+        WHERE user_id IS NOT NULL ORDER BY id"#
+    )
+    .fetch_all(&db.pool)
+    .await?;
+
+    let objects = sqlx::query_as!(BackupObject, "SELECT id, object FROM objects ORDER BY id")
+        .fetch_all(&db.pool)
+        .await?;
+
+    Ok(Backup {
+        format: BACKUP_FORMAT.to_string(),
+        version: BACKUP_VERSION,
+        created: OffsetDateTime::now_utc(),
+        assets,
+        tickers,
+        quotes,
+        accounts,
+        transactions,
+        fingerprints,
+        users,
+        rights,
+        user_settings,
+        objects,
+    })
+}
+
+/// Restore a backup into a database without transactions. Everything is written in a single
+/// database transaction, i.e. nothing is changed if the restore fails. The schema must be up to
+/// date, see `crate::schema::migrate`.
+pub async fn restore_backup(
+    db: &PostgresDB,
+    backup: &Backup,
+) -> Result<RestoreReport, BackupError> {
+    if backup.format != BACKUP_FORMAT {
+        return Err(BackupError::InvalidFormat);
+    }
+    if backup.version > BACKUP_VERSION {
+        return Err(BackupError::UnsupportedVersion(backup.version));
+    }
+    let mut tx = db.pool.begin().await?;
+    let transaction_count = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM transactions"#)
+        .fetch_one(&mut *tx)
+        .await?;
+    if transaction_count > 0 {
+        return Err(BackupError::NotEmpty);
+    }
+
+    let mut maps = IdMaps::default();
+    let mut report = RestoreReport::default();
+
+    for asset in &backup.assets {
+        let mut stock_name = asset.stock.as_ref().map(|stock| stock.name.clone());
+        let existing: Option<i32> = if let Some(currency) = &asset.currency {
+            sqlx::query_scalar!(
+                "SELECT id FROM currencies WHERE iso_code = $1",
+                currency.iso_code
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+        } else if let Some(stock) = &asset.stock {
+            let ids = sqlx::query_scalar!(
+                "SELECT id FROM stocks WHERE isin = $1 OR wkn = $2
+                    OR ($1::TEXT IS NULL AND $2::TEXT IS NULL AND name = $3)",
+                stock.isin,
+                stock.wkn,
+                stock.name
+            )
+            .fetch_all(&mut *tx)
+            .await?;
+            if ids.len() > 1 {
+                return Err(BackupError::AmbiguousStock(stock.name.clone()));
+            }
+            let name_taken = ids.is_empty()
+                && sqlx::query_scalar!("SELECT id FROM stocks WHERE name = $1", stock.name)
+                    .fetch_optional(&mut *tx)
+                    .await?
+                    .is_some();
+            if name_taken {
+                let key = stock
+                    .isin
+                    .as_ref()
+                    .or(stock.wkn.as_ref())
+                    .unwrap_or(&stock.name);
+                let name = format!("{} ({})", stock.name, key);
+                report.conflicts.push(format!(
+                    "Stock '{}' exists with a different ISIN or WKN, restored as '{}'",
+                    stock.name, name
+                ));
+                stock_name = Some(name);
+            }
+            ids.into_iter().next()
+        } else {
+            None
+        };
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                let id = sqlx::query_scalar!(
+                    "INSERT INTO assets (asset_class) VALUES ($1) RETURNING id",
+                    asset.asset_class
+                )
+                .fetch_one(&mut *tx)
+                .await?;
+                if let Some(currency) = &asset.currency {
+                    sqlx::query!(
+                        "INSERT INTO currencies (id, iso_code, rounding_digits) VALUES ($1, $2, $3)",
+                        id,
+                        currency.iso_code,
+                        currency.rounding_digits
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                if let Some(stock) = &asset.stock {
+                    sqlx::query!(
+                        "INSERT INTO stocks (id, name, wkn, isin, note) VALUES ($1, $2, $3, $4, $5)",
+                        id,
+                        stock_name,
+                        stock.wkn,
+                        stock.isin,
+                        stock.note
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                report.assets += 1;
+                id
+            }
+        };
+        maps.assets.insert(asset.id, id);
+    }
+
+    for ticker in &backup.tickers {
+        let asset_id = IdMaps::map(&maps.assets, "asset", ticker.asset_id)?;
+        let currency_id = IdMaps::map(&maps.assets, "currency", ticker.currency_id)?;
+        let existing = sqlx::query_scalar!(
+            "SELECT id FROM ticker WHERE name = $1 AND source = $2 AND asset_id = $3",
+            ticker.name,
+            ticker.source,
+            asset_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                report.tickers += 1;
+                sqlx::query_scalar!(
+                    "INSERT INTO ticker (name, asset_id, source, priority, currency_id, factor, tz, cal)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id",
+                    ticker.name,
+                    asset_id,
+                    ticker.source,
+                    ticker.priority,
+                    currency_id,
+                    ticker.factor,
+                    ticker.tz,
+                    ticker.cal
+                )
+                .fetch_one(&mut *tx)
+                .await?
+            }
+        };
+        maps.tickers.insert(ticker.id, id);
+    }
+
+    for quote in &backup.quotes {
+        let ticker_id = IdMaps::map(&maps.tickers, "ticker", quote.ticker_id)?;
+        report.quotes += sqlx::query!(
+            "INSERT INTO quotes (ticker_id, price, time, volume)
+            SELECT $1, $2, $3, $4
+            WHERE NOT EXISTS (SELECT 1 FROM quotes WHERE ticker_id = $1 AND time = $3)",
+            ticker_id,
+            quote.price,
+            quote.time,
+            quote.volume
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected() as usize;
+    }
+
+    for account in &backup.accounts {
+        let id = sqlx::query_scalar!(
+            "INSERT INTO accounts (broker, account_name) VALUES ($1, $2)
+            ON CONFLICT (broker, account_name) DO UPDATE SET broker = EXCLUDED.broker
+            RETURNING id",
+            account.broker,
+            account.account_name
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        maps.accounts.insert(account.id, id);
+        report.accounts += 1;
+    }
+
+    // Related transactions are linked after all transactions got their new ids
+    for transaction in &backup.transactions {
+        let asset_id = transaction
+            .asset_id
+            .map(|id| IdMaps::map(&maps.assets, "asset", id))
+            .transpose()?;
+        let currency_id = IdMaps::map(&maps.assets, "currency", transaction.cash_currency_id)?;
+        let id = sqlx::query_scalar!(
+            "INSERT INTO transactions
+                (trans_type, asset_id, cash_amount, cash_currency_id, cash_date, position, note)
+            VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id",
+            transaction.trans_type,
+            asset_id,
+            transaction.cash_amount,
+            currency_id,
+            transaction.cash_date,
+            transaction.position,
+            transaction.note
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        for account_id in &transaction.account_ids {
+            let account_id = IdMaps::map(&maps.accounts, "account", *account_id)?;
+            sqlx::query!(
+                "INSERT INTO account_transactions (account_id, transaction_id) VALUES ($1, $2)",
+                account_id,
+                id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        maps.transactions.insert(transaction.id, id);
+        report.transactions += 1;
+    }
+    for transaction in &backup.transactions {
+        if let Some(related) = transaction.related_trans {
+            let id = IdMaps::map(&maps.transactions, "transaction", transaction.id)?;
+            let related = IdMaps::map(&maps.transactions, "transaction", related)?;
+            sqlx::query!(
+                "UPDATE transactions SET related_trans = $2 WHERE id = $1",
+                id,
+                related
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    for fingerprint in &backup.fingerprints {
+        let account_id = IdMaps::map(&maps.accounts, "account", fingerprint.account_id)?;
+        let transaction_id = IdMaps::map(
+            &maps.transactions,
+            "transaction",
+            fingerprint.transaction_id,
+        )?;
+        sqlx::query!(
+            "INSERT INTO transaction_fingerprints (account_id, transaction_id, source, fingerprint)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (account_id, fingerprint) DO NOTHING",
+            account_id,
+            transaction_id,
+            fingerprint.source,
+            fingerprint.fingerprint
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for user in &backup.users {
+        let existing = sqlx::query_scalar!("SELECT id FROM users WHERE name = $1", user.name)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                let id = match &user.password_hash {
+                    Some(hash) => {
+                        sqlx::query_scalar!(
+                            "INSERT INTO users (name, display, salt_hash, is_admin)
+                            VALUES ($1, $2, $3, $4) RETURNING id",
+                            user.name,
+                            user.display,
+                            hash,
+                            user.is_admin
+                        )
+                        .fetch_one(&mut *tx)
+                        .await?
+                    }
+                    None => {
+                        let password: String = rand::thread_rng()
+                            .sample_iter(&Alphanumeric)
+                            .take(32)
+                            .map(char::from)
+                            .collect();
+                        report.users_without_password.push(user.name.clone());
+                        sqlx::query_scalar!(
+                            "INSERT INTO users (name, display, salt_hash, is_admin)
+                            VALUES ($1, $2, crypt($3, gen_salt('bf', 8)), $4) RETURNING id",
+                            user.name,
+                            user.display,
+                            password,
+                            user.is_admin
+                        )
+                        .fetch_one(&mut *tx)
+                        .await?
+                    }
+                };
+                report.users += 1;
+                id
+            }
+        };
+        maps.users.insert(user.id, id);
+    }
+
+    for right in &backup.rights {
+        let user_id = IdMaps::map(&maps.users, "user", right.user_id)?;
+        let account_id = IdMaps::map(&maps.accounts, "account", right.account_id)?;
+        sqlx::query!(
+            "INSERT INTO account_rights (user_id, account_id)
+            SELECT $1, $2
+            WHERE NOT EXISTS (SELECT 1 FROM account_rights WHERE user_id = $1 AND account_id = $2)",
+            user_id,
+            account_id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for settings in &backup.user_settings {
+        let mut value = settings.settings.clone();
+        if let Some(value) = value.as_mut() {
+            maps.remap_json(value);
+        }
+        let user_id = IdMaps::map(&maps.users, "user", settings.user_id)?;
+        sqlx::query!(
+            "INSERT INTO user_settings (user_id, settings) VALUES ($1, $2::JSON)
+            ON CONFLICT (user_id) DO UPDATE SET settings = EXCLUDED.settings",
+            user_id,
+            value
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for object in &backup.objects {
+        let mut value = object.object.clone();
+        maps.remap_json(&mut value);
+        sqlx::query!(
+            "INSERT INTO objects (id, object) VALUES ($1, $2::JSON)
+            ON CONFLICT (id) DO UPDATE SET object = EXCLUDED.object",
+            object.id,
+            value
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_remap_json() {
+        let mut maps = IdMaps::default();
+        maps.accounts.insert(1, 11);
+        maps.accounts.insert(2, 12);
+        maps.tickers.insert(5, 15);
+        let mut value = json!({
+            "period_start": "Inception",
+            "account_ids": [1, 2, 3],
+            "series": [{"ticker_id": 5, "name": "HICP"}],
+            "params": {"account_id": 2, "ticker_ids": [5], "weights": [1, 2]},
+        });
+        maps.remap_json(&mut value);
+        // Keys not known to hold ids are kept, even if they look like it
+        assert_eq!(
+            value,
+            json!({
+                "period_start": "Inception",
+                "account_ids": [11, 12, 3],
+                "series": [{"ticker_id": 15, "name": "HICP"}],
+                "params": {"account_id": 2, "ticker_ids": [5], "weights": [1, 2]},
+            })
+        );
+    }
+}
@@ -0,0 +1,721 @@
+//! Import of bank statements in the formats ISO 20022 CAMT.053 (XML) and SWIFT MT940
+//!
+//! Statement entries are converted into rows of `crate::csv_import`, such that they are stored
+//! by `import_rows` including detection of entries imported before. The kind of transaction is
+//! derived by mapping rules from the bank transaction code and the remittance text. Interest,
+//! fees and taxes become transactions of these types, securities settlements are skipped since
+//! they are part of the buy and sell transactions imported from the broker, and all other
+//! entries become cash deposits or withdrawals.
+//!
+//! The bank transaction code of an entry is given as `<domain>-<family>-<sub family>` followed by
+//! the proprietary code for CAMT.053 (e.g. `PMNT-RCDT-ESCT NTRF+166`) and as
+//! `<transaction type>+<business transaction code>` for MT940 (e.g. `NTRF+166`).
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use lazy_static::lazy_static;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use time::{Date, Month};
+
+use crate::csv_import::{ImportError, ImportKind, ImportPreview, ImportRow, RowError};
+use crate::portfolio_performance::xstream::{XStreamError, XmlDocument};
+
+#[derive(Error, Debug)]
+pub enum StatementError {
+    #[error("Failed to read XML")]
+    XmlError(#[from] XStreamError),
+    #[error("Invalid bank statement: {0}")]
+    InvalidFile(String),
+    #[error("Line {0}: {1}")]
+    InvalidLine(usize, String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementFormat {
+    Camt053,
+    Mt940,
+}
+
+impl StatementFormat {
+    /// Guess the format from the content of the file
+    pub fn detect(text: &str) -> Self {
+        if text.trim_start().starts_with('<') {
+            StatementFormat::Camt053
+        } else {
+            StatementFormat::Mt940
+        }
+    }
+}
+
+impl fmt::Display for StatementFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatementFormat::Camt053 => write!(f, "CAMT.053"),
+            StatementFormat::Mt940 => write!(f, "MT940"),
+        }
+    }
+}
+
+impl FromStr for StatementFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().replace('.', "").as_str() {
+            "CAMT053" | "CAMT" => Ok(StatementFormat::Camt053),
+            "MT940" => Ok(StatementFormat::Mt940),
+            _ => Err(format!("unknown bank statement format '{}'", s)),
+        }
+    }
+}
+
+/// Single booking of a bank statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatementEntry {
+    /// Line of the entry in MT940 files or number of the entry in CAMT.053 files
+    pub line: usize,
+    pub booking_date: Date,
+    pub value_date: Option<Date>,
+    /// Amount, negative for debits
+    pub amount: f64,
+    pub currency: String,
+    /// Bank transaction code, see module documentation
+    pub code: Option<String>,
+    pub counterparty: Option<String>,
+    /// Reference assigned by the bank
+    pub reference: Option<String>,
+    /// Remittance information and posting text
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Statement {
+    /// IBAN or other identification of the bank account
+    pub account: Option<String>,
+    pub currency: String,
+    pub entries: Vec<StatementEntry>,
+}
+
+/// Entries whose bank transaction code and text (counterparty and remittance information)
+/// match the given case insensitive regular expressions are imported as the given kind of
+/// transaction. Missing patterns match any entry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatementRule {
+    #[serde(default)]
+    pub code: Option<String>,
+    #[serde(default)]
+    pub text: Option<String>,
+    pub kind: ImportKind,
+}
+
+impl StatementRule {
+    fn new(code: Option<&str>, text: Option<&str>, kind: ImportKind) -> Self {
+        StatementRule {
+            code: code.map(|s| s.to_string()),
+            text: text.map(|s| s.to_string()),
+            kind,
+        }
+    }
+}
+
+/// Rules recognising securities settlements, taxes, interest and fees
+pub fn default_statement_rules() -> Vec<StatementRule> {
+    vec![
+        StatementRule::new(Some(r"^SECU-"), None, ImportKind::Skip),
+        StatementRule::new(
+            None,
+            Some(r"wertpapier|effekten|securities settlement"),
+            ImportKind::Skip,
+        ),
+        StatementRule::new(
+            None,
+            Some(
+                r"kapitalertrags?steuer|abgeltungs?steuer|solidarit.tszuschlag|kirchensteuer|withholding tax",
+            ),
+            ImportKind::Tax,
+        ),
+        StatementRule::new(
+            Some(r"^[A-Z]{4}-[A-Z]{4}-INTR\b"),
+            None,
+            ImportKind::Interest,
+        ),
+        StatementRule::new(
+            None,
+            Some(r"\b(haben|soll)?zinsen\b|\binterest\b"),
+            ImportKind::Interest,
+        ),
+        StatementRule::new(Some(r"^[A-Z]{4}-[A-Z]{4}-CHRG\b"), None, ImportKind::Fee),
+        StatementRule::new(
+            None,
+            Some(r"geb(ü|ue)hr|entgelt|kontof(ü|ue)hrung|\bfees?\b|\bcharges?\b"),
+            ImportKind::Fee,
+        ),
+    ]
+}
+
+fn parse_iso_date(text: &str) -> Option<Date> {
+    crate::csv_import::parse_date(text.get(..10)?, "%Y-%m-%d")
+}
+
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Child elements along the given path, ignoring namespace prefixes
+fn find(doc: &XmlDocument, id: usize, path: &[&str]) -> Option<usize> {
+    path.iter().try_fold(id, |id, name| {
+        doc.node(id)
+            .children
+            .iter()
+            .copied()
+            .find(|&child| local_name(&doc.node(child).name) == *name)
+    })
+}
+
+fn find_all(doc: &XmlDocument, id: usize, name: &str) -> Vec<usize> {
+    doc.node(id)
+        .children
+        .iter()
+        .copied()
+        .filter(|&child| local_name(&doc.node(child).name) == name)
+        .collect()
+}
+
+fn find_text(doc: &XmlDocument, id: usize, path: &[&str]) -> Option<String> {
+    find(doc, id, path)
+        .map(|node| doc.node(node).text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+fn camt_date(doc: &XmlDocument, entry: usize, name: &str) -> Option<Date> {
+    find_text(doc, entry, &[name, "Dt"])
+        .or_else(|| find_text(doc, entry, &[name, "DtTm"]))
+        .and_then(|date| parse_iso_date(&date))
+}
+
+/// Parse a CAMT.053 file, which may contain several statements
+pub fn parse_camt053(text: &str) -> Result<Vec<Statement>, StatementError> {
+    let doc = XmlDocument::parse(text)?;
+    let root = find(&doc, doc.root(), &["BkToCstmrStmt"])
+        .ok_or_else(|| StatementError::InvalidFile("missing element BkToCstmrStmt".to_string()))?;
+
+    let mut statements = Vec::new();
+    let mut number = 0;
+    for stmt in find_all(&doc, root, "Stmt") {
+        let currency = find_text(&doc, stmt, &["Acct", "Ccy"]).unwrap_or_else(|| "EUR".to_string());
+        let mut statement = Statement {
+            account: find_text(&doc, stmt, &["Acct", "Id", "IBAN"])
+                .or_else(|| find_text(&doc, stmt, &["Acct", "Id", "Othr", "Id"])),
+            currency: currency.clone(),
+            entries: Vec::new(),
+        };
+        for entry in find_all(&doc, stmt, "Ntry") {
+            number += 1;
+            let invalid = |msg: &str| StatementError::InvalidLine(number, msg.to_string());
+            // Only booked entries are part of the statement
+            let status = find_text(&doc, entry, &["Sts", "Cd"])
+                .or_else(|| find_text(&doc, entry, &["Sts"]))
+                .unwrap_or_default();
+            if status == "PDNG" || status == "INFO" {
+                continue;
+            }
+
+            let amount_node =
+                find(&doc, entry, &["Amt"]).ok_or_else(|| invalid("missing amount"))?;
+            let mut amount: f64 = doc
+                .node(amount_node)
+                .text
+                .trim()
+                .parse()
+                .map_err(|_| invalid("invalid amount"))?;
+            let debit = find_text(&doc, entry, &["CdtDbtInd"]).as_deref() == Some("DBIT");
+            if debit {
+                amount = -amount;
+            }
+            let booking_date = camt_date(&doc, entry, "BookgDt")
+                .or_else(|| camt_date(&doc, entry, "ValDt"))
+                .ok_or_else(|| invalid("missing booking date"))?;
+
+            let mut codes = Vec::new();
+            if let Some(domain) = find(&doc, entry, &["BkTxCd", "Domn"]) {
+                codes.push(
+                    [
+                        find_text(&doc, domain, &["Cd"]),
+                        find_text(&doc, domain, &["Fmly", "Cd"]),
+                        find_text(&doc, domain, &["Fmly", "SubFmlyCd"]),
+                    ]
+                    .iter()
+                    .flatten()
+                    .cloned()
+                    .collect::<Vec<String>>()
+                    .join("-"),
+                );
+            }
+            codes.extend(find_text(&doc, entry, &["BkTxCd", "Prtry", "Cd"]));
+
+            // Details of the first transaction, batch bookings are imported as a whole
+            let details = find(&doc, entry, &["NtryDtls", "TxDtls"]);
+            let counterparty = details.and_then(|details| {
+                let party = if debit { "Cdtr" } else { "Dbtr" };
+                find_text(&doc, details, &["RltdPties", party, "Nm"])
+                    .or_else(|| find_text(&doc, details, &["RltdPties", party, "Pty", "Nm"]))
+            });
+            let mut text: Vec<String> = details
+                .and_then(|details| find(&doc, details, &["RmtInf"]))
+                .map(|info| {
+                    find_all(&doc, info, "Ustrd")
+                        .into_iter()
+                        .map(|node| doc.node(node).text.trim().to_string())
+                        .collect()
+                })
+                .unwrap_or_default();
+            text.extend(find_text(&doc, entry, &["AddtlNtryInf"]));
+
+            statement.entries.push(StatementEntry {
+                line: number,
+                booking_date,
+                value_date: camt_date(&doc, entry, "ValDt"),
+                amount,
+                currency: doc
+                    .attr(amount_node, "Ccy")
+                    .map(|c| c.to_string())
+                    .unwrap_or_else(|| currency.clone()),
+                code: if codes.is_empty() {
+                    None
+                } else {
+                    Some(codes.join(" "))
+                },
+                counterparty,
+                reference: find_text(&doc, entry, &["AcctSvcrRef"])
+                    .or_else(|| details.and_then(|d| find_text(&doc, d, &["Refs", "AcctSvcrRef"]))),
+                text: text.join(" "),
+            });
+        }
+        statements.push(statement);
+    }
+    Ok(statements)
+}
+
+/// Parse a number with decimal comma as used in MT940 files
+fn parse_mt940_amount(text: &str) -> Option<f64> {
+    text.replace(',', ".").parse().ok()
+}
+
+/// Two digit years are always in this century, while `%y` would map e.g. `99` to 1999
+fn parse_yymmdd(text: &str) -> Option<Date> {
+    crate::csv_import::parse_date(&format!("20{}", text.get(..6)?), "%Y%m%d")
+}
+
+/// Business transaction code, counterparty and text of field 86. German banks use a structured
+/// format, e.g. `166?00GUTSCHRIFT?20Invoice 4711?32ACME Corp.`, where the text is built from
+/// the posting text and the remittance information.
+fn parse_field_86(text: &str) -> (Option<String>, Option<String>, String) {
+    let (code, separator) = match (text.get(..3), text.get(3..4)) {
+        (Some(code), Some(separator))
+            if code.chars().all(|c| c.is_ascii_digit())
+                && !separator.chars().all(char::is_alphanumeric) =>
+        {
+            (code, separator.chars().next().unwrap_or('?'))
+        }
+        _ => return (None, None, text.replace('\n', " ").trim().to_string()),
+    };
+    let mut posting_text = String::new();
+    let mut purpose = Vec::new();
+    let mut name = String::new();
+    for subfield in text[4..].replace('\n', "").split(separator) {
+        let (key, value) = match (subfield.get(..2), subfield.get(2..)) {
+            (Some(key), Some(value)) => (key, value),
+            _ => continue,
+        };
+        match key {
+            "00" => posting_text = value.to_string(),
+            "20" | "21" | "22" | "23" | "24" | "25" | "26" | "27" | "28" | "29" | "60" | "61"
+            | "62" | "63" => purpose.push(value),
+            "32" | "33" => name.push_str(value),
+            _ => {}
+        }
+    }
+    let purpose = purpose.concat();
+    let text = [posting_text.trim(), purpose.trim()]
+        .iter()
+        .filter(|part| !part.is_empty())
+        .copied()
+        .collect::<Vec<&str>>()
+        .join(" ");
+    let name = name.trim();
+    (
+        Some(code.to_string()),
+        if name.is_empty() {
+            None
+        } else {
+            Some(name.to_string())
+        },
+        text,
+    )
+}
+
+lazy_static! {
+    /// Start of a field, e.g. `:61:`
+    static ref FIELD: Regex = Regex::new(r"^:(\d{2}[A-Z]?):(.*)$").unwrap();
+    /// Statement line: value date, booking date, debit/credit mark, funds code, amount,
+    /// transaction type, customer reference and bank reference
+    static ref LINE_61: Regex = Regex::new(
+        r"^(\d{6})(\d{4})?(RC|RD|C|D)([A-Z])?(\d+,\d*)([A-Z][A-Z0-9]{3})(.*?)(?://(.*))?$"
+    )
+    .unwrap();
+}
+
+/// Parse an MT940 file, which may contain several statements
+pub fn parse_mt940(text: &str) -> Result<Vec<Statement>, StatementError> {
+    // Collect fields as tag, line number and content, continuation lines are appended
+    let mut fields: Vec<(String, usize, String)> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if let Some(captures) = FIELD.captures(line) {
+            fields.push((captures[1].to_string(), index + 1, captures[2].to_string()));
+        } else if line.starts_with('-') || line.starts_with('{') || line.trim().is_empty() {
+            // End of message or SWIFT header
+            fields.push(("-".to_string(), index + 1, String::new()));
+        } else if let Some((_, _, content)) = fields.last_mut() {
+            content.push('\n');
+            content.push_str(line);
+        }
+    }
+
+    let mut statements = Vec::new();
+    let mut statement: Option<Statement> = None;
+    for (tag, line, content) in fields {
+        let invalid = |msg: &str| StatementError::InvalidLine(line, msg.to_string());
+        match tag.as_str() {
+            "20" => {
+                statements.extend(statement.take());
+                statement = Some(Statement::default());
+            }
+            "25" => {
+                statement.get_or_insert_with(Statement::default).account =
+                    Some(content.trim().to_string())
+            }
+            "60F" | "60M" => {
+                statement.get_or_insert_with(Statement::default).currency = content
+                    .get(7..10)
+                    .ok_or_else(|| invalid("invalid opening balance"))?
+                    .to_string();
+            }
+            "61" => {
+                let statement = statement.get_or_insert_with(Statement::default);
+                let first_line = content.lines().next().unwrap_or_default();
+                let captures = LINE_61
+                    .captures(first_line)
+                    .ok_or_else(|| invalid("invalid statement line"))?;
+                let value_date =
+                    parse_yymmdd(&captures[1]).ok_or_else(|| invalid("invalid value date"))?;
+                // The booking date is given without year
+                let booking_date = match captures.get(2) {
+                    Some(booking) => {
+                        let month: u8 = booking.as_str()[..2].parse().unwrap_or_default();
+                        let day: u8 = booking.as_str()[2..].parse().unwrap_or_default();
+                        let value_month = value_date.month() as u8;
+                        let year = match (value_month, month) {
+                            (12, 1) => value_date.year() + 1,
+                            (1, 12) => value_date.year() - 1,
+                            _ => value_date.year(),
+                        };
+                        Month::try_from(month)
+                            .ok()
+                            .and_then(|month| Date::from_calendar_date(year, month, day).ok())
+                            .ok_or_else(|| invalid("invalid booking date"))?
+                    }
+                    None => value_date,
+                };
+                let mut amount =
+                    parse_mt940_amount(&captures[5]).ok_or_else(|| invalid("invalid amount"))?;
+                if matches!(&captures[3], "D" | "RC") {
+                    amount = -amount;
+                }
+                let customer_reference = captures[7].trim();
+                let reference = captures
+                    .get(8)
+                    .map(|r| r.as_str().trim().to_string())
+                    .filter(|r| !r.is_empty())
+                    .or_else(|| {
+                        Some(customer_reference.to_string())
+                            .filter(|r| !r.is_empty() && r != "NONREF")
+                    });
+                let currency = if statement.currency.is_empty() {
+                    "EUR".to_string()
+                } else {
+                    statement.currency.clone()
+                };
+                statement.entries.push(StatementEntry {
+                    line,
+                    booking_date,
+                    value_date: Some(value_date),
+                    amount,
+                    currency,
+                    code: Some(captures[6].to_string()),
+                    counterparty: None,
+                    reference,
+                    text: String::new(),
+                });
+            }
+            "86" => {
+                let entry = statement
+                    .as_mut()
+                    .and_then(|s| s.entries.last_mut())
+                    .ok_or_else(|| invalid("field 86 without statement line"))?;
+                let (business_code, counterparty, text) = parse_field_86(&content);
+                if let Some(business_code) = business_code {
+                    entry.code = Some(format!(
+                        "{}+{}",
+                        entry.code.as_deref().unwrap_or_default(),
+                        business_code
+                    ));
+                }
+                entry.counterparty = counterparty;
+                entry.text = text;
+            }
+            _ => {}
+        }
+    }
+    statements.extend(statement);
+    Ok(statements)
+}
+
+/// Parse a bank statement file in the given format
+pub fn parse_statement(
+    format: StatementFormat,
+    text: &str,
+) -> Result<Vec<Statement>, StatementError> {
+    match format {
+        StatementFormat::Camt053 => parse_camt053(text),
+        StatementFormat::Mt940 => parse_mt940(text),
+    }
+}
+
+/// Convert statement entries into rows to be imported, applying the first matching rule
+pub fn statement_rows(
+    statements: &[Statement],
+    rules: &[StatementRule],
+) -> Result<ImportPreview, ImportError> {
+    let compile = |pattern: &Option<String>| -> Result<Option<Regex>, ImportError> {
+        pattern
+            .as_ref()
+            .map(|pattern| {
+                RegexBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|_| ImportError::InvalidPattern(pattern.clone()))
+            })
+            .transpose()
+    };
+    let rules = rules
+        .iter()
+        .map(|rule| Ok((compile(&rule.code)?, compile(&rule.text)?, rule.kind)))
+        .collect::<Result<Vec<_>, ImportError>>()?;
+
+    let mut preview = ImportPreview::default();
+    for entry in statements.iter().flat_map(|s| s.entries.iter()) {
+        let code = entry.code.as_deref().unwrap_or_default();
+        let text = format!(
+            "{} {}",
+            entry.counterparty.as_deref().unwrap_or_default(),
+            entry.text
+        );
+        let matched = rules.iter().find(|(code_regex, text_regex, _)| {
+            code_regex.as_ref().is_none_or(|r| r.is_match(code))
+                && text_regex.as_ref().is_none_or(|r| r.is_match(&text))
+        });
+        let kind = match matched {
+            Some((_, _, kind)) => *kind,
+            None if entry.amount >= 0.0 => ImportKind::Deposit,
+            None => ImportKind::Withdrawal,
+        };
+        if kind == ImportKind::Skip {
+            preview.skipped += 1;
+            continue;
+        }
+        if kind.needs_asset() {
+            preview.errors.push(RowError {
+                line: entry.line,
+                message: format!(
+                    "{} transactions can't be imported from bank statements",
+                    kind
+                ),
+            });
+            continue;
+        }
+        let note = text.trim();
+        preview.rows.push(ImportRow {
+            line: entry.line,
+            date: entry.booking_date,
+            kind,
+            isin: None,
+            wkn: None,
+            asset_name: None,
+            position: None,
+            amount: entry.amount,
+            currency: entry.currency.clone(),
+            fee: 0.0,
+            tax: 0.0,
+            note: if note.is_empty() {
+                None
+            } else {
+                Some(note.to_string())
+            },
+            external_id: entry.reference.clone(),
+        });
+    }
+    Ok(preview)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    const CAMT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">
+  <BkToCstmrStmt>
+    <GrpHdr><MsgId>1</MsgId></GrpHdr>
+    <Stmt>
+      <Id>1</Id>
+      <Acct><Id><IBAN>DE02120300000000202051</IBAN></Id><Ccy>EUR</Ccy></Acct>
+      <Ntry>
+        <Amt Ccy="EUR">1500.00</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <Sts>BOOK</Sts>
+        <BookgDt><Dt>2023-01-02</Dt></BookgDt>
+        <ValDt><Dt>2023-01-02</Dt></ValDt>
+        <AcctSvcrRef>REF-1</AcctSvcrRef>
+        <BkTxCd>
+          <Domn><Cd>PMNT</Cd><Fmly><Cd>RCDT</Cd><SubFmlyCd>ESCT</SubFmlyCd></Fmly></Domn>
+          <Prtry><Cd>NTRF+166</Cd></Prtry>
+        </BkTxCd>
+        <NtryDtls><TxDtls>
+          <RltdPties><Dbtr><Nm>Jane Doe</Nm></Dbtr></RltdPties>
+          <RmtInf><Ustrd>Salary January</Ustrd></RmtInf>
+        </TxDtls></NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">4.90</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts>BOOK</Sts>
+        <BookgDt><Dt>2023-01-31</Dt></BookgDt>
+        <BkTxCd><Domn><Cd>ACMT</Cd><Fmly><Cd>MDOP</Cd><SubFmlyCd>CHRG</SubFmlyCd></Fmly></Domn></BkTxCd>
+        <AddtlNtryInf>Account maintenance</AddtlNtryInf>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">1000.00</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts>BOOK</Sts>
+        <BookgDt><Dt>2023-01-31</Dt></BookgDt>
+        <BkTxCd><Domn><Cd>SECU</Cd><Fmly><Cd>SETT</Cd><SubFmlyCd>TRAD</SubFmlyCd></Fmly></Domn></BkTxCd>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">10.00</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts>PDNG</Sts>
+        <BookgDt><Dt>2023-02-01</Dt></BookgDt>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>"#;
+
+    const MT940: &str = ":20:STARTUMS
+:25:10020030/1234567
+:28C:00001/001
+:60F:C221230EUR1000,00
+:61:2301020102C1500,00NTRFNONREF//4711
+:86:166?00GUTSCHRIFT?20Salary?21 January?32Jane Doe
+:61:2212310102D12,34NMSCNONREF
+:86:805?00ABSCHLUSS?20Habenzinsen?21 und Sollzinsen
+:62F:C230102EUR2487,66
+-";
+
+    #[test]
+    fn test_parse_camt053() {
+        let statements = parse_camt053(CAMT).unwrap();
+        assert_eq!(statements.len(), 1);
+        let statement = &statements[0];
+        assert_eq!(statement.account.as_deref(), Some("DE02120300000000202051"));
+        assert_eq!(statement.entries.len(), 3);
+        let salary = &statement.entries[0];
+        assert_eq!(salary.booking_date, date!(2023 - 01 - 02));
+        assert_eq!(salary.amount, 1500.0);
+        assert_eq!(salary.code.as_deref(), Some("PMNT-RCDT-ESCT NTRF+166"));
+        assert_eq!(salary.counterparty.as_deref(), Some("Jane Doe"));
+        assert_eq!(salary.reference.as_deref(), Some("REF-1"));
+        assert_eq!(salary.text, "Salary January");
+        assert_eq!(statement.entries[1].amount, -4.9);
+
+        let preview = statement_rows(&statements, &default_statement_rules()).unwrap();
+        assert_eq!(preview.skipped, 1);
+        assert_eq!(preview.rows.len(), 2);
+        assert_eq!(preview.rows[0].kind, ImportKind::Deposit);
+        assert_eq!(
+            preview.rows[0].note.as_deref(),
+            Some("Jane Doe Salary January")
+        );
+        assert_eq!(preview.rows[1].kind, ImportKind::Fee);
+        assert_eq!(preview.rows[1].amount, -4.9);
+    }
+
+    #[test]
+    fn test_parse_mt940() {
+        let statements = parse_mt940(MT940).unwrap();
+        assert_eq!(statements.len(), 1);
+        let statement = &statements[0];
+        assert_eq!(statement.account.as_deref(), Some("10020030/1234567"));
+        assert_eq!(statement.currency, "EUR");
+        assert_eq!(statement.entries.len(), 2);
+
+        let salary = &statement.entries[0];
+        assert_eq!(salary.line, 5);
+        assert_eq!(salary.booking_date, date!(2023 - 01 - 02));
+        assert_eq!(salary.amount, 1500.0);
+        assert_eq!(salary.code.as_deref(), Some("NTRF+166"));
+        assert_eq!(salary.reference.as_deref(), Some("4711"));
+        assert_eq!(salary.counterparty.as_deref(), Some("Jane Doe"));
+        assert_eq!(salary.text, "GUTSCHRIFT Salary January");
+
+        // Booked in the next year
+        let interest = &statement.entries[1];
+        assert_eq!(interest.value_date, Some(date!(2022 - 12 - 31)));
+        assert_eq!(interest.booking_date, date!(2023 - 01 - 02));
+        assert_eq!(interest.amount, -12.34);
+        assert_eq!(interest.reference, None);
+
+        let preview = statement_rows(&statements, &default_statement_rules()).unwrap();
+        assert_eq!(preview.rows[0].kind, ImportKind::Deposit);
+        assert_eq!(preview.rows[1].kind, ImportKind::Interest);
+    }
+
+    #[test]
+    fn test_parse_yymmdd() {
+        assert_eq!(parse_yymmdd("230102"), Some(date!(2023 - 01 - 02)));
+        assert_eq!(parse_yymmdd("991231C"), Some(date!(2099 - 12 - 31)));
+        assert_eq!(parse_yymmdd("231302"), None);
+        assert_eq!(parse_yymmdd("2301"), None);
+    }
+
+    #[test]
+    fn test_rules() {
+        let rules = vec![StatementRule::new(
+            Some("^NMSC"),
+            Some("rent"),
+            ImportKind::Skip,
+        )];
+        let mut statements = parse_mt940(MT940).unwrap();
+        statements[0].entries[1].text = "Rent".to_string();
+        let preview = statement_rows(&statements, &rules).unwrap();
+        assert_eq!(preview.skipped, 1);
+        assert_eq!(preview.rows[0].kind, ImportKind::Deposit);
+
+        let invalid = vec![StatementRule::new(Some("("), None, ImportKind::Skip)];
+        assert!(statement_rows(&statements, &invalid).is_err());
+        assert_eq!(StatementFormat::detect(CAMT), StatementFormat::Camt053);
+        assert_eq!("mt940".parse(), Ok(StatementFormat::Mt940));
+    }
+}
@@ -0,0 +1,840 @@
+//! Import of transactions from CSV exports of brokers and banks
+//!
+//! Since every broker exports transactions differently, the layout of a file is described by a
+//! named mapping profile. A profile maps columns to transaction fields, defines date and number
+//! formats and contains rules to derive the transaction type from a column. Fees and taxes given
+//! in separate columns are stored as transactions related to the main transaction.
+//!
+//! Importing is done in two steps: `parse_csv` reads and validates the file without touching the
+//! database, such that the result can be previewed. `import_rows` then stores the rows, skipping
+//! rows imported before (see `crate::duplicates`).
+//!
+//! A minimal profile for a semicolon separated file with German number and date formats:
+//! ```json
+//! {
+//!   "name": "MyBank",
+//!   "delimiter": ";",
+//!   "date_format": "%d.%m.%Y",
+//!   "decimal_separator": ",",
+//!   "thousands_separator": ".",
+//!   "columns": { "date": "Buchungstag", "amount": "Betrag", "transaction_type": "Vorgang" },
+//!   "type_rules": [
+//!     { "pattern": "^Kauf", "kind": "Buy" },
+//!     { "pattern": "Zinsen", "kind": "Interest" }
+//!   ]
+//! }
+//! ```
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fmt;
+
+use regex::RegexBuilder;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use time::{Date, Month};
+
+use finql::datatypes::{
+    date_time_helper::date_to_offset_date_time, Asset, AssetHandler, CashFlow, DataError,
+    ObjectHandler, Quote, QuoteHandler, Stock, Ticker, Transaction, TransactionHandler,
+    TransactionType,
+};
+use finql::market::MarketError;
+use finql::Market;
+
+use crate::accounts::AccountHandler;
+use crate::duplicates::{row_fingerprints, FingerprintHandler};
+
+/// Name of the object the list of mapping profiles is stored as
+const IMPORT_PROFILES_OBJECT: &str = "csv_import_profiles";
+
+#[derive(Error, Debug)]
+pub enum ImportError {
+    #[error("Database access failed")]
+    DataError(#[from] DataError),
+    #[error("Market data access failed")]
+    MarketError(#[from] MarketError),
+    #[error("Failed to read CSV file")]
+    CsvError(#[from] csv::Error),
+    #[error("Failed to read file")]
+    IoError(#[from] std::io::Error),
+    #[error("Column '{0}' not found")]
+    MissingColumn(String),
+    #[error("Invalid type rule pattern '{0}'")]
+    InvalidPattern(String),
+    #[error("Unknown mapping profile '{0}'")]
+    UnknownProfile(String),
+    #[error("Line {0}: asset '{1}' not found")]
+    UnknownAsset(usize, String),
+}
+
+/// Column given by its header name or by its position, starting at 0
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Column {
+    Index(usize),
+    Name(String),
+}
+
+impl Column {
+    /// Position of the column, names are looked up case insensitively in the headers
+    pub(crate) fn position(
+        &self,
+        headers: Option<&csv::StringRecord>,
+    ) -> Result<usize, ImportError> {
+        match (self, headers) {
+            (Column::Index(index), _) => Ok(*index),
+            (Column::Name(name), Some(headers)) => headers
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(name.trim()))
+                .ok_or_else(|| ImportError::MissingColumn(name.clone())),
+            (Column::Name(name), None) => Err(ImportError::MissingColumn(name.clone())),
+        }
+    }
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Column::Index(index) => write!(f, "#{}", index),
+            Column::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// Mapping of columns to transaction fields, only date and amount are required
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnMapping {
+    pub date: Column,
+    /// Cash amount of the transaction, excluding fees and taxes given in separate columns
+    pub amount: Column,
+    /// Column the type rules are applied to, the note column is used if missing
+    #[serde(default)]
+    pub transaction_type: Option<Column>,
+    #[serde(default)]
+    pub isin: Option<Column>,
+    #[serde(default)]
+    pub wkn: Option<Column>,
+    #[serde(default)]
+    pub asset_name: Option<Column>,
+    /// Number of units bought or sold
+    #[serde(default)]
+    pub position: Option<Column>,
+    #[serde(default)]
+    pub currency: Option<Column>,
+    #[serde(default)]
+    pub fee: Option<Column>,
+    #[serde(default)]
+    pub tax: Option<Column>,
+    #[serde(default)]
+    pub note: Option<Column>,
+    /// Unique id of the transaction assigned by the broker, if any
+    #[serde(default)]
+    pub external_id: Option<Column>,
+}
+
+/// Kind of transaction a row is imported as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImportKind {
+    Buy,
+    Sell,
+    Dividend,
+    /// Interest received or, with negative amount, paid
+    Interest,
+    Deposit,
+    Withdrawal,
+    Fee,
+    Tax,
+    /// Rows which should not be imported, e.g. balance lines
+    Skip,
+}
+
+impl ImportKind {
+    /// True if the transaction refers to an asset
+    pub fn needs_asset(&self) -> bool {
+        matches!(
+            self,
+            ImportKind::Buy | ImportKind::Sell | ImportKind::Dividend
+        )
+    }
+}
+
+impl fmt::Display for ImportKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Rows whose type column matches the case insensitive regular expression
+/// are imported as the given kind of transaction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TypeRule {
+    pub pattern: String,
+    pub kind: ImportKind,
+}
+
+pub(crate) fn default_delimiter() -> char {
+    ','
+}
+
+pub(crate) fn default_true() -> bool {
+    true
+}
+
+pub(crate) fn default_date_format() -> String {
+    "%Y-%m-%d".to_string()
+}
+
+pub(crate) fn default_decimal_separator() -> char {
+    '.'
+}
+
+fn default_currency() -> String {
+    "EUR".to_string()
+}
+
+/// Description of the layout of a CSV export
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MappingProfile {
+    pub name: String,
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+    /// If false, columns must be given by their position
+    #[serde(default = "default_true")]
+    pub has_headers: bool,
+    /// Number of lines before the header line, e.g. account information
+    #[serde(default)]
+    pub skip_lines: usize,
+    pub columns: ColumnMapping,
+    /// Date format in strftime syntax, e.g. "%d.%m.%Y"
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    #[serde(default = "default_decimal_separator")]
+    pub decimal_separator: char,
+    #[serde(default)]
+    pub thousands_separator: Option<char>,
+    /// Currency of rows without currency column
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    /// Applied in order, the first matching rule wins
+    #[serde(default)]
+    pub type_rules: Vec<TypeRule>,
+}
+
+/// Validated row of a CSV file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportRow {
+    /// Line number in the file
+    pub line: usize,
+    pub date: Date,
+    pub kind: ImportKind,
+    pub isin: Option<String>,
+    pub wkn: Option<String>,
+    pub asset_name: Option<String>,
+    /// Signed change of the position, for buy and sell transactions only
+    pub position: Option<f64>,
+    /// Signed cash amount, negative for outgoing payments
+    pub amount: f64,
+    pub currency: String,
+    /// Fee paid, as a positive number
+    pub fee: f64,
+    /// Tax paid, as a positive number
+    pub tax: f64,
+    pub note: Option<String>,
+    pub external_id: Option<String>,
+}
+
+impl ImportRow {
+    /// Human readable identification of the asset
+    pub fn asset_description(&self) -> Option<String> {
+        self.asset_name
+            .clone()
+            .or_else(|| self.isin.clone())
+            .or_else(|| self.wkn.clone())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Result of reading a CSV file, before anything is written to the database
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportPreview {
+    pub rows: Vec<ImportRow>,
+    /// Number of rows skipped by type rules
+    pub skipped: usize,
+    pub errors: Vec<RowError>,
+}
+
+/// Parse a number with the decimal and thousands separators of the profile, ignoring
+/// currency symbols. Trailing minus signs as used by some banks are supported.
+fn parse_number(text: &str, profile: &MappingProfile) -> Option<f64> {
+    parse_decimal(text, profile.decimal_separator, profile.thousands_separator)
+}
+
+/// Parse a number with the given decimal and thousands separators, see `parse_number`
+pub(crate) fn parse_decimal(
+    text: &str,
+    decimal_separator: char,
+    thousands_separator: Option<char>,
+) -> Option<f64> {
+    let mut number: String = text
+        .trim()
+        .chars()
+        .filter(|c| Some(*c) != thousands_separator)
+        .filter(|c| c.is_ascii_digit() || *c == '-' || *c == '+' || *c == decimal_separator)
+        .map(|c| if c == decimal_separator { '.' } else { c })
+        .collect();
+    if number.len() > 1 && number.ends_with('-') {
+        number.pop();
+        number.insert(0, '-');
+    }
+    if number.is_empty() {
+        return None;
+    }
+    number.parse().ok()
+}
+
+pub(crate) fn parse_date(text: &str, format: &str) -> Option<Date> {
+    use chrono::Datelike;
+
+    let date = chrono::NaiveDate::parse_from_str(text.trim(), format).ok()?;
+    let month = Month::try_from(date.month() as u8).ok()?;
+    Date::from_calendar_date(date.year(), month, date.day() as u8).ok()
+}
+
+struct ColumnIndices {
+    date: usize,
+    amount: usize,
+    transaction_type: Option<usize>,
+    isin: Option<usize>,
+    wkn: Option<usize>,
+    asset_name: Option<usize>,
+    position: Option<usize>,
+    currency: Option<usize>,
+    fee: Option<usize>,
+    tax: Option<usize>,
+    note: Option<usize>,
+    external_id: Option<usize>,
+}
+
+impl ColumnIndices {
+    fn new(
+        columns: &ColumnMapping,
+        headers: Option<&csv::StringRecord>,
+    ) -> Result<Self, ImportError> {
+        let index = |column: &Column| column.position(headers);
+        let optional = |column: &Option<Column>| column.as_ref().map(index).transpose();
+        Ok(Self {
+            date: index(&columns.date)?,
+            amount: index(&columns.amount)?,
+            transaction_type: optional(&columns.transaction_type)?,
+            isin: optional(&columns.isin)?,
+            wkn: optional(&columns.wkn)?,
+            asset_name: optional(&columns.asset_name)?,
+            position: optional(&columns.position)?,
+            currency: optional(&columns.currency)?,
+            fee: optional(&columns.fee)?,
+            tax: optional(&columns.tax)?,
+            note: optional(&columns.note)?,
+            external_id: optional(&columns.external_id)?,
+        })
+    }
+}
+
+/// Read and validate all rows of a CSV file. Errors in single rows are collected in the preview,
+/// only errors concerning the whole file (e.g. missing columns) are returned as error.
+pub fn parse_csv(profile: &MappingProfile, text: &str) -> Result<ImportPreview, ImportError> {
+    let rules = profile
+        .type_rules
+        .iter()
+        .map(|rule| {
+            RegexBuilder::new(&rule.pattern)
+                .case_insensitive(true)
+                .build()
+                .map(|regex| (regex, rule.kind))
+                .map_err(|_| ImportError::InvalidPattern(rule.pattern.clone()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let text = text.trim_start_matches('\u{feff}');
+    let body: String = text
+        .lines()
+        .skip(profile.skip_lines)
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(profile.delimiter as u8)
+        .has_headers(profile.has_headers)
+        .flexible(true)
+        .from_reader(body.as_bytes());
+    let headers = if profile.has_headers {
+        Some(rdr.headers()?.clone())
+    } else {
+        None
+    };
+    let columns = ColumnIndices::new(&profile.columns, headers.as_ref())?;
+
+    let mut preview = ImportPreview::default();
+    for record in rdr.records() {
+        let record = record?;
+        let line = record.position().map(|p| p.line() as usize).unwrap_or(0) + profile.skip_lines;
+        // Ignore empty lines and trailing summary lines without any content
+        if record.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+        match parse_row(profile, &rules, &columns, &record, line) {
+            Ok(Some(row)) => preview.rows.push(row),
+            Ok(None) => preview.skipped += 1,
+            Err(message) => preview.errors.push(RowError { line, message }),
+        }
+    }
+    Ok(preview)
+}
+
+fn parse_row(
+    profile: &MappingProfile,
+    rules: &[(regex::Regex, ImportKind)],
+    columns: &ColumnIndices,
+    record: &csv::StringRecord,
+    line: usize,
+) -> Result<Option<ImportRow>, String> {
+    let field = |index: Option<usize>| {
+        index
+            .and_then(|i| record.get(i))
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+    let number = |index: Option<usize>, name: &str| match field(index) {
+        Some(text) => parse_number(&text, profile)
+            .map(Some)
+            .ok_or_else(|| format!("invalid {} '{}'", name, text)),
+        None => Ok(None),
+    };
+
+    let type_text = field(columns.transaction_type.or(columns.note)).unwrap_or_default();
+    let kind = rules
+        .iter()
+        .find(|(regex, _)| regex.is_match(&type_text))
+        .map(|(_, kind)| *kind)
+        .ok_or_else(|| format!("no type rule matches '{}'", type_text))?;
+    if kind == ImportKind::Skip {
+        return Ok(None);
+    }
+
+    let date_text = field(Some(columns.date)).unwrap_or_default();
+    let date = parse_date(&date_text, &profile.date_format)
+        .ok_or_else(|| format!("invalid date '{}'", date_text))?;
+    let amount = number(Some(columns.amount), "amount")?.ok_or("missing amount")?;
+    let position = number(columns.position, "position")?;
+    let fee = number(columns.fee, "fee")?.unwrap_or(0.0).abs();
+    let tax = number(columns.tax, "tax")?.unwrap_or(0.0).abs();
+
+    // Brokers differ in the sign convention, the kind of transaction determines the direction
+    let (amount, position) = match kind {
+        ImportKind::Buy => (
+            -amount.abs(),
+            Some(position.ok_or("missing position")?.abs()),
+        ),
+        ImportKind::Sell => (
+            amount.abs(),
+            Some(-position.ok_or("missing position")?.abs()),
+        ),
+        ImportKind::Dividend | ImportKind::Deposit => (amount.abs(), None),
+        ImportKind::Withdrawal | ImportKind::Fee | ImportKind::Tax => (-amount.abs(), None),
+        ImportKind::Interest | ImportKind::Skip => (amount, None),
+    };
+
+    let row = ImportRow {
+        line,
+        date,
+        kind,
+        isin: field(columns.isin),
+        wkn: field(columns.wkn),
+        asset_name: field(columns.asset_name),
+        position,
+        amount,
+        currency: field(columns.currency).unwrap_or_else(|| profile.currency.clone()),
+        fee,
+        tax,
+        note: field(columns.note),
+        external_id: field(columns.external_id),
+    };
+    if kind.needs_asset() && row.asset_description().is_none() {
+        return Err("missing asset".to_string());
+    }
+    Ok(Some(row))
+}
+
+fn row_asset(row: &ImportRow) -> Asset {
+    Asset::Stock(Stock {
+        id: None,
+        name: row.asset_description().unwrap_or_default(),
+        wkn: row.wkn.clone(),
+        isin: row.isin.clone(),
+        note: None,
+    })
+}
+
+/// Id of the asset the row refers to, if it exists in the database
+pub async fn resolve_asset<DB: AssetHandler + Sync>(db: &DB, row: &ImportRow) -> Option<i32> {
+    row.asset_description()?;
+    db.get_asset_id(&row_asset(row)).await
+}
+
+/// Options of an import
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportOptions {
+    /// Name of the source, e.g. the mapping profile, which qualifies external transaction ids
+    pub source: String,
+    /// Create assets not yet in the database instead of aborting the import
+    pub create_missing_assets: bool,
+    /// Skip rows which have been imported into the account before
+    pub skip_duplicates: bool,
+}
+
+/// Summary of an import
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportReport {
+    /// Ids of all stored transactions, including related fee and tax transactions
+    pub transaction_ids: Vec<i32>,
+    /// Names of assets created for unknown assets
+    pub created_assets: Vec<String>,
+    /// Line numbers of rows skipped as duplicates
+    pub duplicates: Vec<usize>,
+}
+
+/// Flag all rows which have been imported into the account before
+pub async fn find_duplicate_rows<DB: FingerprintHandler + Sync>(
+    db: &DB,
+    account_id: i32,
+    source: &str,
+    rows: &[ImportRow],
+) -> Result<Vec<bool>, ImportError> {
+    let fingerprints = row_fingerprints(rows, source);
+    let existing = db.find_fingerprints(account_id, &fingerprints).await?;
+    Ok(fingerprints
+        .iter()
+        .map(|fingerprint| existing.contains(fingerprint))
+        .collect())
+}
+
+/// Store the rows as transactions of the given account together with their fingerprints.
+/// Unless `create_missing_assets` is set, all assets must exist already; this is checked before
+/// anything is written.
+pub async fn import_rows<DB>(
+    db: &DB,
+    market: &Market,
+    rows: &[ImportRow],
+    account_id: i32,
+    options: &ImportOptions,
+) -> Result<ImportReport, ImportError>
+where
+    DB: AccountHandler + AssetHandler + FingerprintHandler + Sync,
+{
+    let mut report = ImportReport::default();
+
+    let fingerprints = row_fingerprints(rows, &options.source);
+    let existing = db.find_fingerprints(account_id, &fingerprints).await?;
+    let mut new_rows = Vec::with_capacity(rows.len());
+    for (row, fingerprint) in rows.iter().zip(fingerprints) {
+        if options.skip_duplicates && existing.contains(&fingerprint) {
+            report.duplicates.push(row.line);
+            continue;
+        }
+        let asset_id = resolve_asset(db, row).await;
+        if asset_id.is_none() && row.kind.needs_asset() && !options.create_missing_assets {
+            return Err(ImportError::UnknownAsset(
+                row.line,
+                row.asset_description().unwrap_or_default(),
+            ));
+        }
+        new_rows.push((row, asset_id, fingerprint));
+    }
+
+    for (row, asset_id, fingerprint) in new_rows {
+        let currency = market.get_currency_from_str(&row.currency).await?;
+        let asset_id = match asset_id {
+            Some(asset_id) => Some(asset_id),
+            None if row.kind.needs_asset() => {
+                // An asset created for a previous row may be reused
+                match resolve_asset(db, row).await {
+                    Some(asset_id) => Some(asset_id),
+                    None => {
+                        report
+                            .created_assets
+                            .push(row.asset_description().unwrap_or_default());
+                        Some(db.insert_asset(&row_asset(row)).await?)
+                    }
+                }
+            }
+            None => None,
+        };
+        let transaction_type = match (row.kind, asset_id) {
+            (ImportKind::Buy, Some(asset_id)) | (ImportKind::Sell, Some(asset_id)) => {
+                TransactionType::Asset {
+                    asset_id,
+                    position: row.position.unwrap_or(0.0),
+                }
+            }
+            (ImportKind::Dividend, Some(asset_id)) => TransactionType::Dividend { asset_id },
+            // Interest on cash is attributed to the currency itself
+            (ImportKind::Interest, asset_id) => match asset_id.or(currency.id) {
+                Some(asset_id) => TransactionType::Interest { asset_id },
+                None => TransactionType::Cash,
+            },
+            (ImportKind::Fee, _) => TransactionType::Fee {
+                transaction_ref: None,
+            },
+            (ImportKind::Tax, _) => TransactionType::Tax {
+                transaction_ref: None,
+            },
+            _ => TransactionType::Cash,
+        };
+
+        let main_id = db
+            .insert_transaction(&Transaction {
+                id: None,
+                transaction_type,
+                cash_flow: CashFlow::new(row.amount, currency, row.date),
+                note: row.note.clone(),
+            })
+            .await?;
+        db.add_transaction_to_account(account_id, main_id).await?;
+        db.insert_fingerprint(account_id, main_id, &options.source, &fingerprint)
+            .await?;
+        report.transaction_ids.push(main_id);
+
+        let related = [
+            (
+                row.fee,
+                TransactionType::Fee {
+                    transaction_ref: Some(main_id as usize),
+                },
+            ),
+            (
+                row.tax,
+                TransactionType::Tax {
+                    transaction_ref: Some(main_id as usize),
+                },
+            ),
+        ];
+        for (amount, transaction_type) in related {
+            if amount == 0.0 {
+                continue;
+            }
+            let id = db
+                .insert_transaction(&Transaction {
+                    id: None,
+                    transaction_type,
+                    cash_flow: CashFlow::new(-amount, currency, row.date),
+                    note: None,
+                })
+                .await?;
+            db.add_transaction_to_account(account_id, id).await?;
+            report.transaction_ids.push(id);
+        }
+    }
+    Ok(report)
+}
+
+/// Store prices of an asset as quotes of a ticker of source "manual" with the given name, which
+/// is created if it does not exist. Prices for dates already stored are skipped, such that
+/// repeated imports only add new quotes. Returns the number of new quotes.
+pub async fn store_manual_quotes<DB: QuoteHandler + Sync>(
+    db: &DB,
+    market: &Market,
+    asset_id: i32,
+    ticker_name: &str,
+    currency: &str,
+    prices: &[(Date, f64)],
+) -> Result<usize, ImportError> {
+    if prices.is_empty() {
+        return Ok(0);
+    }
+    let existing = db
+        .get_all_ticker_for_asset(asset_id)
+        .await?
+        .into_iter()
+        .find(|t| t.source == "manual" && t.name == ticker_name);
+    let (ticker_id, known_dates) = match existing.and_then(|t| t.id) {
+        Some(ticker_id) => {
+            let dates: HashSet<Date> = db
+                .get_all_quotes_for_ticker(ticker_id)
+                .await?
+                .into_iter()
+                .map(|q| q.time.date())
+                .collect();
+            (ticker_id, dates)
+        }
+        None => {
+            let currency = market.get_currency_from_str(currency).await?;
+            let ticker_id = db
+                .insert_ticker(&Ticker {
+                    id: None,
+                    name: ticker_name.to_string(),
+                    asset: asset_id,
+                    currency,
+                    source: "manual".to_string(),
+                    priority: 10,
+                    factor: 1.0,
+                    cal: None,
+                    tz: None,
+                })
+                .await?;
+            (ticker_id, HashSet::new())
+        }
+    };
+    let mut count = 0;
+    for (date, price) in prices {
+        if known_dates.contains(date) {
+            continue;
+        }
+        db.insert_quote(&Quote {
+            id: None,
+            ticker: ticker_id,
+            price: *price,
+            time: date_to_offset_date_time(date, 18, None)?,
+            volume: None,
+        })
+        .await?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// All stored mapping profiles
+pub async fn get_import_profiles<DB: ObjectHandler + Sync>(db: &DB) -> Vec<MappingProfile> {
+    db.get_object(IMPORT_PROFILES_OBJECT)
+        .await
+        .unwrap_or_default()
+}
+
+pub async fn get_import_profile<DB: ObjectHandler + Sync>(
+    db: &DB,
+    name: &str,
+) -> Result<MappingProfile, ImportError> {
+    get_import_profiles(db)
+        .await
+        .into_iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| ImportError::UnknownProfile(name.to_string()))
+}
+
+/// Store the profile, replacing any profile of the same name
+pub async fn save_import_profile<DB: ObjectHandler + Sync>(
+    db: &DB,
+    profile: &MappingProfile,
+) -> Result<(), ImportError> {
+    let mut profiles = get_import_profiles(db).await;
+    match profiles.iter_mut().find(|p| p.name == profile.name) {
+        Some(existing) => *existing = profile.clone(),
+        None => profiles.push(profile.clone()),
+    }
+    db.store_object(IMPORT_PROFILES_OBJECT, &profiles).await?;
+    Ok(())
+}
+
+pub async fn delete_import_profile<DB: ObjectHandler + Sync>(
+    db: &DB,
+    name: &str,
+) -> Result<(), ImportError> {
+    let mut profiles = get_import_profiles(db).await;
+    profiles.retain(|p| p.name != name);
+    db.store_object(IMPORT_PROFILES_OBJECT, &profiles).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    fn profile() -> MappingProfile {
+        serde_json::from_str(
+            r#"{
+                "name": "Test",
+                "delimiter": ";",
+                "skip_lines": 1,
+                "date_format": "%d.%m.%Y",
+                "decimal_separator": ",",
+                "thousands_separator": ".",
+                "columns": {
+                    "date": "Datum",
+                    "amount": "Betrag",
+                    "transaction_type": "Art",
+                    "isin": "ISIN",
+                    "position": "Stück",
+                    "fee": "Provision"
+                },
+                "type_rules": [
+                    { "pattern": "^kauf", "kind": "Buy" },
+                    { "pattern": "^verkauf", "kind": "Sell" },
+                    { "pattern": "dividende", "kind": "Dividend" },
+                    { "pattern": "saldo", "kind": "Skip" }
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_number() {
+        let profile = profile();
+        assert_eq!(parse_number("1.234,56 €", &profile), Some(1234.56));
+        assert_eq!(parse_number("12,5-", &profile), Some(-12.5));
+        assert_eq!(parse_number("", &profile), None);
+        assert_eq!(parse_number("n/a", &profile), None);
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let text = "Depot 1234567\n\
+            Datum;Art;ISIN;Stück;Betrag;Provision\n\
+            02.01.2023;Kauf;DE0001234567;10;1.000,00;4,95\n\
+            15.03.2023;Verkauf;DE0001234567;-5;550,00;\n\
+            05.05.2023;Dividende;DE0001234567;;12,34;\n\
+            31.12.2023;Saldo;;;1.234,00;\n\
+            32.12.2023;Kauf;DE0001234567;1;10,00;\n\
+            01.06.2023;Umbuchung;;;10,00;\n";
+        let preview = parse_csv(&profile(), text).unwrap();
+        assert_eq!(preview.rows.len(), 3);
+        assert_eq!(preview.skipped, 1);
+        assert_eq!(preview.errors.len(), 2);
+        assert_eq!(preview.errors[0].line, 7);
+        assert!(preview.errors[1].message.contains("Umbuchung"));
+
+        let buy = &preview.rows[0];
+        assert_eq!(buy.line, 3);
+        assert_eq!(buy.date, date!(2023 - 01 - 02));
+        assert_eq!(buy.kind, ImportKind::Buy);
+        assert_eq!(buy.isin.as_deref(), Some("DE0001234567"));
+        assert_eq!(buy.position, Some(10.0));
+        assert_eq!(buy.amount, -1000.0);
+        assert_eq!(buy.fee, 4.95);
+        assert_eq!(buy.currency, "EUR");
+
+        let sell = &preview.rows[1];
+        assert_eq!(sell.position, Some(-5.0));
+        assert_eq!(sell.amount, 550.0);
+        assert_eq!(sell.fee, 0.0);
+        assert_eq!(preview.rows[2].kind, ImportKind::Dividend);
+    }
+
+    #[test]
+    fn test_missing_column() {
+        let text = "skipped\nDatum;Betrag\n01.01.2023;1,00\n";
+        assert!(matches!(
+            parse_csv(&profile(), text),
+            Err(ImportError::MissingColumn(_))
+        ));
+    }
+}
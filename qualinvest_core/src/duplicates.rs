@@ -0,0 +1,234 @@
+//! Detection of duplicated transactions
+//!
+//! Every imported transaction gets a fingerprint, which is either the transaction id assigned by
+//! the source (e.g. the broker) or a hash of date, type, amount, asset and position. Importing the
+//! same file twice then finds all rows already stored. Transactions entered before fingerprints
+//! were introduced or entered manually are covered by `find_duplicate_transactions`, which
+//! lists transactions of an account agreeing in all relevant fields.
+use std::collections::{HashMap, HashSet};
+
+use async_trait::async_trait;
+use data_encoding::HEXLOWER;
+use ring::digest;
+use serde::{Deserialize, Serialize};
+use time::Date;
+
+use finql::datatypes::DataError;
+use finql::postgres::PostgresDB;
+
+use crate::csv_import::ImportRow;
+
+/// Fingerprint of a single row, see `row_fingerprints`
+fn fingerprint(row: &ImportRow, source: &str) -> String {
+    if let Some(external_id) = &row.external_id {
+        return format!("{}:{}", source, external_id);
+    }
+    let asset = row
+        .isin
+        .as_ref()
+        .or(row.wkn.as_ref())
+        .or(row.asset_name.as_ref())
+        .map(|a| a.trim().to_uppercase())
+        .unwrap_or_default();
+    let key = format!(
+        "{}|{}|{}|{:.6}|{:.2}|{:.2}|{:.2}|{}",
+        row.date,
+        row.kind,
+        asset,
+        row.position.unwrap_or(0.0),
+        row.amount,
+        row.fee,
+        row.tax,
+        row.currency.to_uppercase()
+    );
+    let hash = digest::digest(&digest::SHA256, key.as_bytes());
+    format!("sha256:{}", HEXLOWER.encode(hash.as_ref()))
+}
+
+/// Fingerprints of all rows of an import. Rows with an external id are identified by source and
+/// id, all others by a hash of their content, which is independent of the source. Identical rows
+/// within one file (e.g. two equal orders on the same day) are numbered, such that they are
+/// distinguished from each other but still match on a repeated import.
+pub fn row_fingerprints(rows: &[ImportRow], source: &str) -> Vec<String> {
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+    rows.iter()
+        .map(|row| {
+            let fingerprint = fingerprint(row, source);
+            let count = occurrences.entry(fingerprint.clone()).or_insert(0);
+            *count += 1;
+            if *count == 1 {
+                fingerprint
+            } else {
+                format!("{}#{}", fingerprint, count)
+            }
+        })
+        .collect()
+}
+
+/// Group of transactions of one account which agree in date, type, asset, position and amount
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub account_id: i32,
+    pub date: Date,
+    pub transaction_type: String,
+    pub asset_id: Option<i32>,
+    pub position: Option<f64>,
+    pub amount: f64,
+    pub currency: String,
+    pub transaction_ids: Vec<i32>,
+}
+
+#[async_trait]
+pub trait FingerprintHandler {
+    /// Remove all fingerprints, the table is kept
+    async fn clean_fingerprints(&self) -> Result<(), DataError>;
+
+    async fn insert_fingerprint(
+        &self,
+        account_id: i32,
+        transaction_id: i32,
+        source: &str,
+        fingerprint: &str,
+    ) -> Result<(), DataError>;
+
+    /// Return the subset of the given fingerprints already stored for the account
+    async fn find_fingerprints(
+        &self,
+        account_id: i32,
+        fingerprints: &[String],
+    ) -> Result<HashSet<String>, DataError>;
+
+    /// Find suspected duplicates among all transactions of the given accounts. Fees and taxes
+    /// related to other transactions are not considered on their own.
+    async fn find_duplicate_transactions(
+        &self,
+        account_ids: &[i32],
+    ) -> Result<Vec<DuplicateGroup>, DataError>;
+}
+
+#[async_trait]
+impl FingerprintHandler for PostgresDB {
+    async fn clean_fingerprints(&self) -> Result<(), DataError> {
+        sqlx::query!("TRUNCATE transaction_fingerprints")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn insert_fingerprint(
+        &self,
+        account_id: i32,
+        transaction_id: i32,
+        source: &str,
+        fingerprint: &str,
+    ) -> Result<(), DataError> {
+        sqlx::query!(
+            "INSERT INTO transaction_fingerprints (account_id, transaction_id, source, fingerprint)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (account_id, fingerprint) DO NOTHING",
+            account_id,
+            transaction_id,
+            source,
+            fingerprint,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn find_fingerprints(
+        &self,
+        account_id: i32,
+        fingerprints: &[String],
+    ) -> Result<HashSet<String>, DataError> {
+        let rows = sqlx::query!(
+            "SELECT fingerprint FROM transaction_fingerprints
+            WHERE account_id = $1 AND fingerprint = ANY($2)",
+            account_id,
+            fingerprints,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(|row| row.fingerprint).collect())
+    }
+
+    async fn find_duplicate_transactions(
+        &self,
+        account_ids: &[i32],
+    ) -> Result<Vec<DuplicateGroup>, DataError> {
+        let rows = sqlx::query!(
+            r#"SELECT a.account_id, t.cash_date, t.trans_type, t.asset_id, t.position,
+                t.cash_amount, c.iso_code, array_agg(t.id ORDER BY t.id) AS "ids!"
+            FROM transactions t
+                JOIN account_transactions a ON a.transaction_id = t.id
+                JOIN currencies c ON c.id = t.cash_currency_id
+            WHERE a.account_id = ANY($1) AND t.related_trans IS NULL
+            GROUP BY a.account_id, t.cash_date, t.trans_type, t.asset_id, t.position,
+                t.cash_amount, c.iso_code
+            HAVING COUNT(*) > 1
+            ORDER BY a.account_id, t.cash_date"#,
+            account_ids,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| DuplicateGroup {
+                account_id: row.account_id,
+                date: row.cash_date,
+                transaction_type: row.trans_type,
+                asset_id: row.asset_id,
+                position: row.position,
+                amount: row.cash_amount,
+                currency: row.iso_code,
+                transaction_ids: row.ids,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv_import::ImportKind;
+    use time::macros::date;
+
+    fn row() -> ImportRow {
+        ImportRow {
+            line: 2,
+            date: date!(2023 - 01 - 02),
+            kind: ImportKind::Buy,
+            isin: Some("DE0001234567".to_string()),
+            wkn: None,
+            asset_name: Some("ACME Corp.".to_string()),
+            position: Some(10.0),
+            amount: -1000.0,
+            currency: "EUR".to_string(),
+            fee: 4.95,
+            tax: 0.0,
+            note: None,
+            external_id: None,
+        }
+    }
+
+    #[test]
+    fn test_row_fingerprints() {
+        let mut other = row();
+        other.line = 3;
+        other.position = Some(11.0);
+        let rows = vec![row(), other, row()];
+        let fingerprints = row_fingerprints(&rows, "Broker");
+        assert!(fingerprints[0].starts_with("sha256:"));
+        assert_ne!(fingerprints[0], fingerprints[1]);
+        assert_eq!(fingerprints[2], format!("{}#2", fingerprints[0]));
+        // Content hashes do not depend on source, line number or asset name
+        let mut renamed = row();
+        renamed.line = 10;
+        renamed.asset_name = Some("Acme".to_string());
+        assert_eq!(row_fingerprints(&[renamed], "Other")[0], fingerprints[0]);
+
+        let mut with_id = row();
+        with_id.external_id = Some("4711".to_string());
+        assert_eq!(row_fingerprints(&[with_id], "Broker")[0], "Broker:4711");
+    }
+}
@@ -0,0 +1,266 @@
+//! Inflation adjustment of performance and simulation results
+//!
+//! A consumer price index (e.g. the Eurostat HICP) is stored like the quotes of any other ticker.
+//! Nominal values are converted into real terms of a base year by multiplying them with the
+//! ratio of the average index level in the base year and the index level at the value's date.
+//! The asset of the ticker is no investment and is therefore left out of the lists of assets to
+//! choose from, see `get_cpi_asset_ids`.
+use std::io::Read;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use time::Date;
+
+use finql::datatypes::{
+    date_time_helper::date_to_offset_date_time, Asset, AssetHandler, Currency, DataError,
+    ObjectHandler, Quote, QuoteHandler, Stock, Ticker, Transaction,
+};
+use finql::time_series::TimeValue;
+
+/// Name of the object the list of registered price index series is stored as
+const CPI_SERIES_OBJECT: &str = "cpi_series";
+
+#[derive(Error, Debug)]
+pub enum InflationError {
+    #[error("Database access failed")]
+    DataError(#[from] DataError),
+    #[error("Failed to read CSV file")]
+    CsvError(#[from] csv::Error),
+    #[error("Invalid date '{0}', expected YYYY-MM or YYYY-MM-DD")]
+    InvalidDate(String),
+    #[error("Invalid index value '{0}'")]
+    InvalidValue(String),
+    #[error("Date calculation error")]
+    DateError(#[from] finql::datatypes::date_time_helper::DateTimeError),
+    #[error("No price index values for base year {0}")]
+    MissingBaseYear(i32),
+}
+
+/// Consumer price index series registered for inflation adjustment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CpiSeries {
+    pub ticker_id: i32,
+    pub asset_id: i32,
+    pub name: String,
+}
+
+/// Parse price index values from a CSV file
+///
+/// Eurostat's SDMX-CSV downloads are supported by looking for the columns `TIME_PERIOD` and
+/// `OBS_VALUE`. Any other file must have the date in the first and the index value in the second
+/// column. Monthly periods like `2023-05` are mapped to the first day of the month.
+pub fn parse_cpi_csv<R: Read>(reader: R) -> Result<Vec<(Date, f64)>, InflationError> {
+    let mut rdr = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+    let headers = rdr.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|h| h.eq_ignore_ascii_case(name));
+    let (date_col, value_col) = match (column("TIME_PERIOD"), column("OBS_VALUE")) {
+        (Some(date_col), Some(value_col)) => (date_col, value_col),
+        _ => (0, 1),
+    };
+
+    let mut values = Vec::new();
+    for record in rdr.records() {
+        let record = record?;
+        let (date, value) = match (record.get(date_col), record.get(value_col)) {
+            (Some(date), Some(value)) => (date.trim(), value.trim()),
+            _ => continue,
+        };
+        // Eurostat marks missing observations with an empty value or ':'
+        if value.is_empty() || value == ":" {
+            continue;
+        }
+        let value: f64 = value
+            .parse()
+            .map_err(|_| InflationError::InvalidValue(value.to_string()))?;
+        values.push((parse_period(date)?, value));
+    }
+    values.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(values)
+}
+
+fn parse_period(period: &str) -> Result<Date, InflationError> {
+    let date = crate::csv_import::parse_date(period, "%Y-%m-%d")
+        .or_else(|| crate::csv_import::parse_date(&format!("{}-01", period), "%Y-%m-%d"));
+    date.ok_or_else(|| InflationError::InvalidDate(period.to_string()))
+}
+
+/// Store the price index values as quotes of a new ticker and register the series
+/// for inflation adjustment. Returns the id of the new ticker.
+pub async fn register_cpi_series<DB>(
+    db: &DB,
+    name: &str,
+    currency: Currency,
+    values: &[(Date, f64)],
+) -> Result<i32, InflationError>
+where
+    DB: AssetHandler + QuoteHandler + ObjectHandler + Sync,
+{
+    let asset_id = db
+        .insert_asset(&Asset::Stock(Stock {
+            id: None,
+            name: name.to_string(),
+            wkn: None,
+            isin: None,
+            note: Some("consumer price index".to_string()),
+        }))
+        .await?;
+    let ticker_id = db
+        .insert_ticker(&Ticker {
+            id: None,
+            name: name.to_string(),
+            asset: asset_id,
+            currency,
+            source: "manual".to_string(),
+            priority: 10,
+            factor: 1.0,
+            cal: None,
+            tz: None,
+        })
+        .await?;
+    for (date, value) in values {
+        db.insert_quote(&Quote {
+            id: None,
+            ticker: ticker_id,
+            price: *value,
+            time: date_to_offset_date_time(date, 0, None)?,
+            volume: None,
+        })
+        .await?;
+    }
+
+    let mut series = get_cpi_series(db).await;
+    series.push(CpiSeries {
+        ticker_id,
+        asset_id,
+        name: name.to_string(),
+    });
+    db.store_object(CPI_SERIES_OBJECT, &series).await?;
+    Ok(ticker_id)
+}
+
+/// List of all price index series registered for inflation adjustment
+pub async fn get_cpi_series<DB: ObjectHandler + Sync>(db: &DB) -> Vec<CpiSeries> {
+    db.get_object(CPI_SERIES_OBJECT).await.unwrap_or_default()
+}
+
+/// Ids of the assets the registered price index series are stored as
+pub async fn get_cpi_asset_ids<DB: ObjectHandler + Sync>(db: &DB) -> Vec<i32> {
+    get_cpi_series(db)
+        .await
+        .into_iter()
+        .map(|series| series.asset_id)
+        .collect()
+}
+
+/// Converts nominal values into real values in terms of a base year
+#[derive(Debug, Clone)]
+pub struct Deflator {
+    /// Price index values sorted by date
+    index: Vec<(Date, f64)>,
+    /// Average index level in base year
+    base_level: f64,
+}
+
+impl Deflator {
+    pub fn new(mut index: Vec<(Date, f64)>, base_year: i32) -> Result<Self, InflationError> {
+        index.sort_by(|a, b| a.0.cmp(&b.0));
+        let base_values: Vec<f64> = index
+            .iter()
+            .filter(|(date, _)| date.year() == base_year)
+            .map(|(_, value)| *value)
+            .collect();
+        if base_values.is_empty() {
+            return Err(InflationError::MissingBaseYear(base_year));
+        }
+        let base_level = base_values.iter().sum::<f64>() / base_values.len() as f64;
+        Ok(Self { index, base_level })
+    }
+
+    /// Load the price index from the quotes of the given ticker
+    pub async fn from_ticker(
+        db: &(dyn QuoteHandler + Send + Sync),
+        ticker_id: i32,
+        base_year: i32,
+    ) -> Result<Self, InflationError> {
+        let index = db
+            .get_all_quotes_for_ticker(ticker_id)
+            .await?
+            .into_iter()
+            .map(|q| (q.time.date(), q.price))
+            .collect();
+        Self::new(index, base_year)
+    }
+
+    /// Factor that converts a nominal value at the given date into base year terms,
+    /// using the latest index value at or before this date
+    pub fn factor(&self, date: Date) -> f64 {
+        let idx = self.index.partition_point(|(d, _)| *d <= date);
+        match idx {
+            // Before the first index value, the first value is the best guess
+            0 => self.base_level / self.index[0].1,
+            _ => self.base_level / self.index[idx - 1].1,
+        }
+    }
+
+    pub fn deflate(&self, value: f64, date: Date) -> f64 {
+        value * self.factor(date)
+    }
+
+    /// Deflate the cash flow of each transaction at its own date
+    pub fn deflate_transactions(&self, transactions: &[Transaction]) -> Vec<Transaction> {
+        transactions
+            .iter()
+            .map(|t| {
+                let mut t = t.clone();
+                t.cash_flow.amount.amount =
+                    self.deflate(t.cash_flow.amount.amount, t.cash_flow.date);
+                t
+            })
+            .collect()
+    }
+
+    pub fn deflate_series(&self, series: &[TimeValue]) -> Vec<TimeValue> {
+        series
+            .iter()
+            .map(|tv| TimeValue {
+                time: tv.time,
+                value: self.deflate(tv.value, tv.time.date()),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    #[test]
+    fn test_parse_eurostat_csv() {
+        let csv = "DATAFLOW,LAST UPDATE,freq,unit,coicop,geo,TIME_PERIOD,OBS_VALUE,OBS_FLAG\n\
+            ESTAT:PRC_HICP_MIDX(1.0),15/01/24,M,I15,CP00,EA,2023-02,121.32,\n\
+            ESTAT:PRC_HICP_MIDX(1.0),15/01/24,M,I15,CP00,EA,2023-01,120.52,\n\
+            ESTAT:PRC_HICP_MIDX(1.0),15/01/24,M,I15,CP00,EA,2023-03,:,\n";
+        let values = parse_cpi_csv(csv.as_bytes()).unwrap();
+        assert_eq!(
+            values,
+            vec![
+                (date!(2023 - 01 - 01), 120.52),
+                (date!(2023 - 02 - 01), 121.32)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deflate() {
+        let index = vec![
+            (date!(2020 - 01 - 01), 100.0),
+            (date!(2020 - 07 - 01), 102.0),
+            (date!(2021 - 01 - 01), 110.0),
+        ];
+        let deflator = Deflator::new(index, 2020).unwrap();
+        assert!((deflator.deflate(110.0, date!(2021 - 03 - 15)) - 101.0).abs() < 1e-10);
+        assert!((deflator.deflate(100.0, date!(2019 - 12 - 31)) - 101.0).abs() < 1e-10);
+        assert!(Deflator::new(Vec::new(), 2020).is_err());
+    }
+}
@@ -0,0 +1,535 @@
+//! Checks of the database for inconsistencies
+//!
+//! Over the years, manual edits, partial imports and bugs of earlier versions leave traces like
+//! transactions without account or orphaned tickers. `check_integrity` scans the database for
+//! such issues and explains how to fix each of them. Some issues have an obvious solution, e.g.
+//! assigning a transaction to the account of the transaction it belongs to; these come with a
+//! `Fix` that can be applied by `apply_fix`. Fixes that move or delete data are marked by
+//! `Fix::requires_confirmation` and should only be applied on explicit request.
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use time::macros::datetime;
+use time::OffsetDateTime;
+
+use finql::datatypes::{DataError, ObjectHandler};
+use finql::postgres::PostgresDB;
+
+/// Positions below this threshold are considered to be zero
+const POSITION_TOLERANCE: f64 = 1e-6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IssueKind {
+    /// Transaction not linked to any account
+    TransactionWithoutAccount,
+    /// Transaction related to a transaction in another account
+    CrossAccountRelation,
+    /// Position of an asset in an account becomes negative
+    NegativePosition,
+    /// Cash transactions in a currency without FX ticker to the base currency
+    MissingFxTicker,
+    TickerWithoutQuotes,
+    /// Quotes before the inception date or in the future
+    QuoteOutsideRange,
+    /// Non-admin user without rights on any account
+    UserWithoutRights,
+}
+
+impl fmt::Display for IssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            IssueKind::TransactionWithoutAccount => "transaction without account",
+            IssueKind::CrossAccountRelation => "cross account relation",
+            IssueKind::NegativePosition => "negative position",
+            IssueKind::MissingFxTicker => "missing FX ticker",
+            IssueKind::TickerWithoutQuotes => "ticker without quotes",
+            IssueKind::QuoteOutsideRange => "quote outside range",
+            IssueKind::UserWithoutRights => "user without rights",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+/// Automatic fix of an issue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Fix {
+    /// Link a transaction without account to the given account
+    AssignAccount {
+        transaction_id: i32,
+        account_id: i32,
+    },
+    /// Move a transaction into the account of the transaction it is related to
+    MoveToAccount {
+        transaction_id: i32,
+        account_id: i32,
+    },
+    /// Delete the quotes of a ticker before the inception date or in the future
+    DeleteQuotesOutsideRange { ticker_id: i32 },
+}
+
+impl Fix {
+    /// True if the fix deletes data, which can't be restored afterwards
+    pub fn deletes_data(&self) -> bool {
+        matches!(self, Fix::DeleteQuotesOutsideRange { .. })
+    }
+
+    /// True if the fix changes existing data instead of only completing it, e.g. moves a
+    /// transaction and thereby changes the balances of two accounts
+    pub fn requires_confirmation(&self) -> bool {
+        self.deletes_data() || matches!(self, Fix::MoveToAccount { .. })
+    }
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fix::AssignAccount {
+                transaction_id,
+                account_id,
+            } => write!(
+                f,
+                "assign transaction {} to account {}",
+                transaction_id, account_id
+            ),
+            Fix::MoveToAccount {
+                transaction_id,
+                account_id,
+            } => write!(
+                f,
+                "move transaction {} to account {}",
+                transaction_id, account_id
+            ),
+            Fix::DeleteQuotesOutsideRange { ticker_id } => write!(
+                f,
+                "delete quotes of ticker {} outside the inception range",
+                ticker_id
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Issue {
+    pub kind: IssueKind,
+    /// Description of the inconsistency
+    pub message: String,
+    /// How to fix the issue
+    pub hint: String,
+    pub fix: Option<Fix>,
+}
+
+/// Net change of the position of an asset in an account on a single day
+#[derive(Debug, Clone, PartialEq)]
+struct DailyPosition {
+    account_id: i32,
+    asset_id: Option<i32>,
+    name: Option<String>,
+    cash_date: String,
+    position: f64,
+}
+
+/// Days on which the position of an asset in an account becomes negative for the first time,
+/// together with the position at the end of that day. Transactions of the same day are netted,
+/// since their order within a day is unknown. The days must be ordered by account, asset and
+/// date.
+fn first_negative_positions(days: &[DailyPosition]) -> Vec<(&DailyPosition, f64)> {
+    let mut negative = Vec::new();
+    let mut running_position = 0.0;
+    let mut reported = false;
+    for (i, day) in days.iter().enumerate() {
+        let previous = i.checked_sub(1).map(|i| &days[i]);
+        if previous.is_none_or(|p| p.account_id != day.account_id || p.asset_id != day.asset_id) {
+            running_position = 0.0;
+            reported = false;
+        }
+        running_position += day.position;
+        if !reported && running_position < -POSITION_TOLERANCE {
+            negative.push((day, running_position));
+            reported = true;
+        }
+    }
+    negative
+}
+
+fn without_account_issue(
+    transaction_id: i32,
+    trans_type: &str,
+    cash_date: &str,
+    suggested_account: Option<i32>,
+) -> Issue {
+    Issue {
+        kind: IssueKind::TransactionWithoutAccount,
+        message: format!(
+            "Transaction {} of type '{}' on {} is not linked to any account",
+            transaction_id, trans_type, cash_date
+        ),
+        hint: match suggested_account {
+            Some(account_id) => format!(
+                "Assign it to account {} of its related transactions",
+                account_id
+            ),
+            None => "Assign it to an account or delete it".to_string(),
+        },
+        fix: suggested_account.map(|account_id| Fix::AssignAccount {
+            transaction_id,
+            account_id,
+        }),
+    }
+}
+
+fn cross_account_issue(
+    transaction_id: i32,
+    account_id: i32,
+    related_trans: i32,
+    related_account: i32,
+) -> Issue {
+    Issue {
+        kind: IssueKind::CrossAccountRelation,
+        message: format!(
+            "Transaction {} in account {} is related to transaction {} in account {}",
+            transaction_id, account_id, related_trans, related_account
+        ),
+        hint: format!(
+            "Move transaction {} into account {}, if it has been booked to the wrong account",
+            transaction_id, related_account
+        ),
+        fix: Some(Fix::MoveToAccount {
+            transaction_id,
+            account_id: related_account,
+        }),
+    }
+}
+
+#[derive(Deserialize)]
+struct InceptionSettings {
+    inception_date: OffsetDateTime,
+}
+
+/// Inception date of the global settings, i.e. the earliest date of relevant market data
+pub async fn inception_date(db: &PostgresDB) -> OffsetDateTime {
+    let settings: Result<InceptionSettings, DataError> = db.get_object("global_settings").await;
+    settings
+        .map(|settings| settings.inception_date)
+        .unwrap_or(datetime!(2000-01-01 0:00 UTC))
+}
+
+/// Scan the database for inconsistencies, FX tickers are expected for all currencies of cash
+/// transactions other than the base currency
+pub async fn check_integrity(
+    db: &PostgresDB,
+    base_currency: &str,
+) -> Result<Vec<Issue>, DataError> {
+    let mut issues = Vec::new();
+
+    let rows = sqlx::query!(
+        r#"SELECT t.id, t.trans_type, t.cash_date::TEXT AS "cash_date!",
+            COALESCE(ra.account_id, ca.account_id) AS "suggested_account?"
+        FROM transactions t
+        LEFT JOIN account_transactions ta ON ta.transaction_id = t.id
+        LEFT JOIN account_transactions ra ON ra.transaction_id = t.related_trans
+        LEFT JOIN LATERAL (
+            SELECT a.account_id FROM transactions c
+            JOIN account_transactions a ON a.transaction_id = c.id
+            WHERE c.related_trans = t.id LIMIT 1
+        ) ca ON true
+        WHERE ta.id IS NULL
+        ORDER BY t.id"#
+    )
+    .fetch_all(&db.pool)
+    .await?;
+    for row in rows {
+        issues.push(without_account_issue(
+            row.id,
+            &row.trans_type,
+            &row.cash_date,
+            row.suggested_account,
+        ));
+    }
+
+    let rows = sqlx::query!(
+        r#"SELECT t.id, ta.account_id, t.related_trans AS "related_trans!",
+            ra.account_id AS related_account
+        FROM transactions t
+        JOIN account_transactions ta ON ta.transaction_id = t.id
+        JOIN account_transactions ra ON ra.transaction_id = t.related_trans
+        WHERE ta.account_id <> ra.account_id
+        ORDER BY t.id"#
+    )
+    .fetch_all(&db.pool)
+    .await?;
+    for row in rows {
+        issues.push(cross_account_issue(
+            row.id,
+            row.account_id,
+            row.related_trans,
+            row.related_account,
+        ));
+    }
+
+    let days = sqlx::query_as!(
+        DailyPosition,
+        r#"SELECT a.account_id, t.asset_id, s.name AS "name?",
+            t.cash_date::TEXT AS "cash_date!", SUM(t.position) AS "position!"
+        FROM transactions t
+        JOIN account_transactions a ON a.transaction_id = t.id
+        LEFT JOIN stocks s ON s.id = t.asset_id
+        WHERE t.trans_type = 'a' AND t.position IS NOT NULL
+        GROUP BY a.account_id, t.asset_id, s.name, t.cash_date
+        ORDER BY a.account_id, t.asset_id, t.cash_date"#
+    )
+    .fetch_all(&db.pool)
+    .await?;
+    for (day, position) in first_negative_positions(&days) {
+        issues.push(Issue {
+            kind: IssueKind::NegativePosition,
+            message: format!(
+                "Position of asset {} in account {} becomes {} on {}",
+                day.name
+                    .clone()
+                    .or_else(|| day.asset_id.map(|id| id.to_string()))
+                    .unwrap_or_default(),
+                day.account_id,
+                position,
+                day.cash_date
+            ),
+            hint: "Add the missing purchase or correct the position of the sale".to_string(),
+            fix: None,
+        });
+    }
+
+    let rows = sqlx::query!(
+        "SELECT DISTINCT c.id, c.iso_code
+        FROM transactions t
+        JOIN currencies c ON c.id = t.cash_currency_id
+        LEFT JOIN currencies b ON b.iso_code = $1
+        WHERE c.iso_code <> $1 AND NOT EXISTS (
+            SELECT 1 FROM ticker k
+            WHERE (k.asset_id = c.id AND k.currency_id = b.id)
+                OR (k.asset_id = b.id AND k.currency_id = c.id)
+        )
+        ORDER BY c.iso_code",
+        base_currency
+    )
+    .fetch_all(&db.pool)
+    .await?;
+    for row in rows {
+        let iso_code = row.iso_code;
+        issues.push(Issue {
+            kind: IssueKind::MissingFxTicker,
+            message: format!(
+                "Cash transactions in {} can't be converted to {}",
+                iso_code, base_currency
+            ),
+            hint: format!(
+                "Add a ticker with FX rates of {} in {}, e.g. {}{}=X from yahoo",
+                iso_code, base_currency, iso_code, base_currency
+            ),
+            fix: None,
+        });
+    }
+
+    let rows = sqlx::query!(
+        "SELECT k.id, k.name FROM ticker k
+        WHERE NOT EXISTS (SELECT 1 FROM quotes q WHERE q.ticker_id = k.id)
+        ORDER BY k.id",
+    )
+    .fetch_all(&db.pool)
+    .await?;
+    for row in rows {
+        let ticker_id = row.id;
+        issues.push(Issue {
+            kind: IssueKind::TickerWithoutQuotes,
+            message: format!("Ticker {} ({}) has no quotes", ticker_id, row.name),
+            hint: format!(
+                "Load its history with `qualinvest update --history --ticker-id {}` or delete the ticker",
+                ticker_id
+            ),
+            fix: None,
+        });
+    }
+
+    let inception = inception_date(db).await;
+    let rows = sqlx::query!(
+        r#"SELECT k.id, k.name, COUNT(q.id) AS "count!"
+        FROM quotes q JOIN ticker k ON k.id = q.ticker_id
+        WHERE q.time < $1 OR q.time::date > current_date
+        GROUP BY k.id, k.name
+        ORDER BY k.id"#,
+        inception
+    )
+    .fetch_all(&db.pool)
+    .await?;
+    for row in rows {
+        let ticker_id = row.id;
+        issues.push(Issue {
+            kind: IssueKind::QuoteOutsideRange,
+            message: format!(
+                "Ticker {} ({}) has {} quotes before {} or in the future",
+                ticker_id,
+                row.name,
+                row.count,
+                inception.date()
+            ),
+            hint: "Quotes in the future are wrong and quotes before the inception date are \
+                usually not needed; delete them unless they are still used, e.g. by simulations"
+                .to_string(),
+            fix: Some(Fix::DeleteQuotesOutsideRange { ticker_id }),
+        });
+    }
+
+    let rows = sqlx::query!(
+        "SELECT u.id, u.name FROM users u
+        WHERE NOT u.is_admin
+            AND NOT EXISTS (SELECT 1 FROM account_rights r WHERE r.user_id = u.id)
+        ORDER BY u.id",
+    )
+    .fetch_all(&db.pool)
+    .await?;
+    for row in rows {
+        let name = row.name;
+        issues.push(Issue {
+            kind: IssueKind::UserWithoutRights,
+            message: format!("User {} has no rights on any account", name),
+            hint: format!(
+                "Grant rights with `qualinvest rights grant {} <account ids>` or delete the user",
+                name
+            ),
+            fix: None,
+        });
+    }
+
+    Ok(issues)
+}
+
+/// Apply an automatic fix found by `check_integrity`
+pub async fn apply_fix(db: &PostgresDB, fix: &Fix) -> Result<(), DataError> {
+    match fix {
+        Fix::AssignAccount {
+            transaction_id,
+            account_id,
+        } => {
+            sqlx::query!(
+                "INSERT INTO account_transactions (account_id, transaction_id)
+                SELECT $1, $2 WHERE NOT EXISTS (
+                    SELECT 1 FROM account_transactions WHERE transaction_id = $2
+                )",
+                account_id,
+                transaction_id
+            )
+            .execute(&db.pool)
+            .await?;
+        }
+        Fix::MoveToAccount {
+            transaction_id,
+            account_id,
+        } => {
+            sqlx::query!(
+                "UPDATE account_transactions SET account_id = $1 WHERE transaction_id = $2",
+                account_id,
+                transaction_id
+            )
+            .execute(&db.pool)
+            .await?;
+        }
+        Fix::DeleteQuotesOutsideRange { ticker_id } => {
+            let inception = inception_date(db).await;
+            sqlx::query!(
+                "DELETE FROM quotes WHERE ticker_id = $1
+                AND (time < $2 OR time::date > current_date)",
+                ticker_id,
+                inception
+            )
+            .execute(&db.pool)
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fix_round_trip() {
+        let fixes = [
+            Fix::AssignAccount {
+                transaction_id: 1,
+                account_id: 2,
+            },
+            Fix::MoveToAccount {
+                transaction_id: 3,
+                account_id: 4,
+            },
+            Fix::DeleteQuotesOutsideRange { ticker_id: 5 },
+        ];
+        for fix in fixes.iter() {
+            let json = serde_json::to_string(fix).unwrap();
+            assert_eq!(&serde_json::from_str::<Fix>(&json).unwrap(), fix);
+        }
+        assert_eq!(fixes[0].to_string(), "assign transaction 1 to account 2");
+        assert!(!fixes[1].deletes_data());
+        assert!(fixes[2].deletes_data());
+        assert!(!fixes[0].requires_confirmation());
+        assert!(fixes[1].requires_confirmation());
+        assert!(fixes[2].requires_confirmation());
+    }
+
+    fn day(account_id: i32, asset_id: i32, cash_date: &str, position: f64) -> DailyPosition {
+        DailyPosition {
+            account_id,
+            asset_id: Some(asset_id),
+            name: None,
+            cash_date: cash_date.to_string(),
+            position,
+        }
+    }
+
+    #[test]
+    fn test_first_negative_positions() {
+        let days = vec![
+            // Purchase and sale on the same day, netted by the query
+            day(1, 10, "2023-01-02", 0.0),
+            day(1, 10, "2023-02-01", -5.0),
+            day(1, 10, "2023-03-01", -1.0),
+            day(1, 20, "2023-01-02", 10.0),
+            // Rounding errors are tolerated
+            day(1, 20, "2023-02-01", -10.0 - 1e-9),
+            // Positions are tracked per account
+            day(2, 10, "2023-01-02", 5.0),
+            day(2, 10, "2023-01-03", -5.0),
+        ];
+        let negative: Vec<(&str, i32, f64)> = first_negative_positions(&days)
+            .into_iter()
+            .map(|(day, position)| (day.cash_date.as_str(), day.account_id, position))
+            .collect();
+        // Only the first day with a negative position is reported
+        assert_eq!(negative, vec![("2023-02-01", 1, -5.0)]);
+    }
+
+    #[test]
+    fn test_issue_fixes() {
+        let issue = without_account_issue(1, "a", "2023-01-02", Some(2));
+        assert_eq!(
+            issue.fix,
+            Some(Fix::AssignAccount {
+                transaction_id: 1,
+                account_id: 2
+            })
+        );
+        assert!(!issue.fix.unwrap().requires_confirmation());
+        let issue = without_account_issue(1, "a", "2023-01-02", None);
+        assert_eq!(issue.fix, None);
+
+        let issue = cross_account_issue(3, 1, 4, 2);
+        assert_eq!(issue.kind, IssueKind::CrossAccountRelation);
+        assert_eq!(
+            issue.fix,
+            Some(Fix::MoveToAccount {
+                transaction_id: 3,
+                account_id: 2
+            })
+        );
+        assert!(issue.fix.unwrap().requires_confirmation());
+    }
+}
@@ -0,0 +1,324 @@
+//! Persistent state of long running background jobs
+//!
+//! Jobs like strategy simulations or filling gaps in quote histories may take much longer than
+//! a single request should. Their status, progress and final result is stored in the database,
+//! such that clients can poll for it and results survive the request that started the job.
+use std::fmt;
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use finql::datatypes::DataError;
+use finql::postgres::PostgresDB;
+
+use crate::scheduler::SCHEDULED_JOB_PREFIX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JobStatus {
+    /// Waiting for a free worker
+    Queued,
+    Running,
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    /// True if the job will not change its status anymore
+    pub fn is_done(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Finished | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+impl fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            JobStatus::Queued => "Queued",
+            JobStatus::Running => "Running",
+            JobStatus::Finished => "Finished",
+            JobStatus::Failed => "Failed",
+            JobStatus::Cancelled => "Cancelled",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+impl FromStr for JobStatus {
+    type Err = DataError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Queued" => Ok(JobStatus::Queued),
+            "Running" => Ok(JobStatus::Running),
+            "Finished" => Ok(JobStatus::Finished),
+            "Failed" => Ok(JobStatus::Failed),
+            "Cancelled" => Ok(JobStatus::Cancelled),
+            _ => Err(DataError::DataAccessFailure(format!(
+                "unknown job status '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: i32,
+    /// Type of the job, e.g. "simulation"
+    pub kind: String,
+    /// User who submitted the job, if any
+    pub user_id: Option<i32>,
+    pub status: JobStatus,
+    /// Fraction of work done, in [0, 1]
+    pub progress: f64,
+    /// Description of the current step or error message
+    pub message: Option<String>,
+    /// Serialized result of a finished job
+    pub result: Option<String>,
+    pub created: OffsetDateTime,
+    pub finished: Option<OffsetDateTime>,
+}
+
+#[async_trait]
+pub trait JobHandler {
+    /// Store a new job in queued state and return its id
+    async fn insert_job(&self, kind: &str, user_id: Option<i32>) -> Result<i32, DataError>;
+
+    async fn get_job(&self, id: i32) -> Result<Job, DataError>;
+
+    /// Get the most recent jobs, either of all users or of the given user only
+    async fn get_jobs(&self, user_id: Option<i32>, limit: i64) -> Result<Vec<Job>, DataError>;
+
+    /// Get the most recent job of each kind starting with the given prefix
+    async fn get_latest_jobs(&self, kind_prefix: &str) -> Result<Vec<Job>, DataError>;
+
+    async fn set_job_running(&self, id: i32) -> Result<(), DataError>;
+
+    async fn set_job_progress(
+        &self,
+        id: i32,
+        progress: f64,
+        message: Option<&str>,
+    ) -> Result<(), DataError>;
+
+    /// Store the final status of a job together with its result or error message.
+    /// Jobs already done (e.g. cancelled) are not changed anymore.
+    async fn finish_job(
+        &self,
+        id: i32,
+        status: JobStatus,
+        result: Option<&str>,
+        message: Option<&str>,
+    ) -> Result<(), DataError>;
+
+    /// Mark all jobs that are still queued or running as failed, e.g. after a restart of the
+    /// server that executed them. Jobs of the scheduler are not affected, since they are run by
+    /// the separate daemon process. Returns the number of jobs affected.
+    async fn fail_interrupted_jobs(&self) -> Result<u64, DataError>;
+
+    /// Mark all jobs of the scheduler that are still queued or running as failed, e.g. after a
+    /// restart of the daemon. Returns the number of jobs affected.
+    async fn fail_interrupted_scheduled_jobs(&self) -> Result<u64, DataError>;
+}
+
+/// Row of the table `jobs`, the status is converted when building a `Job`
+struct JobRow {
+    id: i32,
+    kind: String,
+    user_id: Option<i32>,
+    status: String,
+    progress: f64,
+    message: Option<String>,
+    result: Option<String>,
+    created: OffsetDateTime,
+    finished: Option<OffsetDateTime>,
+}
+
+fn job_from_row(row: JobRow) -> Result<Job, DataError> {
+    Ok(Job {
+        id: row.id,
+        kind: row.kind,
+        user_id: row.user_id,
+        status: row.status.parse()?,
+        progress: row.progress,
+        message: row.message,
+        result: row.result,
+        created: row.created,
+        finished: row.finished,
+    })
+}
+
+#[async_trait]
+impl JobHandler for PostgresDB {
+    async fn insert_job(&self, kind: &str, user_id: Option<i32>) -> Result<i32, DataError> {
+        let row = sqlx::query!(
+            "INSERT INTO jobs (kind, user_id, status) VALUES ($1, $2, $3) RETURNING id",
+            kind,
+            user_id,
+            JobStatus::Queued.to_string(),
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.id)
+    }
+
+    async fn get_job(&self, id: i32) -> Result<Job, DataError> {
+        let row = sqlx::query_as!(
+            JobRow,
+            "SELECT id, kind, user_id, status, progress, message, result, created, finished
+            FROM jobs WHERE id = $1",
+            id,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        job_from_row(row)
+    }
+
+    async fn get_jobs(&self, user_id: Option<i32>, limit: i64) -> Result<Vec<Job>, DataError> {
+        // Results are not loaded, since they might be large
+        sqlx::query_as!(
+            JobRow,
+            r#"SELECT id, kind, user_id, status, progress, message, NULL::TEXT AS "result?",
+                created, finished
+            FROM jobs WHERE $1::INTEGER IS NULL OR user_id = $1
+            ORDER BY created DESC LIMIT $2"#,
+            user_id,
+            limit,
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(job_from_row)
+        .collect()
+    }
+
+    async fn get_latest_jobs(&self, kind_prefix: &str) -> Result<Vec<Job>, DataError> {
+        sqlx::query_as!(
+            JobRow,
+            r#"SELECT DISTINCT ON (kind) id, kind, user_id, status, progress, message,
+                NULL::TEXT AS "result?", created, finished
+            FROM jobs WHERE starts_with(kind, $1)
+            ORDER BY kind, created DESC"#,
+            kind_prefix,
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(job_from_row)
+        .collect()
+    }
+
+    async fn set_job_running(&self, id: i32) -> Result<(), DataError> {
+        sqlx::query!(
+            "UPDATE jobs SET status = $2 WHERE id = $1 AND status = $3",
+            id,
+            JobStatus::Running.to_string(),
+            JobStatus::Queued.to_string(),
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn set_job_progress(
+        &self,
+        id: i32,
+        progress: f64,
+        message: Option<&str>,
+    ) -> Result<(), DataError> {
+        sqlx::query!(
+            "UPDATE jobs SET progress = $2, message = $3 WHERE id = $1",
+            id,
+            progress.clamp(0.0, 1.0),
+            message,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn finish_job(
+        &self,
+        id: i32,
+        status: JobStatus,
+        result: Option<&str>,
+        message: Option<&str>,
+    ) -> Result<(), DataError> {
+        let progress = if status == JobStatus::Finished {
+            1.0
+        } else {
+            0.0
+        };
+        sqlx::query!(
+            "UPDATE jobs SET status = $2, result = $3, message = $4, finished = now(),
+                progress = GREATEST(progress, $5)
+            WHERE id = $1 AND status IN ($6, $7)",
+            id,
+            status.to_string(),
+            result,
+            message,
+            progress,
+            JobStatus::Queued.to_string(),
+            JobStatus::Running.to_string(),
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn fail_interrupted_jobs(&self) -> Result<u64, DataError> {
+        let result = sqlx::query!(
+            "UPDATE jobs SET status = $1, message = 'interrupted by server shutdown',
+                finished = now()
+            WHERE status IN ($2, $3) AND NOT starts_with(kind, $4)",
+            JobStatus::Failed.to_string(),
+            JobStatus::Queued.to_string(),
+            JobStatus::Running.to_string(),
+            SCHEDULED_JOB_PREFIX,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn fail_interrupted_scheduled_jobs(&self) -> Result<u64, DataError> {
+        let result = sqlx::query!(
+            "UPDATE jobs SET status = $1, message = 'interrupted by daemon shutdown',
+                finished = now()
+            WHERE status IN ($2, $3) AND starts_with(kind, $4)",
+            JobStatus::Failed.to_string(),
+            JobStatus::Queued.to_string(),
+            JobStatus::Running.to_string(),
+            SCHEDULED_JOB_PREFIX,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_status_round_trip() {
+        for status in [
+            JobStatus::Queued,
+            JobStatus::Running,
+            JobStatus::Finished,
+            JobStatus::Failed,
+            JobStatus::Cancelled,
+        ] {
+            assert_eq!(status.to_string().parse::<JobStatus>().unwrap(), status);
+        }
+        assert!("Done".parse::<JobStatus>().is_err());
+        assert!(JobStatus::Cancelled.is_done());
+        assert!(!JobStatus::Running.is_done());
+    }
+}
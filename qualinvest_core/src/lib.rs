@@ -15,11 +15,27 @@ use finql::{
 };
 
 pub mod accounts;
+pub mod backup;
+pub mod bank_statement;
+pub mod csv_import;
+pub mod duplicates;
+pub mod inflation;
+pub mod integrity;
+pub mod jobs;
 pub mod performance;
+pub mod plain_text_accounting;
 pub mod plot;
+pub mod portfolio_performance;
 pub mod position;
 pub mod postgres_user;
+pub mod quote_import;
+pub mod replay;
 pub mod sanitization;
+pub mod scheduler;
+pub mod schema;
+pub mod statement_parser;
+pub mod strategy;
+pub mod synthetic;
 pub mod user;
 
 /// Configuration parameters
@@ -28,6 +44,8 @@ pub struct Config {
     pub db: DbParams,
     pub market_data: MarketDataProviders,
     pub server: ServerSettings,
+    #[serde(default)]
+    pub scheduler: scheduler::SchedulerConfig,
     pub debug: bool,
 }
 
@@ -53,6 +71,10 @@ pub struct MarketDataProviders {
     pub alpha_vantage_token: Option<String>,
     pub gurufocus_token: Option<String>,
     pub eod_historical_data_token: Option<String>,
+    /// Directory of the quote files of tickers of source "file"
+    pub quote_directory: Option<String>,
+    #[serde(default)]
+    pub quote_file_format: quote_import::QuoteCsvFormat,
 }
 
 /// Server settings
@@ -92,6 +114,15 @@ fn set_market_providers(market: &finql::Market, providers: &MarketDataProviders)
         &providers.eod_historical_data_token,
         MarketDataSource::EodHistData,
     );
+    if let Some(directory) = &providers.quote_directory {
+        market.add_provider(
+            quote_import::FILE_SOURCE.to_string(),
+            Arc::new(quote_import::FileQuoteProvider::new(
+                directory.into(),
+                providers.quote_file_format.clone(),
+            )),
+        );
+    }
 }
 
 pub async fn setup_market(
@@ -104,6 +135,19 @@ pub async fn setup_market(
 }
 
 pub async fn fill_quote_gaps(market: &mut Market, min_size: usize) -> Result<(), MarketError> {
+    fill_quote_gaps_with_progress(market, min_size, |_, _| {}).await
+}
+
+/// Like `fill_quote_gaps`, but calls `progress` with the number of tickers processed so far
+/// and the total number of tickers after each ticker
+pub async fn fill_quote_gaps_with_progress<F>(
+    market: &mut Market,
+    min_size: usize,
+    progress: F,
+) -> Result<(), MarketError>
+where
+    F: Fn(usize, usize) + Send,
+{
     use finql::datatypes::date_time_helper::date_to_offset_date_time;
     use finql::time_series::{TimeSeries, TimeValue};
 
@@ -119,7 +163,9 @@ pub async fn fill_quote_gaps(market: &mut Market, min_size: usize) -> Result<(),
         2000,
         today.year(),
     )?;
-    for ticker in tickers {
+    let total = tickers.len();
+    for (count, ticker) in tickers.into_iter().enumerate() {
+        progress(count, total);
         if let Some(ticker_id) = ticker.id {
             let quotes = market.db().get_all_quotes_for_ticker(ticker_id).await?;
             let quote_series: Vec<TimeValue> = quotes
@@ -151,6 +197,7 @@ pub async fn fill_quote_gaps(market: &mut Market, min_size: usize) -> Result<(),
             }
         }
     }
+    progress(total, total);
 
     Ok(())
 }
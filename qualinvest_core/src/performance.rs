@@ -69,3 +69,92 @@ pub async fn calc_performance(
     }
     Ok(total_return)
 }
+
+/// Summary statistics of a series of total portfolio values
+///
+/// Since the values include deposits and withdrawals, the change in value is not a return in the
+/// strict sense, but only an overview of how the portfolio evolved over the period.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerformanceSummary {
+    pub start: Date,
+    pub end: Date,
+    pub start_value: f64,
+    pub end_value: f64,
+    pub min_value: f64,
+    pub max_value: f64,
+    /// Largest relative decline from a previous maximum, as a positive fraction
+    pub max_drawdown: f64,
+}
+
+impl PerformanceSummary {
+    /// Summarize a series of total values, returns `None` for an empty series
+    pub fn from_series(series: &[TimeValue]) -> Option<Self> {
+        let first = series.first()?;
+        let last = series.last()?;
+        let mut summary = PerformanceSummary {
+            start: first.time.date(),
+            end: last.time.date(),
+            start_value: first.value,
+            end_value: last.value,
+            min_value: first.value,
+            max_value: first.value,
+            max_drawdown: 0.0,
+        };
+        for tv in series {
+            summary.min_value = summary.min_value.min(tv.value);
+            summary.max_value = summary.max_value.max(tv.value);
+            if summary.max_value > 0.0 {
+                summary.max_drawdown = summary
+                    .max_drawdown
+                    .max((summary.max_value - tv.value) / summary.max_value);
+            }
+        }
+        Some(summary)
+    }
+
+    /// Change in value over the whole period
+    pub fn change(&self) -> f64 {
+        self.end_value - self.start_value
+    }
+
+    /// Change in value relative to the start value, `None` if the start value is zero
+    pub fn relative_change(&self) -> Option<f64> {
+        if self.start_value == 0.0 {
+            None
+        } else {
+            Some(self.change() / self.start_value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::{date, datetime};
+
+    #[test]
+    fn test_performance_summary() {
+        assert_eq!(PerformanceSummary::from_series(&[]), None);
+
+        let series: Vec<TimeValue> = [
+            (datetime!(2023-01-02 20:00 UTC), 100.0),
+            (datetime!(2023-01-03 20:00 UTC), 120.0),
+            (datetime!(2023-01-04 20:00 UTC), 90.0),
+            (datetime!(2023-01-05 20:00 UTC), 110.0),
+        ]
+        .iter()
+        .map(|(time, value)| TimeValue {
+            time: *time,
+            value: *value,
+        })
+        .collect();
+        let summary = PerformanceSummary::from_series(&series).unwrap();
+        assert_eq!(summary.start, date!(2023 - 01 - 02));
+        assert_eq!(summary.end, date!(2023 - 01 - 05));
+        assert_eq!(summary.min_value, 90.0);
+        assert_eq!(summary.max_value, 120.0);
+        assert_eq!(summary.change(), 10.0);
+        assert_eq!(summary.relative_change(), Some(0.1));
+        assert_eq!(summary.max_drawdown, 0.25);
+    }
+}
@@ -0,0 +1,1219 @@
+//! Export and import of journals in plain-text accounting formats
+//!
+//! Accounts, transactions and quotes can be written as beancount file or in the syntax of
+//! ledger and hledger, such that qualinvest serves as investment sub-ledger of a plain-text
+//! bookkeeping. Each account is written as asset account `Assets:<Broker>:<Account>` holding
+//! cash and assets, with sub-accounts for income, expenses and transfers, e.g.
+//! `Income:<Broker>:<Account>:Dividends`. Assets become commodities named by their ISIN (or
+//! `WKN-<wkn>` or `ASSET-<id>`) and quotes become price directives.
+//!
+//! Each transaction is written as one journal entry, with related fees and taxes as additional
+//! postings. The transaction id is stored as metadata `id`, such that importing a journal again
+//! only adds new entries (see `crate::duplicates`). The importer reads the subset of both
+//! syntaxes written by the exporter.
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+use thiserror::Error;
+use time::{Date, Month};
+
+use finql::datatypes::{Asset, AssetHandler, DataError, QuoteHandler, Stock, TransactionType};
+use finql::market::MarketError;
+use finql::Market;
+
+use crate::accounts::{Account, AccountHandler};
+use crate::csv_import::{
+    import_rows, store_manual_quotes, ImportError, ImportKind, ImportOptions, ImportReport,
+    ImportRow,
+};
+use crate::duplicates::FingerprintHandler;
+
+/// Source of the fingerprints of imported journal entries
+const JOURNAL_SOURCE: &str = "Plain text accounting";
+
+#[derive(Error, Debug)]
+pub enum JournalError {
+    #[error("Database access failed")]
+    DataError(#[from] DataError),
+    #[error("Market data access failed")]
+    MarketError(#[from] MarketError),
+    #[error("Failed to import transactions")]
+    ImportError(#[from] ImportError),
+    #[error("Invalid journal in line {0}: {1}")]
+    InvalidLine(usize, String),
+    #[error("Unknown account {0}")]
+    UnknownAccount(i32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalFormat {
+    Beancount,
+    /// Syntax understood by ledger and hledger
+    Ledger,
+}
+
+impl fmt::Display for JournalFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JournalFormat::Beancount => write!(f, "beancount"),
+            JournalFormat::Ledger => write!(f, "ledger"),
+        }
+    }
+}
+
+impl FromStr for JournalFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "beancount" => Ok(JournalFormat::Beancount),
+            "ledger" | "hledger" => Ok(JournalFormat::Ledger),
+            _ => Err(format!("unknown journal format '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalCommodity {
+    pub symbol: String,
+    pub name: String,
+    pub isin: Option<String>,
+    pub wkn: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalAccount {
+    /// Name of the asset account in the journal, e.g. `Assets:MyBank:Depot`
+    pub path: String,
+    pub broker: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalEntry {
+    /// Path of the asset account
+    pub account: String,
+    /// Symbol of the asset traded or paying dividends
+    pub asset: Option<String>,
+    pub row: ImportRow,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalPrice {
+    pub date: Date,
+    pub symbol: String,
+    pub price: f64,
+    pub currency: String,
+}
+
+/// Content of a journal as far as written by the exporter
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Journal {
+    /// Operating currency
+    pub currency: String,
+    pub commodities: Vec<JournalCommodity>,
+    pub accounts: Vec<JournalAccount>,
+    pub entries: Vec<JournalEntry>,
+    pub prices: Vec<JournalPrice>,
+}
+
+fn is_isin(s: &str) -> bool {
+    s.len() == 12
+        && s.chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        && s.chars().take(2).all(|c| c.is_ascii_uppercase())
+}
+
+/// Commodity symbol of an asset, valid in beancount and ledger (if quoted)
+pub fn commodity_symbol(asset_id: i32, stock: &Stock) -> String {
+    let normalize = |s: &str| -> String {
+        s.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_uppercase()
+    };
+    if let Some(isin) = stock.isin.as_deref().map(normalize) {
+        if is_isin(&isin) {
+            return isin;
+        }
+    }
+    match stock.wkn.as_deref().map(normalize) {
+        Some(wkn) if !wkn.is_empty() => format!("WKN-{}", wkn),
+        _ => format!("ASSET-{}", asset_id),
+    }
+}
+
+/// Convert a name into an account name component, which must start with a capital letter or
+/// digit and contain only letters, digits and dashes
+fn account_component(name: &str) -> String {
+    let transliterated = name
+        .replace('ä', "ae")
+        .replace('ö', "oe")
+        .replace('ü', "ue")
+        .replace('Ä', "Ae")
+        .replace('Ö', "Oe")
+        .replace('Ü', "Ue")
+        .replace('ß', "ss");
+    let words: Vec<String> = transliterated
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let mut chars = w.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect();
+    if words.is_empty() {
+        "Unknown".to_string()
+    } else {
+        words.join("-")
+    }
+}
+
+/// Journal account of the given kind belonging to an asset account, e.g.
+/// `Income:MyBank:Depot:Dividends` for `Assets:MyBank:Depot`
+fn sub_account(path: &str, root: &str, leaf: &str) -> String {
+    let rest = path.strip_prefix("Assets:").unwrap_or(path);
+    format!("{}:{}:{}", root, rest, leaf)
+}
+
+const SUB_ACCOUNTS: [(&str, &str); 6] = [
+    ("Income", "Dividends"),
+    ("Income", "Interest"),
+    ("Income", "Gains"),
+    ("Expenses", "Fees"),
+    ("Expenses", "Taxes"),
+    ("Equity", "Transfers"),
+];
+
+/// Format a number with the given maximal number of decimal digits, omitting trailing zeros
+/// beyond `min_digits`
+fn format_number(value: f64, min_digits: usize, max_digits: usize) -> String {
+    let mut s = format!("{:.*}", max_digits, value);
+    if max_digits > min_digits {
+        let dot = s.find('.').unwrap_or(s.len());
+        let min_len = dot + 1 + min_digits;
+        while s.len() > min_len && s.ends_with('0') {
+            s.pop();
+        }
+        if s.ends_with('.') {
+            s.pop();
+        }
+    }
+    if s.starts_with('-') && s[1..].chars().all(|c| c == '0' || c == '.') {
+        s.remove(0);
+    }
+    s
+}
+
+fn cash(value: f64) -> String {
+    format_number(value, 2, 2)
+}
+
+struct JournalWriter {
+    format: JournalFormat,
+    out: String,
+}
+
+impl JournalWriter {
+    fn quote(&self, text: &str) -> String {
+        let text = text.replace('\n', " ");
+        match self.format {
+            JournalFormat::Beancount => format!("\"{}\"", text.replace('"', "\\\"")),
+            JournalFormat::Ledger => text,
+        }
+    }
+
+    fn symbol(&self, symbol: &str) -> String {
+        match self.format {
+            JournalFormat::Beancount => symbol.to_string(),
+            JournalFormat::Ledger => format!("\"{}\"", symbol),
+        }
+    }
+
+    fn meta(&mut self, key: &str, value: &str) {
+        let value = self.quote(value);
+        let _ = match self.format {
+            JournalFormat::Beancount => writeln!(self.out, "  {}: {}", key, value),
+            JournalFormat::Ledger => writeln!(self.out, "    ; {}: {}", key, value),
+        };
+    }
+
+    fn posting(&mut self, account: &str, amount: Option<String>) {
+        let indent = match self.format {
+            JournalFormat::Beancount => "  ",
+            JournalFormat::Ledger => "    ",
+        };
+        let _ = match amount {
+            Some(amount) => writeln!(self.out, "{}{}  {}", indent, account, amount),
+            None => writeln!(self.out, "{}{}", indent, account),
+        };
+    }
+
+    fn entry(&mut self, entry: &JournalEntry, names: &HashMap<&str, &str>) {
+        let row = &entry.row;
+        let account = entry.account.as_str();
+        let currency = row.currency.as_str();
+        let description = match entry.asset.as_deref() {
+            Some(symbol) => format!(
+                "{} {}",
+                row.kind,
+                names.get(symbol).copied().unwrap_or(symbol)
+            ),
+            None => row.kind.to_string(),
+        };
+        let header = format!("{} * {}", row.date, self.quote(&description));
+        let _ = writeln!(self.out, "{}", header);
+        if let Some(id) = &row.external_id {
+            self.meta("id", id);
+        }
+        if let Some(note) = &row.note {
+            self.meta("note", note);
+        }
+
+        let net = row.amount - row.fee - row.tax;
+        match (row.kind, row.position, entry.asset.as_deref()) {
+            (ImportKind::Buy, Some(position), Some(symbol))
+            | (ImportKind::Sell, Some(position), Some(symbol)) => {
+                let total = format!("{} {}", cash(row.amount.abs()), currency);
+                let amount = match (self.format, row.kind) {
+                    (JournalFormat::Beancount, ImportKind::Buy) => format!("{{{{{}}}}}", total),
+                    (JournalFormat::Beancount, _) => format!("{{}} @@ {}", total),
+                    (JournalFormat::Ledger, _) => format!("@@ {}", total),
+                };
+                let position = format!(
+                    "{} {} {}",
+                    format_number(position, 0, 8),
+                    self.symbol(symbol),
+                    amount
+                );
+                self.posting(account, Some(position));
+                if net != 0.0 {
+                    self.posting(account, Some(format!("{} {}", cash(net), currency)));
+                }
+            }
+            _ => {
+                self.meta_asset(entry);
+                self.posting(account, Some(format!("{} {}", cash(net), currency)));
+                let (root, leaf) = match row.kind {
+                    ImportKind::Dividend => ("Income", "Dividends"),
+                    ImportKind::Interest => ("Income", "Interest"),
+                    ImportKind::Fee => ("Expenses", "Fees"),
+                    ImportKind::Tax => ("Expenses", "Taxes"),
+                    _ => ("Equity", "Transfers"),
+                };
+                self.posting(
+                    &sub_account(account, root, leaf),
+                    Some(format!("{} {}", cash(-row.amount), currency)),
+                );
+            }
+        }
+        if row.fee != 0.0 {
+            self.posting(
+                &sub_account(account, "Expenses", "Fees"),
+                Some(format!("{} {}", cash(row.fee), currency)),
+            );
+        }
+        if row.tax != 0.0 {
+            self.posting(
+                &sub_account(account, "Expenses", "Taxes"),
+                Some(format!("{} {}", cash(row.tax), currency)),
+            );
+        }
+        // Beancount books the difference between sale price and cost as capital gains
+        if self.format == JournalFormat::Beancount && row.kind == ImportKind::Sell {
+            self.posting(&sub_account(account, "Income", "Gains"), None);
+        }
+        self.out.push('\n');
+    }
+
+    fn meta_asset(&mut self, entry: &JournalEntry) {
+        if let Some(symbol) = &entry.asset {
+            self.meta("asset", symbol);
+        }
+    }
+}
+
+/// Write the journal in the given format
+pub fn write_journal(journal: &Journal, format: JournalFormat) -> String {
+    let mut writer = JournalWriter {
+        format,
+        out: String::new(),
+    };
+    // Accounts and commodities must be opened before their first use in beancount
+    let start = journal
+        .entries
+        .iter()
+        .map(|e| e.row.date)
+        .chain(journal.prices.iter().map(|p| p.date))
+        .min()
+        .unwrap_or_else(|| Date::from_calendar_date(1970, Month::January, 1).unwrap());
+
+    match format {
+        JournalFormat::Beancount => {
+            let _ = writeln!(
+                writer.out,
+                "option \"operating_currency\" \"{}\"\n",
+                journal.currency
+            );
+        }
+        JournalFormat::Ledger => {
+            let _ = writeln!(writer.out, "; operating currency: {}\n", journal.currency);
+        }
+    }
+
+    for account in &journal.accounts {
+        match format {
+            JournalFormat::Beancount => {
+                let _ = writeln!(writer.out, "{} open {}", start, account.path);
+                writer.meta("broker", &account.broker);
+                writer.meta("name", &account.name);
+                for (root, leaf) in SUB_ACCOUNTS.iter() {
+                    let _ = writeln!(
+                        writer.out,
+                        "{} open {}",
+                        start,
+                        sub_account(&account.path, root, leaf)
+                    );
+                }
+            }
+            JournalFormat::Ledger => {
+                let _ = writeln!(writer.out, "account {}", account.path);
+                writer.meta("broker", &account.broker);
+                writer.meta("name", &account.name);
+            }
+        }
+        writer.out.push('\n');
+    }
+
+    for commodity in &journal.commodities {
+        match format {
+            JournalFormat::Beancount => {
+                let _ = writeln!(writer.out, "{} commodity {}", start, commodity.symbol);
+            }
+            JournalFormat::Ledger => {
+                let _ = writeln!(writer.out, "commodity \"{}\"", commodity.symbol);
+            }
+        }
+        writer.meta("name", &commodity.name);
+        if let Some(isin) = &commodity.isin {
+            writer.meta("isin", isin);
+        }
+        if let Some(wkn) = &commodity.wkn {
+            writer.meta("wkn", wkn);
+        }
+        writer.out.push('\n');
+    }
+
+    let names: HashMap<&str, &str> = journal
+        .commodities
+        .iter()
+        .map(|c| (c.symbol.as_str(), c.name.as_str()))
+        .collect();
+    let mut entries: Vec<&JournalEntry> = journal.entries.iter().collect();
+    entries.sort_by_key(|e| e.row.date);
+    for entry in entries {
+        writer.entry(entry, &names);
+    }
+
+    let mut prices: Vec<&JournalPrice> = journal.prices.iter().collect();
+    prices.sort_by(|a, b| (a.date, &a.symbol).cmp(&(b.date, &b.symbol)));
+    for price in prices {
+        let price_text = format_number(price.price, 2, 8);
+        let _ = match format {
+            JournalFormat::Beancount => writeln!(
+                writer.out,
+                "{} price {} {} {}",
+                price.date, price.symbol, price_text, price.currency
+            ),
+            JournalFormat::Ledger => writeln!(
+                writer.out,
+                "P {} \"{}\" {} {}",
+                price.date, price.symbol, price_text, price.currency
+            ),
+        };
+    }
+    writer.out
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Amount {
+    value: f64,
+    commodity: String,
+    /// Total price or cost given as `{{...}}` or `@@ ...`, per unit prices are converted
+    total_price: Option<(f64, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Posting {
+    account: String,
+    amount: Option<Amount>,
+}
+
+fn unquote(s: &str) -> String {
+    let s = s.trim();
+    if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
+        s[1..s.len() - 1].replace("\\\"", "\"")
+    } else {
+        s.to_string()
+    }
+}
+
+fn parse_date(text: &str) -> Option<Date> {
+    crate::csv_import::parse_date(text, "%Y-%m-%d")
+        .or_else(|| crate::csv_import::parse_date(text, "%Y/%m/%d"))
+}
+
+/// Parse a value followed by a commodity, e.g. `10 "DE0001234567"` or `-4.95 EUR`
+fn parse_value(text: &str) -> Option<(f64, String)> {
+    let text = text.trim();
+    let split = text.find(char::is_whitespace)?;
+    let value = text[..split].replace(',', "").parse().ok()?;
+    let commodity = unquote(&text[split..]);
+    if commodity.is_empty() {
+        return None;
+    }
+    Some((value, commodity))
+}
+
+fn parse_amount(text: &str) -> Option<Amount> {
+    let split = text.find(|c| c == '{' || c == '@').unwrap_or(text.len());
+    let (value, commodity) = parse_value(&text[..split])?;
+    let annotation = &text[split..];
+    let price_text: String = annotation
+        .chars()
+        .filter(|c| !matches!(c, '{' | '}' | '@'))
+        .collect();
+    let total_price = if price_text.trim().is_empty() {
+        None
+    } else {
+        let (price, currency) = parse_value(&price_text)?;
+        if annotation.contains("{{") || annotation.contains("@@") {
+            Some((price, currency))
+        } else {
+            Some((price * value.abs(), currency))
+        }
+    };
+    Some(Amount {
+        value,
+        commodity,
+        total_price,
+    })
+}
+
+fn parse_posting(text: &str) -> Option<Posting> {
+    let text = text.trim();
+    let split = text
+        .find("  ")
+        .into_iter()
+        .chain(text.find('\t'))
+        .min()
+        .unwrap_or(text.len());
+    let account = text[..split].trim().to_string();
+    let amount_text = text[split..].trim();
+    let amount = if amount_text.is_empty() {
+        None
+    } else {
+        Some(parse_amount(amount_text)?)
+    };
+    Some(Posting { account, amount })
+}
+
+/// Split a metadata line, i.e. `key: "value"` (beancount) or `; key: value` (ledger)
+fn parse_meta(text: &str) -> Option<(String, String)> {
+    let text = text.trim().trim_start_matches(';').trim();
+    let (key, value) = text.split_once(':')?;
+    if key.is_empty()
+        || !key.starts_with(|c: char| c.is_ascii_lowercase())
+        || !key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return None;
+    }
+    Some((key.to_string(), unquote(value)))
+}
+
+enum Block {
+    None,
+    Account(JournalAccount),
+    Commodity(JournalCommodity),
+    Transaction {
+        line: usize,
+        date: Date,
+        meta: HashMap<String, String>,
+        postings: Vec<Posting>,
+    },
+}
+
+fn account_from_path(path: &str) -> JournalAccount {
+    let parts: Vec<&str> = path.split(':').collect();
+    JournalAccount {
+        path: path.to_string(),
+        broker: parts.get(1).copied().unwrap_or_default().to_string(),
+        name: parts.get(2..).map(|p| p.join(":")).unwrap_or_default(),
+    }
+}
+
+fn transaction_entry(
+    line: usize,
+    date: Date,
+    meta: &HashMap<String, String>,
+    postings: &[Posting],
+    journal: &Journal,
+) -> Result<JournalEntry, JournalError> {
+    let invalid = |msg: &str| JournalError::InvalidLine(line, msg.to_string());
+    let account = postings
+        .iter()
+        .find(|p| p.account.starts_with("Assets:"))
+        .ok_or_else(|| invalid("no asset account"))?
+        .account
+        .clone();
+
+    let (mut cash, mut fee, mut tax) = (0.0, 0.0, 0.0);
+    let mut currency = None;
+    let mut position = None;
+    let mut income = None;
+    let mut transfer = false;
+    for posting in postings {
+        let amount = match &posting.amount {
+            Some(amount) => amount,
+            None => continue,
+        };
+        let leaf = posting.account.rsplit(':').next().unwrap_or_default();
+        if posting.account == account {
+            match &amount.total_price {
+                Some((total, price_currency)) => {
+                    currency = Some(price_currency.clone());
+                    position = Some((amount.value, amount.commodity.clone(), *total));
+                }
+                None => {
+                    cash += amount.value;
+                    currency = Some(amount.commodity.clone());
+                }
+            }
+        } else if posting.account.starts_with("Expenses:") && leaf == "Fees" {
+            fee += amount.value;
+        } else if posting.account.starts_with("Expenses:") && leaf == "Taxes" {
+            tax += amount.value;
+        } else if posting.account.starts_with("Income:") && leaf == "Dividends" {
+            income = Some((ImportKind::Dividend, -amount.value));
+        } else if posting.account.starts_with("Income:") && leaf == "Interest" {
+            income = Some((ImportKind::Interest, -amount.value));
+        } else if posting.account.starts_with("Equity:") && leaf == "Transfers" {
+            transfer = true;
+        }
+    }
+    let round = |x: f64| (x * 100.0).round() / 100.0;
+
+    let mut asset = meta.get("asset").cloned();
+    let (kind, amount, position, fee, tax) = match (position, income) {
+        (Some((shares, symbol, total)), _) => {
+            asset = Some(symbol);
+            if shares >= 0.0 {
+                (ImportKind::Buy, -total, Some(shares), fee, tax)
+            } else {
+                (ImportKind::Sell, total, Some(shares), fee, tax)
+            }
+        }
+        (None, Some((kind, amount))) => (kind, amount, None, fee, tax),
+        (None, None) if transfer && cash >= 0.0 => (ImportKind::Deposit, cash, None, 0.0, 0.0),
+        (None, None) if transfer => (ImportKind::Withdrawal, cash, None, 0.0, 0.0),
+        (None, None) if fee != 0.0 && tax == 0.0 => (ImportKind::Fee, cash, None, 0.0, 0.0),
+        (None, None) if tax != 0.0 && fee == 0.0 => (ImportKind::Tax, cash, None, 0.0, 0.0),
+        _ => return Err(invalid("unsupported transaction")),
+    };
+    let commodity = asset
+        .as_deref()
+        .and_then(|symbol| journal.commodities.iter().find(|c| c.symbol == symbol));
+    let (isin, wkn, asset_name) = match (commodity, asset.as_deref()) {
+        (Some(c), _) => (c.isin.clone(), c.wkn.clone(), Some(c.name.clone())),
+        (None, Some(symbol)) if is_isin(symbol) => (Some(symbol.to_string()), None, None),
+        (None, Some(symbol)) => (
+            None,
+            symbol.strip_prefix("WKN-").map(|wkn| wkn.to_string()),
+            Some(symbol.to_string()),
+        ),
+        (None, None) => (None, None, None),
+    };
+
+    Ok(JournalEntry {
+        account,
+        asset,
+        row: ImportRow {
+            line,
+            date,
+            kind,
+            isin,
+            wkn,
+            asset_name,
+            position,
+            amount: round(amount),
+            currency: currency.unwrap_or_else(|| journal.currency.clone()),
+            fee: round(fee),
+            tax: round(tax),
+            note: meta.get("note").cloned(),
+            external_id: meta.get("id").cloned(),
+        },
+    })
+}
+
+fn finish_block(block: Block, journal: &mut Journal) -> Result<(), JournalError> {
+    match block {
+        Block::None => {}
+        Block::Account(account) => journal.accounts.push(account),
+        Block::Commodity(commodity) => journal.commodities.push(commodity),
+        Block::Transaction {
+            line,
+            date,
+            meta,
+            postings,
+        } => {
+            let entry = transaction_entry(line, date, &meta, &postings, journal)?;
+            journal.entries.push(entry);
+        }
+    }
+    Ok(())
+}
+
+/// Read a journal written by `write_journal` in either format
+pub fn parse_journal(text: &str) -> Result<Journal, JournalError> {
+    let mut journal = Journal {
+        currency: "EUR".to_string(),
+        ..Default::default()
+    };
+    let mut block = Block::None;
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let invalid = |msg: &str| JournalError::InvalidLine(line_number, msg.to_string());
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        // Indented lines belong to the current block
+        if line.starts_with(|c: char| c.is_whitespace()) {
+            let meta = parse_meta(line);
+            match &mut block {
+                Block::Account(account) => {
+                    if let Some((key, value)) = meta {
+                        match key.as_str() {
+                            "broker" => account.broker = value,
+                            "name" => account.name = value,
+                            _ => {}
+                        }
+                    }
+                }
+                Block::Commodity(commodity) => match meta {
+                    Some((key, value)) => match key.as_str() {
+                        "name" => commodity.name = value,
+                        "isin" => commodity.isin = Some(value),
+                        "wkn" => commodity.wkn = Some(value),
+                        _ => {}
+                    },
+                    None => {
+                        if let Some(note) = line.trim().strip_prefix("note ") {
+                            commodity.name = note.trim().to_string();
+                        }
+                    }
+                },
+                Block::Transaction {
+                    meta: m, postings, ..
+                } => match meta {
+                    Some((key, value)) => {
+                        m.insert(key, value);
+                    }
+                    None if line.trim().starts_with(';') => {}
+                    None => postings
+                        .push(parse_posting(line).ok_or_else(|| invalid("invalid posting"))?),
+                },
+                Block::None => {}
+            }
+            continue;
+        }
+
+        finish_block(std::mem::replace(&mut block, Block::None), &mut journal)?;
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            [";", "operating", "currency:", currency] => journal.currency = currency.to_string(),
+            [";", ..] | ["#", ..] | ["*", ..] => {}
+            ["option", "\"operating_currency\"", currency, ..] => {
+                journal.currency = unquote(currency)
+            }
+            ["account", path, ..] if path.starts_with("Assets:") => {
+                block = Block::Account(account_from_path(path))
+            }
+            ["account", ..] => {}
+            ["commodity", symbol, ..] => {
+                block = Block::Commodity(JournalCommodity {
+                    symbol: unquote(symbol),
+                    name: unquote(symbol),
+                    isin: None,
+                    wkn: None,
+                })
+            }
+            ["P", date_text, _, ..] => {
+                let date = parse_date(date_text).ok_or_else(|| invalid("invalid date"))?;
+                let rest = line.trim_start()[1..].trim_start()[date_text.len()..].trim();
+                journal
+                    .prices
+                    .push(parse_price(date, rest).ok_or_else(|| invalid("invalid price"))?);
+            }
+            [date, keyword, ..] if parse_date(date).is_some() => {
+                let date = parse_date(date).unwrap();
+                let rest = line[line.find(*keyword).unwrap_or(0)..].trim();
+                match *keyword {
+                    // Sub-accounts for income, expenses and transfers are derived
+                    "open" => match tokens.get(2) {
+                        Some(path) if path.starts_with("Assets:") => {
+                            block = Block::Account(account_from_path(path))
+                        }
+                        _ => {}
+                    },
+                    "commodity" => {
+                        let symbol = tokens.get(2).ok_or_else(|| invalid("missing symbol"))?;
+                        block = Block::Commodity(JournalCommodity {
+                            symbol: symbol.to_string(),
+                            name: symbol.to_string(),
+                            isin: None,
+                            wkn: None,
+                        })
+                    }
+                    "price" => journal.prices.push(
+                        parse_price(date, rest["price".len()..].trim())
+                            .ok_or_else(|| invalid("invalid price"))?,
+                    ),
+                    "*" | "!" | "txn" => {
+                        block = Block::Transaction {
+                            line: line_number,
+                            date,
+                            meta: HashMap::new(),
+                            postings: Vec::new(),
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ if line.starts_with(';') => {}
+            _ => return Err(invalid("unsupported directive")),
+        }
+    }
+    finish_block(block, &mut journal)?;
+    Ok(journal)
+}
+
+/// Parse the part of a price directive after the date, e.g. `"DE0001234567" 101.50 EUR`
+fn parse_price(date: Date, text: &str) -> Option<JournalPrice> {
+    let (symbol, rest) = if let Some(quoted) = text.strip_prefix('"') {
+        let end = quoted.find('"')?;
+        (quoted[..end].to_string(), &quoted[end + 1..])
+    } else {
+        let split = text.find(char::is_whitespace)?;
+        (text[..split].to_string(), &text[split..])
+    };
+    let (price, currency) = parse_value(rest)?;
+    Some(JournalPrice {
+        date,
+        symbol,
+        price,
+        currency,
+    })
+}
+
+/// Collect the given accounts with all their transactions, the assets involved and their
+/// quotes. The account path is derived from broker and account name.
+pub async fn load_journal<DB>(
+    db: &DB,
+    account_ids: &[i32],
+    currency: &str,
+) -> Result<Journal, JournalError>
+where
+    DB: AccountHandler + AssetHandler + QuoteHandler + Sync,
+{
+    let mut journal = Journal {
+        currency: currency.to_string(),
+        ..Default::default()
+    };
+    let accounts = db.get_all_accounts().await;
+    let mut paths = HashSet::new();
+    let mut symbols: BTreeMap<i32, String> = BTreeMap::new();
+
+    for &account_id in account_ids {
+        let account = accounts
+            .iter()
+            .find(|a| a.id == Some(account_id))
+            .ok_or(JournalError::UnknownAccount(account_id))?;
+        let mut path = format!(
+            "Assets:{}:{}",
+            account_component(&account.broker),
+            account_component(&account.account_name)
+        );
+        if !paths.insert(path.clone()) {
+            path = format!("{}-{}", path, account_id);
+            paths.insert(path.clone());
+        }
+        journal.accounts.push(JournalAccount {
+            path: path.clone(),
+            broker: account.broker.clone(),
+            name: account.account_name.clone(),
+        });
+
+        let transactions = db.get_all_transactions_with_account(account_id).await?;
+        let ids: HashSet<i32> = transactions.iter().filter_map(|t| t.id).collect();
+        // Fees and taxes related to other transactions become postings of these
+        let mut related: HashMap<i32, (f64, f64)> = HashMap::new();
+        for t in &transactions {
+            match t.transaction_type {
+                TransactionType::Fee {
+                    transaction_ref: Some(id),
+                } if ids.contains(&(id as i32)) => {
+                    related.entry(id as i32).or_default().0 -= t.cash_flow.amount.amount
+                }
+                TransactionType::Tax {
+                    transaction_ref: Some(id),
+                } if ids.contains(&(id as i32)) => {
+                    related.entry(id as i32).or_default().1 -= t.cash_flow.amount.amount
+                }
+                _ => {}
+            }
+        }
+
+        for t in &transactions {
+            let id = t.id.unwrap_or_default();
+            let amount = t.cash_flow.amount.amount;
+            let (fee, tax) = related.get(&id).copied().unwrap_or_default();
+            let (kind, asset_id, position) = match t.transaction_type {
+                TransactionType::Asset { asset_id, position } if position >= 0.0 => {
+                    (ImportKind::Buy, Some(asset_id), Some(position))
+                }
+                TransactionType::Asset { asset_id, position } => {
+                    (ImportKind::Sell, Some(asset_id), Some(position))
+                }
+                TransactionType::Dividend { asset_id } => {
+                    (ImportKind::Dividend, Some(asset_id), None)
+                }
+                TransactionType::Interest { asset_id } => {
+                    (ImportKind::Interest, Some(asset_id), None)
+                }
+                TransactionType::Cash if amount >= 0.0 => (ImportKind::Deposit, None, None),
+                TransactionType::Cash => (ImportKind::Withdrawal, None, None),
+                TransactionType::Fee {
+                    transaction_ref: Some(main),
+                }
+                | TransactionType::Tax {
+                    transaction_ref: Some(main),
+                } if ids.contains(&(main as i32)) => continue,
+                TransactionType::Fee { .. } => (ImportKind::Fee, None, None),
+                TransactionType::Tax { .. } => (ImportKind::Tax, None, None),
+            };
+
+            // Only stocks become commodities, interest on cash refers to the currency
+            let mut asset = None;
+            if let Some(asset_id) = asset_id {
+                if let Some(symbol) = symbols.get(&asset_id) {
+                    asset = Some(symbol.clone());
+                } else if let Asset::Stock(stock) = db.get_asset_by_id(asset_id).await? {
+                    let symbol = commodity_symbol(asset_id, &stock);
+                    symbols.insert(asset_id, symbol.clone());
+                    journal.commodities.push(JournalCommodity {
+                        symbol: symbol.clone(),
+                        name: stock.name,
+                        isin: stock.isin,
+                        wkn: stock.wkn,
+                    });
+                    asset = Some(symbol);
+                }
+            }
+            let commodity = asset
+                .as_deref()
+                .and_then(|s| journal.commodities.iter().find(|c| c.symbol == s));
+            journal.entries.push(JournalEntry {
+                account: path.clone(),
+                row: ImportRow {
+                    line: 0,
+                    date: t.cash_flow.date,
+                    kind,
+                    isin: commodity.and_then(|c| c.isin.clone()),
+                    wkn: commodity.and_then(|c| c.wkn.clone()),
+                    asset_name: commodity.map(|c| c.name.clone()),
+                    position,
+                    amount,
+                    currency: t.cash_flow.amount.currency.to_string(),
+                    fee,
+                    tax,
+                    note: t.note.clone(),
+                    external_id: Some(id.to_string()),
+                },
+                asset,
+            });
+        }
+    }
+
+    for (asset_id, symbol) in &symbols {
+        let ticker = match db
+            .get_all_ticker_for_asset(*asset_id)
+            .await?
+            .into_iter()
+            .next()
+        {
+            Some(ticker) => ticker,
+            None => continue,
+        };
+        let ticker_id = match ticker.id {
+            Some(ticker_id) => ticker_id,
+            None => continue,
+        };
+        // One price per day, the latest one wins
+        let mut prices: BTreeMap<Date, f64> = BTreeMap::new();
+        let mut quotes = db.get_all_quotes_for_ticker(ticker_id).await?;
+        quotes.sort_by_key(|q| q.time);
+        for quote in quotes {
+            prices.insert(quote.time.date(), quote.price * ticker.factor);
+        }
+        journal
+            .prices
+            .extend(prices.into_iter().map(|(date, price)| JournalPrice {
+                date,
+                symbol: symbol.clone(),
+                price,
+                currency: ticker.currency.to_string(),
+            }));
+    }
+    Ok(journal)
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JournalImportReport {
+    /// Names of commodities added as new assets
+    pub created_assets: Vec<String>,
+    /// Number of new quotes
+    pub quotes: usize,
+    /// Name, id and import summary of all accounts
+    pub accounts: Vec<(String, i32, ImportReport)>,
+}
+
+/// Store commodities, prices, accounts and entries of a journal. Existing assets and accounts
+/// are reused, entries imported before are skipped.
+pub async fn import_journal<DB>(
+    db: &DB,
+    market: &Market,
+    journal: &Journal,
+) -> Result<JournalImportReport, JournalError>
+where
+    DB: AccountHandler + AssetHandler + QuoteHandler + FingerprintHandler + Sync,
+{
+    let mut report = JournalImportReport::default();
+    for commodity in &journal.commodities {
+        let asset = Asset::Stock(Stock {
+            id: None,
+            name: commodity.name.clone(),
+            wkn: commodity.wkn.clone(),
+            isin: commodity.isin.clone(),
+            note: None,
+        });
+        let asset_id = match db.get_asset_id(&asset).await {
+            Some(asset_id) => asset_id,
+            None => {
+                report.created_assets.push(commodity.name.clone());
+                db.insert_asset(&asset).await?
+            }
+        };
+        let mut prices: HashMap<&str, Vec<(Date, f64)>> = HashMap::new();
+        for price in journal
+            .prices
+            .iter()
+            .filter(|p| p.symbol == commodity.symbol)
+        {
+            prices
+                .entry(price.currency.as_str())
+                .or_default()
+                .push((price.date, price.price));
+        }
+        for (currency, prices) in prices {
+            report.quotes +=
+                store_manual_quotes(db, market, asset_id, &commodity.symbol, currency, &prices)
+                    .await?;
+        }
+    }
+
+    let options = ImportOptions {
+        source: JOURNAL_SOURCE.to_string(),
+        create_missing_assets: true,
+        skip_duplicates: true,
+    };
+    let mut accounts = journal.accounts.clone();
+    for entry in &journal.entries {
+        if !accounts.iter().any(|a| a.path == entry.account) {
+            accounts.push(account_from_path(&entry.account));
+        }
+    }
+    for account in &accounts {
+        let rows: Vec<ImportRow> = journal
+            .entries
+            .iter()
+            .filter(|e| e.account == account.path)
+            .map(|e| e.row.clone())
+            .collect();
+        let account_id = db
+            .insert_account_if_new(&Account {
+                id: None,
+                broker: account.broker.clone(),
+                account_name: account.name.clone(),
+            })
+            .await?;
+        let account_report = import_rows(db, market, &rows, account_id, &options).await?;
+        report
+            .accounts
+            .push((account.name.clone(), account_id, account_report));
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    fn row(kind: ImportKind, amount: f64) -> ImportRow {
+        ImportRow {
+            line: 0,
+            date: date!(2023 - 01 - 03),
+            kind,
+            isin: None,
+            wkn: None,
+            asset_name: None,
+            position: None,
+            amount,
+            currency: "EUR".to_string(),
+            fee: 0.0,
+            tax: 0.0,
+            note: None,
+            external_id: None,
+        }
+    }
+
+    fn sample() -> Journal {
+        let path = "Assets:My-Bank:Depot".to_string();
+        let mut deposit = row(ImportKind::Deposit, 5000.0);
+        deposit.external_id = Some("1".to_string());
+        deposit.date = date!(2023 - 01 - 02);
+        let mut buy = row(ImportKind::Buy, -1000.0);
+        buy.position = Some(10.0);
+        buy.fee = 4.95;
+        buy.external_id = Some("2".to_string());
+        buy.note = Some("Order \"42\"".to_string());
+        let mut sell = row(ImportKind::Sell, 550.5);
+        sell.position = Some(-5.0);
+        sell.fee = 4.95;
+        sell.tax = 12.0;
+        sell.external_id = Some("4".to_string());
+        let mut dividend = row(ImportKind::Dividend, 12.5);
+        dividend.tax = 2.5;
+        dividend.external_id = Some("6".to_string());
+        let mut fee = row(ImportKind::Fee, -10.0);
+        fee.external_id = Some("8".to_string());
+        let commodity = JournalCommodity {
+            symbol: "DE0001234567".to_string(),
+            name: "ACME Corp.".to_string(),
+            isin: Some("DE0001234567".to_string()),
+            wkn: Some("123456".to_string()),
+        };
+        let asset = Some(commodity.symbol.clone());
+        let entry = |row: ImportRow, asset: Option<String>| {
+            let mut row = row;
+            if asset.is_some() {
+                row.isin = commodity.isin.clone();
+                row.wkn = commodity.wkn.clone();
+                row.asset_name = Some(commodity.name.clone());
+            }
+            JournalEntry {
+                account: path.clone(),
+                asset,
+                row,
+            }
+        };
+        Journal {
+            currency: "EUR".to_string(),
+            accounts: vec![JournalAccount {
+                path: path.clone(),
+                broker: "My Bank".to_string(),
+                name: "Depot".to_string(),
+            }],
+            entries: vec![
+                entry(deposit, None),
+                entry(buy, asset.clone()),
+                entry(sell, asset.clone()),
+                entry(dividend, asset.clone()),
+                entry(fee, None),
+            ],
+            prices: vec![JournalPrice {
+                date: date!(2023 - 01 - 03),
+                symbol: commodity.symbol.clone(),
+                price: 101.5,
+                currency: "EUR".to_string(),
+            }],
+            commodities: vec![commodity],
+        }
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(account_component("Comdirect Bank AG"), "Comdirect-Bank-AG");
+        assert_eq!(account_component("Depot Müller"), "Depot-Mueller");
+        assert_eq!(account_component("  "), "Unknown");
+        let stock = Stock {
+            id: None,
+            name: "ACME".to_string(),
+            wkn: Some("a0b1c2".to_string()),
+            isin: None,
+            note: None,
+        };
+        assert_eq!(commodity_symbol(7, &stock), "WKN-A0B1C2");
+        assert_eq!(format_number(10.5, 0, 8), "10.5");
+        assert_eq!(format_number(-0.001, 2, 2), "0.00");
+    }
+
+    fn parse(text: &str) -> Journal {
+        let mut journal = parse_journal(text).unwrap();
+        for entry in &mut journal.entries {
+            assert!(entry.row.line > 0);
+            entry.row.line = 0;
+        }
+        journal
+    }
+
+    #[test]
+    fn test_beancount() {
+        let journal = sample();
+        let text = write_journal(&journal, JournalFormat::Beancount);
+        assert!(text.contains("2023-01-02 open Assets:My-Bank:Depot\n  broker: \"My Bank\""));
+        assert!(text.contains("  Assets:My-Bank:Depot  10 DE0001234567 {{1000.00 EUR}}\n"));
+        assert!(text.contains("  Assets:My-Bank:Depot  -5 DE0001234567 {} @@ 550.50 EUR\n"));
+        assert!(text.contains("  note: \"Order \\\"42\\\"\"\n"));
+        assert!(text.contains("2023-01-03 price DE0001234567 101.50 EUR\n"));
+        assert_eq!(parse(&text), journal);
+    }
+
+    #[test]
+    fn test_ledger() {
+        let journal = sample();
+        let text = write_journal(&journal, JournalFormat::Ledger);
+        assert!(text.contains("    Assets:My-Bank:Depot  10 \"DE0001234567\" @@ 1000.00 EUR\n"));
+        assert!(text.contains("    Expenses:My-Bank:Depot:Taxes  2.50 EUR\n"));
+        assert!(text.contains("P 2023-01-03 \"DE0001234567\" 101.50 EUR\n"));
+        assert_eq!(parse(&text), journal);
+    }
+
+    #[test]
+    fn test_price_date_formats() {
+        let journal =
+            parse_journal("P 2023/1/3 ACME 101.50 EUR\nP 2023-01-04 ACME 102 EUR\n").unwrap();
+        assert_eq!(journal.prices.len(), 2);
+        assert_eq!(journal.prices[0].date, date!(2023 - 01 - 03));
+        assert_eq!(journal.prices[0].symbol, "ACME");
+        assert_eq!(journal.prices[0].price, 101.5);
+        assert_eq!(journal.prices[1].price, 102.0);
+    }
+}
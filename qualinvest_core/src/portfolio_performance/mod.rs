@@ -0,0 +1,979 @@
+//! Import and export of files of the Portfolio Performance desktop application
+//!
+//! Portfolio Performance stores its data as XStream XML file (choose "Save as > XML" for
+//! unencrypted files). Cash accounts and securities accounts ("portfolios") are separate there,
+//! while qualinvest accounts hold both. Each Portfolio Performance account becomes a qualinvest
+//! account, and the transactions of a portfolio are added to the account of its reference
+//! account. Fee and tax units of a transaction are stored as related transactions.
+//!
+//! Amounts are stored as hundredths, shares and prices with 8 decimal digits.
+//! Transactions are identified by their uuid, such that importing the same file again only adds
+//! new transactions (see `crate::duplicates`).
+pub mod xstream;
+
+use std::collections::{HashMap, HashSet};
+
+use data_encoding::HEXLOWER;
+use ring::digest;
+use thiserror::Error;
+use time::Date;
+
+use finql::datatypes::{
+    Asset, AssetHandler, DataError, QuoteHandler, Stock, Transaction, TransactionType,
+};
+use finql::market::MarketError;
+use finql::Market;
+
+use crate::accounts::{Account, AccountHandler};
+use crate::csv_import::{
+    import_rows, store_manual_quotes, ImportError, ImportKind, ImportOptions, ImportReport,
+    ImportRow,
+};
+use crate::duplicates::FingerprintHandler;
+use xstream::{ObjectContent, XStreamError, XStreamWriter, XmlDocument, XmlElement};
+
+/// Broker name of accounts imported from Portfolio Performance
+pub const PP_BROKER: &str = "Portfolio Performance";
+/// Source of the fingerprints of imported transactions
+const PP_SOURCE: &str = "Portfolio Performance";
+/// File format version written by the exporter
+const PP_FILE_VERSION: i32 = 56;
+
+const AMOUNT_FACTOR: f64 = 100.0;
+const SHARES_FACTOR: f64 = 100_000_000.0;
+const PRICE_FACTOR: f64 = 100_000_000.0;
+
+#[derive(Error, Debug)]
+pub enum PortfolioPerformanceError {
+    #[error("Database access failed")]
+    DataError(#[from] DataError),
+    #[error("Market data access failed")]
+    MarketError(#[from] MarketError),
+    #[error("Failed to import transactions")]
+    ImportError(#[from] ImportError),
+    #[error("Failed to read XML")]
+    XmlError(#[from] XStreamError),
+    #[error("Invalid Portfolio Performance file: {0}")]
+    InvalidFile(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PpSecurity {
+    pub name: String,
+    pub isin: Option<String>,
+    pub wkn: Option<String>,
+    pub ticker_symbol: Option<String>,
+    pub currency: String,
+    pub prices: Vec<(Date, f64)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PpAccount {
+    pub name: String,
+    pub currency: String,
+    /// Transactions of the account and of all portfolios referring to it, ordered by date
+    pub rows: Vec<ImportRow>,
+}
+
+/// Content of a Portfolio Performance file
+#[derive(Debug, Clone, PartialEq)]
+pub struct PpClient {
+    pub base_currency: String,
+    pub securities: Vec<PpSecurity>,
+    pub accounts: Vec<PpAccount>,
+}
+
+fn invalid(msg: String) -> PortfolioPerformanceError {
+    PortfolioPerformanceError::InvalidFile(msg)
+}
+
+/// Parse a date of the form "2020-01-02" or "2020-01-02T00:00"
+fn parse_date(text: &str) -> Result<Date, PortfolioPerformanceError> {
+    text.get(..10)
+        .and_then(|date| crate::csv_import::parse_date(date, "%Y-%m-%d"))
+        .ok_or_else(|| invalid(format!("invalid date '{}'", text)))
+}
+
+fn parse_scaled(text: Option<&str>, factor: f64) -> Result<f64, PortfolioPerformanceError> {
+    match text {
+        Some(text) => text
+            .parse::<i64>()
+            .map(|value| value as f64 / factor)
+            .map_err(|_| invalid(format!("invalid number '{}'", text))),
+        None => Ok(0.0),
+    }
+}
+
+fn parse_security(doc: &XmlDocument, node: usize) -> Result<PpSecurity, PortfolioPerformanceError> {
+    let text = |name: &str| -> Result<Option<String>, PortfolioPerformanceError> {
+        Ok(doc.child_text(node, name)?.map(|s| s.to_string()))
+    };
+    let mut prices = Vec::new();
+    if let Some(list) = doc.child(node, "prices")? {
+        for price in doc.children(list, "price")? {
+            let date = doc
+                .attr(price, "t")
+                .ok_or_else(|| invalid("price without date".to_string()))?;
+            prices.push((
+                parse_date(date)?,
+                parse_scaled(doc.attr(price, "v"), PRICE_FACTOR)?,
+            ));
+        }
+    }
+    Ok(PpSecurity {
+        name: text("name")?.ok_or_else(|| invalid("security without name".to_string()))?,
+        isin: text("isin")?,
+        wkn: text("wkn")?,
+        ticker_symbol: text("tickerSymbol")?,
+        currency: text("currencyCode")?.unwrap_or_else(|| "EUR".to_string()),
+        prices,
+    })
+}
+
+/// Convert an account or portfolio transaction into rows. The account side of buy and sell
+/// transactions is skipped, since it is imported from the portfolio side. Deliveries are booked
+/// as buy or sell with their value, offset by a deposit or withdrawal of the same amount.
+fn transaction_rows(
+    doc: &XmlDocument,
+    node: usize,
+    portfolio: bool,
+    securities: &HashMap<usize, PpSecurity>,
+    line: usize,
+) -> Result<Vec<ImportRow>, PortfolioPerformanceError> {
+    let transaction_type = doc.child_text(node, "type")?.unwrap_or_default();
+    let date = parse_date(doc.child_text(node, "date")?.unwrap_or_default())?;
+    let amount = parse_scaled(doc.child_text(node, "amount")?, AMOUNT_FACTOR)?;
+    let shares = parse_scaled(doc.child_text(node, "shares")?, SHARES_FACTOR)?;
+    let security = match doc.child(node, "security")? {
+        Some(security) => Some(
+            securities
+                .get(&security)
+                .ok_or_else(|| invalid("unknown security".to_string()))?,
+        ),
+        None => None,
+    };
+
+    let (mut fee, mut tax) = (0.0, 0.0);
+    if let Some(units) = doc.child(node, "units")? {
+        for unit in doc.children(units, "unit")? {
+            let value = match doc.child(unit, "amount")? {
+                Some(value) => parse_scaled(doc.attr(value, "amount"), AMOUNT_FACTOR)?,
+                None => 0.0,
+            };
+            match doc.attr(unit, "type") {
+                Some("FEE") => fee += value,
+                Some("TAX") => tax += value,
+                _ => {}
+            }
+        }
+    }
+
+    let (kind, amount, position) = match (portfolio, transaction_type) {
+        (true, "BUY") | (true, "DELIVERY_INBOUND") | (true, "TRANSFER_IN") => {
+            (ImportKind::Buy, -(amount - fee - tax), Some(shares))
+        }
+        (true, "SELL") | (true, "DELIVERY_OUTBOUND") | (true, "TRANSFER_OUT") => {
+            (ImportKind::Sell, amount + fee + tax, Some(-shares))
+        }
+        (false, "BUY") | (false, "SELL") => return Ok(Vec::new()),
+        (false, "DEPOSIT") | (false, "TRANSFER_IN") => (ImportKind::Deposit, amount, None),
+        (false, "REMOVAL") | (false, "TRANSFER_OUT") => (ImportKind::Withdrawal, -amount, None),
+        (false, "DIVIDENDS") => (ImportKind::Dividend, amount + fee + tax, None),
+        (false, "INTEREST") => (ImportKind::Interest, amount + fee + tax, None),
+        (false, "INTEREST_CHARGE") => (ImportKind::Interest, -amount, None),
+        (false, "FEES") => (ImportKind::Fee, -amount, None),
+        (false, "FEES_REFUND") => (ImportKind::Fee, amount, None),
+        (false, "TAXES") => (ImportKind::Tax, -amount, None),
+        (false, "TAX_REFUND") => (ImportKind::Tax, amount, None),
+        (_, other) => return Err(invalid(format!("unknown transaction type '{}'", other))),
+    };
+    if kind.needs_asset() && security.is_none() {
+        return Err(invalid(format!(
+            "{} transaction without security",
+            transaction_type
+        )));
+    }
+    // Fees and taxes without own transaction are part of the main transaction
+    if matches!(kind, ImportKind::Fee | ImportKind::Tax) {
+        fee = 0.0;
+        tax = 0.0;
+    }
+
+    // Avoid rounding errors of sums of amounts given in cents
+    let round = |amount: f64| (amount * AMOUNT_FACTOR).round() / AMOUNT_FACTOR;
+    let row = ImportRow {
+        line,
+        date,
+        kind,
+        isin: security.and_then(|s| s.isin.clone()),
+        wkn: security.and_then(|s| s.wkn.clone()),
+        asset_name: security.map(|s| s.name.clone()),
+        position,
+        amount: round(amount),
+        currency: doc
+            .child_text(node, "currencyCode")?
+            .unwrap_or("EUR")
+            .to_string(),
+        fee,
+        tax,
+        note: doc.child_text(node, "note")?.map(|s| s.to_string()),
+        external_id: doc.child_text(node, "uuid")?.map(|s| s.to_string()),
+    };
+
+    // The cash flows of a delivery, including fees and taxes, are offset by a deposit or withdrawal
+    let offset = round(row.fee + row.tax - row.amount);
+    let offset_kind = match transaction_type {
+        "DELIVERY_INBOUND" | "TRANSFER_IN" if portfolio => Some(ImportKind::Deposit),
+        "DELIVERY_OUTBOUND" | "TRANSFER_OUT" if portfolio => Some(ImportKind::Withdrawal),
+        _ => None,
+    };
+    let mut rows = vec![row];
+    if let Some(kind) = offset_kind.filter(|_| offset != 0.0) {
+        let offset_row = ImportRow {
+            kind,
+            isin: None,
+            wkn: None,
+            asset_name: None,
+            position: None,
+            amount: offset,
+            fee: 0.0,
+            tax: 0.0,
+            external_id: rows[0]
+                .external_id
+                .as_ref()
+                .map(|id| format!("{}/cash", id)),
+            ..rows[0].clone()
+        };
+        rows.push(offset_row);
+    }
+    Ok(rows)
+}
+
+/// Read the content of a Portfolio Performance XML file
+pub fn parse_pp_xml(text: &str) -> Result<PpClient, PortfolioPerformanceError> {
+    let doc = XmlDocument::parse(text)?;
+    let root = doc.root();
+    if doc.node(root).name != "client" {
+        return Err(invalid("missing element 'client'".to_string()));
+    }
+    let base_currency = doc
+        .child_text(root, "baseCurrency")?
+        .unwrap_or("EUR")
+        .to_string();
+
+    let mut securities = HashMap::new();
+    let mut security_list = Vec::new();
+    if let Some(list) = doc.child(root, "securities")? {
+        for node in doc.children(list, "security")? {
+            let security = parse_security(&doc, node)?;
+            security_list.push(security.clone());
+            securities.insert(node, security);
+        }
+    }
+
+    let mut line = 0;
+    let mut accounts = Vec::new();
+    let mut account_index = HashMap::new();
+    if let Some(list) = doc.child(root, "accounts")? {
+        for node in doc.children(list, "account")? {
+            let mut rows = Vec::new();
+            if let Some(transactions) = doc.child(node, "transactions")? {
+                for transaction in doc.children(transactions, "account-transaction")? {
+                    line += 1;
+                    rows.extend(transaction_rows(
+                        &doc,
+                        transaction,
+                        false,
+                        &securities,
+                        line,
+                    )?);
+                }
+            }
+            account_index.insert(node, accounts.len());
+            accounts.push(PpAccount {
+                name: doc
+                    .child_text(node, "name")?
+                    .ok_or_else(|| invalid("account without name".to_string()))?
+                    .to_string(),
+                currency: doc
+                    .child_text(node, "currencyCode")?
+                    .unwrap_or(&base_currency)
+                    .to_string(),
+                rows,
+            });
+        }
+    }
+
+    if let Some(list) = doc.child(root, "portfolios")? {
+        for node in doc.children(list, "portfolio")? {
+            let index = match doc.child(node, "referenceAccount")? {
+                Some(account) => *account_index
+                    .get(&account)
+                    .ok_or_else(|| invalid("unknown reference account".to_string()))?,
+                None => {
+                    accounts.push(PpAccount {
+                        name: doc
+                            .child_text(node, "name")?
+                            .ok_or_else(|| invalid("portfolio without name".to_string()))?
+                            .to_string(),
+                        currency: base_currency.clone(),
+                        rows: Vec::new(),
+                    });
+                    accounts.len() - 1
+                }
+            };
+            if let Some(transactions) = doc.child(node, "transactions")? {
+                for transaction in doc.children(transactions, "portfolio-transaction")? {
+                    line += 1;
+                    let rows = transaction_rows(&doc, transaction, true, &securities, line)?;
+                    accounts[index].rows.extend(rows);
+                }
+            }
+        }
+    }
+    for account in &mut accounts {
+        account.rows.sort_by_key(|row| row.date);
+    }
+
+    Ok(PpClient {
+        base_currency,
+        securities: security_list,
+        accounts,
+    })
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PpImportReport {
+    /// Names of securities added as new assets
+    pub created_assets: Vec<String>,
+    /// Number of new quotes
+    pub quotes: usize,
+    /// Name, id and import summary of all accounts
+    pub accounts: Vec<(String, i32, ImportReport)>,
+}
+
+/// Store securities, prices, accounts and transactions of a Portfolio Performance file.
+/// Existing assets and accounts are reused, transactions imported before are skipped.
+pub async fn import_pp_client<DB>(
+    db: &DB,
+    market: &Market,
+    client: &PpClient,
+) -> Result<PpImportReport, PortfolioPerformanceError>
+where
+    DB: AccountHandler + AssetHandler + QuoteHandler + FingerprintHandler + Sync,
+{
+    let mut report = PpImportReport::default();
+    for security in &client.securities {
+        let asset = Asset::Stock(Stock {
+            id: None,
+            name: security.name.clone(),
+            wkn: security.wkn.clone(),
+            isin: security.isin.clone(),
+            note: None,
+        });
+        let asset_id = match db.get_asset_id(&asset).await {
+            Some(asset_id) => asset_id,
+            None => {
+                report.created_assets.push(security.name.clone());
+                db.insert_asset(&asset).await?
+            }
+        };
+        let ticker_name = security
+            .ticker_symbol
+            .clone()
+            .unwrap_or_else(|| security.name.clone());
+        report.quotes += store_manual_quotes(
+            db,
+            market,
+            asset_id,
+            &ticker_name,
+            &security.currency,
+            &security.prices,
+        )
+        .await?;
+    }
+
+    let options = ImportOptions {
+        source: PP_SOURCE.to_string(),
+        create_missing_assets: true,
+        skip_duplicates: true,
+    };
+    for account in &client.accounts {
+        let account_id = db
+            .insert_account_if_new(&Account {
+                id: None,
+                broker: PP_BROKER.to_string(),
+                account_name: account.name.clone(),
+            })
+            .await?;
+        let account_report = import_rows(db, market, &account.rows, account_id, &options).await?;
+        report
+            .accounts
+            .push((account.name.clone(), account_id, account_report));
+    }
+    Ok(report)
+}
+
+/// Stable uuid of a qualinvest object, such that repeated exports are identical
+fn uuid(kind: &str, id: i32) -> String {
+    let hash = digest::digest(
+        &digest::SHA256,
+        format!("qualinvest:{}:{}", kind, id).as_bytes(),
+    );
+    let hex = HEXLOWER.encode(&hash.as_ref()[..16]);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+fn scaled(value: f64, factor: f64) -> i64 {
+    (value.abs() * factor).round() as i64
+}
+
+fn units(fee: f64, tax: f64, currency: &str) -> Option<XmlElement> {
+    let mut units = Vec::new();
+    for (unit_type, value) in [("FEE", fee), ("TAX", tax)] {
+        if value != 0.0 {
+            let mut unit = XmlElement::with_attrs("unit", &[("type", unit_type.to_string())]);
+            if let XmlElement::Element { children, .. } = &mut unit {
+                children.push(XmlElement::with_attrs(
+                    "amount",
+                    &[
+                        ("currency", currency.to_string()),
+                        ("amount", scaled(value, AMOUNT_FACTOR).to_string()),
+                    ],
+                ));
+            }
+            units.push(unit);
+        }
+    }
+    if units.is_empty() {
+        None
+    } else {
+        Some(XmlElement::new("units", units))
+    }
+}
+
+/// Fields common to account and portfolio transactions
+struct PpTransaction<'a> {
+    uuid: String,
+    transaction: &'a Transaction,
+    pp_type: &'static str,
+    amount: f64,
+    security: Option<i32>,
+    shares: f64,
+    fee: f64,
+    tax: f64,
+    cross_entry: Option<String>,
+}
+
+impl PpTransaction<'_> {
+    fn content(&self) -> ObjectContent {
+        let currency = self.transaction.cash_flow.amount.currency.to_string();
+        let mut children = vec![
+            XmlElement::text("uuid", &self.uuid),
+            XmlElement::text("date", format!("{}T00:00", self.transaction.cash_flow.date)),
+            XmlElement::text("currencyCode", &currency),
+            XmlElement::text("amount", scaled(self.amount, AMOUNT_FACTOR)),
+        ];
+        if let Some(asset_id) = self.security {
+            children.push(XmlElement::object(
+                "security",
+                &format!("security:{}", asset_id),
+            ));
+        }
+        if let Some(cross_entry) = &self.cross_entry {
+            children.push(XmlElement::Object {
+                name: "crossEntry".to_string(),
+                attrs: vec![("class".to_string(), "buysell".to_string())],
+                id: cross_entry.clone(),
+            });
+        }
+        children.push(XmlElement::text(
+            "shares",
+            scaled(self.shares, SHARES_FACTOR),
+        ));
+        if let Some(note) = &self.transaction.note {
+            children.push(XmlElement::text("note", note));
+        }
+        children.extend(units(self.fee, self.tax, &currency));
+        children.push(XmlElement::text("type", self.pp_type));
+        ObjectContent { children }
+    }
+}
+
+/// Write the given accounts with all their transactions, the securities involved and their
+/// quotes as Portfolio Performance XML file. Each account becomes a cash account and a
+/// securities account referring to it.
+pub async fn export_pp_xml<DB>(
+    db: &DB,
+    account_ids: &[i32],
+    base_currency: &str,
+) -> Result<String, PortfolioPerformanceError>
+where
+    DB: AccountHandler + AssetHandler + QuoteHandler + Sync,
+{
+    let mut writer = XStreamWriter::default();
+    let accounts = db.get_all_accounts().await;
+    let mut securities: Vec<i32> = Vec::new();
+    let mut account_objects = Vec::new();
+    let mut portfolio_objects = Vec::new();
+
+    for &account_id in account_ids {
+        let account = accounts
+            .iter()
+            .find(|a| a.id == Some(account_id))
+            .ok_or_else(|| invalid(format!("unknown account {}", account_id)))?;
+        let transactions = db.get_all_transactions_with_account(account_id).await?;
+
+        // Fees and taxes related to other transactions become units of these
+        let ids: HashSet<i32> = transactions.iter().filter_map(|t| t.id).collect();
+        let mut related: HashMap<i32, (f64, f64)> = HashMap::new();
+        for t in &transactions {
+            match t.transaction_type {
+                TransactionType::Fee {
+                    transaction_ref: Some(id),
+                } if ids.contains(&(id as i32)) => {
+                    related.entry(id as i32).or_default().0 -= t.cash_flow.amount.amount
+                }
+                TransactionType::Tax {
+                    transaction_ref: Some(id),
+                } if ids.contains(&(id as i32)) => {
+                    related.entry(id as i32).or_default().1 -= t.cash_flow.amount.amount
+                }
+                _ => {}
+            }
+        }
+
+        let account_key = format!("account:{}", account_id);
+        let portfolio_key = format!("portfolio:{}", account_id);
+        let mut account_transactions = Vec::new();
+        let mut portfolio_transactions = Vec::new();
+        for t in &transactions {
+            let id = t.id.unwrap_or_default();
+            let amount = t.cash_flow.amount.amount;
+            let (fee, tax) = related.get(&id).copied().unwrap_or_default();
+            let mut security_of = |asset_id: i32| {
+                if !securities.contains(&asset_id) {
+                    securities.push(asset_id);
+                }
+                Some(asset_id)
+            };
+            let pp = |pp_type, amount, security, shares, fee, tax| PpTransaction {
+                uuid: uuid("transaction", id),
+                transaction: t,
+                pp_type,
+                amount,
+                security,
+                shares,
+                fee,
+                tax,
+                cross_entry: None,
+            };
+            match t.transaction_type {
+                TransactionType::Asset { asset_id, position } => {
+                    let security = security_of(asset_id);
+                    let buy = position > 0.0;
+                    if amount == 0.0 {
+                        let pp_type = if buy {
+                            "DELIVERY_INBOUND"
+                        } else {
+                            "DELIVERY_OUTBOUND"
+                        };
+                        let key = format!("pt:{}", id);
+                        writer.define(
+                            &key,
+                            pp(pp_type, 0.0, security, position, 0.0, 0.0).content(),
+                        );
+                        portfolio_transactions
+                            .push(XmlElement::object("portfolio-transaction", &key));
+                        continue;
+                    }
+                    let (pp_type, total) = if buy {
+                        ("BUY", amount.abs() + fee + tax)
+                    } else {
+                        ("SELL", amount.abs() - fee - tax)
+                    };
+                    let cross_entry = format!("crossEntry:{}", id);
+                    let mut portfolio_side = pp(pp_type, total, security, position, fee, tax);
+                    portfolio_side.cross_entry = Some(cross_entry.clone());
+                    let mut account_side = pp(pp_type, total, security, 0.0, fee, tax);
+                    account_side.uuid = uuid("cash transaction", id);
+                    account_side.cross_entry = Some(cross_entry.clone());
+                    let (pt_key, at_key) = (format!("pt:{}", id), format!("at:{}", id));
+                    writer.define(&pt_key, portfolio_side.content());
+                    writer.define(&at_key, account_side.content());
+                    writer.define(
+                        &cross_entry,
+                        ObjectContent {
+                            children: vec![
+                                XmlElement::object("portfolio", &portfolio_key),
+                                XmlElement::object("portfolioTransaction", &pt_key),
+                                XmlElement::object("account", &account_key),
+                                XmlElement::object("accountTransaction", &at_key),
+                            ],
+                        },
+                    );
+                    portfolio_transactions
+                        .push(XmlElement::object("portfolio-transaction", &pt_key));
+                    account_transactions.push(XmlElement::object("account-transaction", &at_key));
+                    continue;
+                }
+                // Related fees and taxes are units of their main transaction
+                TransactionType::Fee {
+                    transaction_ref: Some(main),
+                }
+                | TransactionType::Tax {
+                    transaction_ref: Some(main),
+                } if ids.contains(&(main as i32)) => continue,
+                _ => {}
+            }
+            let transaction = match t.transaction_type {
+                TransactionType::Cash if amount >= 0.0 => {
+                    pp("DEPOSIT", amount, None, 0.0, 0.0, 0.0)
+                }
+                TransactionType::Cash => pp("REMOVAL", amount, None, 0.0, 0.0, 0.0),
+                TransactionType::Dividend { asset_id } => pp(
+                    "DIVIDENDS",
+                    amount - fee - tax,
+                    security_of(asset_id),
+                    0.0,
+                    fee,
+                    tax,
+                ),
+                TransactionType::Interest { asset_id } => {
+                    // Interest on cash refers to the currency, which is no security
+                    let security = match db.get_asset_by_id(asset_id).await? {
+                        Asset::Stock(_) => security_of(asset_id),
+                        _ => None,
+                    };
+                    if amount >= 0.0 {
+                        pp("INTEREST", amount - fee - tax, security, 0.0, fee, tax)
+                    } else {
+                        pp("INTEREST_CHARGE", amount, security, 0.0, 0.0, 0.0)
+                    }
+                }
+                TransactionType::Fee { .. } if amount <= 0.0 => {
+                    pp("FEES", amount, None, 0.0, 0.0, 0.0)
+                }
+                TransactionType::Fee { .. } => pp("FEES_REFUND", amount, None, 0.0, 0.0, 0.0),
+                TransactionType::Tax { .. } if amount <= 0.0 => {
+                    pp("TAXES", amount, None, 0.0, 0.0, 0.0)
+                }
+                TransactionType::Tax { .. } => pp("TAX_REFUND", amount, None, 0.0, 0.0, 0.0),
+                TransactionType::Asset { .. } => continue,
+            };
+            let key = format!("at:{}", id);
+            writer.define(&key, transaction.content());
+            account_transactions.push(XmlElement::object("account-transaction", &key));
+        }
+
+        let currency = transactions
+            .first()
+            .map(|t| t.cash_flow.amount.currency.to_string())
+            .unwrap_or_else(|| base_currency.to_string());
+        writer.define(
+            &account_key,
+            ObjectContent {
+                children: vec![
+                    XmlElement::text("uuid", uuid("account", account_id)),
+                    XmlElement::text("name", &account.account_name),
+                    XmlElement::text("currencyCode", currency),
+                    XmlElement::text("isRetired", false),
+                    XmlElement::new("transactions", account_transactions),
+                ],
+            },
+        );
+        writer.define(
+            &portfolio_key,
+            ObjectContent {
+                children: vec![
+                    XmlElement::text("uuid", uuid("portfolio", account_id)),
+                    XmlElement::text("name", &account.account_name),
+                    XmlElement::text("isRetired", false),
+                    XmlElement::object("referenceAccount", &account_key),
+                    XmlElement::new("transactions", portfolio_transactions),
+                ],
+            },
+        );
+        account_objects.push(XmlElement::object("account", &account_key));
+        portfolio_objects.push(XmlElement::object("portfolio", &portfolio_key));
+    }
+
+    let mut security_objects = Vec::new();
+    for asset_id in securities {
+        let stock = match db.get_asset_by_id(asset_id).await? {
+            Asset::Stock(stock) => stock,
+            _ => continue,
+        };
+        let tickers = db.get_all_ticker_for_asset(asset_id).await?;
+        let mut prices: Vec<(Date, f64)> = Vec::new();
+        let mut currency = base_currency.to_string();
+        if let Some(ticker) = tickers.first() {
+            currency = ticker.currency.to_string();
+            if let Some(ticker_id) = ticker.id {
+                for quote in db.get_all_quotes_for_ticker(ticker_id).await? {
+                    prices.push((quote.time.date(), quote.price * ticker.factor));
+                }
+            }
+        }
+        // One price per day, the latest one wins
+        prices.sort_by_key(|(date, _)| *date);
+        prices.dedup_by(|later, earlier| {
+            if later.0 == earlier.0 {
+                earlier.1 = later.1;
+                true
+            } else {
+                false
+            }
+        });
+
+        let mut children = vec![
+            XmlElement::text("uuid", uuid("security", asset_id)),
+            XmlElement::text("name", &stock.name),
+            XmlElement::text("currencyCode", currency),
+        ];
+        if let Some(isin) = &stock.isin {
+            children.push(XmlElement::text("isin", isin));
+        }
+        if let Some(wkn) = &stock.wkn {
+            children.push(XmlElement::text("wkn", wkn));
+        }
+        if let Some(ticker) = tickers.first() {
+            children.push(XmlElement::text("tickerSymbol", &ticker.name));
+        }
+        children.push(XmlElement::text("feed", "MANUAL"));
+        children.push(XmlElement::new(
+            "prices",
+            prices
+                .iter()
+                .map(|(date, price)| {
+                    XmlElement::with_attrs(
+                        "price",
+                        &[
+                            ("t", date.to_string()),
+                            ("v", scaled(*price, PRICE_FACTOR).to_string()),
+                        ],
+                    )
+                })
+                .collect(),
+        ));
+        children.push(XmlElement::text("isRetired", false));
+        let key = format!("security:{}", asset_id);
+        writer.define(&key, ObjectContent { children });
+        security_objects.push(XmlElement::object("security", &key));
+    }
+
+    let root = XmlElement::new(
+        "client",
+        vec![
+            XmlElement::text("version", PP_FILE_VERSION),
+            XmlElement::text("baseCurrency", base_currency),
+            XmlElement::new("securities", security_objects),
+            XmlElement::new("watchlists", Vec::new()),
+            XmlElement::new("accounts", account_objects),
+            XmlElement::new("portfolios", portfolio_objects),
+            XmlElement::new("plans", Vec::new()),
+            XmlElement::new("taxonomies", Vec::new()),
+            XmlElement::new("dashboards", Vec::new()),
+            XmlElement::new("properties", Vec::new()),
+        ],
+    );
+    Ok(format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}",
+        writer.write(&root)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    const SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<client>
+  <version>56</version>
+  <baseCurrency>EUR</baseCurrency>
+  <securities>
+    <security>
+      <uuid>s1</uuid>
+      <name>ACME Corp.</name>
+      <currencyCode>EUR</currencyCode>
+      <isin>DE0001234567</isin>
+      <wkn>123456</wkn>
+      <prices>
+        <price t="2023-01-02" v="10050000000"/>
+        <price t="2023-01-03" v="10100000000"/>
+      </prices>
+    </security>
+  </securities>
+  <accounts>
+    <account>
+      <uuid>a1</uuid>
+      <name>Cash</name>
+      <currencyCode>EUR</currencyCode>
+      <transactions>
+        <account-transaction>
+          <uuid>t1</uuid>
+          <date>2023-01-02T00:00</date>
+          <currencyCode>EUR</currencyCode>
+          <amount>500000</amount>
+          <shares>0</shares>
+          <type>DEPOSIT</type>
+        </account-transaction>
+        <account-transaction>
+          <uuid>t2</uuid>
+          <date>2023-01-03T00:00</date>
+          <currencyCode>EUR</currencyCode>
+          <amount>101495</amount>
+          <security reference="../../../../../securities/security"/>
+          <crossEntry class="buysell">
+            <portfolio>
+              <uuid>p1</uuid>
+              <name>Depot</name>
+              <referenceAccount reference="../../../../.."/>
+              <transactions>
+                <portfolio-transaction>
+                  <uuid>t3</uuid>
+                  <date>2023-01-03T00:00</date>
+                  <currencyCode>EUR</currencyCode>
+                  <amount>101495</amount>
+                  <security reference="../../../../../../../../../securities/security"/>
+                  <crossEntry class="buysell" reference="../../../.."/>
+                  <shares>1000000000</shares>
+                  <units>
+                    <unit type="FEE">
+                      <amount currency="EUR" amount="1495"/>
+                    </unit>
+                  </units>
+                  <type>BUY</type>
+                </portfolio-transaction>
+              </transactions>
+            </portfolio>
+            <portfolioTransaction reference="../portfolio/transactions/portfolio-transaction"/>
+            <account reference="../../../.."/>
+            <accountTransaction reference="../.."/>
+          </crossEntry>
+          <shares>0</shares>
+          <type>BUY</type>
+        </account-transaction>
+        <account-transaction>
+          <uuid>t4</uuid>
+          <date>2023-05-05T00:00</date>
+          <currencyCode>EUR</currencyCode>
+          <amount>1000</amount>
+          <security reference="../../../../../securities/security"/>
+          <shares>0</shares>
+          <units>
+            <unit type="TAX">
+              <amount currency="EUR" amount="250"/>
+            </unit>
+          </units>
+          <type>DIVIDENDS</type>
+        </account-transaction>
+      </transactions>
+    </account>
+  </accounts>
+  <portfolios>
+    <portfolio reference="../../accounts/account/transactions/account-transaction[2]/crossEntry/portfolio"/>
+  </portfolios>
+</client>"#;
+
+    #[test]
+    fn test_parse_pp_xml() {
+        let client = parse_pp_xml(SAMPLE).unwrap();
+        assert_eq!(client.base_currency, "EUR");
+        assert_eq!(client.securities.len(), 1);
+        let security = &client.securities[0];
+        assert_eq!(security.isin.as_deref(), Some("DE0001234567"));
+        assert_eq!(security.prices[1], (date!(2023 - 01 - 03), 101.0));
+
+        assert_eq!(client.accounts.len(), 1);
+        let rows = &client.accounts[0].rows;
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].kind, ImportKind::Deposit);
+        assert_eq!(rows[0].amount, 5000.0);
+
+        let buy = &rows[1];
+        assert_eq!(buy.kind, ImportKind::Buy);
+        assert_eq!(buy.external_id.as_deref(), Some("t3"));
+        assert_eq!(buy.position, Some(10.0));
+        assert_eq!(buy.amount, -1000.0);
+        assert_eq!(buy.fee, 14.95);
+
+        let dividend = &rows[2];
+        assert_eq!(dividend.kind, ImportKind::Dividend);
+        assert_eq!(dividend.amount, 12.5);
+        assert_eq!(dividend.tax, 2.5);
+    }
+
+    #[test]
+    fn test_delivery() {
+        let xml = r#"<client>
+  <baseCurrency>EUR</baseCurrency>
+  <securities>
+    <security>
+      <name>ACME Corp.</name>
+      <isin>DE0001234567</isin>
+    </security>
+  </securities>
+  <portfolios>
+    <portfolio>
+      <name>Depot</name>
+      <transactions>
+        <portfolio-transaction>
+          <uuid>t1</uuid>
+          <date>2023-01-03T00:00</date>
+          <currencyCode>EUR</currencyCode>
+          <amount>101495</amount>
+          <security reference="../../../../../securities/security"/>
+          <shares>1000000000</shares>
+          <units>
+            <unit type="FEE">
+              <amount currency="EUR" amount="1495"/>
+            </unit>
+          </units>
+          <type>DELIVERY_INBOUND</type>
+        </portfolio-transaction>
+        <portfolio-transaction>
+          <uuid>t2</uuid>
+          <date>2023-02-03T00:00</date>
+          <currencyCode>EUR</currencyCode>
+          <amount>50000</amount>
+          <security reference="../../../../../securities/security"/>
+          <shares>400000000</shares>
+          <type>DELIVERY_OUTBOUND</type>
+        </portfolio-transaction>
+      </transactions>
+    </portfolio>
+  </portfolios>
+</client>"#;
+        let client = parse_pp_xml(xml).unwrap();
+        let rows = &client.accounts[0].rows;
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[0].kind, ImportKind::Buy);
+        assert_eq!(rows[0].amount, -1000.0);
+        assert_eq!(rows[0].fee, 14.95);
+        assert_eq!(rows[1].kind, ImportKind::Deposit);
+        assert_eq!(rows[1].amount, 1014.95);
+        assert_eq!(rows[1].external_id.as_deref(), Some("t1/cash"));
+        assert_eq!(rows[2].kind, ImportKind::Sell);
+        assert_eq!(rows[2].position, Some(-4.0));
+        assert_eq!(rows[2].amount, 500.0);
+        assert_eq!(rows[3].kind, ImportKind::Withdrawal);
+        assert_eq!(rows[3].amount, -500.0);
+    }
+
+    #[test]
+    fn test_uuid() {
+        let id = uuid("transaction", 42);
+        assert_eq!(id.len(), 36);
+        assert_eq!(id, uuid("transaction", 42));
+        assert_ne!(id, uuid("transaction", 43));
+    }
+}
@@ -0,0 +1,395 @@
+//! Reading and writing of XML files in the format of the XStream library
+//!
+//! XStream serializes an object graph as XML tree. Each object is written in full at its first
+//! occurrence only, every further occurrence is an empty element with a `reference` attribute
+//! holding the relative path to the first one, e.g. `../../securities/security[2]`.
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum XStreamError {
+    #[error("Invalid XML: {0}")]
+    Xml(String),
+    #[error("Unresolvable reference '{0}'")]
+    InvalidReference(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct XmlNode {
+    pub name: String,
+    pub attrs: Vec<(String, String)>,
+    pub text: String,
+    pub children: Vec<usize>,
+    parent: Option<usize>,
+}
+
+/// XML document stored as tree of nodes, the first node is the root element
+#[derive(Debug, Clone, Default)]
+pub struct XmlDocument {
+    nodes: Vec<XmlNode>,
+}
+
+impl XmlDocument {
+    pub fn parse(text: &str) -> Result<Self, XStreamError> {
+        let xml_error = |e: quick_xml::Error| XStreamError::Xml(e.to_string());
+        let mut doc = XmlDocument::default();
+        let mut reader = Reader::from_str(text);
+        reader.config_mut().trim_text(true);
+        let mut stack: Vec<usize> = Vec::new();
+        loop {
+            match reader.read_event().map_err(xml_error)? {
+                Event::Start(e) => {
+                    let id = doc.push(&e, stack.last().copied())?;
+                    stack.push(id);
+                }
+                Event::Empty(e) => {
+                    doc.push(&e, stack.last().copied())?;
+                }
+                Event::End(_) => {
+                    stack.pop();
+                }
+                Event::Text(t) => {
+                    if let Some(&id) = stack.last() {
+                        doc.nodes[id]
+                            .text
+                            .push_str(&t.unescape().map_err(xml_error)?);
+                    }
+                }
+                Event::CData(t) => {
+                    if let Some(&id) = stack.last() {
+                        doc.nodes[id].text.push_str(&String::from_utf8_lossy(&t));
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        if doc.nodes.is_empty() {
+            return Err(XStreamError::Xml("no root element".to_string()));
+        }
+        Ok(doc)
+    }
+
+    fn push(&mut self, e: &BytesStart, parent: Option<usize>) -> Result<usize, XStreamError> {
+        let mut attrs = Vec::new();
+        for attr in e.attributes() {
+            let attr = attr.map_err(|e| XStreamError::Xml(e.to_string()))?;
+            let value = attr
+                .unescape_value()
+                .map_err(|e| XStreamError::Xml(e.to_string()))?;
+            attrs.push((
+                String::from_utf8_lossy(attr.key.as_ref()).into_owned(),
+                value.into_owned(),
+            ));
+        }
+        let id = self.nodes.len();
+        self.nodes.push(XmlNode {
+            name: String::from_utf8_lossy(e.name().as_ref()).into_owned(),
+            attrs,
+            text: String::new(),
+            children: Vec::new(),
+            parent,
+        });
+        if let Some(parent) = parent {
+            self.nodes[parent].children.push(id);
+        }
+        Ok(id)
+    }
+
+    pub fn root(&self) -> usize {
+        0
+    }
+
+    pub fn node(&self, id: usize) -> &XmlNode {
+        &self.nodes[id]
+    }
+
+    pub fn attr(&self, id: usize, name: &str) -> Option<&str> {
+        self.nodes[id]
+            .attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Follow the reference of the node, if any
+    pub fn resolve(&self, id: usize) -> Result<usize, XStreamError> {
+        let path = match self.attr(id, "reference") {
+            Some(path) => path,
+            None => return Ok(id),
+        };
+        let invalid = || XStreamError::InvalidReference(path.to_string());
+        let mut current = id;
+        for segment in path.split('/') {
+            if segment == ".." {
+                current = self.nodes[current].parent.ok_or_else(invalid)?;
+                continue;
+            }
+            let (name, index) = match segment.split_once('[') {
+                Some((name, index)) => (
+                    name,
+                    index
+                        .trim_end_matches(']')
+                        .parse::<usize>()
+                        .map_err(|_| invalid())?,
+                ),
+                None => (segment, 1),
+            };
+            current = self.nodes[current]
+                .children
+                .iter()
+                .copied()
+                .filter(|&child| self.nodes[child].name == name)
+                .nth(index.max(1) - 1)
+                .ok_or_else(invalid)?;
+        }
+        Ok(current)
+    }
+
+    /// First child element of the given name, with references resolved
+    pub fn child(&self, id: usize, name: &str) -> Result<Option<usize>, XStreamError> {
+        self.nodes[id]
+            .children
+            .iter()
+            .find(|&&child| self.nodes[child].name == name)
+            .map(|&child| self.resolve(child))
+            .transpose()
+    }
+
+    /// All child elements of the given name, with references resolved
+    pub fn children(&self, id: usize, name: &str) -> Result<Vec<usize>, XStreamError> {
+        self.nodes[id]
+            .children
+            .iter()
+            .filter(|&&child| self.nodes[child].name == name)
+            .map(|&child| self.resolve(child))
+            .collect()
+    }
+
+    /// Text of the first child element of the given name, if not empty
+    pub fn child_text(&self, id: usize, name: &str) -> Result<Option<&str>, XStreamError> {
+        Ok(self
+            .child(id, name)?
+            .map(|child| self.nodes[child].text.trim())
+            .filter(|text| !text.is_empty()))
+    }
+}
+
+/// Element to be written, objects which may occur more than once are given as `Object`
+#[derive(Debug, Clone, PartialEq)]
+pub enum XmlElement {
+    Element {
+        name: String,
+        attrs: Vec<(String, String)>,
+        text: Option<String>,
+        children: Vec<XmlElement>,
+    },
+    /// Object defined by `XStreamWriter::define` with the element name and attributes given here
+    Object {
+        name: String,
+        attrs: Vec<(String, String)>,
+        id: String,
+    },
+}
+
+impl XmlElement {
+    pub fn new(name: &str, children: Vec<XmlElement>) -> Self {
+        XmlElement::Element {
+            name: name.to_string(),
+            attrs: Vec::new(),
+            text: None,
+            children,
+        }
+    }
+
+    pub fn text<T: ToString>(name: &str, text: T) -> Self {
+        XmlElement::Element {
+            name: name.to_string(),
+            attrs: Vec::new(),
+            text: Some(text.to_string()),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn with_attrs(name: &str, attrs: &[(&str, String)]) -> Self {
+        XmlElement::Element {
+            name: name.to_string(),
+            attrs: attrs
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect(),
+            text: None,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn object(name: &str, id: &str) -> Self {
+        XmlElement::Object {
+            name: name.to_string(),
+            attrs: Vec::new(),
+            id: id.to_string(),
+        }
+    }
+}
+
+/// Content of an object, i.e. an element without name
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjectContent {
+    pub children: Vec<XmlElement>,
+}
+
+/// Path of an element as list of names and 1-based positions among siblings of the same name
+type ElementPath = Vec<(String, usize)>;
+
+fn relative_path(from: &[(String, usize)], to: &[(String, usize)]) -> String {
+    let common = from.iter().zip(to).take_while(|(a, b)| a == b).count();
+    let mut segments: Vec<String> = vec!["..".to_string(); from.len() - common];
+    for (name, index) in &to[common..] {
+        if *index == 1 {
+            segments.push(name.clone());
+        } else {
+            segments.push(format!("{}[{}]", name, index));
+        }
+    }
+    segments.join("/")
+}
+
+#[derive(Debug, Default)]
+pub struct XStreamWriter {
+    objects: HashMap<String, ObjectContent>,
+    written: HashMap<String, ElementPath>,
+}
+
+impl XStreamWriter {
+    pub fn define(&mut self, id: &str, content: ObjectContent) {
+        self.objects.insert(id.to_string(), content);
+    }
+
+    pub fn write(&mut self, root: &XmlElement) -> String {
+        self.written.clear();
+        let mut out = String::new();
+        let mut path = Vec::new();
+        let mut counts = HashMap::new();
+        self.write_element(&mut out, root, &mut path, &mut counts);
+        out
+    }
+
+    fn write_element(
+        &mut self,
+        out: &mut String,
+        element: &XmlElement,
+        path: &mut ElementPath,
+        sibling_counts: &mut HashMap<String, usize>,
+    ) {
+        let (name, attrs) = match element {
+            XmlElement::Element { name, attrs, .. } => (name, attrs),
+            XmlElement::Object { name, attrs, .. } => (name, attrs),
+        };
+        let index = sibling_counts.entry(name.clone()).or_insert(0);
+        *index += 1;
+        path.push((name.clone(), *index));
+        let indent = "  ".repeat(path.len() - 1);
+
+        let _ = write!(out, "{}<{}", indent, name);
+        for (key, value) in attrs {
+            let _ = write!(out, " {}=\"{}\"", key, escape(value.as_str()));
+        }
+        let (text, children) = match element {
+            XmlElement::Element { text, children, .. } => (text.clone(), children.clone()),
+            XmlElement::Object { id, .. } => {
+                if let Some(target) = self.written.get(id) {
+                    let _ = writeln!(out, " reference=\"{}\"/>", relative_path(path, target));
+                    path.pop();
+                    return;
+                }
+                self.written.insert(id.clone(), path.clone());
+                let children = self
+                    .objects
+                    .get(id)
+                    .map(|content| content.children.clone())
+                    .unwrap_or_default();
+                (None, children)
+            }
+        };
+        match (text, children.is_empty()) {
+            (Some(text), _) => {
+                let _ = writeln!(out, ">{}</{}>", escape(text.as_str()), name);
+            }
+            (None, true) => {
+                let _ = writeln!(out, "/>");
+            }
+            (None, false) => {
+                let _ = writeln!(out, ">");
+                let mut counts = HashMap::new();
+                for child in &children {
+                    self.write_element(out, child, path, &mut counts);
+                }
+                let _ = writeln!(out, "{}</{}>", indent, name);
+            }
+        }
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_references() {
+        let mut writer = XStreamWriter::default();
+        writer.define(
+            "a",
+            ObjectContent {
+                children: vec![XmlElement::text("name", "A & B")],
+            },
+        );
+        writer.define(
+            "b",
+            ObjectContent {
+                children: vec![
+                    XmlElement::text("name", "B"),
+                    XmlElement::object("other", "a"),
+                ],
+            },
+        );
+        let root = XmlElement::new(
+            "client",
+            vec![
+                XmlElement::new(
+                    "items",
+                    vec![
+                        XmlElement::object("item", "b"),
+                        XmlElement::object("item", "a"),
+                    ],
+                ),
+                XmlElement::new("refs", vec![XmlElement::object("ref", "a")]),
+            ],
+        );
+        let xml = writer.write(&root);
+        assert!(xml.contains("<item reference=\"../item/other\"/>"));
+        assert!(xml.contains("<ref reference=\"../../items/item/other\"/>"));
+
+        let doc = XmlDocument::parse(&xml).unwrap();
+        let items = doc.child(doc.root(), "items").unwrap().unwrap();
+        let items = doc.children(items, "item").unwrap();
+        assert_eq!(doc.child_text(items[1], "name").unwrap(), Some("A & B"));
+        let refs = doc.child(doc.root(), "refs").unwrap().unwrap();
+        assert_eq!(doc.children(refs, "ref").unwrap(), vec![items[1]]);
+        assert_eq!(doc.child(items[0], "other").unwrap(), Some(items[1]));
+    }
+
+    #[test]
+    fn test_indexed_reference() {
+        let xml = r#"<client><list><a><v>1</v></a><a><v>2</v></a></list>
+            <x reference="../list/a[2]"/></client>"#;
+        let doc = XmlDocument::parse(xml).unwrap();
+        let x = doc.child(doc.root(), "x").unwrap().unwrap();
+        assert_eq!(doc.child_text(x, "v").unwrap(), Some("2"));
+    }
+}
@@ -1,14 +1,19 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use finql::datatypes::Currency;
+use serde::{Deserialize, Serialize};
+use time::Date;
+
+use finql::datatypes::{CashFlow, Currency, DataError, Transaction, TransactionType};
 use finql::market::Market;
 use finql::period_date::PeriodDate;
 use finql::portfolio::{
-    calculate_position_for_period, PortfolioPosition, PositionError, PositionTotals,
+    calculate_position_for_period, PortfolioPosition, Position, PositionError, PositionTotals,
 };
 use finql::postgres::PostgresDB;
 
 use crate::accounts::AccountHandler;
+use crate::inflation::Deflator;
 
 // Calculate position for a given period for transactions in a set of accounts
 pub async fn calculate_position_for_period_for_accounts(
@@ -24,3 +29,191 @@ pub async fn calculate_position_for_period_for_accounts(
     let market = Market::new_with_date_range(db, start, end).await?;
     calculate_position_for_period(currency, &transactions, start, end, &market).await
 }
+
+/// Position in real terms of the deflator's base year: purchase values, income, fees and taxes
+/// are deflated at the dates of their cash flows, while cash and market values are deflated at
+/// the end date.
+pub async fn calculate_real_position_for_period_for_accounts(
+    currency: Currency,
+    account_ids: &[i32],
+    start: PeriodDate,
+    end: PeriodDate,
+    deflator: &Deflator,
+    db: Arc<PostgresDB>,
+) -> Result<PortfolioPosition, PositionError> {
+    let end = end.date(None)?;
+    let transactions = db.get_transactions_before_time(account_ids, end).await?;
+    let start = start.date_from_trades(&transactions)?;
+    let market = Market::new_with_date_range(db, start, end).await?;
+    let (nominal, _) =
+        calculate_position_for_period(currency, &transactions, start, end, &market).await?;
+    let (mut real, _) = calculate_position_for_period(
+        currency,
+        &deflator.deflate_transactions(&transactions),
+        start,
+        end,
+        &market,
+    )
+    .await?;
+    let factor = deflator.factor(end);
+    real.cash.position = nominal.cash.position * factor;
+    for pos in real.assets.values_mut() {
+        pos.last_quote = pos.last_quote.map(|quote| quote * factor);
+    }
+    Ok(real)
+}
+
+/// Transactions that recreate the given position at the given date, e.g. as start of a simulation.
+///
+/// Assets are booked at their original purchase value to preserve the cost basis, while a single
+/// cash transaction balances the cash position.
+pub fn start_transactions_from_position(
+    position: &PortfolioPosition,
+    date: Date,
+) -> Vec<Transaction> {
+    let currency = position.cash.currency;
+    let mut transactions = Vec::new();
+    let mut invested = 0.0;
+    for (asset_id, pos) in &position.assets {
+        if pos.position == 0.0 {
+            continue;
+        }
+        invested += pos.purchase_value;
+        transactions.push(Transaction {
+            id: None,
+            transaction_type: TransactionType::Asset {
+                asset_id: *asset_id,
+                position: pos.position,
+            },
+            cash_flow: CashFlow::new(pos.purchase_value, currency, date),
+            note: Some("initial position".to_string()),
+        });
+    }
+    transactions.insert(
+        0,
+        Transaction {
+            id: None,
+            transaction_type: TransactionType::Cash,
+            cash_flow: CashFlow::new(position.cash.position - invested, currency, date),
+            note: Some("initial cash".to_string()),
+        },
+    );
+    transactions
+}
+
+/// Totals over the cash and asset positions of a portfolio, as shown below the position table
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PositionSummary {
+    pub value: f64,
+    pub trading_pnl: f64,
+    pub unrealized_pnl: f64,
+    pub dividend: f64,
+    pub interest: f64,
+    pub tax: f64,
+    pub fees: f64,
+}
+
+impl PositionSummary {
+    /// Totals of the cash and all asset positions of a portfolio
+    pub fn from_position(portfolio: &PortfolioPosition) -> Self {
+        let mut summary = PositionSummary::default();
+        summary.add_cash(&portfolio.cash);
+        for pos in portfolio.assets.values() {
+            summary.add_asset(pos);
+        }
+        summary
+    }
+
+    pub fn add_cash(&mut self, cash: &Position) {
+        self.value += cash.position;
+        self.add_income(cash);
+    }
+
+    pub fn add_asset(&mut self, pos: &Position) {
+        let value = asset_value(pos);
+        self.value += value;
+        self.unrealized_pnl += value + pos.purchase_value;
+        self.add_income(pos);
+    }
+
+    pub fn add(&mut self, other: &PositionSummary) {
+        self.value += other.value;
+        self.trading_pnl += other.trading_pnl;
+        self.unrealized_pnl += other.unrealized_pnl;
+        self.dividend += other.dividend;
+        self.interest += other.interest;
+        self.tax += other.tax;
+        self.fees += other.fees;
+    }
+
+    fn add_income(&mut self, pos: &Position) {
+        self.trading_pnl += pos.trading_pnl;
+        self.dividend += pos.dividend;
+        self.interest += pos.interest;
+        self.tax += pos.tax;
+        self.fees += pos.fees;
+    }
+}
+
+/// Market value of an asset position, or its purchase value if no quote is available
+pub fn asset_value(pos: &Position) -> f64 {
+    match pos.last_quote {
+        Some(quote) => pos.position * quote,
+        None => -pos.purchase_value,
+    }
+}
+
+/// Asset class (e.g. "stock" or "currency") of all assets by asset id
+pub async fn get_asset_classes(db: &PostgresDB) -> Result<HashMap<i32, String>, DataError> {
+    let rows = sqlx::query!("SELECT id, asset_class FROM assets")
+        .fetch_all(&db.pool)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.id, row.asset_class))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use finql::datatypes::CurrencyISOCode;
+
+    #[test]
+    fn test_position_summary() {
+        let eur = Currency::new(None, CurrencyISOCode::new("EUR").unwrap(), Some(2));
+        let mut portfolio = PortfolioPosition::new(eur);
+        portfolio.cash.position = 1000.0;
+        portfolio.cash.interest = 5.0;
+        portfolio.cash.fees = 2.0;
+        let mut quoted = Position::new(Some(1), eur);
+        quoted.position = 10.0;
+        quoted.purchase_value = -900.0;
+        quoted.last_quote = Some(100.0);
+        quoted.dividend = 12.0;
+        quoted.tax = 3.0;
+        let mut unquoted = Position::new(Some(2), eur);
+        unquoted.position = 5.0;
+        unquoted.purchase_value = -500.0;
+        unquoted.trading_pnl = 7.0;
+        portfolio.assets.insert(1, quoted);
+        portfolio.assets.insert(2, unquoted);
+
+        let summary = PositionSummary::from_position(&portfolio);
+        let expected = PositionSummary {
+            value: 2500.0,
+            trading_pnl: 7.0,
+            unrealized_pnl: 100.0,
+            dividend: 12.0,
+            interest: 5.0,
+            tax: 3.0,
+            fees: 2.0,
+        };
+        assert_eq!(summary, expected);
+
+        let mut total = summary.clone();
+        total.add(&summary);
+        assert_eq!(total.value, 5000.0);
+        assert_eq!(total.unrealized_pnl, 200.0);
+    }
+}
@@ -7,52 +7,9 @@ use crate::user::{User, UserHandler, UserSettings};
 
 #[async_trait]
 impl UserHandler for PostgresDB {
-    /// Clean database by dropping all tables related to user management and run init_users
+    /// Remove all users, their rights and settings
     async fn clean_users(&self) -> Result<(), DataError> {
-        sqlx::query!("DROP TABLE IF EXISTS account_rights")
-            .execute(&self.pool)
-            .await?;
-        self.init_users().await?;
-        sqlx::query!("DROP TABLE IF EXISTS users")
-            .execute(&self.pool)
-            .await?;
-        self.init_users().await?;
-        Ok(())
-    }
-
-    /// Set up new tables for user management
-    async fn init_users(&self) -> Result<(), DataError> {
-        sqlx::query!(
-            "CREATE TABLE IF NOT EXISTS users (
-            id SERIAL PRIMARY KEY,
-            name TEXT NOT NULL,
-            display TEXT,
-            salt_hash TEXT NOT NULL,
-            is_admin BOOLEAN NOT NULL DEFAULT False,
-            UNIQUE (name))"
-        )
-        .execute(&self.pool)
-        .await?;
-        sqlx::query!(
-            "CREATE TABLE IF NOT EXISTS account_rights (
-            id SERIAL PRIMARY KEY,
-            user_id INTEGER NOT NULL,
-            account_id INTEGER NOT NULL,
-            FOREIGN KEY(user_id) REFERENCES users(id),
-            FOREIGN KEY(account_id) REFERENCES accounts(id))"
-        )
-        .execute(&self.pool)
-        .await?;
-        sqlx::query!(
-            "CREATE TABLE IF NOT EXISTS user_settings (
-                id SERIAL PRIMARY KEY,
-                user_id INTEGER UNIQUE,
-                settings JSON,
-                FOREIGN KEY(user_id) REFERENCES users(id))"
-        )
-        .execute(&self.pool)
-        .await?;
-        sqlx::query!("CREATE EXTENSION pgcrypto")
+        sqlx::query!("TRUNCATE account_rights, user_settings, users RESTART IDENTITY CASCADE")
             .execute(&self.pool)
             .await?;
         Ok(())
@@ -64,7 +21,7 @@ impl UserHandler for PostgresDB {
             "INSERT INTO users (name, display, salt_hash, is_admin)
                 VALUES ($1, $2, crypt($3,gen_salt('bf',8)), $4) RETURNING id",
             user.name,
-            user.display,
+            user.display.as_deref().unwrap_or_default(),
             password,
             user.is_admin,
         )
@@ -211,7 +168,7 @@ impl UserHandler for PostgresDB {
             "UPDATE users SET name=$2, display=$3, is_admin=$4 WHERE id=$1",
             id,
             user.name,
-            user.display,
+            user.display.as_deref().unwrap_or_default(),
             user.is_admin
         )
         .execute(&self.pool)
@@ -231,11 +188,19 @@ impl UserHandler for PostgresDB {
         Ok(())
     }
 
-    /// Remove all user information form data base
+    /// Remove all user information form data base, including account rights and settings
     async fn delete_user(&self, user_id: i32) -> Result<(), DataError> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!("DELETE FROM account_rights WHERE user_id=$1", user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM user_settings WHERE user_id=$1", user_id)
+            .execute(&mut *tx)
+            .await?;
         sqlx::query!("DELETE FROM users WHERE id=$1;", user_id)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await?;
+        tx.commit().await?;
         Ok(())
     }
 
@@ -0,0 +1,424 @@
+//! Import of quote histories from CSV files
+//!
+//! Quotes can be imported once for a given ticker, e.g. via `qualinvest import-quotes`, or be
+//! provided continuously by the market data provider "file": for each ticker of source "file",
+//! the file `<ticker name>.csv` in the configured quote directory is read, with characters not
+//! allowed in file names removed from the ticker name. New or changed files are picked up by
+//! `qualinvest daemon`, and the provider works fully offline, which makes it also suitable for
+//! tests. The columns and formats of the files are configured by a `QuoteCsvFormat`, the default
+//! matches files as downloaded from yahoo, e.g.
+//!
+//! ```text
+//! Date,Open,High,Low,Close,Adj Close,Volume
+//! 2023-03-01,100.0,101.5,99.0,101.0,101.0,12000
+//! ```
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use time::{Date, OffsetDateTime};
+
+use finql::datatypes::{date_time_helper::date_to_offset_date_time, Quote, QuoteHandler, Ticker};
+use finql::market_quotes::{MarketQuoteError, MarketQuoteProvider};
+
+use crate::csv_import::{
+    default_date_format, default_decimal_separator, default_delimiter, default_true, parse_date,
+    parse_decimal, Column, ImportError, RowError,
+};
+
+/// Name of the market data source of tickers with quotes read from files
+pub const FILE_SOURCE: &str = "file";
+
+/// Hour of the day quotes read from files are stored at, in the time zone of the ticker
+const QUOTE_HOUR: u8 = 18;
+
+/// Layout of CSV files containing quotes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuoteCsvFormat {
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+    #[serde(default = "default_true")]
+    pub has_headers: bool,
+    /// Number of lines to skip before the header line
+    #[serde(default)]
+    pub skip_lines: usize,
+    #[serde(default = "default_date_column")]
+    pub date: Column,
+    #[serde(default = "default_price_column")]
+    pub price: Column,
+    pub volume: Option<Column>,
+    /// Format of dates as used by `strftime`, e.g. "%d.%m.%Y"
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    #[serde(default = "default_decimal_separator")]
+    pub decimal_separator: char,
+    pub thousands_separator: Option<char>,
+}
+
+fn default_date_column() -> Column {
+    Column::Name("Date".to_string())
+}
+
+fn default_price_column() -> Column {
+    Column::Name("Close".to_string())
+}
+
+impl Default for QuoteCsvFormat {
+    fn default() -> Self {
+        QuoteCsvFormat {
+            delimiter: default_delimiter(),
+            has_headers: true,
+            skip_lines: 0,
+            date: default_date_column(),
+            price: default_price_column(),
+            volume: None,
+            date_format: default_date_format(),
+            decimal_separator: default_decimal_separator(),
+            thousands_separator: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuoteRow {
+    pub date: Date,
+    pub price: f64,
+    pub volume: Option<f64>,
+}
+
+/// Quotes read from a CSV file, sorted by date
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QuotePreview {
+    pub quotes: Vec<QuoteRow>,
+    pub errors: Vec<RowError>,
+}
+
+/// Read all quotes of a CSV file. Errors in single rows are collected in the preview, only
+/// errors concerning the whole file (e.g. missing columns) are returned as error.
+pub fn parse_quote_csv(format: &QuoteCsvFormat, text: &str) -> Result<QuotePreview, ImportError> {
+    let text = text.trim_start_matches('\u{feff}');
+    let body: String = text
+        .lines()
+        .skip(format.skip_lines)
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(format.delimiter as u8)
+        .has_headers(format.has_headers)
+        .flexible(true)
+        .from_reader(body.as_bytes());
+    let headers = if format.has_headers {
+        Some(rdr.headers()?.clone())
+    } else {
+        None
+    };
+    let date_index = format.date.position(headers.as_ref())?;
+    let price_index = format.price.position(headers.as_ref())?;
+    let volume_index = format
+        .volume
+        .as_ref()
+        .map(|column| column.position(headers.as_ref()))
+        .transpose()?;
+
+    let mut preview = QuotePreview::default();
+    for record in rdr.records() {
+        let record = record?;
+        let line = record.position().map(|p| p.line() as usize).unwrap_or(0) + format.skip_lines;
+        if record.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+        match parse_quote_row(format, &record, date_index, price_index, volume_index) {
+            Ok(quote) => preview.quotes.push(quote),
+            Err(message) => preview.errors.push(RowError { line, message }),
+        }
+    }
+    preview.quotes.sort_by_key(|quote| quote.date);
+    Ok(preview)
+}
+
+fn parse_quote_row(
+    format: &QuoteCsvFormat,
+    record: &csv::StringRecord,
+    date_index: usize,
+    price_index: usize,
+    volume_index: Option<usize>,
+) -> Result<QuoteRow, String> {
+    let field = |index: usize| record.get(index).map(str::trim).unwrap_or_default();
+    let number =
+        |text: &str| parse_decimal(text, format.decimal_separator, format.thousands_separator);
+
+    let date_text = field(date_index);
+    let date = parse_date(date_text, &format.date_format)
+        .ok_or_else(|| format!("invalid date '{}'", date_text))?;
+    let price_text = field(price_index);
+    // Some providers mark missing quotes with "null" or similar
+    let price = number(price_text)
+        .filter(|price| *price > 0.0)
+        .ok_or_else(|| format!("invalid price '{}'", price_text))?;
+    let volume = volume_index.map(field).and_then(number);
+    Ok(QuoteRow {
+        date,
+        price,
+        volume,
+    })
+}
+
+/// Store the quotes as quotes of the given ticker. Quotes for dates already stored are skipped,
+/// such that repeated imports of the same file only add new quotes. Returns the number of new
+/// quotes.
+pub async fn import_quotes<DB: QuoteHandler + Sync>(
+    db: &DB,
+    ticker: &Ticker,
+    quotes: &[QuoteRow],
+) -> Result<usize, ImportError> {
+    let ticker_id = match ticker.id {
+        Some(ticker_id) => ticker_id,
+        None => return Ok(0),
+    };
+    let known_dates: HashSet<Date> = db
+        .get_all_quotes_for_ticker(ticker_id)
+        .await?
+        .into_iter()
+        .map(|q| q.time.date())
+        .collect();
+    let mut count = 0;
+    for quote in quotes {
+        if known_dates.contains(&quote.date) {
+            continue;
+        }
+        db.insert_quote(&Quote {
+            id: None,
+            ticker: ticker_id,
+            price: quote.price,
+            time: date_to_offset_date_time(&quote.date, QUOTE_HOUR, ticker.tz.clone())?,
+            volume: quote.volume,
+        })
+        .await?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Path of the quote file of a ticker of source "file". Characters of the ticker name not
+/// allowed in file names, e.g. path separators, are removed.
+pub fn quote_file_path(directory: &Path, ticker: &Ticker) -> PathBuf {
+    directory.join(format!("{}.csv", sanitize_filename::sanitize(&ticker.name)))
+}
+
+/// Market data provider reading quotes from the files in a directory
+pub struct FileQuoteProvider {
+    directory: PathBuf,
+    format: QuoteCsvFormat,
+}
+
+impl FileQuoteProvider {
+    pub fn new(directory: PathBuf, format: QuoteCsvFormat) -> Self {
+        FileQuoteProvider { directory, format }
+    }
+
+    fn read_quotes(&self, ticker: &Ticker) -> Result<Vec<Quote>, MarketQuoteError> {
+        let ticker_id = ticker.id.ok_or_else(|| {
+            MarketQuoteError::FetchFailed(format!("ticker '{}' is not stored", ticker.name))
+        })?;
+        let path = quote_file_path(&self.directory, ticker);
+        let text = fs::read_to_string(&path).map_err(|e| {
+            MarketQuoteError::FetchFailed(format!("failed to read {}: {}", path.display(), e))
+        })?;
+        let preview = parse_quote_csv(&self.format, &text).map_err(|e| {
+            MarketQuoteError::FetchFailed(format!("failed to parse {}: {}", path.display(), e))
+        })?;
+        preview
+            .quotes
+            .into_iter()
+            .map(|quote| {
+                Ok(Quote {
+                    id: None,
+                    ticker: ticker_id,
+                    price: quote.price,
+                    time: date_to_offset_date_time(&quote.date, QUOTE_HOUR, ticker.tz.clone())
+                        .map_err(|e| MarketQuoteError::FetchFailed(e.to_string()))?,
+                    volume: quote.volume,
+                })
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl MarketQuoteProvider for FileQuoteProvider {
+    async fn fetch_latest_quote(&self, ticker: &Ticker) -> Result<Quote, MarketQuoteError> {
+        self.read_quotes(ticker)?.pop().ok_or_else(|| {
+            MarketQuoteError::FetchFailed(format!("no quotes found for '{}'", ticker.name))
+        })
+    }
+
+    async fn fetch_quote_history(
+        &self,
+        ticker: &Ticker,
+        start: OffsetDateTime,
+        end: OffsetDateTime,
+    ) -> Result<Vec<Quote>, MarketQuoteError> {
+        Ok(self
+            .read_quotes(ticker)?
+            .into_iter()
+            .filter(|quote| quote.time >= start && quote.time <= end)
+            .collect())
+    }
+}
+
+/// Outcome of importing a single quote file
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuoteFileImport {
+    pub ticker: String,
+    pub path: PathBuf,
+    /// Number of new quotes stored
+    pub new_quotes: usize,
+    /// Errors reading the file or single lines of it
+    pub errors: Vec<String>,
+}
+
+/// Picks up new or changed quote files of tickers of source "file"
+pub struct QuoteFileWatcher {
+    directory: PathBuf,
+    format: QuoteCsvFormat,
+    /// Time of the last scan, files not modified since then are skipped
+    last_scan: Option<SystemTime>,
+}
+
+impl QuoteFileWatcher {
+    pub fn new(directory: PathBuf, format: QuoteCsvFormat) -> Self {
+        QuoteFileWatcher {
+            directory,
+            format,
+            last_scan: None,
+        }
+    }
+
+    /// Import the quotes of all files modified since the last scan. Files that can't be read
+    /// or parsed don't stop the scan, but are reported in the result.
+    pub async fn scan<DB: QuoteHandler + Sync>(
+        &mut self,
+        db: &DB,
+    ) -> Result<Vec<QuoteFileImport>, ImportError> {
+        let scan_time = SystemTime::now();
+        let mut imports = Vec::new();
+        for ticker in db.get_all_ticker().await? {
+            if !ticker.source.eq_ignore_ascii_case(FILE_SOURCE) {
+                continue;
+            }
+            let path = quote_file_path(&self.directory, &ticker);
+            let modified = match fs::metadata(&path).and_then(|m| m.modified()) {
+                Ok(modified) => modified,
+                Err(_) => continue,
+            };
+            if self.last_scan.is_some_and(|last_scan| modified < last_scan) {
+                continue;
+            }
+            let mut import = QuoteFileImport {
+                ticker: ticker.name.clone(),
+                path: path.clone(),
+                new_quotes: 0,
+                errors: Vec::new(),
+            };
+            match fs::read_to_string(&path)
+                .map_err(ImportError::from)
+                .and_then(|text| parse_quote_csv(&self.format, &text))
+            {
+                Ok(preview) => {
+                    import.new_quotes = import_quotes(db, &ticker, &preview.quotes).await?;
+                    import.errors = preview.errors.iter().map(|e| e.to_string()).collect();
+                }
+                Err(e) => import.errors.push(e.to_string()),
+            }
+            imports.push(import);
+        }
+        self.last_scan = Some(scan_time);
+        Ok(imports)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    #[test]
+    fn test_parse_yahoo_quotes() {
+        let text = "Date,Open,High,Low,Close,Adj Close,Volume\n\
+                    2023-03-02,101.0,102.0,100.0,101.5,101.5,9000\n\
+                    2023-03-01,100.0,101.5,99.0,101.0,101.0,12000\n\
+                    2023-03-03,null,null,null,null,null,null\n";
+        let preview = parse_quote_csv(&QuoteCsvFormat::default(), text).unwrap();
+        assert_eq!(preview.quotes.len(), 2);
+        assert_eq!(preview.quotes[0].date, date!(2023 - 03 - 01));
+        assert_eq!(preview.quotes[0].price, 101.0);
+        assert_eq!(preview.quotes[0].volume, None);
+        assert_eq!(preview.quotes[1].price, 101.5);
+        assert_eq!(preview.errors.len(), 1);
+        assert_eq!(preview.errors[0].line, 4);
+    }
+
+    #[test]
+    fn test_parse_custom_format() {
+        let format: QuoteCsvFormat = serde_json::from_str(
+            r#"{
+                "delimiter": ";",
+                "skip_lines": 1,
+                "date": "Datum",
+                "price": 2,
+                "volume": "Umsatz",
+                "date_format": "%d.%m.%Y",
+                "decimal_separator": ",",
+                "thousands_separator": "."
+            }"#,
+        )
+        .unwrap();
+        let text = "Kurshistorie\n\
+                    Datum;Eröffnung;Schluss;Umsatz\n\
+                    01.03.2023;1.000,00;1.012,50;3.000\n";
+        let preview = parse_quote_csv(&format, text).unwrap();
+        assert!(preview.errors.is_empty());
+        assert_eq!(
+            preview.quotes,
+            vec![QuoteRow {
+                date: date!(2023 - 03 - 01),
+                price: 1012.5,
+                volume: Some(3000.0),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_missing_column() {
+        let format = QuoteCsvFormat {
+            price: Column::Name("Price".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            parse_quote_csv(&format, "Date,Close\n2023-03-01,1.0\n"),
+            Err(ImportError::MissingColumn(_))
+        ));
+    }
+
+    #[test]
+    fn test_quote_file_path() {
+        use finql::datatypes::{Currency, CurrencyISOCode};
+
+        let ticker = Ticker {
+            id: None,
+            name: "../BRK/B".to_string(),
+            asset: 1,
+            currency: Currency::new(None, CurrencyISOCode::new("USD").unwrap(), Some(2)),
+            source: "file".to_string(),
+            priority: 10,
+            factor: 1.0,
+            cal: None,
+            tz: None,
+        };
+        let path = quote_file_path(Path::new("/quotes"), &ticker);
+        assert_eq!(path, Path::new("/quotes/..BRKB.csv"));
+    }
+}
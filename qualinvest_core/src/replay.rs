@@ -0,0 +1,334 @@
+//! Replay the external cash flows of real accounts into alternative, passive strategies
+//!
+//! Deposits and withdrawals of the selected accounts are taken as they happened in reality,
+//! but are invested into a fixed allocation of assets (e.g. a single index ETF or a 60/40 savings
+//! plan) instead. The resulting transactions can be valued like any other set of transactions,
+//! which allows to answer the question whether a passive alternative would have done better.
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use thiserror::Error;
+use time::Date;
+
+use finql::{
+    datatypes::{
+        date_time_helper::date_to_offset_date_time, CashFlow, Currency, DataError, Transaction,
+        TransactionType,
+    },
+    period_date::PeriodDate,
+    portfolio::calc_position,
+    postgres::PostgresDB,
+    strategy::StockTransactionCosts,
+    time_series::TimeSeries,
+    Market,
+};
+
+use crate::accounts::AccountHandler;
+use crate::performance::{calc_performance, PerformanceError};
+pub use crate::strategy::TargetWeight;
+use crate::strategy::{asset_price, trade};
+
+#[derive(Error, Debug)]
+pub enum ReplayError {
+    #[error("Database access failed")]
+    DataError(#[from] DataError),
+    #[error("Failed to calculate position")]
+    PositionError(#[from] finql::portfolio::PositionError),
+    #[error("Failed to calculate performance")]
+    PerformanceError(#[from] PerformanceError),
+    #[error("Date calculation error")]
+    DateError(#[from] finql::datatypes::date_time_helper::DateTimeError),
+    #[error("Market error")]
+    MarketError(#[from] finql::market::MarketError),
+    #[error("Target weights must be non-negative and sum up to at most 100%")]
+    InvalidWeights,
+    #[error("No price of asset {0} available at {1}")]
+    MissingPrice(i32, Date),
+    #[error("Currency {0} is not stored in the database, can't convert it")]
+    UnknownCurrency(String),
+}
+
+/// Alternative investment strategy the real cash flows are replayed into
+pub struct Alternative {
+    /// Title of the resulting time series
+    pub label: String,
+    /// Allocation of each deposit; the part not allocated remains in cash
+    pub targets: Vec<TargetWeight>,
+    pub costs: StockTransactionCosts,
+}
+
+/// Extract the external cash flows (deposits and withdrawals) from a list of transactions
+pub fn external_cash_flows(transactions: &[Transaction]) -> Vec<CashFlow> {
+    let mut cash_flows: Vec<CashFlow> = transactions
+        .iter()
+        .filter(|t| matches!(t.transaction_type, TransactionType::Cash))
+        .map(|t| t.cash_flow.clone())
+        .collect();
+    cash_flows.sort_by(|a, b| a.date.cmp(&b.date));
+    cash_flows
+}
+
+fn check_weights(targets: &[TargetWeight]) -> Result<(), ReplayError> {
+    let total: f64 = targets.iter().map(|t| t.weight).sum();
+    if targets.iter().any(|t| t.weight < 0.0) || total > 1.0 + 1e-9 {
+        return Err(ReplayError::InvalidWeights);
+    }
+    Ok(())
+}
+
+/// Holding of a single asset built up while replaying the cash flows
+#[derive(Debug, Default, Clone, Copy)]
+struct Holding {
+    position: f64,
+    cost_basis: f64,
+}
+
+/// Fee and tax of selling assets of the given volume and cost basis
+fn sale_costs(volume: f64, cost_basis: f64, costs: &StockTransactionCosts) -> (f64, f64) {
+    let fee = costs.fee.calc_fee(volume);
+    let gain = volume - fee - cost_basis;
+    let tax = if gain > 0.0 {
+        gain * costs.tax_rate
+    } else {
+        0.0
+    };
+    (fee, tax)
+}
+
+/// Proceeds after fees and taxes of selling the given fraction of each holding, given by its
+/// value and cost basis
+fn net_proceeds(fraction: f64, holdings: &[(f64, f64)], costs: &StockTransactionCosts) -> f64 {
+    holdings
+        .iter()
+        .map(|(value, cost_basis)| {
+            let volume = value * fraction;
+            let (fee, tax) = sale_costs(volume, cost_basis * fraction, costs);
+            volume - fee - tax
+        })
+        .sum()
+}
+
+/// Fraction of all holdings to sell, such that the proceeds after fees and taxes cover the
+/// withdrawal. All holdings are sold if they don't suffice.
+fn withdrawal_fraction(
+    withdrawal: f64,
+    holdings: &[(f64, f64)],
+    costs: &StockTransactionCosts,
+) -> f64 {
+    if net_proceeds(1.0, holdings, costs) <= withdrawal {
+        return 1.0;
+    }
+    // The proceeds grow with the fraction sold, therefore bisection converges
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..50 {
+        let mid = (low + high) / 2.0;
+        if net_proceeds(mid, holdings, costs) < withdrawal {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    high
+}
+
+/// Build the transactions that result from investing each deposit according to the target
+/// weights and from selling all assets proportionally on each withdrawal.
+pub async fn replay_cash_flows(
+    currency: Currency,
+    cash_flows: &[CashFlow],
+    alternative: &Alternative,
+    market: &Market,
+) -> Result<Vec<Transaction>, ReplayError> {
+    check_weights(&alternative.targets)?;
+
+    let mut transactions = Vec::new();
+    let mut holdings: BTreeMap<i32, Holding> = BTreeMap::new();
+    for cash_flow in cash_flows {
+        let date = cash_flow.date;
+        let amount = convert_amount(cash_flow, currency, market).await?;
+        transactions.push(Transaction {
+            id: None,
+            transaction_type: TransactionType::Cash,
+            cash_flow: CashFlow::new(amount, currency, date),
+            note: Some("replayed cash flow".to_string()),
+        });
+
+        if amount > 0.0 {
+            for target in &alternative.targets {
+                let volume = amount * target.weight;
+                if volume <= 0.0 {
+                    continue;
+                }
+                let fee = alternative.costs.fee.calc_fee(volume);
+                let price = price_at(target.asset_id, currency, date, market).await?;
+                let position = (volume - fee) / price;
+                let holding = holdings.entry(target.asset_id).or_default();
+                holding.position += position;
+                holding.cost_basis += volume;
+                transactions.extend(trade(
+                    target.asset_id,
+                    position,
+                    price,
+                    fee,
+                    0.0,
+                    currency,
+                    date,
+                ));
+            }
+        } else if amount < 0.0 {
+            let mut total_value = 0.0;
+            let mut prices = BTreeMap::new();
+            let mut values = Vec::new();
+            for (asset_id, holding) in &holdings {
+                let price = price_at(*asset_id, currency, date, market).await?;
+                total_value += holding.position * price;
+                prices.insert(*asset_id, price);
+                values.push((holding.position * price, holding.cost_basis));
+            }
+            if total_value <= 0.0 {
+                continue;
+            }
+            let fraction = withdrawal_fraction(-amount, &values, &alternative.costs);
+            for (asset_id, holding) in holdings.iter_mut() {
+                let price = prices[asset_id];
+                let position = holding.position * fraction;
+                let volume = position * price;
+                let cost_basis = holding.cost_basis * fraction;
+                let (fee, tax) = sale_costs(volume, cost_basis, &alternative.costs);
+                holding.position -= position;
+                holding.cost_basis -= cost_basis;
+                transactions.extend(trade(*asset_id, -position, price, fee, tax, currency, date));
+            }
+        }
+    }
+    Ok(transactions)
+}
+
+/// Convert cash flow into given currency, using the currencies' quotes as FX rates
+async fn convert_amount(
+    cash_flow: &CashFlow,
+    currency: Currency,
+    market: &Market,
+) -> Result<f64, ReplayError> {
+    let foreign = cash_flow.amount.currency;
+    if foreign == currency {
+        return Ok(cash_flow.amount.amount);
+    }
+    let foreign_id = foreign
+        .id
+        .ok_or_else(|| ReplayError::UnknownCurrency(foreign.iso_code.to_string()))?;
+    let fx_rate = price_at(foreign_id, currency, cash_flow.date, market).await?;
+    Ok(cash_flow.amount.amount * fx_rate)
+}
+
+async fn price_at(
+    asset_id: i32,
+    currency: Currency,
+    date: Date,
+    market: &Market,
+) -> Result<f64, ReplayError> {
+    asset_price(asset_id, currency, date, market)
+        .await
+        .ok_or(ReplayError::MissingPrice(asset_id, date))
+}
+
+/// Calculate the value of the real portfolio of the given accounts over the given period
+/// together with the value of each alternative the external cash flows are replayed into.
+///
+/// If the period starts after the first transaction, the real portfolio value at the start
+/// date is treated as initial deposit of each alternative.
+pub async fn what_if_replay(
+    currency: Currency,
+    account_ids: &[i32],
+    start: PeriodDate,
+    end: PeriodDate,
+    alternatives: &[Alternative],
+    db: Arc<PostgresDB>,
+) -> Result<Vec<TimeSeries>, ReplayError> {
+    let end = end.date(None)?;
+    let transactions = db.get_transactions_before_time(account_ids, end).await?;
+    let start = start.date_from_trades(&transactions)?;
+    let market = Market::new_with_date_range(db.clone(), start, end).await?;
+
+    let real_performance =
+        calc_performance(currency, &transactions, start, end, &market, "TARGET").await?;
+
+    let transactions_before_start: Vec<Transaction> = transactions
+        .iter()
+        .filter(|t| t.cash_flow.date < start)
+        .cloned()
+        .collect();
+    let mut cash_flows = Vec::new();
+    if !transactions_before_start.is_empty() {
+        let mut position = calc_position(
+            currency,
+            &transactions_before_start,
+            Some(start),
+            market.clone(),
+        )
+        .await?;
+        position
+            .add_quote(date_to_offset_date_time(&start, 20, None)?, &market)
+            .await;
+        let initial_value = position.calc_totals().value;
+        if initial_value > 0.0 {
+            cash_flows.push(CashFlow::new(initial_value, currency, start));
+        }
+    }
+    let transactions_in_range = db
+        .get_transactions_in_range(account_ids, start, end)
+        .await?;
+    cash_flows.extend(external_cash_flows(&transactions_in_range));
+
+    let mut all_series = vec![TimeSeries {
+        title: "Real portfolio".to_string(),
+        series: real_performance,
+    }];
+    for alternative in alternatives {
+        let replayed = replay_cash_flows(currency, &cash_flows, alternative, &market).await?;
+        let performance =
+            calc_performance(currency, &replayed, start, end, &market, "TARGET").await?;
+        all_series.push(TimeSeries {
+            title: alternative.label.clone(),
+            series: performance,
+        });
+    }
+    Ok(all_series)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use finql::strategy::StockTransactionFee;
+
+    fn costs(proportional_fee: f64, tax_rate: f64) -> StockTransactionCosts {
+        StockTransactionCosts {
+            fee: StockTransactionFee::new(0.0, None, proportional_fee),
+            tax_rate,
+        }
+    }
+
+    #[test]
+    fn test_withdrawal_covers_fees() {
+        let costs = costs(0.01, 0.0);
+        let holdings = [(1000.0, 1000.0)];
+        let fraction = withdrawal_fraction(99.0, &holdings, &costs);
+        assert!((fraction - 0.1).abs() < 1e-9);
+        assert!((net_proceeds(fraction, &holdings, &costs) - 99.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_withdrawal_covers_taxes() {
+        let costs = costs(0.0, 0.25);
+        // Half of the value is gain, such that 12.5% of the proceeds are paid as tax
+        let holdings = [(600.0, 300.0), (400.0, 200.0)];
+        let fraction = withdrawal_fraction(175.0, &holdings, &costs);
+        assert!((fraction - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_withdrawal_exceeding_holdings() {
+        let costs = costs(0.01, 0.25);
+        assert_eq!(withdrawal_fraction(2000.0, &[(1000.0, 500.0)], &costs), 1.0);
+    }
+}
@@ -0,0 +1,371 @@
+//! Scheduled quote updates and gap filling
+//!
+//! Instead of external cron jobs calling `qualinvest update` and `qualinvest fill-gaps`, the
+//! command `qualinvest daemon` runs the schedules configured in the section `[scheduler]` of the
+//! configuration file. Quotes are updated once a day per schedule at the given hour, tickers are
+//! skipped on days that are no business days of their calendar. Each run is recorded as
+//! background job, such that the outcome of the last runs is visible on the settings page.
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{de::Error as _, Deserialize, Deserializer};
+use thiserror::Error;
+use time::OffsetDateTime;
+
+use finql::datatypes::date_time_helper::{date_to_offset_date_time, DateTimeError};
+use finql::datatypes::{DataError, Ticker};
+use finql::market::{Market, MarketError};
+use finql::postgres::PostgresDB;
+
+use crate::jobs::{JobHandler, JobStatus};
+
+/// Prefix of the kind of all jobs started by the scheduler
+pub const SCHEDULED_JOB_PREFIX: &str = "scheduled ";
+
+#[derive(Error, Debug)]
+pub enum SchedulerError {
+    #[error("failed to record scheduled run")]
+    DataError(#[from] DataError),
+    #[error("invalid schedule time")]
+    DateError(#[from] DateTimeError),
+    #[error("market data access failed: {0}")]
+    MarketError(#[from] MarketError),
+    #[error("{0}")]
+    UpdateFailed(String),
+}
+
+/// Configuration of scheduled tasks
+#[derive(Debug, Clone, Deserialize)]
+pub struct SchedulerConfig {
+    #[serde(default)]
+    pub quote_updates: Vec<QuoteUpdateSchedule>,
+    /// Hour of the day (local time) at which gaps in quote histories are filled,
+    /// gaps are not filled if not given
+    #[serde(default, deserialize_with = "deserialize_optional_hour")]
+    pub fill_gaps_hour: Option<u8>,
+    /// Minimal number of missing quotes to be considered as gap
+    #[serde(default = "default_fill_gaps_min_size")]
+    pub fill_gaps_min_size: usize,
+    /// Seconds between two checks for due tasks
+    #[serde(default = "default_check_interval")]
+    pub check_interval: u64,
+}
+
+fn default_fill_gaps_min_size() -> usize {
+    1
+}
+
+fn default_check_interval() -> u64 {
+    60
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        SchedulerConfig {
+            quote_updates: Vec::new(),
+            fill_gaps_hour: None,
+            fill_gaps_min_size: default_fill_gaps_min_size(),
+            check_interval: default_check_interval(),
+        }
+    }
+}
+
+/// Daily update of the latest quotes of a set of tickers
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuoteUpdateSchedule {
+    /// Market data source of the tickers to update, e.g. "yahoo", all sources if not given
+    pub source: Option<String>,
+    /// Time zone of the tickers to update, e.g. "America/New_York". If given, the hour
+    /// refers to this time zone, otherwise to the local time and all tickers of the source
+    /// are updated.
+    pub timezone: Option<String>,
+    /// Hour of the day at which quotes are updated
+    #[serde(deserialize_with = "deserialize_hour")]
+    pub hour: u8,
+}
+
+/// Hours are given as in a 24-hour clock, i.e. from 0 to 23
+fn deserialize_hour<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u8, D::Error> {
+    let hour = u8::deserialize(deserializer)?;
+    if hour > 23 {
+        return Err(D::Error::custom(format!(
+            "invalid hour {}, expected 0 to 23",
+            hour
+        )));
+    }
+    Ok(hour)
+}
+
+fn deserialize_optional_hour<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u8>, D::Error> {
+    #[derive(Deserialize)]
+    struct Hour(#[serde(deserialize_with = "deserialize_hour")] u8);
+    Ok(Option::<Hour>::deserialize(deserializer)?.map(|Hour(hour)| hour))
+}
+
+impl QuoteUpdateSchedule {
+    /// Kind of the jobs recording the runs of this schedule
+    pub fn job_kind(&self) -> String {
+        format!(
+            "{}quote update ({}, {})",
+            SCHEDULED_JOB_PREFIX,
+            self.source.as_deref().unwrap_or("all sources"),
+            self.timezone.as_deref().unwrap_or("local time")
+        )
+    }
+
+    fn matches(&self, ticker: &Ticker) -> bool {
+        self.source
+            .as_ref()
+            .is_none_or(|source| source.eq_ignore_ascii_case(&ticker.source))
+            && self
+                .timezone
+                .as_ref()
+                .is_none_or(|tz| ticker.tz.as_ref() == Some(tz))
+    }
+}
+
+fn fill_gaps_job_kind() -> String {
+    format!("{}gap filling", SCHEDULED_JOB_PREFIX)
+}
+
+/// Most recent time at the given hour of a day in the given time zone before or at `now`. The
+/// current day in the time zone may differ by one from the day of `now` in either direction.
+pub fn last_due_time(
+    now: OffsetDateTime,
+    hour: u8,
+    tz: Option<String>,
+) -> Result<OffsetDateTime, DateTimeError> {
+    let mut date = now.date().next_day().unwrap_or_else(|| now.date());
+    loop {
+        let due = date_to_offset_date_time(&date, hour, tz.clone())?;
+        match date.previous_day() {
+            Some(previous) if due > now => date = previous,
+            _ => return Ok(due),
+        }
+    }
+}
+
+/// A task is due if it has not run since it became due the last time
+pub fn is_due(due: OffsetDateTime, last_run: Option<OffsetDateTime>) -> bool {
+    last_run.is_none_or(|last_run| last_run < due)
+}
+
+/// Runs the configured tasks when they become due
+pub struct Scheduler {
+    config: SchedulerConfig,
+    db: Arc<PostgresDB>,
+    market: Market,
+    /// Start of the last run by kind of job
+    last_runs: HashMap<String, OffsetDateTime>,
+}
+
+impl Scheduler {
+    /// Create a new scheduler; the last runs are read from the database, such that tasks
+    /// already run before a restart are not repeated. Runs still marked as running have been
+    /// interrupted by the restart and are marked as failed.
+    pub async fn new(
+        config: SchedulerConfig,
+        db: Arc<PostgresDB>,
+        market: Market,
+    ) -> Result<Self, SchedulerError> {
+        db.fail_interrupted_scheduled_jobs().await?;
+        let last_runs = db
+            .get_latest_jobs(SCHEDULED_JOB_PREFIX)
+            .await?
+            .into_iter()
+            .map(|job| (job.kind, job.created))
+            .collect();
+        Ok(Scheduler {
+            config,
+            db,
+            market,
+            last_runs,
+        })
+    }
+
+    /// Time to wait between two calls of `run_due_tasks`
+    pub fn check_interval(&self) -> Duration {
+        Duration::from_secs(self.config.check_interval.max(1))
+    }
+
+    /// Run all tasks that became due since their last run, returns the ids of the jobs
+    /// recording the runs
+    pub async fn run_due_tasks(&mut self, now: OffsetDateTime) -> Result<Vec<i32>, SchedulerError> {
+        let mut job_ids = Vec::new();
+        for schedule in self.config.quote_updates.clone() {
+            let kind = schedule.job_kind();
+            let due = last_due_time(now, schedule.hour, schedule.timezone.clone())?;
+            if is_due(due, self.last_runs.get(&kind).copied()) {
+                let job_id = self.start_job(&kind, now).await?;
+                let outcome = self.update_quotes(&schedule, due).await;
+                self.finish_job(job_id, outcome).await?;
+                job_ids.push(job_id);
+            }
+        }
+        if let Some(hour) = self.config.fill_gaps_hour {
+            let kind = fill_gaps_job_kind();
+            let due = last_due_time(now, hour, None)?;
+            if is_due(due, self.last_runs.get(&kind).copied()) {
+                let job_id = self.start_job(&kind, now).await?;
+                let outcome =
+                    crate::fill_quote_gaps(&mut self.market, self.config.fill_gaps_min_size)
+                        .await
+                        .map(|_| "Gaps in quote histories filled".to_string());
+                self.finish_job(job_id, outcome).await?;
+                job_ids.push(job_id);
+            }
+        }
+        Ok(job_ids)
+    }
+
+    async fn start_job(&mut self, kind: &str, now: OffsetDateTime) -> Result<i32, DataError> {
+        let job_id = self.db.insert_job(kind, None).await?;
+        self.db.set_job_running(job_id).await?;
+        self.last_runs.insert(kind.to_string(), now);
+        Ok(job_id)
+    }
+
+    async fn finish_job<E: std::fmt::Display>(
+        &self,
+        job_id: i32,
+        outcome: Result<String, E>,
+    ) -> Result<(), DataError> {
+        match outcome {
+            Ok(summary) => {
+                self.db
+                    .finish_job(job_id, JobStatus::Finished, None, Some(&summary))
+                    .await
+            }
+            Err(e) => {
+                self.db
+                    .finish_job(job_id, JobStatus::Failed, None, Some(&e.to_string()))
+                    .await
+            }
+        }
+    }
+
+    /// Update the latest quotes of all tickers of the schedule for which the due date is a
+    /// business day and return a summary of the outcome, which is an error if the update of
+    /// any ticker failed
+    async fn update_quotes(
+        &self,
+        schedule: &QuoteUpdateSchedule,
+        due: OffsetDateTime,
+    ) -> Result<String, SchedulerError> {
+        let tickers = self.market.db().get_all_ticker().await?;
+        let mut updated = 0;
+        let mut skipped = 0;
+        let mut failed = Vec::new();
+        for ticker in tickers.iter().filter(|ticker| schedule.matches(ticker)) {
+            let ticker_id = match ticker.id {
+                Some(ticker_id) => ticker_id,
+                None => continue,
+            };
+            if let Some(cal) = &ticker.cal {
+                if !self.market.get_calendar(cal)?.is_business_day(due.date()) {
+                    skipped += 1;
+                    continue;
+                }
+            }
+            match self.market.update_quote_for_ticker(ticker_id).await {
+                Ok(_) => updated += 1,
+                Err(_) => failed.push(ticker.name.clone()),
+            }
+        }
+        let summary = format!(
+            "{} tickers updated, {} skipped on holidays",
+            updated, skipped
+        );
+        if failed.is_empty() {
+            Ok(summary)
+        } else {
+            Err(SchedulerError::UpdateFailed(format!(
+                "{}, failed: {}",
+                summary,
+                failed.join(", ")
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn test_is_due() {
+        let due = datetime!(2023-03-01 18:00 UTC);
+        assert!(is_due(due, None));
+        assert!(is_due(due, Some(datetime!(2023-02-28 18:00 UTC))));
+        assert!(!is_due(due, Some(datetime!(2023-03-01 18:01 UTC))));
+    }
+
+    #[test]
+    fn test_last_due_time() {
+        let utc = Some("UTC".to_string());
+        // Shortly after midnight, the last run was due on the previous day
+        assert_eq!(
+            last_due_time(datetime!(2023-03-02 01:00 UTC), 18, utc.clone()).unwrap(),
+            datetime!(2023-03-01 18:00 UTC)
+        );
+        assert_eq!(
+            last_due_time(datetime!(2023-03-02 18:00 UTC), 18, utc).unwrap(),
+            datetime!(2023-03-02 18:00 UTC)
+        );
+        // West of UTC, the day in the time zone lags behind
+        let new_york = Some("America/New_York".to_string());
+        assert_eq!(
+            last_due_time(datetime!(2023-03-02 01:00 UTC), 18, new_york.clone()).unwrap(),
+            datetime!(2023-03-01 23:00 UTC)
+        );
+        assert_eq!(
+            last_due_time(datetime!(2023-03-01 22:59 UTC), 18, new_york).unwrap(),
+            datetime!(2023-02-28 23:00 UTC)
+        );
+        // East of UTC, the day in the time zone is ahead
+        let tokyo = Some("Asia/Tokyo".to_string());
+        assert_eq!(
+            last_due_time(datetime!(2023-03-01 23:30 UTC), 8, tokyo.clone()).unwrap(),
+            datetime!(2023-03-01 23:00 UTC)
+        );
+        assert_eq!(
+            last_due_time(datetime!(2023-03-01 22:30 UTC), 8, tokyo).unwrap(),
+            datetime!(2023-02-28 23:00 UTC)
+        );
+    }
+
+    #[test]
+    fn test_scheduler_config() {
+        let config: SchedulerConfig = serde_json::from_str(
+            r#"{
+                "fill_gaps_hour": 2,
+                "quote_updates": [
+                    { "source": "yahoo", "timezone": "America/New_York", "hour": 18 }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.fill_gaps_hour, Some(2));
+        assert_eq!(config.fill_gaps_min_size, 1);
+        assert_eq!(config.check_interval, 60);
+        assert_eq!(config.quote_updates.len(), 1);
+        assert_eq!(
+            config.quote_updates[0].job_kind(),
+            "scheduled quote update (yahoo, America/New_York)"
+        );
+    }
+
+    #[test]
+    fn test_scheduler_config_rejects_invalid_hours() {
+        let config = |json: &str| serde_json::from_str::<SchedulerConfig>(json);
+        assert!(config(r#"{ "fill_gaps_hour": 23 }"#).is_ok());
+        assert!(config(r#"{ "fill_gaps_hour": 24 }"#).is_err());
+        assert!(config(r#"{ "quote_updates": [{ "source": "yahoo", "hour": 0 }] }"#).is_ok());
+        assert!(config(r#"{ "quote_updates": [{ "source": "yahoo", "hour": 25 }] }"#).is_err());
+        assert_eq!(config("{}").unwrap().fill_gaps_hour, None);
+    }
+}
@@ -0,0 +1,74 @@
+//! Versioned migrations of the database schema
+//!
+//! The migrations in the directory `migrations` of this crate are the single source of the
+//! database schema. They are embedded at compile time and applied in order of their version,
+//! the versions already applied are recorded by sqlx in the table `_sqlx_migrations`. The first
+//! migration only creates missing tables, such that databases set up before migrations were
+//! introduced are upgraded without loss of data.
+use std::collections::HashMap;
+
+use sqlx::migrate::{Migrate, MigrateError, Migrator};
+
+use finql::postgres::PostgresDB;
+
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// State of a single migration
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub applied: bool,
+    /// True if the migration has been applied in a different version, i.e. has been modified
+    /// after it was applied
+    pub modified: bool,
+}
+
+/// Apply all pending migrations
+pub async fn migrate(db: &PostgresDB) -> Result<(), MigrateError> {
+    MIGRATOR.run(&db.pool).await
+}
+
+/// List all migrations known to this version of qualinvest and whether they have been applied
+pub async fn migration_status(db: &PostgresDB) -> Result<Vec<MigrationStatus>, MigrateError> {
+    let mut conn = db.pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+    let applied: HashMap<i64, Vec<u8>> = conn
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|migration| (migration.version, migration.checksum.into_owned()))
+        .collect();
+    Ok(MIGRATOR
+        .iter()
+        .map(|migration| {
+            let checksum = applied.get(&migration.version);
+            MigrationStatus {
+                version: migration.version,
+                description: migration.description.to_string(),
+                applied: checksum.is_some(),
+                modified: checksum.is_some_and(|c| c.as_slice() != migration.checksum.as_ref()),
+            }
+        })
+        .collect())
+}
+
+/// Drop all tables of the current schema including the record of applied migrations and set up
+/// an empty database from scratch
+pub async fn reset_database(db: &PostgresDB) -> Result<(), MigrateError> {
+    let tables = sqlx::query_scalar!(
+        r#"SELECT table_name::TEXT AS "table_name!" FROM information_schema.tables
+        WHERE table_schema = current_schema() AND table_type = 'BASE TABLE'"#
+    )
+    .fetch_all(&db.pool)
+    .await?;
+    for table in tables {
+        sqlx::query(&format!(
+            "DROP TABLE IF EXISTS \"{}\" CASCADE",
+            table.replace('"', "\"\"")
+        ))
+        .execute(&db.pool)
+        .await?;
+    }
+    migrate(db).await
+}
@@ -0,0 +1,134 @@
+//! Depot transactions ("Depotumsätze") exported as CSV file by comdirect
+//!
+//! The export contains buy and sell transactions with the number of shares, the execution price
+//! and the total amount settled, but no separate column for fees. Fees are therefore derived as
+//! the difference between the amount settled and the value of the shares. For sell transactions
+//! this difference includes withheld taxes, which are only reported in the tax statement.
+use super::{field, parse_german_date, parse_german_number, ParserError, StatementParser, Table};
+use crate::csv_import::{ImportKind, ImportPreview, ImportRow, RowError};
+
+const HEADER: &str = "Buchungstag;Geschäftstag;Stück / Nom.";
+
+pub struct ComdirectDepotParser;
+
+impl StatementParser for ComdirectDepotParser {
+    fn name(&self) -> &str {
+        "comdirect depot"
+    }
+
+    fn description(&self) -> &str {
+        "Depot transactions (Depotumsätze) exported by comdirect"
+    }
+
+    fn detect(&self, input: &str) -> bool {
+        input
+            .lines()
+            .any(|line| line.replace('"', "").starts_with(HEADER))
+    }
+
+    fn parse(&self, input: &str) -> Result<ImportPreview, ParserError> {
+        let table = Table::read(input, HEADER, b';')?;
+        let date = table.column("Geschäftstag")?;
+        let position = table.column("Stück / Nom.")?;
+        let name = table.column("Bezeichnung")?;
+        let wkn = table.column("WKN")?;
+        let currency = table.column("Währung")?;
+        let price = table.column("Ausführungskurs")?;
+        let amount = table
+            .headers
+            .iter()
+            .position(|h| h.starts_with("Umsatz in "))
+            .ok_or_else(|| ParserError::MissingColumn("Umsatz".to_string()))?;
+        let settlement_currency = table.headers[amount]
+            .trim_start_matches("Umsatz in ")
+            .trim()
+            .to_string();
+
+        let mut preview = ImportPreview::default();
+        for (line, record) in &table.records {
+            let row = || -> Result<ImportRow, String> {
+                let date = parse_german_date(field(record, date))
+                    .ok_or_else(|| "invalid date".to_string())?;
+                let position = parse_german_number(field(record, position))
+                    .ok_or_else(|| "invalid number of shares".to_string())?
+                    .abs();
+                let price = parse_german_number(field(record, price))
+                    .ok_or_else(|| "invalid price".to_string())?;
+                let amount = parse_german_number(field(record, amount))
+                    .ok_or_else(|| "invalid amount".to_string())?;
+                if field(record, currency) != settlement_currency {
+                    return Err("prices in foreign currency are not supported".to_string());
+                }
+                let value = ((position * price) * 100.0).round() / 100.0;
+                let (kind, position, fee) = if amount < 0.0 {
+                    (ImportKind::Buy, position, -amount - value)
+                } else {
+                    (ImportKind::Sell, -position, value - amount)
+                };
+                Ok(ImportRow {
+                    line: *line,
+                    date,
+                    kind,
+                    isin: None,
+                    wkn: Some(field(record, wkn).to_string()).filter(|w| !w.is_empty()),
+                    asset_name: Some(field(record, name).to_string()).filter(|n| !n.is_empty()),
+                    position: Some(position),
+                    amount: if kind == ImportKind::Buy {
+                        -value
+                    } else {
+                        value
+                    },
+                    currency: settlement_currency.clone(),
+                    fee: (fee.max(0.0) * 100.0).round() / 100.0,
+                    tax: 0.0,
+                    note: None,
+                    external_id: None,
+                })
+            };
+            match row() {
+                Ok(row) => preview.rows.push(row),
+                Err(message) => preview.errors.push(RowError {
+                    line: *line,
+                    message,
+                }),
+            }
+        }
+        Ok(preview)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    #[test]
+    fn test_comdirect_depot() {
+        let preview = ComdirectDepotParser
+            .parse(include_str!("samples/comdirect_depot.csv"))
+            .unwrap();
+        assert_eq!(preview.rows.len(), 2);
+        let buy = &preview.rows[0];
+        assert_eq!(buy.line, 7);
+        assert_eq!(buy.date, date!(2023 - 01 - 03));
+        assert_eq!(buy.kind, ImportKind::Buy);
+        assert_eq!(buy.wkn.as_deref(), Some("A0B1C2"));
+        assert_eq!(
+            buy.asset_name.as_deref(),
+            Some("MUSTER AG NAMENS-AKTIEN O.N.")
+        );
+        assert_eq!(buy.position, Some(10.0));
+        assert_eq!(buy.amount, -952.0);
+        assert_eq!(buy.fee, 9.9);
+        assert_eq!(buy.currency, "EUR");
+
+        let sell = &preview.rows[1];
+        assert_eq!(sell.kind, ImportKind::Sell);
+        assert_eq!(sell.position, Some(-5.0));
+        assert_eq!(sell.amount, 600.0);
+        assert_eq!(sell.fee, 7.9);
+
+        assert_eq!(preview.errors.len(), 1);
+        assert_eq!(preview.errors[0].line, 9);
+    }
+}
@@ -0,0 +1,136 @@
+//! Account transactions exported as CSV file by DKB
+//!
+//! Only booked transactions are imported, pending ones are ignored since they may still change.
+//! As for bank statements, interest, fees and taxes are recognised by the default statement
+//! rules, securities settlements are skipped and all other transactions become deposits or
+//! withdrawals.
+use super::{field, parse_german_date, parse_german_number, ParserError, StatementParser, Table};
+use crate::bank_statement::{default_statement_rules, statement_rows, Statement, StatementEntry};
+use crate::csv_import::{ImportPreview, RowError};
+
+const HEADER: &str = "Buchungsdatum;Wertstellung;Status";
+
+pub struct DkbGiroParser;
+
+impl StatementParser for DkbGiroParser {
+    fn name(&self) -> &str {
+        "DKB giro"
+    }
+
+    fn description(&self) -> &str {
+        "Account transactions exported by DKB"
+    }
+
+    fn detect(&self, input: &str) -> bool {
+        input
+            .lines()
+            .any(|line| line.replace('"', "").starts_with(HEADER))
+    }
+
+    fn parse(&self, input: &str) -> Result<ImportPreview, ParserError> {
+        let table = Table::read(input, HEADER, b';')?;
+        let booking_date = table.column("Buchungsdatum")?;
+        let value_date = table.column("Wertstellung")?;
+        let status = table.column("Status")?;
+        let payer = table.column("Zahlungspflichtige*r")?;
+        let payee = table.column("Zahlungsempfänger*in")?;
+        let purpose = table.column("Verwendungszweck")?;
+        let reference = table.column("Kundenreferenz")?;
+        // The currency is part of the header, e.g. "Betrag (€)"
+        let amount = table
+            .headers
+            .iter()
+            .position(|h| h.starts_with("Betrag"))
+            .ok_or_else(|| ParserError::MissingColumn("Betrag".to_string()))?;
+        let currency = match table.headers[amount]
+            .trim_start_matches("Betrag")
+            .trim_matches(|c: char| c.is_whitespace() || c == '(' || c == ')')
+        {
+            "" | "€" => "EUR",
+            currency => currency,
+        }
+        .to_string();
+
+        let mut statement = Statement {
+            account: input.lines().next().and_then(|line| {
+                line.replace('"', "")
+                    .split_whitespace()
+                    .last()
+                    .map(String::from)
+            }),
+            currency: currency.clone(),
+            entries: Vec::new(),
+        };
+        let mut errors = Vec::new();
+        for (line, record) in &table.records {
+            if field(record, status) != "Gebucht" {
+                continue;
+            }
+            let booking_date = parse_german_date(field(record, booking_date));
+            let amount = parse_german_number(field(record, amount));
+            let (booking_date, amount) = match (booking_date, amount) {
+                (Some(booking_date), Some(amount)) => (booking_date, amount),
+                _ => {
+                    errors.push(RowError {
+                        line: *line,
+                        message: "invalid date or amount".to_string(),
+                    });
+                    continue;
+                }
+            };
+            let counterparty = if amount < 0.0 { payee } else { payer };
+            statement.entries.push(StatementEntry {
+                line: *line,
+                booking_date,
+                value_date: parse_german_date(field(record, value_date)),
+                amount,
+                currency: currency.clone(),
+                code: None,
+                counterparty: Some(field(record, counterparty).to_string())
+                    .filter(|c| !c.is_empty()),
+                reference: Some(field(record, reference).to_string()).filter(|r| !r.is_empty()),
+                text: field(record, purpose).to_string(),
+            });
+        }
+
+        let mut preview = statement_rows(&[statement], &default_statement_rules())?;
+        preview.errors.extend(errors);
+        Ok(preview)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv_import::ImportKind;
+    use time::macros::date;
+
+    #[test]
+    fn test_dkb_giro() {
+        let preview = DkbGiroParser
+            .parse(include_str!("samples/dkb_giro.csv"))
+            .unwrap();
+        assert!(preview.errors.is_empty());
+        assert_eq!(preview.rows.len(), 3);
+
+        let interest = &preview.rows[0];
+        assert_eq!(interest.line, 6);
+        assert_eq!(interest.date, date!(2023 - 01 - 31));
+        assert_eq!(interest.kind, ImportKind::Interest);
+        assert_eq!(interest.amount, 0.56);
+        assert_eq!(interest.currency, "EUR");
+
+        let withdrawal = &preview.rows[1];
+        assert_eq!(withdrawal.kind, ImportKind::Withdrawal);
+        assert_eq!(withdrawal.amount, -85.0);
+        assert_eq!(
+            withdrawal.note.as_deref(),
+            Some("Strom GmbH Abschlag Januar")
+        );
+
+        let deposit = &preview.rows[2];
+        assert_eq!(deposit.kind, ImportKind::Deposit);
+        assert_eq!(deposit.amount, 1500.0);
+        assert_eq!(deposit.external_id.as_deref(), Some("REF-4711"));
+    }
+}
@@ -0,0 +1,118 @@
+//! Account transactions ("Umsatzanzeige") exported as CSV file by ING
+//!
+//! The transactions are mapped like entries of bank statements, i.e. interest, fees and taxes
+//! are recognised by the default statement rules, securities settlements are skipped and all
+//! other transactions become deposits or withdrawals.
+use super::{field, parse_german_date, parse_german_number, ParserError, StatementParser, Table};
+use crate::bank_statement::{default_statement_rules, statement_rows, Statement, StatementEntry};
+use crate::csv_import::{ImportPreview, RowError};
+
+const HEADER: &str = "Buchung;Valuta;Auftraggeber/Empfänger";
+
+pub struct IngGiroParser;
+
+impl StatementParser for IngGiroParser {
+    fn name(&self) -> &str {
+        "ING giro"
+    }
+
+    fn description(&self) -> &str {
+        "Account transactions (Umsatzanzeige) exported by ING"
+    }
+
+    fn detect(&self, input: &str) -> bool {
+        input.lines().any(|line| line.starts_with(HEADER))
+    }
+
+    fn parse(&self, input: &str) -> Result<ImportPreview, ParserError> {
+        let table = Table::read(input, HEADER, b';')?;
+        let booking_date = table.column("Buchung")?;
+        let value_date = table.column("Valuta")?;
+        let counterparty = table.column("Auftraggeber/Empfänger")?;
+        let posting_text = table.column("Buchungstext")?;
+        let purpose = table.column("Verwendungszweck")?;
+        let amount = table.column("Betrag")?;
+        // The currency of the amount follows the amount, the first currency column belongs to
+        // the balance
+        let currency = amount + 1;
+
+        let mut statement = Statement {
+            account: input
+                .lines()
+                .find_map(|line| line.strip_prefix("IBAN;"))
+                .map(|iban| iban.replace(' ', "")),
+            currency: "EUR".to_string(),
+            entries: Vec::new(),
+        };
+        let mut errors = Vec::new();
+        for (line, record) in &table.records {
+            let booking_date = parse_german_date(field(record, booking_date));
+            let amount = parse_german_number(field(record, amount));
+            let (booking_date, amount) = match (booking_date, amount) {
+                (Some(booking_date), Some(amount)) => (booking_date, amount),
+                _ => {
+                    errors.push(RowError {
+                        line: *line,
+                        message: "invalid date or amount".to_string(),
+                    });
+                    continue;
+                }
+            };
+            statement.entries.push(StatementEntry {
+                line: *line,
+                booking_date,
+                value_date: parse_german_date(field(record, value_date)),
+                amount,
+                currency: Some(field(record, currency))
+                    .filter(|c| !c.is_empty())
+                    .unwrap_or("EUR")
+                    .to_string(),
+                code: None,
+                counterparty: Some(field(record, counterparty).to_string())
+                    .filter(|c| !c.is_empty()),
+                reference: None,
+                text: format!("{} {}", field(record, posting_text), field(record, purpose))
+                    .trim()
+                    .to_string(),
+            });
+        }
+
+        let mut preview = statement_rows(&[statement], &default_statement_rules())?;
+        preview.errors.extend(errors);
+        Ok(preview)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csv_import::ImportKind;
+    use time::macros::date;
+
+    #[test]
+    fn test_ing_giro() {
+        let preview = IngGiroParser
+            .parse(include_str!("samples/ing_giro.csv"))
+            .unwrap();
+        assert!(preview.errors.is_empty());
+        assert_eq!(preview.skipped, 1);
+        assert_eq!(preview.rows.len(), 3);
+
+        let interest = &preview.rows[0];
+        assert_eq!(interest.line, 16);
+        assert_eq!(interest.date, date!(2023 - 01 - 31));
+        assert_eq!(interest.kind, ImportKind::Interest);
+        assert_eq!(interest.amount, 0.56);
+
+        assert_eq!(preview.rows[1].kind, ImportKind::Fee);
+        assert_eq!(preview.rows[1].amount, -4.9);
+
+        let deposit = &preview.rows[2];
+        assert_eq!(deposit.kind, ImportKind::Deposit);
+        assert_eq!(deposit.amount, 1500.0);
+        assert_eq!(
+            deposit.note.as_deref(),
+            Some("Erika Musterfrau Gutschrift Miete Januar")
+        );
+    }
+}
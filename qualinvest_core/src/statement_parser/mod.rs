@@ -0,0 +1,302 @@
+//! Parsers turning statements of brokers and banks into transactions
+//!
+//! A `StatementParser` reads a text, CSV or XML export and returns the rows to be imported, with
+//! fees and taxes attached to their main transaction, such that `crate::csv_import::import_rows`
+//! stores them as related transactions. Parsers are collected in a `StatementParserRegistry`,
+//! which also detects the parser for a given file. Front ends look up parsers by name, so
+//! stored mapping profiles can be registered as parsers as well (see `ProfileParser`).
+//!
+//! The name of a parser is used as source of the fingerprints of imported transactions and must
+//! not change once files have been imported with it.
+mod comdirect;
+mod dkb;
+mod ing;
+
+use thiserror::Error;
+use time::Date;
+
+use crate::bank_statement::{
+    parse_statement, statement_rows, StatementError, StatementFormat, StatementRule,
+};
+use crate::csv_import::{parse_csv, ImportError, ImportPreview, MappingProfile};
+
+pub use comdirect::ComdirectDepotParser;
+pub use dkb::DkbGiroParser;
+pub use ing::IngGiroParser;
+
+#[derive(Error, Debug)]
+pub enum ParserError {
+    #[error("Failed to import transactions")]
+    ImportError(#[from] ImportError),
+    #[error("Failed to read bank statement")]
+    StatementError(#[from] StatementError),
+    #[error("Failed to read CSV file")]
+    CsvError(#[from] csv::Error),
+    #[error("Header line '{0}' not found")]
+    MissingHeader(String),
+    #[error("Column '{0}' not found")]
+    MissingColumn(String),
+}
+
+pub trait StatementParser: Send + Sync {
+    /// Unique name of the parser
+    fn name(&self) -> &str;
+
+    fn description(&self) -> &str;
+
+    /// True if the input looks like an export this parser understands
+    fn detect(&self, input: &str) -> bool;
+
+    /// Read all transactions, errors in single rows are collected in the preview
+    fn parse(&self, input: &str) -> Result<ImportPreview, ParserError>;
+}
+
+/// Collection of all statement parsers available for imports
+pub struct StatementParserRegistry {
+    parsers: Vec<Box<dyn StatementParser>>,
+}
+
+impl StatementParserRegistry {
+    /// Registry without any parser
+    pub fn empty() -> Self {
+        Self {
+            parsers: Vec::new(),
+        }
+    }
+
+    /// Register a parser, replacing any parser registered under the same name
+    pub fn register(&mut self, parser: Box<dyn StatementParser>) {
+        match self.parsers.iter_mut().find(|p| p.name() == parser.name()) {
+            Some(existing) => *existing = parser,
+            None => self.parsers.push(parser),
+        }
+    }
+
+    /// All registered parsers in order of registration
+    pub fn parsers(&self) -> &[Box<dyn StatementParser>] {
+        &self.parsers
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn StatementParser> {
+        self.parsers
+            .iter()
+            .find(|p| p.name() == name)
+            .map(|p| p.as_ref())
+    }
+
+    /// First parser which recognises the input
+    pub fn detect(&self, input: &str) -> Option<&dyn StatementParser> {
+        self.parsers
+            .iter()
+            .find(|p| p.detect(input))
+            .map(|p| p.as_ref())
+    }
+}
+
+impl Default for StatementParserRegistry {
+    /// Registry with bank statement formats and the broker exports of qualinvest_core
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(Box::new(BankStatementParser::new(StatementFormat::Camt053)));
+        registry.register(Box::new(BankStatementParser::new(StatementFormat::Mt940)));
+        registry.register(Box::new(ComdirectDepotParser));
+        registry.register(Box::new(IngGiroParser));
+        registry.register(Box::new(DkbGiroParser));
+        registry
+    }
+}
+
+/// Bank statements in CAMT.053 or MT940 format, see `crate::bank_statement`
+pub struct BankStatementParser {
+    format: StatementFormat,
+    name: String,
+    rules: Vec<StatementRule>,
+}
+
+impl BankStatementParser {
+    /// Parser using the default mapping rules
+    pub fn new(format: StatementFormat) -> Self {
+        Self::with_rules(format, crate::bank_statement::default_statement_rules())
+    }
+
+    pub fn with_rules(format: StatementFormat, rules: Vec<StatementRule>) -> Self {
+        Self {
+            format,
+            name: format.to_string(),
+            rules,
+        }
+    }
+}
+
+impl StatementParser for BankStatementParser {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        match self.format {
+            StatementFormat::Camt053 => "Bank statement in ISO 20022 CAMT.053 format (XML)",
+            StatementFormat::Mt940 => "Bank statement in SWIFT MT940 format",
+        }
+    }
+
+    fn detect(&self, input: &str) -> bool {
+        match self.format {
+            StatementFormat::Camt053 => {
+                input.trim_start().starts_with('<') && input.contains("BkToCstmrStmt")
+            }
+            StatementFormat::Mt940 => input.contains(":20:") && input.contains(":61:"),
+        }
+    }
+
+    fn parse(&self, input: &str) -> Result<ImportPreview, ParserError> {
+        let statements = parse_statement(self.format, input)?;
+        Ok(statement_rows(&statements, &self.rules)?)
+    }
+}
+
+/// CSV files described by a mapping profile, which are never detected automatically
+pub struct ProfileParser(pub MappingProfile);
+
+impl StatementParser for ProfileParser {
+    fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    fn description(&self) -> &str {
+        "CSV file described by a mapping profile"
+    }
+
+    fn detect(&self, _input: &str) -> bool {
+        false
+    }
+
+    fn parse(&self, input: &str) -> Result<ImportPreview, ParserError> {
+        Ok(parse_csv(&self.0, input)?)
+    }
+}
+
+/// Decode the content of an export, which is UTF-8 or, as common for German banks,
+/// Windows-1252 encoded
+pub fn decode_input(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.trim_start_matches('\u{feff}').to_string(),
+        Err(_) => bytes
+            .iter()
+            .map(|&b| match b {
+                0x80 => '€',
+                _ => b as char,
+            })
+            .collect(),
+    }
+}
+
+/// Parse a number in German format, e.g. `-1.234,56 €`
+fn parse_german_number(text: &str) -> Option<f64> {
+    let number: String = text
+        .chars()
+        .filter(|c| c.is_ascii_digit() || matches!(c, ',' | '-' | '+'))
+        .map(|c| if c == ',' { '.' } else { c })
+        .collect();
+    if number.is_empty() {
+        return None;
+    }
+    number.parse().ok()
+}
+
+/// Parse a date in German format with two or four digit year, e.g. `02.01.2023` or `02.01.23`.
+/// Two digit years are always in this century, while `%y` would map e.g. `99` to 1999.
+fn parse_german_date(text: &str) -> Option<Date> {
+    let text = text.trim();
+    let text = match text.rsplit_once('.') {
+        Some((day_month, year)) if year.len() == 2 => format!("{}.20{}", day_month, year),
+        _ => text.to_string(),
+    };
+    crate::csv_import::parse_date(&text, "%d.%m.%Y")
+}
+
+/// Table of a CSV export starting at the header line, which is preceded by account information
+struct Table {
+    headers: csv::StringRecord,
+    /// Records with their line number in the file
+    records: Vec<(usize, csv::StringRecord)>,
+}
+
+impl Table {
+    /// Read the table whose header line starts with the given columns
+    fn read(input: &str, header_start: &str, delimiter: u8) -> Result<Self, ParserError> {
+        let skip = input
+            .lines()
+            .position(|line| line.replace('"', "").starts_with(header_start))
+            .ok_or_else(|| ParserError::MissingHeader(header_start.to_string()))?;
+        let body: String = input.lines().skip(skip).collect::<Vec<_>>().join("\n");
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .flexible(true)
+            .from_reader(body.as_bytes());
+        let headers = rdr.headers()?.clone();
+        let mut records = Vec::new();
+        for record in rdr.records() {
+            let record = record?;
+            if record.iter().all(|field| field.trim().is_empty()) {
+                continue;
+            }
+            let line = record.position().map(|p| p.line() as usize).unwrap_or(0) + skip;
+            records.push((line, record));
+        }
+        Ok(Self { headers, records })
+    }
+
+    /// Index of the first column with the given name
+    fn column(&self, name: &str) -> Result<usize, ParserError> {
+        self.headers
+            .iter()
+            .position(|h| h.trim() == name)
+            .ok_or_else(|| ParserError::MissingColumn(name.to_string()))
+    }
+}
+
+fn field(record: &csv::StringRecord, index: usize) -> &str {
+    record.get(index).unwrap_or_default().trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    #[test]
+    fn test_helpers() {
+        assert_eq!(parse_german_number("-1.234,56 €"), Some(-1234.56));
+        assert_eq!(parse_german_number(""), None);
+        assert_eq!(parse_german_date("02.01.23"), Some(date!(2023 - 01 - 02)));
+        assert_eq!(parse_german_date("02.01.2023"), Some(date!(2023 - 01 - 02)));
+        assert_eq!(parse_german_date("31.12.99"), Some(date!(2099 - 12 - 31)));
+        assert_eq!(parse_german_date("31.12.1999"), Some(date!(1999 - 12 - 31)));
+        assert_eq!(decode_input(b"Geb\xfchr \x80"), "Gebühr €");
+    }
+
+    #[test]
+    fn test_registry() {
+        let registry = StatementParserRegistry::default();
+        let samples = [
+            (
+                "comdirect depot",
+                include_str!("samples/comdirect_depot.csv"),
+            ),
+            ("ING giro", include_str!("samples/ing_giro.csv")),
+            ("DKB giro", include_str!("samples/dkb_giro.csv")),
+        ];
+        for (name, sample) in samples.iter() {
+            assert_eq!(registry.detect(sample).map(|p| p.name()), Some(*name));
+        }
+        assert_eq!(
+            registry
+                .detect(":20:STARTUMS\n:61:2301020102C1,00NTRFNONREF\n")
+                .map(|p| p.name()),
+            Some("MT940")
+        );
+        assert!(registry.detect("date,amount\n").is_none());
+        assert!(registry.get("CAMT.053").is_some());
+    }
+}
@@ -0,0 +1,213 @@
+//! Interest on cash balances in simulations
+//!
+//! Interest accrues on each simulated day on the cash balance held since the previous day,
+//! using the deposit rate for positive and the borrowing rate for negative balances. The accrued
+//! interest is booked at the first day of each month and at the last day of the simulation as
+//! interest transaction. Interest is attributed to the asset of the currency, therefore the
+//! currency of the simulation must be stored in the database.
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use cal_calc::Calendar;
+use time::Date;
+
+use finql::datatypes::{CashFlow, Currency, Transaction, TransactionType};
+use finql::portfolio::PortfolioPosition;
+use finql::strategy::Strategy;
+use finql::Market;
+
+use super::{asset_price, StrategyError};
+
+/// Annual interest rate, as a fraction
+#[derive(Debug, Clone)]
+pub enum InterestRate {
+    Fixed(f64),
+    /// Rates valid from the given dates on, zero before the first date
+    Schedule(Vec<(Date, f64)>),
+    /// Quotes of a reference rate like €STR given in percent, plus a spread
+    Reference {
+        asset_id: i32,
+        spread: f64,
+    },
+}
+
+impl InterestRate {
+    async fn rate(&self, date: Date, currency: Currency, market: &Market) -> f64 {
+        match self {
+            InterestRate::Fixed(rate) => *rate,
+            InterestRate::Schedule(steps) => scheduled_rate(steps, date),
+            InterestRate::Reference { asset_id, spread } => {
+                asset_price(*asset_id, currency, date, market)
+                    .await
+                    .map(|quote| quote / 100.0)
+                    .unwrap_or(0.0)
+                    + spread
+            }
+        }
+    }
+}
+
+fn scheduled_rate(steps: &[(Date, f64)], date: Date) -> f64 {
+    steps
+        .iter()
+        .filter(|(start, _)| *start <= date)
+        .max_by_key(|(start, _)| *start)
+        .map(|(_, rate)| *rate)
+        .unwrap_or(0.0)
+}
+
+/// Interest on the balance held from `from` to `to`, using the money market convention act/360
+fn accrued_interest(balance: f64, rate: f64, from: Date, to: Date) -> f64 {
+    balance * rate * (to - from).whole_days() as f64 / 360.0
+}
+
+#[derive(Debug, Clone)]
+pub struct CashInterest {
+    /// Rate paid on positive cash balances
+    pub deposit: InterestRate,
+    /// Rate charged on negative cash balances
+    pub borrowing: InterestRate,
+}
+
+#[derive(Debug, Default)]
+struct InterestState {
+    last_date: Option<Date>,
+    accrued: f64,
+    /// Last simulated day, at which all interest accrued so far is booked
+    final_day: Option<Date>,
+}
+
+/// Books interest on the cash balance of the portfolio managed by another strategy
+pub struct CashInterestStrategy {
+    inner: Box<dyn Strategy>,
+    interest: CashInterest,
+    /// Asset id of the currency of the simulation
+    currency_id: i32,
+    market: Market,
+    /// End of the simulation
+    end: Date,
+    state: Mutex<InterestState>,
+}
+
+impl CashInterestStrategy {
+    /// Fails if the currency of the simulation is not stored in the database
+    pub fn new(
+        inner: Box<dyn Strategy>,
+        interest: CashInterest,
+        currency: Currency,
+        market: Market,
+        end: Date,
+    ) -> Result<Self, StrategyError> {
+        Ok(Self {
+            inner,
+            interest,
+            currency_id: currency_id(currency)?,
+            market,
+            end,
+            state: Mutex::new(InterestState::default()),
+        })
+    }
+
+    fn interest_transaction(&self, amount: f64, currency: Currency, date: Date) -> Transaction {
+        Transaction {
+            id: None,
+            transaction_type: TransactionType::Interest {
+                asset_id: self.currency_id,
+            },
+            cash_flow: CashFlow::new(amount, currency, date),
+            note: Some("interest on cash".to_string()),
+        }
+    }
+}
+
+/// Interest on cash is attributed to the currency itself, therefore no interest can be booked
+/// for a currency not stored in the database
+fn currency_id(currency: Currency) -> Result<i32, StrategyError> {
+    currency
+        .id
+        .ok_or_else(|| StrategyError::UnknownCurrency(currency.iso_code.to_string()))
+}
+
+#[async_trait]
+impl Strategy for CashInterestStrategy {
+    fn next_day(&self, date: Date, calendar: &Calendar) -> Date {
+        let next = self.inner.next_day(date, calendar);
+        // Look ahead to find the last simulated day at or before the end of the simulation
+        if next <= self.end && self.inner.next_day(next, calendar) > self.end {
+            self.state.lock().unwrap().final_day = Some(next);
+        }
+        next
+    }
+
+    async fn apply_rules(&self, position: &PortfolioPosition, date: Date) -> Vec<Transaction> {
+        let currency = position.cash.currency;
+        let balance = position.cash.position;
+        let (last_date, final_day) = {
+            let state = self.state.lock().unwrap();
+            (state.last_date, state.final_day)
+        };
+        let is_final_day = final_day == Some(date) || date >= self.end;
+
+        let mut booking = None;
+        if let Some(last_date) = last_date {
+            let rate = if balance >= 0.0 {
+                &self.interest.deposit
+            } else {
+                &self.interest.borrowing
+            };
+            let rate = rate.rate(last_date, currency, &self.market).await;
+            let mut state = self.state.lock().unwrap();
+            state.accrued += accrued_interest(balance, rate, last_date, date);
+            if (last_date.month() != date.month() || is_final_day) && state.accrued != 0.0 {
+                booking = Some(state.accrued);
+                state.accrued = 0.0;
+            }
+        }
+        self.state.lock().unwrap().last_date = Some(date);
+
+        let mut transactions = Vec::new();
+        if let Some(amount) = booking {
+            transactions.push(self.interest_transaction(amount, currency, date));
+        }
+        transactions.extend(self.inner.apply_rules(position, date).await);
+        transactions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use finql::datatypes::CurrencyISOCode;
+    use time::macros::date;
+
+    #[test]
+    fn test_scheduled_rate() {
+        let steps = vec![(date!(2023 - 01 - 01), 0.01), (date!(2023 - 07 - 01), 0.02)];
+        assert_eq!(scheduled_rate(&steps, date!(2022 - 12 - 31)), 0.0);
+        assert_eq!(scheduled_rate(&steps, date!(2023 - 06 - 30)), 0.01);
+        assert_eq!(scheduled_rate(&steps, date!(2023 - 07 - 01)), 0.02);
+    }
+
+    #[test]
+    fn test_accrued_interest() {
+        let interest =
+            accrued_interest(36000.0, 0.02, date!(2023 - 03 - 03), date!(2023 - 03 - 06));
+        assert!((interest - 6.0).abs() < 1e-10);
+        assert_eq!(
+            accrued_interest(-1000.0, 0.05, date!(2023 - 03 - 03), date!(2023 - 03 - 03)),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_interest_requires_stored_currency() {
+        let iso_code = CurrencyISOCode::new("EUR").unwrap();
+        let stored = Currency::new(Some(1), iso_code, Some(2));
+        assert_eq!(currency_id(stored), Ok(1));
+        let unknown = Currency::new(None, iso_code, Some(2));
+        assert_eq!(
+            currency_id(unknown),
+            Err(StrategyError::UnknownCurrency("EUR".to_string()))
+        );
+    }
+}
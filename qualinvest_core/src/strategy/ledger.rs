@@ -0,0 +1,390 @@
+//! Ledger of all transactions of a simulation
+//!
+//! `calc_strategy` only returns the valuation of the simulated portfolio. The transactions
+//! generated on the way are recorded by wrapping the strategy into a `LedgerStrategy`, such that
+//! trades, fees, taxes and dividends can be inspected, exported as CSV or imported into a
+//! sandbox account.
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use cal_calc::Calendar;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use time::Date;
+
+use finql::datatypes::{
+    CashFlow, Currency, DataError, Transaction, TransactionHandler, TransactionType,
+};
+use finql::market::MarketError;
+use finql::portfolio::PortfolioPosition;
+use finql::strategy::{calc_strategy, Strategy};
+use finql::time_series::TimeValue;
+use finql::Market;
+
+use crate::accounts::{Account, AccountHandler};
+
+/// Broker name of accounts created from simulation ledgers
+pub const SANDBOX_BROKER: &str = "Sandbox";
+
+#[derive(Error, Debug)]
+pub enum LedgerError {
+    #[error("Database access failed")]
+    DataError(#[from] DataError),
+    #[error("Market data access failed")]
+    MarketError(#[from] MarketError),
+    #[error("Failed to read or write CSV")]
+    CsvError(#[from] csv::Error),
+    #[error("Invalid ledger entry: {0}")]
+    InvalidEntry(String),
+    #[error("Account '{0}' already exists")]
+    AccountExists(String),
+}
+
+/// Shared list of recorded transactions
+#[derive(Debug, Clone, Default)]
+pub struct Ledger(Arc<Mutex<Vec<Transaction>>>);
+
+impl Ledger {
+    pub fn transactions(&self) -> Vec<Transaction> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// Records all transactions generated by another strategy
+pub struct LedgerStrategy {
+    inner: Box<dyn Strategy>,
+    ledger: Ledger,
+}
+
+impl LedgerStrategy {
+    pub fn new(inner: Box<dyn Strategy>, ledger: Ledger) -> Self {
+        Self { inner, ledger }
+    }
+}
+
+#[async_trait]
+impl Strategy for LedgerStrategy {
+    fn next_day(&self, date: Date, calendar: &Calendar) -> Date {
+        self.inner.next_day(date, calendar)
+    }
+
+    async fn apply_rules(&self, position: &PortfolioPosition, date: Date) -> Vec<Transaction> {
+        let transactions = self.inner.apply_rules(position, date).await;
+        self.ledger
+            .0
+            .lock()
+            .unwrap()
+            .extend(transactions.iter().cloned());
+        transactions
+    }
+}
+
+/// Run `calc_strategy` and return the valuation together with the full ledger, which starts
+/// with the given start transactions
+pub async fn calc_strategy_with_ledger(
+    currency: Currency,
+    start_transactions: &[Transaction],
+    strategy: Box<dyn Strategy>,
+    start: Date,
+    end: Date,
+    market: Market,
+) -> (Vec<TimeValue>, Vec<Transaction>) {
+    let ledger = Ledger::default();
+    let strategy = LedgerStrategy::new(strategy, ledger.clone());
+    let series = calc_strategy(currency, start_transactions, &strategy, start, end, market).await;
+    let mut transactions = start_transactions.to_vec();
+    transactions.extend(ledger.transactions());
+    (series, transactions)
+}
+
+/// Row of a ledger in CSV format
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    /// As YYYY-MM-DD
+    pub date: String,
+    /// "Cash" | "Asset" | "Dividend" | "Interest" | "Tax" | "Fee"
+    pub transaction_type: String,
+    pub asset_id: Option<i32>,
+    pub asset_name: Option<String>,
+    pub position: Option<f64>,
+    pub amount: f64,
+    pub currency: String,
+    pub note: Option<String>,
+    /// Row (starting at 0) of the entry a fee or tax belongs to
+    #[serde(default)]
+    pub transaction_ref: Option<usize>,
+}
+
+impl LedgerEntry {
+    pub fn new(transaction: &Transaction, asset_names: &HashMap<i32, String>) -> Self {
+        let (transaction_type, asset_id, position) = match transaction.transaction_type {
+            TransactionType::Cash => ("Cash", None, None),
+            TransactionType::Asset { asset_id, position } => {
+                ("Asset", Some(asset_id), Some(position))
+            }
+            TransactionType::Dividend { asset_id } => ("Dividend", Some(asset_id), None),
+            TransactionType::Interest { asset_id } => ("Interest", Some(asset_id), None),
+            TransactionType::Tax { .. } => ("Tax", None, None),
+            TransactionType::Fee { .. } => ("Fee", None, None),
+        };
+        Self {
+            date: transaction.cash_flow.date.to_string(),
+            transaction_type: transaction_type.to_string(),
+            asset_id,
+            asset_name: asset_id.and_then(|id| asset_names.get(&id).cloned()),
+            position,
+            amount: transaction.cash_flow.amount.amount,
+            currency: transaction.cash_flow.amount.currency.to_string(),
+            note: transaction.note.clone(),
+            transaction_ref: None,
+        }
+    }
+
+    /// Convert the entry back into a transaction in the given currency, fees and taxes are
+    /// linked to the transaction with id `transaction_ref`
+    pub fn to_transaction(
+        &self,
+        currency: Currency,
+        transaction_ref: Option<usize>,
+    ) -> Result<Transaction, LedgerError> {
+        let invalid = |msg: &str| LedgerError::InvalidEntry(format!("{} ({:?})", msg, self));
+        let asset_id = || self.asset_id.ok_or_else(|| invalid("missing asset id"));
+        let transaction_type = match self.transaction_type.as_str() {
+            "Cash" => TransactionType::Cash,
+            "Asset" => TransactionType::Asset {
+                asset_id: asset_id()?,
+                position: self.position.ok_or_else(|| invalid("missing position"))?,
+            },
+            "Dividend" => TransactionType::Dividend {
+                asset_id: asset_id()?,
+            },
+            "Interest" => TransactionType::Interest {
+                asset_id: asset_id()?,
+            },
+            "Tax" => TransactionType::Tax { transaction_ref },
+            "Fee" => TransactionType::Fee { transaction_ref },
+            _ => return Err(invalid("unknown transaction type")),
+        };
+        if self.transaction_ref.is_some()
+            && !matches!(self.transaction_type.as_str(), "Tax" | "Fee")
+        {
+            return Err(invalid("only fees and taxes refer to other entries"));
+        }
+        Ok(Transaction {
+            id: None,
+            transaction_type,
+            cash_flow: CashFlow::new(self.amount, currency, parse_date(&self.date)?),
+            note: self.note.clone(),
+        })
+    }
+}
+
+fn parse_date(date: &str) -> Result<Date, LedgerError> {
+    crate::csv_import::parse_date(date, "%Y-%m-%d")
+        .ok_or_else(|| LedgerError::InvalidEntry(format!("invalid date '{}'", date)))
+}
+
+/// Convert the transactions into ledger entries, with asset names where known. Fees and taxes
+/// refer to the row of their related transaction.
+pub fn ledger_entries(
+    transactions: &[Transaction],
+    asset_names: &HashMap<i32, String>,
+) -> Vec<LedgerEntry> {
+    let rows: HashMap<i32, usize> = transactions
+        .iter()
+        .enumerate()
+        .filter_map(|(row, t)| t.id.map(|id| (id, row)))
+        .collect();
+    transactions
+        .iter()
+        .map(|t| {
+            let mut entry = LedgerEntry::new(t, asset_names);
+            entry.transaction_ref = match t.transaction_type {
+                TransactionType::Fee {
+                    transaction_ref: Some(id),
+                }
+                | TransactionType::Tax {
+                    transaction_ref: Some(id),
+                } => rows.get(&(id as i32)).copied(),
+                _ => None,
+            };
+            entry
+        })
+        .collect()
+}
+
+pub fn write_ledger_csv<W: Write>(writer: W, entries: &[LedgerEntry]) -> Result<(), LedgerError> {
+    let mut wtr = csv::Writer::from_writer(writer);
+    for entry in entries {
+        wtr.serialize(entry)?;
+    }
+    wtr.flush().map_err(csv::Error::from)?;
+    Ok(())
+}
+
+pub fn read_ledger_csv<R: Read>(reader: R) -> Result<Vec<LedgerEntry>, LedgerError> {
+    let mut rdr = csv::Reader::from_reader(reader);
+    let mut entries = Vec::new();
+    for entry in rdr.deserialize() {
+        entries.push(entry?);
+    }
+    Ok(entries)
+}
+
+/// Store the ledger as transactions of a new sandbox account and return the account id
+pub async fn import_ledger<DB: AccountHandler + Sync>(
+    db: &DB,
+    market: &Market,
+    account_name: &str,
+    entries: &[LedgerEntry],
+) -> Result<i32, LedgerError> {
+    let account = Account {
+        id: None,
+        broker: SANDBOX_BROKER.to_string(),
+        account_name: account_name.to_string(),
+    };
+    if db.get_account_id(&account).await.is_ok() {
+        return Err(LedgerError::AccountExists(account_name.to_string()));
+    }
+    // Convert all entries first, such that invalid ledgers leave no partial account behind
+    let mut currencies = Vec::new();
+    for (row, entry) in entries.iter().enumerate() {
+        if let Some(main) = entry.transaction_ref {
+            if entries
+                .get(main)
+                .is_none_or(|main| main.transaction_ref.is_some())
+            {
+                return Err(LedgerError::InvalidEntry(format!(
+                    "row {} refers to invalid row {}",
+                    row, main
+                )));
+            }
+        }
+        let currency = market.get_currency_from_str(&entry.currency).await?;
+        entry.to_transaction(currency, None)?;
+        currencies.push(currency);
+    }
+    let account_id = db.insert_account_if_new(&account).await?;
+    // Fees and taxes are stored after all other entries to link them to the new ids
+    let mut ids = vec![None; entries.len()];
+    for pass in [false, true].iter() {
+        for (row, entry) in entries.iter().enumerate() {
+            if entry.transaction_ref.is_some() != *pass {
+                continue;
+            }
+            let transaction_ref = entry
+                .transaction_ref
+                .and_then(|main| ids[main])
+                .map(|id: i32| id as usize);
+            let transaction = entry.to_transaction(currencies[row], transaction_ref)?;
+            let transaction_id = db.insert_transaction(&transaction).await?;
+            db.add_transaction_to_account(account_id, transaction_id)
+                .await?;
+            ids[row] = Some(transaction_id);
+        }
+    }
+    Ok(account_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use finql::datatypes::CurrencyISOCode;
+    use time::macros::date;
+    use time::Month;
+
+    #[test]
+    fn test_ledger_csv_round_trip() {
+        let entries = vec![
+            LedgerEntry {
+                date: "2023-01-02".to_string(),
+                transaction_type: "Cash".to_string(),
+                asset_id: None,
+                asset_name: None,
+                position: None,
+                amount: 10000.0,
+                currency: "EUR".to_string(),
+                note: Some("initial cash".to_string()),
+                transaction_ref: None,
+            },
+            LedgerEntry {
+                date: "2023-01-02".to_string(),
+                transaction_type: "Asset".to_string(),
+                asset_id: Some(3),
+                asset_name: Some("ACME Corp.".to_string()),
+                position: Some(10.0),
+                amount: -995.5,
+                currency: "EUR".to_string(),
+                note: None,
+                transaction_ref: None,
+            },
+            LedgerEntry {
+                date: "2023-01-02".to_string(),
+                transaction_type: "Fee".to_string(),
+                asset_id: None,
+                asset_name: None,
+                position: None,
+                amount: -4.5,
+                currency: "EUR".to_string(),
+                note: None,
+                transaction_ref: Some(1),
+            },
+        ];
+        let mut csv = Vec::new();
+        write_ledger_csv(&mut csv, &entries).unwrap();
+        let text = String::from_utf8(csv.clone()).unwrap();
+        assert!(text.starts_with(
+            "date,transaction_type,asset_id,asset_name,position,amount,currency,note,transaction_ref\n"
+        ));
+        assert_eq!(read_ledger_csv(csv.as_slice()).unwrap(), entries);
+    }
+
+    #[test]
+    fn test_ledger_entries_refer_to_rows() {
+        let eur = Currency::new(None, CurrencyISOCode::new("EUR").unwrap(), Some(2));
+        let transactions = vec![
+            Transaction {
+                id: Some(7),
+                transaction_type: TransactionType::Asset {
+                    asset_id: 3,
+                    position: 10.0,
+                },
+                cash_flow: CashFlow::new(-995.5, eur, date!(2023 - 01 - 02)),
+                note: None,
+            },
+            Transaction {
+                id: Some(8),
+                transaction_type: TransactionType::Fee {
+                    transaction_ref: Some(7),
+                },
+                cash_flow: CashFlow::new(-4.5, eur, date!(2023 - 01 - 02)),
+                note: None,
+            },
+        ];
+        let entries = ledger_entries(&transactions, &HashMap::new());
+        assert_eq!(entries[0].transaction_ref, None);
+        assert_eq!(entries[1].transaction_ref, Some(0));
+        let fee = entries[1].to_transaction(eur, Some(12)).unwrap();
+        assert!(matches!(
+            fee.transaction_type,
+            TransactionType::Fee {
+                transaction_ref: Some(12)
+            }
+        ));
+        let mut invalid = entries[0].clone();
+        invalid.transaction_ref = Some(1);
+        assert!(invalid.to_transaction(eur, None).is_err());
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(
+            parse_date("2023-03-31").unwrap(),
+            Date::from_calendar_date(2023, Month::March, 31).unwrap()
+        );
+        assert!(parse_date("2023-02-30").is_err());
+        assert!(parse_date("31.03.2023").is_err());
+    }
+}
@@ -0,0 +1,102 @@
+//! Investment strategies for simulations with `finql::strategy::calc_strategy`
+//!
+//! The strategies complement the single stock strategies provided by finql.
+use cal_calc::Calendar;
+use time::{Date, Duration};
+
+use finql::datatypes::{
+    date_time_helper::date_to_offset_date_time, CashFlow, Currency, Transaction, TransactionType,
+};
+use finql::portfolio::PortfolioPosition;
+use finql::Market;
+
+pub mod interest;
+pub mod ledger;
+pub mod portfolio;
+pub mod registry;
+pub mod signals;
+pub mod tax;
+
+pub use interest::{CashInterest, CashInterestStrategy, InterestRate};
+pub use ledger::{calc_strategy_with_ledger, LedgerEntry, LedgerStrategy};
+pub use portfolio::{KeepHolding, RebalanceFrequency, RebalanceToTargets};
+pub use registry::{
+    ParamKind, ParamSpec, ParamValue, ParamValues, RegisteredStrategy, StrategyContext,
+    StrategyError, StrategyRegistry,
+};
+pub use signals::{Momentum, MovingAverageCrossover, TrailingStop};
+pub use tax::{AssetTaxInfo, GermanTaxParams, GermanTaxStrategy, TaxClass};
+
+/// Target weight of an asset within a portfolio
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TargetWeight {
+    pub asset_id: i32,
+    /// As a fraction in [0, 1]
+    pub weight: f64,
+}
+
+/// Asset transaction together with its related fee and tax payments
+pub fn trade(
+    asset_id: i32,
+    position: f64,
+    price: f64,
+    fee: f64,
+    tax: f64,
+    currency: Currency,
+    date: Date,
+) -> Vec<Transaction> {
+    let mut transactions = vec![Transaction {
+        id: None,
+        transaction_type: TransactionType::Asset { asset_id, position },
+        cash_flow: CashFlow::new(-position * price, currency, date),
+        note: None,
+    }];
+    if fee != 0.0 {
+        transactions.push(Transaction {
+            id: None,
+            transaction_type: TransactionType::Fee {
+                transaction_ref: None,
+            },
+            cash_flow: CashFlow::new(-fee, currency, date),
+            note: None,
+        });
+    }
+    if tax != 0.0 {
+        transactions.push(Transaction {
+            id: None,
+            transaction_type: TransactionType::Tax {
+                transaction_ref: None,
+            },
+            cash_flow: CashFlow::new(-tax, currency, date),
+            note: None,
+        });
+    }
+    transactions
+}
+
+/// Gain realized by selling `sold` units of an asset out of the given position for the net
+/// proceeds `proceeds`, based on the average purchase price of the position
+pub fn realized_gain(position: &PortfolioPosition, asset_id: i32, sold: f64, proceeds: f64) -> f64 {
+    match position.assets.get(&asset_id) {
+        Some(pos) if pos.position > 0.0 => proceeds + pos.purchase_value * sold / pos.position,
+        _ => 0.0,
+    }
+}
+
+/// Price of an asset at the given date, or `None` if no quote is available
+pub async fn asset_price(
+    asset_id: i32,
+    currency: Currency,
+    date: Date,
+    market: &Market,
+) -> Option<f64> {
+    let time = date_to_offset_date_time(&date, 20, None).ok()?;
+    market.get_asset_price(asset_id, currency, time).await.ok()
+}
+
+/// Next business day according to the given calendar
+pub fn next_business_day(date: Date, calendar: &Calendar) -> Date {
+    calendar
+        .next_bday(date)
+        .unwrap_or_else(|_| date + Duration::days(1))
+}
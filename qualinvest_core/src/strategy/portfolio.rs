@@ -0,0 +1,368 @@
+//! Strategies that operate on a whole portfolio instead of a single stock
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use cal_calc::Calendar;
+use time::Date;
+
+use finql::datatypes::Transaction;
+use finql::portfolio::PortfolioPosition;
+use finql::strategy::{StockTransactionCosts, Strategy};
+use finql::Market;
+
+use super::{asset_price, next_business_day, realized_gain, trade, TargetWeight};
+
+/// Keep the initial portfolio unchanged, i.e. never trade
+pub struct KeepHolding {}
+
+#[async_trait]
+impl Strategy for KeepHolding {
+    fn next_day(&self, date: Date, calendar: &Calendar) -> Date {
+        next_business_day(date, calendar)
+    }
+
+    async fn apply_rules(&self, _position: &PortfolioPosition, _date: Date) -> Vec<Transaction> {
+        Vec::new()
+    }
+}
+
+/// How often a portfolio is brought back to its target weights
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RebalanceFrequency {
+    /// Only on the first day of the simulation
+    Once,
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl RebalanceFrequency {
+    pub(crate) fn is_due(&self, last: Option<Date>, date: Date) -> bool {
+        let last = match last {
+            None => return true,
+            Some(last) => last,
+        };
+        match self {
+            RebalanceFrequency::Once => false,
+            RebalanceFrequency::Monthly => {
+                (last.year(), last.month()) != (date.year(), date.month())
+            }
+            RebalanceFrequency::Quarterly => {
+                let quarter = |d: Date| (d.year(), (d.month() as u8 - 1) / 3);
+                quarter(last) != quarter(date)
+            }
+            RebalanceFrequency::Yearly => last.year() != date.year(),
+        }
+    }
+}
+
+impl std::str::FromStr for RebalanceFrequency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Once" => Ok(RebalanceFrequency::Once),
+            "Monthly" => Ok(RebalanceFrequency::Monthly),
+            "Quarterly" => Ok(RebalanceFrequency::Quarterly),
+            "Yearly" => Ok(RebalanceFrequency::Yearly),
+            other => Err(format!("unknown rebalance frequency '{}'", other)),
+        }
+    }
+}
+
+/// Bring the portfolio back to the target weights at the given frequency.
+///
+/// Assets without target weight are sold, the part not allocated by the targets remains in cash.
+/// With a single target of weight 1 and frequency `Once`, this sells everything into one asset,
+/// e.g. a money market fund.
+pub struct RebalanceToTargets {
+    targets: Vec<TargetWeight>,
+    frequency: RebalanceFrequency,
+    costs: StockTransactionCosts,
+    market: Market,
+    last_rebalance: Mutex<Option<Date>>,
+}
+
+impl RebalanceToTargets {
+    pub fn new(
+        targets: Vec<TargetWeight>,
+        frequency: RebalanceFrequency,
+        costs: StockTransactionCosts,
+        market: Market,
+    ) -> Self {
+        Self {
+            targets,
+            frequency,
+            costs,
+            market,
+            last_rebalance: Mutex::new(None),
+        }
+    }
+}
+
+fn target_weight(targets: &[TargetWeight], asset_id: i32) -> f64 {
+    targets
+        .iter()
+        .filter(|t| t.asset_id == asset_id)
+        .map(|t| t.weight)
+        .sum()
+}
+
+/// Trades bringing the holdings, given as asset id, current position and price, to the target
+/// weights. Sales come first to free cash for the purchases.
+fn rebalance_trades(
+    position: &PortfolioPosition,
+    holdings: &[(i32, f64, f64)],
+    targets: &[TargetWeight],
+    costs: &StockTransactionCosts,
+    date: Date,
+) -> Vec<Transaction> {
+    let currency = position.cash.currency;
+    let total_value = position.cash.position
+        + holdings
+            .iter()
+            .map(|(_, current, price)| current * price)
+            .sum::<f64>();
+    if total_value <= 0.0 {
+        return Vec::new();
+    }
+
+    let mut transactions = Vec::new();
+    let mut cash = position.cash.position;
+    let mut purchases = Vec::new();
+    for &(asset_id, current, price) in holdings {
+        let target = total_value * target_weight(targets, asset_id) / price;
+        let delta = target - current;
+        if (delta * price).abs() < 1e-6 * total_value {
+            continue;
+        }
+        if delta < 0.0 {
+            let volume = -delta * price;
+            let fee = costs.fee.calc_fee(volume);
+            let gain = realized_gain(position, asset_id, -delta, volume - fee);
+            let tax = (gain * costs.tax_rate).max(0.0);
+            cash += volume - fee - tax;
+            transactions.extend(trade(asset_id, delta, price, fee, tax, currency, date));
+        } else {
+            purchases.push((asset_id, delta * price, price));
+        }
+    }
+
+    // Scale purchases down if fees and taxes left not enough cash
+    let required: f64 = purchases.iter().map(|(_, volume, _)| volume).sum();
+    let scale = if required > cash {
+        (cash / required).max(0.0)
+    } else {
+        1.0
+    };
+    for (asset_id, volume, price) in purchases {
+        let volume = volume * scale;
+        let fee = costs.fee.calc_fee(volume);
+        if volume <= fee {
+            continue;
+        }
+        let position = (volume - fee) / price;
+        transactions.extend(trade(asset_id, position, price, fee, 0.0, currency, date));
+    }
+    transactions
+}
+
+#[async_trait]
+impl Strategy for RebalanceToTargets {
+    fn next_day(&self, date: Date, calendar: &Calendar) -> Date {
+        next_business_day(date, calendar)
+    }
+
+    async fn apply_rules(&self, position: &PortfolioPosition, date: Date) -> Vec<Transaction> {
+        let last = *self.last_rebalance.lock().unwrap();
+        if !self.frequency.is_due(last, date) {
+            return Vec::new();
+        }
+        let currency = position.cash.currency;
+
+        // Current holdings and prices of all assets involved
+        let mut asset_ids: Vec<i32> = position.assets.keys().cloned().collect();
+        for target in &self.targets {
+            if !asset_ids.contains(&target.asset_id) {
+                asset_ids.push(target.asset_id);
+            }
+        }
+        let mut holdings = Vec::new();
+        for asset_id in asset_ids {
+            let current = position
+                .assets
+                .get(&asset_id)
+                .map(|p| p.position)
+                .unwrap_or(0.0);
+            let price = match asset_price(asset_id, currency, date, &self.market).await {
+                Some(price) if price > 0.0 => price,
+                // Without a price, the portfolio can't be valued; try again next day
+                _ => return Vec::new(),
+            };
+            holdings.push((asset_id, current, price));
+        }
+        *self.last_rebalance.lock().unwrap() = Some(date);
+        rebalance_trades(position, &holdings, &self.targets, &self.costs, date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    use finql::datatypes::{Currency, CurrencyISOCode, TransactionType};
+    use finql::portfolio::Position;
+    use finql::strategy::StockTransactionFee;
+    use time::macros::date;
+
+    /// Result of a future that completes without waiting
+    fn ready<F: Future>(future: F) -> F::Output {
+        match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("future is not ready"),
+        }
+    }
+
+    fn portfolio() -> PortfolioPosition {
+        let eur = Currency::new(None, CurrencyISOCode::new("EUR").unwrap(), Some(2));
+        let mut portfolio = PortfolioPosition::new(eur);
+        portfolio.cash.position = 1000.0;
+        let mut asset = Position::new(Some(1), eur);
+        asset.position = 10.0;
+        asset.purchase_value = -800.0;
+        portfolio.assets.insert(1, asset);
+        portfolio
+    }
+
+    fn asset_trades(transactions: &[Transaction]) -> Vec<(i32, f64)> {
+        transactions
+            .iter()
+            .filter_map(|t| match t.transaction_type {
+                TransactionType::Asset { asset_id, position } => Some((asset_id, position)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_keep_holding() {
+        let transactions = ready(KeepHolding {}.apply_rules(&portfolio(), date!(2024 - 01 - 02)));
+        assert!(transactions.is_empty());
+    }
+
+    #[test]
+    fn test_rebalance_is_due() {
+        let due = |frequency: RebalanceFrequency, last, date| frequency.is_due(Some(last), date);
+        assert!(RebalanceFrequency::Once.is_due(None, date!(2024 - 01 - 02)));
+        assert!(!due(
+            RebalanceFrequency::Once,
+            date!(2024 - 01 - 02),
+            date!(2030 - 01 - 02)
+        ));
+
+        assert!(due(
+            RebalanceFrequency::Monthly,
+            date!(2024 - 01 - 31),
+            date!(2024 - 02 - 01)
+        ));
+        assert!(!due(
+            RebalanceFrequency::Monthly,
+            date!(2024 - 01 - 02),
+            date!(2024 - 01 - 31)
+        ));
+        // Same month in another year
+        assert!(due(
+            RebalanceFrequency::Monthly,
+            date!(2023 - 01 - 15),
+            date!(2024 - 01 - 15)
+        ));
+
+        assert!(due(
+            RebalanceFrequency::Quarterly,
+            date!(2024 - 03 - 29),
+            date!(2024 - 04 - 01)
+        ));
+        assert!(!due(
+            RebalanceFrequency::Quarterly,
+            date!(2024 - 04 - 01),
+            date!(2024 - 06 - 28)
+        ));
+        assert!(due(
+            RebalanceFrequency::Quarterly,
+            date!(2023 - 12 - 29),
+            date!(2024 - 01 - 02)
+        ));
+
+        assert!(due(
+            RebalanceFrequency::Yearly,
+            date!(2023 - 12 - 29),
+            date!(2024 - 01 - 02)
+        ));
+        assert!(!due(
+            RebalanceFrequency::Yearly,
+            date!(2024 - 01 - 02),
+            date!(2024 - 12 - 31)
+        ));
+    }
+
+    #[test]
+    fn test_rebalance_trades() {
+        let costs = StockTransactionCosts {
+            fee: StockTransactionFee::new(0.0, None, 0.01),
+            tax_rate: 0.25,
+        };
+        let targets = [
+            TargetWeight {
+                asset_id: 1,
+                weight: 0.25,
+            },
+            TargetWeight {
+                asset_id: 2,
+                weight: 0.5,
+            },
+        ];
+        // Total value of 2000 with the asset 1 at 100 and asset 2 at 50
+        let holdings = [(1, 10.0, 100.0), (2, 0.0, 50.0)];
+        let transactions = rebalance_trades(
+            &portfolio(),
+            &holdings,
+            &targets,
+            &costs,
+            date!(2024 - 01 - 02),
+        );
+        // Sell 5 units of asset 1 for 500 with a fee of 5 and a tax of 25% on a gain of 95,
+        // then buy asset 2 for 1000 including a fee of 10
+        let expected = [500.0, -5.0, -23.75, -990.0, -10.0];
+        assert_eq!(transactions.len(), expected.len());
+        for (transaction, amount) in transactions.iter().zip(expected.iter()) {
+            assert!((transaction.cash_flow.amount.amount - amount).abs() < 1e-9);
+        }
+        let trades = asset_trades(&transactions);
+        assert_eq!(trades.len(), 2);
+        assert_eq!(trades[0], (1, -5.0));
+        assert_eq!(trades[1].0, 2);
+        assert!((trades[1].1 - 19.8).abs() < 1e-9);
+
+        // Asset 1 is sold completely, the purchase is scaled down to the cash left after fees
+        // and taxes of the sale
+        let targets = [TargetWeight {
+            asset_id: 2,
+            weight: 1.0,
+        }];
+        let transactions = rebalance_trades(
+            &portfolio(),
+            &holdings,
+            &targets,
+            &costs,
+            date!(2024 - 01 - 02),
+        );
+        let trades = asset_trades(&transactions);
+        assert_eq!(trades.len(), 2);
+        assert_eq!(trades[0], (1, -10.0));
+        let cash: f64 = transactions.iter().map(|t| t.cash_flow.amount.amount).sum();
+        assert!((1000.0 + cash).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,660 @@
+//! Registry of all strategies available for simulations
+//!
+//! Each strategy declares its name, the parameters it takes and a constructor building the
+//! strategy from the parameter values. Front ends use the parameter specification to generate
+//! input forms and parse user input, such that a new strategy only needs to be registered here.
+use std::collections::BTreeMap;
+
+use thiserror::Error;
+use time::Date;
+
+use finql::datatypes::{CashFlow, Currency};
+use finql::strategy::{
+    ReInvestInSingleStock, StaticInSingleStock, StockTransactionCosts, Strategy,
+};
+use finql::Market;
+
+use super::{
+    KeepHolding, Momentum, MovingAverageCrossover, RebalanceFrequency, RebalanceToTargets,
+    TargetWeight, TrailingStop,
+};
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum StrategyError {
+    #[error("Unknown strategy '{0}'")]
+    UnknownStrategy(String),
+    #[error("Missing parameter '{0}'")]
+    MissingParam(String),
+    #[error("Invalid value for parameter '{name}': {reason}")]
+    InvalidParam { name: String, reason: String },
+    #[error("Currency {0} is not stored in the database")]
+    UnknownCurrency(String),
+}
+
+fn invalid(name: &str, reason: impl Into<String>) -> StrategyError {
+    StrategyError::InvalidParam {
+        name: name.to_string(),
+        reason: reason.into(),
+    }
+}
+
+/// Type of a strategy parameter
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamKind {
+    /// Id of an asset
+    Asset,
+    /// Id of a ticker of the selected asset
+    Ticker,
+    Integer {
+        min: Option<i64>,
+        max: Option<i64>,
+    },
+    Number {
+        min: Option<f64>,
+        max: Option<f64>,
+    },
+    /// Fraction entered in percent, bounds are given as fractions
+    Percent {
+        min: Option<f64>,
+        max: Option<f64>,
+    },
+    /// One out of a fixed list of values
+    Choice(Vec<&'static str>),
+    /// List of assets with their target weights, entered as `asset_id:weight%,...`
+    TargetWeights,
+    /// List of payments per share, e.g. dividends, entered as `YYYY-MM-DD:amount,...`
+    CashFlows,
+}
+
+/// Value of a strategy parameter
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamValue {
+    Id(i32),
+    Integer(i64),
+    Number(f64),
+    Text(String),
+    Weights(Vec<TargetWeight>),
+    CashFlows(Vec<(Date, f64)>),
+}
+
+/// Specification of a single strategy parameter
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamSpec {
+    /// Key of the parameter
+    pub name: &'static str,
+    pub label: &'static str,
+    pub description: &'static str,
+    pub kind: ParamKind,
+    pub required: bool,
+    /// Default value in its text representation, used if no value is given
+    pub default: Option<&'static str>,
+}
+
+impl ParamSpec {
+    /// Parse a value from its text representation; an empty text means no value.
+    pub fn parse(&self, text: &str) -> Result<Option<ParamValue>, StrategyError> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(None);
+        }
+        let number = |s: &str| {
+            s.trim()
+                .parse::<f64>()
+                .map_err(|_| invalid(self.name, format!("'{}' is not a number", s)))
+        };
+        let value = match &self.kind {
+            ParamKind::Asset | ParamKind::Ticker => ParamValue::Id(
+                text.parse()
+                    .map_err(|_| invalid(self.name, format!("'{}' is not an id", text)))?,
+            ),
+            ParamKind::Integer { .. } => ParamValue::Integer(
+                text.parse()
+                    .map_err(|_| invalid(self.name, format!("'{}' is not an integer", text)))?,
+            ),
+            ParamKind::Number { .. } => ParamValue::Number(number(text)?),
+            ParamKind::Percent { .. } => ParamValue::Number(number(text)? / 100.0),
+            ParamKind::Choice(_) => ParamValue::Text(text.to_string()),
+            ParamKind::TargetWeights => {
+                let mut weights = Vec::new();
+                for item in text.split(',') {
+                    let (asset_id, weight) = split_pair(self.name, item)?;
+                    let asset_id = asset_id
+                        .parse()
+                        .map_err(|_| invalid(self.name, format!("'{}' is not an id", asset_id)))?;
+                    weights.push(TargetWeight {
+                        asset_id,
+                        weight: number(weight)? / 100.0,
+                    });
+                }
+                ParamValue::Weights(weights)
+            }
+            ParamKind::CashFlows => {
+                let mut cash_flows = Vec::new();
+                for item in text.split(',') {
+                    let (date, amount) = split_pair(self.name, item)?;
+                    cash_flows.push((parse_date(self.name, date)?, number(amount)?));
+                }
+                ParamValue::CashFlows(cash_flows)
+            }
+        };
+        self.check(&value)?;
+        Ok(Some(value))
+    }
+
+    /// Check that the value matches the parameter type and its bounds
+    pub fn check(&self, value: &ParamValue) -> Result<(), StrategyError> {
+        fn in_bounds<T: PartialOrd>(value: T, min: &Option<T>, max: &Option<T>) -> bool {
+            !matches!(min, Some(min) if value < *min) && !matches!(max, Some(max) if value > *max)
+        }
+        let valid = match (&self.kind, value) {
+            (ParamKind::Asset, ParamValue::Id(_)) | (ParamKind::Ticker, ParamValue::Id(_)) => true,
+            (ParamKind::Integer { min, max }, ParamValue::Integer(v)) => in_bounds(*v, min, max),
+            (ParamKind::Number { min, max }, ParamValue::Number(v))
+            | (ParamKind::Percent { min, max }, ParamValue::Number(v)) => in_bounds(*v, min, max),
+            (ParamKind::Choice(choices), ParamValue::Text(v)) => {
+                if !choices.contains(&v.as_str()) {
+                    return Err(invalid(
+                        self.name,
+                        format!("'{}' is not one of {}", v, choices.join(", ")),
+                    ));
+                }
+                true
+            }
+            (ParamKind::TargetWeights, ParamValue::Weights(weights)) => {
+                let total: f64 = weights.iter().map(|w| w.weight).sum();
+                if weights.iter().any(|w| w.weight < 0.0) || total > 1.0 + 1e-9 {
+                    return Err(invalid(
+                        self.name,
+                        "weights must be non-negative and sum up to at most 100%",
+                    ));
+                }
+                true
+            }
+            (ParamKind::CashFlows, ParamValue::CashFlows(_)) => true,
+            _ => return Err(invalid(self.name, "wrong type of value")),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(invalid(self.name, "value out of range"))
+        }
+    }
+}
+
+fn split_pair<'a>(name: &str, item: &'a str) -> Result<(&'a str, &'a str), StrategyError> {
+    match item.split_once(':') {
+        Some((key, value)) => Ok((key.trim(), value.trim())),
+        None => Err(invalid(
+            name,
+            format!("expected 'key:value', got '{}'", item),
+        )),
+    }
+}
+
+fn parse_date(name: &str, text: &str) -> Result<Date, StrategyError> {
+    let error = || {
+        invalid(
+            name,
+            format!("'{}' is not a date of format YYYY-MM-DD", text),
+        )
+    };
+    crate::csv_import::parse_date(text, "%Y-%m-%d").ok_or_else(error)
+}
+
+/// Parameter values of a strategy by parameter name
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParamValues(BTreeMap<String, ParamValue>);
+
+impl ParamValues {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, name: &str, value: ParamValue) {
+        self.0.insert(name.to_string(), value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ParamValue> {
+        self.0.get(name)
+    }
+
+    pub fn id(&self, name: &str) -> Result<i32, StrategyError> {
+        match self.get(name) {
+            Some(ParamValue::Id(id)) => Ok(*id),
+            Some(_) => Err(invalid(name, "expected an id")),
+            None => Err(StrategyError::MissingParam(name.to_string())),
+        }
+    }
+
+    pub fn integer(&self, name: &str) -> Result<i64, StrategyError> {
+        match self.get(name) {
+            Some(ParamValue::Integer(value)) => Ok(*value),
+            Some(_) => Err(invalid(name, "expected an integer")),
+            None => Err(StrategyError::MissingParam(name.to_string())),
+        }
+    }
+
+    pub fn number(&self, name: &str) -> Result<Option<f64>, StrategyError> {
+        match self.get(name) {
+            Some(ParamValue::Number(value)) => Ok(Some(*value)),
+            Some(_) => Err(invalid(name, "expected a number")),
+            None => Ok(None),
+        }
+    }
+
+    pub fn text(&self, name: &str) -> Result<&str, StrategyError> {
+        match self.get(name) {
+            Some(ParamValue::Text(value)) => Ok(value),
+            Some(_) => Err(invalid(name, "expected a text")),
+            None => Err(StrategyError::MissingParam(name.to_string())),
+        }
+    }
+
+    pub fn weights(&self, name: &str) -> Result<Vec<TargetWeight>, StrategyError> {
+        match self.get(name) {
+            Some(ParamValue::Weights(weights)) => Ok(weights.clone()),
+            Some(_) => Err(invalid(name, "expected target weights")),
+            None => Err(StrategyError::MissingParam(name.to_string())),
+        }
+    }
+
+    /// Payments in the given currency, none if the parameter is not set
+    pub fn cash_flows(
+        &self,
+        name: &str,
+        currency: Currency,
+    ) -> Result<Vec<CashFlow>, StrategyError> {
+        match self.get(name) {
+            Some(ParamValue::CashFlows(cash_flows)) => Ok(cash_flows
+                .iter()
+                .filter(|(_, amount)| *amount != 0.0)
+                .map(|(date, amount)| CashFlow::new(*amount, currency, *date))
+                .collect()),
+            Some(_) => Err(invalid(name, "expected a list of payments")),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Everything a strategy needs besides its parameters
+pub struct StrategyContext {
+    pub currency: Currency,
+    pub costs: StockTransactionCosts,
+    pub market: Market,
+}
+
+pub type StrategyConstructor =
+    fn(&ParamValues, StrategyContext) -> Result<Box<dyn Strategy>, StrategyError>;
+
+/// Strategy that can be selected for simulations
+#[derive(Clone)]
+pub struct RegisteredStrategy {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub params: Vec<ParamSpec>,
+    pub constructor: StrategyConstructor,
+}
+
+pub struct StrategyRegistry {
+    strategies: Vec<RegisteredStrategy>,
+}
+
+impl StrategyRegistry {
+    /// Registry without any strategy
+    pub fn empty() -> Self {
+        Self {
+            strategies: Vec::new(),
+        }
+    }
+
+    /// Register a strategy, replacing any strategy registered under the same name
+    pub fn register(&mut self, strategy: RegisteredStrategy) {
+        match self.strategies.iter_mut().find(|s| s.name == strategy.name) {
+            Some(existing) => *existing = strategy,
+            None => self.strategies.push(strategy),
+        }
+    }
+
+    /// All registered strategies in order of registration
+    pub fn strategies(&self) -> &[RegisteredStrategy] {
+        &self.strategies
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RegisteredStrategy> {
+        self.strategies.iter().find(|s| s.name == name)
+    }
+
+    /// Validate the parameter values, fill in defaults for missing ones and build the strategy
+    pub fn build(
+        &self,
+        name: &str,
+        values: &ParamValues,
+        context: StrategyContext,
+    ) -> Result<Box<dyn Strategy>, StrategyError> {
+        let strategy = self
+            .get(name)
+            .ok_or_else(|| StrategyError::UnknownStrategy(name.to_string()))?;
+        let mut checked = ParamValues::new();
+        for spec in &strategy.params {
+            let value = match (values.get(spec.name), spec.default) {
+                (Some(value), _) => {
+                    spec.check(value)?;
+                    Some(value.clone())
+                }
+                (None, Some(default)) => spec.parse(default)?,
+                (None, None) => None,
+            };
+            match value {
+                Some(value) => checked.insert(spec.name, value),
+                None if spec.required => {
+                    return Err(StrategyError::MissingParam(spec.name.to_string()))
+                }
+                None => {}
+            }
+        }
+        (strategy.constructor)(&checked, context)
+    }
+}
+
+impl Default for StrategyRegistry {
+    /// Registry with all strategies of finql and qualinvest_core
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        for strategy in builtin_strategies() {
+            registry.register(strategy);
+        }
+        registry
+    }
+}
+
+fn asset_param() -> ParamSpec {
+    ParamSpec {
+        name: "asset",
+        label: "Asset",
+        description: "Asset the strategy invests in",
+        kind: ParamKind::Asset,
+        required: true,
+        default: None,
+    }
+}
+
+fn dividends_param() -> ParamSpec {
+    ParamSpec {
+        name: "dividends",
+        label: "Dividends",
+        description: "Dividends per share paid by the asset",
+        kind: ParamKind::CashFlows,
+        required: false,
+        default: None,
+    }
+}
+
+fn builtin_strategies() -> Vec<RegisteredStrategy> {
+    vec![
+        RegisteredStrategy {
+            name: "StaticInSingleStock",
+            description: "Buy the asset once and keep it, dividends are kept as cash",
+            params: vec![asset_param(), dividends_param()],
+            constructor: |values, context| {
+                Ok(Box::new(StaticInSingleStock::new(
+                    values.id("asset")?,
+                    values.cash_flows("dividends", context.currency)?,
+                    context.costs,
+                )))
+            },
+        },
+        RegisteredStrategy {
+            name: "ReInvestInSingleStock",
+            description: "Hold the asset and reinvest all dividends into it",
+            params: vec![
+                asset_param(),
+                ParamSpec {
+                    name: "ticker",
+                    label: "Ticker",
+                    description: "Ticker used to buy the asset",
+                    kind: ParamKind::Ticker,
+                    required: true,
+                    default: None,
+                },
+                dividends_param(),
+            ],
+            constructor: |values, context| {
+                Ok(Box::new(ReInvestInSingleStock::new(
+                    values.id("asset")?,
+                    values.id("ticker")?,
+                    context.market,
+                    values.cash_flows("dividends", context.currency)?,
+                    context.costs,
+                )))
+            },
+        },
+        RegisteredStrategy {
+            name: "KeepHolding",
+            description: "Keep the initial portfolio unchanged",
+            params: Vec::new(),
+            constructor: |_, _| Ok(Box::new(KeepHolding {})),
+        },
+        RegisteredStrategy {
+            name: "RebalanceToTargets",
+            description: "Bring the portfolio back to target weights at a fixed frequency",
+            params: vec![
+                ParamSpec {
+                    name: "targets",
+                    label: "Target Weights",
+                    description: "Assets and their weights, the rest is held in cash",
+                    kind: ParamKind::TargetWeights,
+                    required: true,
+                    default: None,
+                },
+                ParamSpec {
+                    name: "rebalance",
+                    label: "Rebalance",
+                    description: "How often the target weights are restored",
+                    kind: ParamKind::Choice(vec!["Once", "Monthly", "Quarterly", "Yearly"]),
+                    required: true,
+                    default: Some("Yearly"),
+                },
+            ],
+            constructor: |values, context| {
+                let frequency: RebalanceFrequency = values
+                    .text("rebalance")?
+                    .parse()
+                    .map_err(|e: String| invalid("rebalance", e))?;
+                Ok(Box::new(RebalanceToTargets::new(
+                    values.weights("targets")?,
+                    frequency,
+                    context.costs,
+                    context.market,
+                )))
+            },
+        },
+        RegisteredStrategy {
+            name: "SellIntoAsset",
+            description: "Sell all holdings once and invest everything into a single asset",
+            params: vec![asset_param()],
+            constructor: |values, context| {
+                let targets = vec![TargetWeight {
+                    asset_id: values.id("asset")?,
+                    weight: 1.0,
+                }];
+                Ok(Box::new(RebalanceToTargets::new(
+                    targets,
+                    RebalanceFrequency::Once,
+                    context.costs,
+                    context.market,
+                )))
+            },
+        },
+        RegisteredStrategy {
+            name: "MovingAverageCrossover",
+            description: "Hold the asset while the short moving average is above the long one",
+            params: vec![
+                asset_param(),
+                ParamSpec {
+                    name: "short_window",
+                    label: "Short Average (days)",
+                    description: "Window of the short moving average in trading days",
+                    kind: ParamKind::Integer {
+                        min: Some(1),
+                        max: None,
+                    },
+                    required: true,
+                    default: Some("50"),
+                },
+                ParamSpec {
+                    name: "long_window",
+                    label: "Long Average (days)",
+                    description: "Window of the long moving average in trading days",
+                    kind: ParamKind::Integer {
+                        min: Some(1),
+                        max: None,
+                    },
+                    required: true,
+                    default: Some("200"),
+                },
+            ],
+            constructor: |values, context| {
+                let short_window = values.integer("short_window")?;
+                let long_window = values.integer("long_window")?;
+                if short_window >= long_window {
+                    return Err(invalid(
+                        "long_window",
+                        "must be longer than the short window",
+                    ));
+                }
+                Ok(Box::new(MovingAverageCrossover::new(
+                    values.id("asset")?,
+                    short_window as usize,
+                    long_window as usize,
+                    context.costs,
+                    context.market,
+                )))
+            },
+        },
+        RegisteredStrategy {
+            name: "Momentum",
+            description: "Hold the asset while its return over the lookback period is positive",
+            params: vec![
+                asset_param(),
+                ParamSpec {
+                    name: "lookback_months",
+                    label: "Lookback (months)",
+                    description: "Period the momentum is measured over",
+                    kind: ParamKind::Integer {
+                        min: Some(1),
+                        max: Some(120),
+                    },
+                    required: true,
+                    default: Some("12"),
+                },
+            ],
+            constructor: |values, context| {
+                Ok(Box::new(Momentum::new(
+                    values.id("asset")?,
+                    values.integer("lookback_months")? as u32,
+                    context.costs,
+                    context.market,
+                )))
+            },
+        },
+        RegisteredStrategy {
+            name: "TrailingStop",
+            description: "Buy once and sell when a trailing stop-loss or take-profit level is hit",
+            params: vec![
+                asset_param(),
+                ParamSpec {
+                    name: "stop_loss",
+                    label: "Trailing Stop-Loss (%)",
+                    description: "Drop below the highest price since entry; empty or 0 disables",
+                    kind: ParamKind::Percent {
+                        min: Some(0.0),
+                        max: Some(1.0),
+                    },
+                    required: false,
+                    default: Some("10"),
+                },
+                ParamSpec {
+                    name: "take_profit",
+                    label: "Take-Profit (%)",
+                    description: "Rise above the entry price; empty or 0 disables",
+                    kind: ParamKind::Percent {
+                        min: Some(0.0),
+                        max: None,
+                    },
+                    required: false,
+                    default: None,
+                },
+            ],
+            constructor: |values, context| {
+                // A level of 0 disables the rule
+                let level = |name| -> Result<Option<f64>, StrategyError> {
+                    Ok(values.number(name)?.filter(|level| *level > 0.0))
+                };
+                Ok(Box::new(TrailingStop::new(
+                    values.id("asset")?,
+                    level("stop_loss")?,
+                    level("take_profit")?,
+                    context.costs,
+                    context.market,
+                )))
+            },
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    #[test]
+    fn test_parse_params() {
+        let registry = StrategyRegistry::default();
+        let rebalance = registry.get("RebalanceToTargets").unwrap();
+        let targets = &rebalance.params[0];
+        assert_eq!(
+            targets.parse("1:60, 2:40").unwrap(),
+            Some(ParamValue::Weights(vec![
+                TargetWeight {
+                    asset_id: 1,
+                    weight: 0.6
+                },
+                TargetWeight {
+                    asset_id: 2,
+                    weight: 0.4
+                },
+            ]))
+        );
+        assert!(targets.parse("1:80,2:40").is_err());
+        assert!(rebalance.params[1].parse("Weekly").is_err());
+
+        let dividends = dividends_param();
+        assert_eq!(
+            dividends.parse("2023-06-15:1.2").unwrap(),
+            Some(ParamValue::CashFlows(vec![(date!(2023 - 06 - 15), 1.2)]))
+        );
+        assert_eq!(dividends.parse(" ").unwrap(), None);
+
+        let momentum = registry.get("Momentum").unwrap();
+        assert!(momentum.params[1].parse("0").is_err());
+        assert!(momentum.params[1].parse("twelve").is_err());
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = StrategyRegistry::default();
+        let count = registry.strategies().len();
+        assert!(registry.get("TrailingStop").is_some());
+        assert!(registry.get("Unknown").is_none());
+
+        // Registering under an existing name replaces the strategy
+        let mut keep = registry.get("KeepHolding").unwrap().clone();
+        keep.description = "Do nothing";
+        registry.register(keep);
+        assert_eq!(registry.strategies().len(), count);
+        assert_eq!(
+            registry.get("KeepHolding").unwrap().description,
+            "Do nothing"
+        );
+    }
+}
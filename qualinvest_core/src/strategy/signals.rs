@@ -0,0 +1,540 @@
+//! Strategies driven by technical trading rules on the quote series of a single asset
+//!
+//! Each strategy switches between being fully invested in the asset and holding cash. All
+//! trades pay fees and taxes according to the given transaction costs, which makes the cost drag
+//! of frequent trading visible in the simulation results.
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use cal_calc::Calendar;
+use time::{Date, Duration, Month, Weekday};
+
+use finql::datatypes::{Currency, Transaction};
+use finql::portfolio::PortfolioPosition;
+use finql::strategy::{StockTransactionCosts, Strategy};
+use finql::Market;
+
+use super::{asset_price, next_business_day, realized_gain, trade, RebalanceFrequency};
+
+/// Invest all available cash into the asset
+fn buy_all(
+    position: &PortfolioPosition,
+    asset_id: i32,
+    price: f64,
+    costs: &StockTransactionCosts,
+    date: Date,
+) -> Vec<Transaction> {
+    let cash = position.cash.position;
+    let fee = costs.fee.calc_fee(cash);
+    if cash <= fee {
+        return Vec::new();
+    }
+    let quantity = (cash - fee) / price;
+    trade(
+        asset_id,
+        quantity,
+        price,
+        fee,
+        0.0,
+        position.cash.currency,
+        date,
+    )
+}
+
+/// Sell the whole position of the asset, paying tax on realized gains
+fn sell_all(
+    position: &PortfolioPosition,
+    asset_id: i32,
+    price: f64,
+    costs: &StockTransactionCosts,
+    date: Date,
+) -> Vec<Transaction> {
+    let quantity = held_quantity(position, asset_id);
+    if quantity <= 0.0 {
+        return Vec::new();
+    }
+    let volume = quantity * price;
+    let fee = costs.fee.calc_fee(volume);
+    let gain = realized_gain(position, asset_id, quantity, volume - fee);
+    let tax = (gain * costs.tax_rate).max(0.0);
+    trade(
+        asset_id,
+        -quantity,
+        price,
+        fee,
+        tax,
+        position.cash.currency,
+        date,
+    )
+}
+
+fn held_quantity(position: &PortfolioPosition, asset_id: i32) -> f64 {
+    position
+        .assets
+        .get(&asset_id)
+        .map(|p| p.position)
+        .unwrap_or(0.0)
+}
+
+/// Move fully into the asset or fully into cash, whatever is requested and not yet the case
+fn switch_to(
+    invested: bool,
+    position: &PortfolioPosition,
+    asset_id: i32,
+    price: f64,
+    costs: &StockTransactionCosts,
+    date: Date,
+) -> Vec<Transaction> {
+    if invested {
+        buy_all(position, asset_id, price, costs, date)
+    } else {
+        sell_all(position, asset_id, price, costs, date)
+    }
+}
+
+/// Prices of up to `count` weekdays before the given date, in chronological order
+async fn price_history(
+    asset_id: i32,
+    currency: Currency,
+    date: Date,
+    count: usize,
+    market: &Market,
+) -> VecDeque<f64> {
+    let mut prices = VecDeque::with_capacity(count);
+    let mut day = date;
+    for _ in 0..2 * count {
+        if prices.len() >= count {
+            break;
+        }
+        day -= Duration::days(1);
+        if matches!(day.weekday(), Weekday::Saturday | Weekday::Sunday) {
+            continue;
+        }
+        if let Some(price) = asset_price(asset_id, currency, day, market).await {
+            prices.push_front(price);
+        }
+    }
+    prices
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    if count == 0 {
+        None
+    } else {
+        Some(sum / count as f64)
+    }
+}
+
+/// Whether to hold the asset according to the moving averages of the given prices in
+/// chronological order, or `None` if there are less prices than the long window
+fn crossover_signal(
+    prices: &VecDeque<f64>,
+    short_window: usize,
+    long_window: usize,
+) -> Option<bool> {
+    if prices.len() < long_window {
+        return None;
+    }
+    let short_average = mean(prices.iter().rev().take(short_window).cloned())?;
+    let long_average = mean(prices.iter().rev().take(long_window).cloned())?;
+    Some(short_average > long_average)
+}
+
+/// Same day of month `months` months before the given date, or the last day of that month
+pub(crate) fn months_before(date: Date, months: u32) -> Date {
+    let total = date.year() * 12 + date.month() as i32 - 1 - months as i32;
+    let year = total.div_euclid(12);
+    let month = Month::try_from((total.rem_euclid(12) + 1) as u8).unwrap();
+    let day = date.day().min(time::util::days_in_year_month(year, month));
+    Date::from_calendar_date(year, month, day).unwrap()
+}
+
+/// Hold the asset while the short moving average of its price is above the long moving average,
+/// hold cash otherwise
+pub struct MovingAverageCrossover {
+    asset_id: i32,
+    short_window: usize,
+    long_window: usize,
+    costs: StockTransactionCosts,
+    market: Market,
+    prices: Mutex<Option<VecDeque<f64>>>,
+}
+
+impl MovingAverageCrossover {
+    /// Windows are given in trading days, e.g. 50 and 200
+    pub fn new(
+        asset_id: i32,
+        short_window: usize,
+        long_window: usize,
+        costs: StockTransactionCosts,
+        market: Market,
+    ) -> Self {
+        Self {
+            asset_id,
+            short_window: short_window.max(1),
+            long_window: long_window.max(short_window).max(1),
+            costs,
+            market,
+            prices: Mutex::new(None),
+        }
+    }
+}
+
+#[async_trait]
+impl Strategy for MovingAverageCrossover {
+    fn next_day(&self, date: Date, calendar: &Calendar) -> Date {
+        next_business_day(date, calendar)
+    }
+
+    async fn apply_rules(&self, position: &PortfolioPosition, date: Date) -> Vec<Transaction> {
+        let currency = position.cash.currency;
+        let price = match asset_price(self.asset_id, currency, date, &self.market).await {
+            Some(price) if price > 0.0 => price,
+            _ => return Vec::new(),
+        };
+        let needs_history = self.prices.lock().unwrap().is_none();
+        if needs_history {
+            let history = price_history(
+                self.asset_id,
+                currency,
+                date,
+                self.long_window,
+                &self.market,
+            )
+            .await;
+            *self.prices.lock().unwrap() = Some(history);
+        }
+
+        let signal = {
+            let mut guard = self.prices.lock().unwrap();
+            let prices = guard.get_or_insert_with(VecDeque::new);
+            prices.push_back(price);
+            while prices.len() > self.long_window {
+                prices.pop_front();
+            }
+            crossover_signal(prices, self.short_window, self.long_window)
+        };
+        match signal {
+            Some(invested) => {
+                switch_to(invested, position, self.asset_id, price, &self.costs, date)
+            }
+            // Not enough history for a meaningful signal yet
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Once a month, hold the asset if its return over the lookback period is positive and move
+/// into cash otherwise
+pub struct Momentum {
+    asset_id: i32,
+    lookback_months: u32,
+    costs: StockTransactionCosts,
+    market: Market,
+    last_check: Mutex<Option<Date>>,
+}
+
+impl Momentum {
+    pub fn new(
+        asset_id: i32,
+        lookback_months: u32,
+        costs: StockTransactionCosts,
+        market: Market,
+    ) -> Self {
+        Self {
+            asset_id,
+            lookback_months: lookback_months.max(1),
+            costs,
+            market,
+            last_check: Mutex::new(None),
+        }
+    }
+}
+
+#[async_trait]
+impl Strategy for Momentum {
+    fn next_day(&self, date: Date, calendar: &Calendar) -> Date {
+        next_business_day(date, calendar)
+    }
+
+    async fn apply_rules(&self, position: &PortfolioPosition, date: Date) -> Vec<Transaction> {
+        let last = *self.last_check.lock().unwrap();
+        if !RebalanceFrequency::Monthly.is_due(last, date) {
+            return Vec::new();
+        }
+        let currency = position.cash.currency;
+        let past = months_before(date, self.lookback_months);
+        let prices = (
+            asset_price(self.asset_id, currency, date, &self.market).await,
+            asset_price(self.asset_id, currency, past, &self.market).await,
+        );
+        let (price, past_price) = match prices {
+            (Some(price), Some(past_price)) if price > 0.0 && past_price > 0.0 => {
+                (price, past_price)
+            }
+            _ => return Vec::new(),
+        };
+        *self.last_check.lock().unwrap() = Some(date);
+        switch_to(
+            price > past_price,
+            position,
+            self.asset_id,
+            price,
+            &self.costs,
+            date,
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct TrailingState {
+    entry_price: Option<f64>,
+    peak_price: f64,
+    exited: bool,
+}
+
+impl TrailingState {
+    /// Process the next price and return whether to buy (`Some(true)`), sell (`Some(false)`) or
+    /// do nothing. The first price is the entry, after the exit nothing is done anymore.
+    fn update(
+        &mut self,
+        price: f64,
+        stop_loss: Option<f64>,
+        take_profit: Option<f64>,
+    ) -> Option<bool> {
+        if self.exited {
+            return None;
+        }
+        let entry_price = match self.entry_price {
+            Some(entry_price) => entry_price,
+            None => {
+                self.entry_price = Some(price);
+                self.peak_price = price;
+                return Some(true);
+            }
+        };
+        self.peak_price = self.peak_price.max(price);
+        let stopped = stop_loss.is_some_and(|stop| price <= self.peak_price * (1.0 - stop));
+        let taken = take_profit.is_some_and(|target| price >= entry_price * (1.0 + target));
+        if stopped || taken {
+            self.exited = true;
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+/// Hold the asset until its price falls by the stop-loss fraction below the highest price since
+/// entry, or rises by the take-profit fraction above the entry price; then sell and stay in cash.
+///
+/// There is no rule to enter the asset again, i.e. the strategy trades at most twice: it invests
+/// all cash on the first day and sells once one of the levels is hit.
+pub struct TrailingStop {
+    asset_id: i32,
+    stop_loss: Option<f64>,
+    take_profit: Option<f64>,
+    costs: StockTransactionCosts,
+    market: Market,
+    state: Mutex<TrailingState>,
+}
+
+impl TrailingStop {
+    /// Stop-loss and take-profit levels are given as fractions, e.g. 0.1 for 10%
+    pub fn new(
+        asset_id: i32,
+        stop_loss: Option<f64>,
+        take_profit: Option<f64>,
+        costs: StockTransactionCosts,
+        market: Market,
+    ) -> Self {
+        Self {
+            asset_id,
+            stop_loss,
+            take_profit,
+            costs,
+            market,
+            state: Mutex::new(TrailingState::default()),
+        }
+    }
+}
+
+#[async_trait]
+impl Strategy for TrailingStop {
+    fn next_day(&self, date: Date, calendar: &Calendar) -> Date {
+        next_business_day(date, calendar)
+    }
+
+    async fn apply_rules(&self, position: &PortfolioPosition, date: Date) -> Vec<Transaction> {
+        let price =
+            match asset_price(self.asset_id, position.cash.currency, date, &self.market).await {
+                Some(price) if price > 0.0 => price,
+                _ => return Vec::new(),
+            };
+        let signal = self
+            .state
+            .lock()
+            .unwrap()
+            .update(price, self.stop_loss, self.take_profit);
+        match signal {
+            // Invest any initial cash at the start, sell everything at the exit
+            Some(invested) => {
+                switch_to(invested, position, self.asset_id, price, &self.costs, date)
+            }
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use finql::datatypes::{CurrencyISOCode, TransactionType};
+    use finql::portfolio::Position;
+    use finql::strategy::StockTransactionFee;
+    use time::macros::date;
+
+    fn costs() -> StockTransactionCosts {
+        StockTransactionCosts {
+            fee: StockTransactionFee::new(0.0, None, 0.01),
+            tax_rate: 0.25,
+        }
+    }
+
+    fn position(cash: f64, quantity: f64, purchase_value: f64) -> PortfolioPosition {
+        let eur = Currency::new(None, CurrencyISOCode::new("EUR").unwrap(), Some(2));
+        let mut position = PortfolioPosition::new(eur);
+        position.cash.position = cash;
+        if quantity != 0.0 {
+            let mut asset = Position::new(Some(1), eur);
+            asset.position = quantity;
+            asset.purchase_value = purchase_value;
+            position.assets.insert(1, asset);
+        }
+        position
+    }
+
+    fn traded_quantity(transactions: &[Transaction]) -> f64 {
+        transactions
+            .iter()
+            .map(|t| match t.transaction_type {
+                TransactionType::Asset { position, .. } => position,
+                _ => 0.0,
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_switch_to() {
+        let date = date!(2024 - 01 - 02);
+        // Buy with all cash after fees
+        let buy = switch_to(true, &position(1000.0, 0.0, 0.0), 1, 99.0, &costs(), date);
+        assert_eq!(buy.len(), 2);
+        assert!((traded_quantity(&buy) - 10.0).abs() < 1e-9);
+        // Sell everything and pay tax on the gain of 1100 - 11 - 800
+        let sell = switch_to(
+            false,
+            &position(0.0, 10.0, -800.0),
+            1,
+            110.0,
+            &costs(),
+            date,
+        );
+        assert_eq!(sell.len(), 3);
+        assert_eq!(traded_quantity(&sell), -10.0);
+        assert!((sell[2].cash_flow.amount.amount + 72.25).abs() < 1e-9);
+        // Nothing to do if already in the requested state
+        assert!(switch_to(false, &position(1000.0, 0.0, 0.0), 1, 99.0, &costs(), date).is_empty());
+        assert!(switch_to(true, &position(0.0, 10.0, -800.0), 1, 99.0, &costs(), date).is_empty());
+    }
+
+    #[test]
+    fn test_crossover_signal() {
+        let prices: VecDeque<f64> = vec![10.0, 11.0, 12.0].into_iter().collect();
+        assert_eq!(crossover_signal(&prices, 2, 4), None);
+        let rising: VecDeque<f64> = vec![10.0, 11.0, 12.0, 13.0].into_iter().collect();
+        assert_eq!(crossover_signal(&rising, 2, 4), Some(true));
+        let falling: VecDeque<f64> = vec![13.0, 12.0, 11.0, 10.0].into_iter().collect();
+        assert_eq!(crossover_signal(&falling, 2, 4), Some(false));
+        // Only the last prices of the long window count
+        let recovered: VecDeque<f64> = vec![1.0, 13.0, 12.0, 11.0, 10.0].into_iter().collect();
+        assert_eq!(crossover_signal(&recovered, 2, 4), Some(false));
+    }
+
+    #[test]
+    fn test_momentum_series() {
+        // Month end prices, the rule holds the asset while the price is above the price three
+        // months before
+        let prices = [100.0, 102.0, 104.0, 103.0, 106.0, 101.0, 99.0, 100.0, 104.0];
+        let mut quantity = 0.0;
+        let mut trades = Vec::new();
+        for month in 3..prices.len() {
+            let (price, past_price) = (prices[month], prices[month - 3]);
+            let cash = if quantity > 0.0 { 0.0 } else { 1000.0 };
+            let transactions = switch_to(
+                price > past_price,
+                &position(cash, quantity, -1000.0),
+                1,
+                price,
+                &costs(),
+                date!(2024 - 01 - 31),
+            );
+            let traded = traded_quantity(&transactions);
+            quantity += traded;
+            trades.push(if traded > 0.0 {
+                "buy"
+            } else if traded < 0.0 {
+                "sell"
+            } else {
+                "hold"
+            });
+        }
+        assert_eq!(trades, vec!["buy", "hold", "sell", "hold", "hold", "buy"]);
+    }
+
+    #[test]
+    fn test_trailing_stop() {
+        let mut state = TrailingState::default();
+        let signals: Vec<Option<bool>> = [100.0, 110.0, 120.0, 109.0, 107.0, 130.0]
+            .iter()
+            .map(|price| state.update(*price, Some(0.1), None))
+            .collect();
+        // Stop at 10% below the peak of 120, no re-entry afterwards
+        assert_eq!(
+            signals,
+            vec![Some(true), None, None, None, Some(false), None]
+        );
+
+        let mut state = TrailingState::default();
+        let signals: Vec<Option<bool>> = [100.0, 90.0, 115.0, 121.0]
+            .iter()
+            .map(|price| state.update(*price, None, Some(0.2)))
+            .collect();
+        assert_eq!(signals, vec![Some(true), None, None, Some(false)]);
+    }
+
+    #[test]
+    fn test_months_before() {
+        assert_eq!(
+            months_before(date!(2024 - 05 - 15), 12),
+            date!(2023 - 05 - 15)
+        );
+        assert_eq!(
+            months_before(date!(2024 - 01 - 31), 1),
+            date!(2023 - 12 - 31)
+        );
+        assert_eq!(
+            months_before(date!(2024 - 03 - 31), 1),
+            date!(2024 - 02 - 29)
+        );
+    }
+
+    #[test]
+    fn test_mean() {
+        assert_eq!(mean(vec![1.0, 2.0, 3.0].into_iter()), Some(2.0));
+        assert_eq!(mean(Vec::new().into_iter()), None);
+    }
+}
@@ -0,0 +1,411 @@
+//! German capital gains tax (Abgeltungsteuer) for simulations
+//!
+//! Realized gains are booked into yearly tax accounts that follow the rules of German banks:
+//! losses from selling stocks can only be offset against gains from selling stocks, all other
+//! losses against any capital income. Losses not used within a year are carried forward, while
+//! the yearly allowance (Sparerpauschbetrag) lapses. Income from investment funds is partially
+//! exempt (Teilfreistellung) and accumulating funds are taxed yearly on the Vorabpauschale.
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use cal_calc::Calendar;
+use serde::{Deserialize, Serialize};
+use time::{Date, Month};
+
+use finql::datatypes::{CashFlow, Currency, Transaction, TransactionType};
+use finql::portfolio::PortfolioPosition;
+use finql::strategy::Strategy;
+use finql::Market;
+
+use super::{asset_price, realized_gain};
+
+/// Tax treatment of an asset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TaxClass {
+    Stock,
+    EquityFund,
+    MixedFund,
+    RealEstateFund,
+    /// Any other asset, e.g. bonds or bond funds
+    Other,
+}
+
+impl TaxClass {
+    /// Tax exempt fraction of the income (Teilfreistellung)
+    pub fn partial_exemption(&self) -> f64 {
+        match self {
+            TaxClass::EquityFund => 0.3,
+            TaxClass::MixedFund => 0.15,
+            TaxClass::RealEstateFund => 0.6,
+            TaxClass::Stock | TaxClass::Other => 0.0,
+        }
+    }
+}
+
+impl FromStr for TaxClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Stock" => Ok(TaxClass::Stock),
+            "EquityFund" => Ok(TaxClass::EquityFund),
+            "MixedFund" => Ok(TaxClass::MixedFund),
+            "RealEstateFund" => Ok(TaxClass::RealEstateFund),
+            "Other" => Ok(TaxClass::Other),
+            other => Err(format!("unknown tax class '{}'", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AssetTaxInfo {
+    pub class: TaxClass,
+    /// Accumulating funds are subject to the Vorabpauschale
+    pub accumulating: bool,
+}
+
+impl Default for AssetTaxInfo {
+    fn default() -> Self {
+        Self {
+            class: TaxClass::Stock,
+            accumulating: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GermanTaxParams {
+    /// Including solidarity surcharge and church tax, if any
+    pub tax_rate: f64,
+    /// Sparerpauschbetrag per year
+    pub allowance: f64,
+    /// Basiszins per year used to calculate the Vorabpauschale, as a fraction
+    pub base_rates: BTreeMap<i32, f64>,
+    /// Tax treatment per asset id, assets not listed are taxed as stocks
+    pub assets: BTreeMap<i32, AssetTaxInfo>,
+}
+
+impl GermanTaxParams {
+    /// Standard parameters for a single person without church tax
+    pub fn new(assets: BTreeMap<i32, AssetTaxInfo>) -> Self {
+        Self {
+            tax_rate: 0.26375,
+            allowance: 1000.0,
+            base_rates: default_base_rates(),
+            assets,
+        }
+    }
+
+    fn info(&self, asset_id: i32) -> AssetTaxInfo {
+        self.assets.get(&asset_id).cloned().unwrap_or_default()
+    }
+}
+
+/// Basiszins published by the German ministry of finance since the introduction of the
+/// Vorabpauschale; negative rates result in no Vorabpauschale
+pub fn default_base_rates() -> BTreeMap<i32, f64> {
+    vec![
+        (2018, 0.0087),
+        (2019, 0.0052),
+        (2020, 0.0007),
+        (2021, -0.0045),
+        (2022, -0.0005),
+        (2023, 0.0255),
+        (2024, 0.0229),
+        (2025, 0.0253),
+    ]
+    .into_iter()
+    .collect()
+}
+
+/// Capital income and taxes of one year, including losses carried forward from previous years
+#[derive(Debug, Default, Clone)]
+struct TaxAccount {
+    year: i32,
+    /// Gains and losses from selling stocks
+    stock_result: f64,
+    /// All other taxable capital income
+    other_result: f64,
+    carried_stock_loss: f64,
+    carried_general_loss: f64,
+    tax_paid: f64,
+}
+
+impl TaxAccount {
+    fn new(year: i32) -> Self {
+        Self {
+            year,
+            ..Default::default()
+        }
+    }
+
+    /// Net results of the stock and the general pot after offsetting losses
+    fn net_results(&self) -> (f64, f64) {
+        let mut stock = self.stock_result - self.carried_stock_loss;
+        let mut other = self.other_result - self.carried_general_loss;
+        // General losses may be offset against stock gains, but not the other way around
+        if other < 0.0 && stock > 0.0 {
+            let offset = stock.min(-other);
+            stock -= offset;
+            other += offset;
+        }
+        (stock, other)
+    }
+
+    fn taxable_income(&self, allowance: f64) -> f64 {
+        let (stock, other) = self.net_results();
+        (stock.max(0.0) + other.max(0.0) - allowance).max(0.0)
+    }
+
+    /// Book taxable income (or loss) and return the resulting additional tax, which is
+    /// negative if taxes paid earlier in the year are refunded
+    fn book(&mut self, stock_pot: bool, amount: f64, params: &GermanTaxParams) -> f64 {
+        if stock_pot {
+            self.stock_result += amount;
+        } else {
+            self.other_result += amount;
+        }
+        let tax_due = self.taxable_income(params.allowance) * params.tax_rate;
+        let tax = tax_due - self.tax_paid;
+        self.tax_paid = tax_due;
+        tax
+    }
+
+    /// Close the year and carry forward unused losses
+    fn start_year(&mut self, year: i32) {
+        let (stock, other) = self.net_results();
+        *self = Self {
+            year,
+            carried_stock_loss: (-stock).max(0.0),
+            carried_general_loss: (-other).max(0.0),
+            ..Default::default()
+        };
+    }
+}
+
+#[derive(Debug, Default)]
+struct TaxState {
+    account: Option<TaxAccount>,
+    /// Start of the simulation
+    start: Option<Date>,
+    /// Vorabpauschale taxed so far per asset, which reduces the taxable gain on sale
+    vorabpauschale: BTreeMap<i32, f64>,
+}
+
+/// Applies German capital gains tax to the transactions of another strategy
+///
+/// Any tax transactions of the wrapped strategy are replaced, so its own flat tax rate should
+/// be zero.
+pub struct GermanTaxStrategy {
+    inner: Box<dyn Strategy>,
+    params: GermanTaxParams,
+    market: Market,
+    state: Mutex<TaxState>,
+}
+
+impl GermanTaxStrategy {
+    pub fn new(inner: Box<dyn Strategy>, params: GermanTaxParams, market: Market) -> Self {
+        Self {
+            inner,
+            params,
+            market,
+            state: Mutex::new(TaxState::default()),
+        }
+    }
+
+    /// Vorabpauschale per accumulating fund for the year before the given date's year.
+    ///
+    /// The position at the beginning of the new year is taken as held during the whole previous
+    /// year, or since the start of the simulation if that is later.
+    async fn vorabpauschale(
+        &self,
+        position: &PortfolioPosition,
+        date: Date,
+        start: Date,
+    ) -> Vec<(i32, f64)> {
+        let currency = position.cash.currency;
+        let year = date.year() - 1;
+        let base_rate = self.params.base_rates.get(&year).cloned().unwrap_or(0.0);
+        if base_rate <= 0.0 {
+            return Vec::new();
+        }
+        let year_start = Date::from_calendar_date(year, Month::January, 1).unwrap();
+        let from = start.max(year_start);
+        // Reduced by one twelfth for each full month before the month of the start
+        let months = 13 - from.month() as u8;
+
+        let mut amounts = Vec::new();
+        for (asset_id, pos) in &position.assets {
+            if pos.position <= 0.0 || !self.params.info(*asset_id).accumulating {
+                continue;
+            }
+            let prices = (
+                asset_price(*asset_id, currency, from, &self.market).await,
+                asset_price(*asset_id, currency, date, &self.market).await,
+            );
+            if let (Some(start_price), Some(end_price)) = prices {
+                let base_yield = start_price * base_rate * 0.7 * months as f64 / 12.0;
+                let amount = pos.position * base_yield.min((end_price - start_price).max(0.0));
+                if amount > 0.0 {
+                    amounts.push((*asset_id, amount));
+                }
+            }
+        }
+        amounts
+    }
+
+    fn tax_transaction(tax: f64, currency: Currency, date: Date) -> Transaction {
+        Transaction {
+            id: None,
+            transaction_type: TransactionType::Tax {
+                transaction_ref: None,
+            },
+            cash_flow: CashFlow::new(-tax, currency, date),
+            note: Some("German capital gains tax".to_string()),
+        }
+    }
+}
+
+#[async_trait]
+impl Strategy for GermanTaxStrategy {
+    fn next_day(&self, date: Date, calendar: &Calendar) -> Date {
+        self.inner.next_day(date, calendar)
+    }
+
+    async fn apply_rules(&self, position: &PortfolioPosition, date: Date) -> Vec<Transaction> {
+        let currency = position.cash.currency;
+        let (new_year, start) = {
+            let mut state = self.state.lock().unwrap();
+            let start = *state.start.get_or_insert(date);
+            let year = state
+                .account
+                .get_or_insert_with(|| TaxAccount::new(date.year()))
+                .year;
+            (year != date.year(), start)
+        };
+        let vorabpauschale = if new_year {
+            self.vorabpauschale(position, date, start).await
+        } else {
+            Vec::new()
+        };
+        let inner_transactions = self.inner.apply_rules(position, date).await;
+
+        let mut state = self.state.lock().unwrap();
+        let TaxState {
+            account,
+            vorabpauschale: taxed_vorabpauschale,
+            ..
+        } = &mut *state;
+        let account = account.get_or_insert_with(|| TaxAccount::new(date.year()));
+        let mut tax = 0.0;
+        if new_year {
+            account.start_year(date.year());
+            for (asset_id, amount) in vorabpauschale {
+                let exemption = self.params.info(asset_id).class.partial_exemption();
+                tax += account.book(false, amount * (1.0 - exemption), &self.params);
+                *taxed_vorabpauschale.entry(asset_id).or_default() += amount;
+            }
+        }
+
+        let mut transactions = Vec::new();
+        for (idx, transaction) in inner_transactions.iter().enumerate() {
+            match transaction.transaction_type {
+                TransactionType::Tax { .. } => continue,
+                TransactionType::Asset {
+                    asset_id,
+                    position: quantity,
+                } if quantity < 0.0 => {
+                    // Fees of the sale directly follow the asset transaction
+                    let fee: f64 = inner_transactions[idx + 1..]
+                        .iter()
+                        .take_while(|t| matches!(t.transaction_type, TransactionType::Fee { .. }))
+                        .map(|t| -t.cash_flow.amount.amount)
+                        .sum();
+                    let proceeds = transaction.cash_flow.amount.amount - fee;
+                    let mut gain = realized_gain(position, asset_id, -quantity, proceeds);
+                    let held = position
+                        .assets
+                        .get(&asset_id)
+                        .map(|p| p.position)
+                        .unwrap_or(0.0);
+                    if held > 0.0 {
+                        if let Some(taxed) = taxed_vorabpauschale.get_mut(&asset_id) {
+                            let share = *taxed * (-quantity / held).min(1.0);
+                            *taxed -= share;
+                            gain -= share;
+                        }
+                    }
+                    let class = self.params.info(asset_id).class;
+                    let taxable = gain * (1.0 - class.partial_exemption());
+                    tax += account.book(class == TaxClass::Stock, taxable, &self.params);
+                }
+                TransactionType::Dividend { asset_id } => {
+                    let exemption = self.params.info(asset_id).class.partial_exemption();
+                    let taxable = transaction.cash_flow.amount.amount * (1.0 - exemption);
+                    tax += account.book(false, taxable, &self.params);
+                }
+                // Interest paid is not deductible for private investors
+                TransactionType::Interest { .. } if transaction.cash_flow.amount.amount > 0.0 => {
+                    tax += account.book(false, transaction.cash_flow.amount.amount, &self.params);
+                }
+                _ => {}
+            }
+            transactions.push(transaction.clone());
+        }
+        if tax.abs() > 1e-9 {
+            transactions.push(Self::tax_transaction(tax, currency, date));
+        }
+        transactions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> GermanTaxParams {
+        GermanTaxParams {
+            tax_rate: 0.25,
+            allowance: 1000.0,
+            base_rates: BTreeMap::new(),
+            assets: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_allowance_and_refund() {
+        let params = params();
+        let mut account = TaxAccount::new(2023);
+        assert_eq!(account.book(false, 800.0, &params), 0.0);
+        assert_eq!(account.book(true, 1200.0, &params), 250.0);
+        // A later loss within the same year leads to a refund
+        assert_eq!(account.book(false, -400.0, &params), -100.0);
+    }
+
+    #[test]
+    fn test_stock_losses_are_carried_forward() {
+        let params = params();
+        let mut account = TaxAccount::new(2023);
+        // Stock losses can't be offset against other income
+        assert_eq!(account.book(true, -2000.0, &params), 0.0);
+        assert_eq!(account.book(false, 2000.0, &params), 250.0);
+        account.start_year(2024);
+        assert_eq!(account.carried_stock_loss, 2000.0);
+        assert_eq!(account.carried_general_loss, 0.0);
+        // The allowance of the new year is still available after offsetting the loss
+        assert_eq!(account.book(true, 3000.0, &params), 0.0);
+        assert_eq!(account.book(true, 1000.0, &params), 250.0);
+    }
+
+    #[test]
+    fn test_general_losses_offset_stock_gains() {
+        let params = params();
+        let mut account = TaxAccount::new(2023);
+        account.book(false, -1500.0, &params);
+        assert_eq!(account.book(true, 3500.0, &params), 250.0);
+        account.start_year(2024);
+        assert_eq!(account.carried_general_loss, 0.0);
+    }
+}
@@ -0,0 +1,365 @@
+//! Synthetic price paths for what-if simulations
+//!
+//! Besides historical quotes, strategies can be simulated on generated price paths to stress-test
+//! them against scenarios the historical record does not contain. A generated path is stored as
+//! the quotes of a virtual ticker, such that the `Market` prices it like any other asset.
+use std::f64::consts::PI;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use time::{Date, Duration, Weekday};
+
+use finql::datatypes::{
+    date_time_helper::date_to_offset_date_time, Asset, AssetHandler, Currency, DataError,
+    ObjectHandler, Quote, QuoteHandler, Stock, Ticker,
+};
+
+/// Name of the object the list of virtual tickers is stored as
+const SYNTHETIC_TICKERS_OBJECT: &str = "synthetic_tickers";
+
+#[derive(Error, Debug)]
+pub enum SyntheticError {
+    #[error("Database access failed")]
+    DataError(#[from] DataError),
+    #[error("Date calculation error")]
+    DateError(#[from] finql::datatypes::date_time_helper::DateTimeError),
+    #[error("Invalid price model: {0}")]
+    InvalidModel(String),
+    #[error("Invalid path segment '{0}', expected 'years:return in %'")]
+    InvalidSegment(String),
+}
+
+/// Part of a scripted path with constant growth rate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PathSegment {
+    /// Length of the segment in years
+    pub years: f64,
+    /// Total return over the whole segment, as a fraction
+    pub total_return: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PriceModel {
+    /// Geometric Brownian motion with annual drift and volatility
+    Gbm { drift: f64, volatility: f64 },
+    /// Merton's jump-diffusion: geometric Brownian motion plus jumps with normally
+    /// distributed log sizes, arriving on average `jump_intensity` times per year.
+    /// The drift is the expected return including the jumps.
+    JumpDiffusion {
+        drift: f64,
+        volatility: f64,
+        jump_intensity: f64,
+        jump_mean: f64,
+        jump_volatility: f64,
+    },
+    /// Deterministic path through the segments, flat after the last one
+    Scripted(Vec<PathSegment>),
+}
+
+impl PriceModel {
+    fn validate(&self) -> Result<(), SyntheticError> {
+        let invalid = |msg: &str| Err(SyntheticError::InvalidModel(msg.to_string()));
+        match self {
+            PriceModel::Gbm { volatility, .. } if *volatility < 0.0 => {
+                invalid("volatility must not be negative")
+            }
+            PriceModel::JumpDiffusion {
+                volatility,
+                jump_intensity,
+                jump_volatility,
+                ..
+            } if *volatility < 0.0 || *jump_intensity < 0.0 || *jump_volatility < 0.0 => {
+                invalid("volatilities and jump intensity must not be negative")
+            }
+            PriceModel::Scripted(segments) if segments.is_empty() => {
+                invalid("a scripted path requires at least one segment")
+            }
+            PriceModel::Scripted(segments)
+                if segments
+                    .iter()
+                    .any(|s| s.years <= 0.0 || s.total_return <= -1.0) =>
+            {
+                invalid("segments must have a positive length and a return above -100%")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Parse a scripted path given as comma separated list of 'years:return in %', e.g.
+/// "1:5,0.25:-40,2:60" for a year of slow growth, a crash of 40% within three months
+/// and a recovery within two years.
+pub fn parse_scripted_path(text: &str) -> Result<Vec<PathSegment>, SyntheticError> {
+    text.split(',')
+        .map(|item| {
+            let invalid = || SyntheticError::InvalidSegment(item.trim().to_string());
+            let (years, total_return) = item.split_once(':').ok_or_else(invalid)?;
+            Ok(PathSegment {
+                years: years.trim().parse().map_err(|_| invalid())?,
+                total_return: total_return
+                    .trim()
+                    .trim_end_matches('%')
+                    .parse::<f64>()
+                    .map_err(|_| invalid())?
+                    / 100.0,
+            })
+        })
+        .collect()
+}
+
+/// Definition of a synthetic price path
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntheticPath {
+    pub model: PriceModel,
+    pub start_price: f64,
+    pub start: Date,
+    pub end: Date,
+    /// Seed of the random number generator, the same seed reproduces the same path
+    pub seed: u64,
+}
+
+impl SyntheticPath {
+    /// Generate prices for all weekdays between start and end date
+    pub fn generate(&self) -> Result<Vec<(Date, f64)>, SyntheticError> {
+        self.model.validate()?;
+        if self.start_price <= 0.0 {
+            return Err(SyntheticError::InvalidModel(
+                "start price must be positive".to_string(),
+            ));
+        }
+        if self.end < self.start {
+            return Err(SyntheticError::InvalidModel(
+                "end date must not be before start date".to_string(),
+            ));
+        }
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut prices = vec![(self.start, self.start_price)];
+        let mut price = self.start_price;
+        let mut last_date = self.start;
+        let mut date = self.start;
+        while date < self.end {
+            date += Duration::days(1);
+            if matches!(date.weekday(), Weekday::Saturday | Weekday::Sunday) {
+                continue;
+            }
+            let dt = (date - last_date).whole_days() as f64 / 365.0;
+            price = match &self.model {
+                PriceModel::Gbm { drift, volatility } => {
+                    price * gbm_step(*drift, *volatility, dt, &mut rng)
+                }
+                PriceModel::JumpDiffusion {
+                    drift,
+                    volatility,
+                    jump_intensity,
+                    jump_mean,
+                    jump_volatility,
+                } => {
+                    // Compensate the drift for the expected relative jump size
+                    let expected_jump = (jump_mean + 0.5 * jump_volatility.powi(2)).exp() - 1.0;
+                    let diffusion_drift = drift - jump_intensity * expected_jump;
+                    let jumps = poisson(jump_intensity * dt, &mut rng) as f64;
+                    let log_jump = jumps * jump_mean
+                        + jump_volatility * jumps.sqrt() * standard_normal(&mut rng);
+                    price * gbm_step(diffusion_drift, *volatility, dt, &mut rng) * log_jump.exp()
+                }
+                PriceModel::Scripted(segments) => {
+                    let years = (date - self.start).whole_days() as f64 / 365.0;
+                    self.start_price * scripted_level(segments, years)
+                }
+            };
+            prices.push((date, price));
+            last_date = date;
+        }
+        Ok(prices)
+    }
+}
+
+fn gbm_step<R: Rng>(drift: f64, volatility: f64, dt: f64, rng: &mut R) -> f64 {
+    ((drift - 0.5 * volatility * volatility) * dt + volatility * dt.sqrt() * standard_normal(rng))
+        .exp()
+}
+
+/// Standard normal random number using the Box-Muller transform
+fn standard_normal<R: Rng>(rng: &mut R) -> f64 {
+    let u1 = 1.0 - rng.gen::<f64>();
+    let u2 = rng.gen::<f64>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+/// Poisson distributed random number, sufficient for small expected values
+fn poisson<R: Rng>(lambda: f64, rng: &mut R) -> u32 {
+    let limit = (-lambda).exp();
+    let mut count = 0;
+    let mut product = rng.gen::<f64>();
+    while product > limit {
+        count += 1;
+        product *= rng.gen::<f64>();
+    }
+    count
+}
+
+/// Price level relative to the start after the given number of years
+fn scripted_level(segments: &[PathSegment], years: f64) -> f64 {
+    let mut level = 1.0;
+    let mut elapsed = 0.0;
+    for segment in segments {
+        if years >= elapsed + segment.years {
+            level *= 1.0 + segment.total_return;
+            elapsed += segment.years;
+        } else {
+            let fraction = (years - elapsed) / segment.years;
+            return level * (1.0 + segment.total_return).powf(fraction);
+        }
+    }
+    level
+}
+
+/// Virtual ticker whose quotes have been generated from a price model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyntheticTicker {
+    pub ticker_id: i32,
+    pub asset_id: i32,
+    pub name: String,
+    pub model: PriceModel,
+    pub seed: u64,
+}
+
+/// Generate the synthetic path and store it as quotes of a new virtual asset and ticker.
+pub async fn register_synthetic_path<DB>(
+    db: &DB,
+    name: &str,
+    currency: Currency,
+    path: &SyntheticPath,
+) -> Result<SyntheticTicker, SyntheticError>
+where
+    DB: AssetHandler + QuoteHandler + ObjectHandler + Sync,
+{
+    let prices = path.generate()?;
+    let asset_id = db
+        .insert_asset(&Asset::Stock(Stock {
+            id: None,
+            name: name.to_string(),
+            wkn: None,
+            isin: None,
+            note: Some("synthetic price path".to_string()),
+        }))
+        .await?;
+    let ticker_id = db
+        .insert_ticker(&Ticker {
+            id: None,
+            name: name.to_string(),
+            asset: asset_id,
+            currency,
+            source: "manual".to_string(),
+            priority: 10,
+            factor: 1.0,
+            cal: None,
+            tz: None,
+        })
+        .await?;
+    for (date, price) in prices {
+        db.insert_quote(&Quote {
+            id: None,
+            ticker: ticker_id,
+            price,
+            time: date_to_offset_date_time(&date, 18, None)?,
+            volume: None,
+        })
+        .await?;
+    }
+
+    let ticker = SyntheticTicker {
+        ticker_id,
+        asset_id,
+        name: name.to_string(),
+        model: path.model.clone(),
+        seed: path.seed,
+    };
+    let mut tickers = get_synthetic_tickers(db).await;
+    tickers.push(ticker.clone());
+    db.store_object(SYNTHETIC_TICKERS_OBJECT, &tickers).await?;
+    Ok(ticker)
+}
+
+/// List of all virtual tickers with synthetic price paths
+pub async fn get_synthetic_tickers<DB: ObjectHandler + Sync>(db: &DB) -> Vec<SyntheticTicker> {
+    db.get_object(SYNTHETIC_TICKERS_OBJECT)
+        .await
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    fn path(model: PriceModel) -> SyntheticPath {
+        SyntheticPath {
+            model,
+            start_price: 100.0,
+            start: date!(2020 - 01 - 01),
+            end: date!(2024 - 12 - 31),
+            seed: 42,
+        }
+    }
+
+    #[test]
+    fn test_scripted_path() {
+        let segments = parse_scripted_path("1:10, 0.4:-40%,2:50").unwrap();
+        assert_eq!(segments.len(), 3);
+        assert!((segments[1].total_return + 0.4).abs() < 1e-12);
+        assert!(parse_scripted_path("1;10").is_err());
+
+        let prices = path(PriceModel::Scripted(segments)).generate().unwrap();
+        let price_at = |d: Date| prices.iter().find(|(date, _)| *date == d).unwrap().1;
+        assert_eq!(price_at(date!(2020 - 01 - 01)), 100.0);
+        assert!((price_at(date!(2020 - 12 - 31)) - 110.0).abs() < 1e-9);
+        assert!((price_at(date!(2021 - 05 - 26)) - 66.0).abs() < 1e-9);
+        // flat after the last segment
+        assert!((prices.last().unwrap().1 - 99.0).abs() < 1e-9);
+        assert!(prices
+            .iter()
+            .all(|(d, _)| !matches!(d.weekday(), Weekday::Saturday | Weekday::Sunday)));
+    }
+
+    #[test]
+    fn test_random_paths() {
+        // without volatility, the path grows with the drift
+        let prices = path(PriceModel::Gbm {
+            drift: 0.05,
+            volatility: 0.0,
+        })
+        .generate()
+        .unwrap();
+        let (end, price) = *prices.last().unwrap();
+        let years = (end - date!(2020 - 01 - 01)).whole_days() as f64 / 365.0;
+        assert!((price - 100.0 * (0.05 * years).exp()).abs() < 1e-9);
+
+        let jump_diffusion = path(PriceModel::JumpDiffusion {
+            drift: 0.05,
+            volatility: 0.2,
+            jump_intensity: 1.0,
+            jump_mean: -0.1,
+            jump_volatility: 0.05,
+        });
+        let prices = jump_diffusion.generate().unwrap();
+        assert_eq!(prices, jump_diffusion.generate().unwrap());
+        assert!(prices.iter().all(|(_, p)| *p > 0.0));
+        let other_seed = SyntheticPath {
+            seed: 7,
+            ..jump_diffusion
+        };
+        assert_ne!(prices, other_seed.generate().unwrap());
+
+        assert!(path(PriceModel::Gbm {
+            drift: 0.05,
+            volatility: -0.1
+        })
+        .generate()
+        .is_err());
+        assert!(path(PriceModel::Scripted(Vec::new())).generate().is_err());
+    }
+}
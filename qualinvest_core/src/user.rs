@@ -25,12 +25,9 @@ pub struct User {
 
 #[async_trait]
 pub trait UserHandler: AccountHandler {
-    /// Clean database by dropping all tables related to user management and run init_users
+    /// Remove all users, their account rights and settings, the tables are kept
     async fn clean_users(&self) -> Result<(), DataError>;
 
-    /// Set up new tables for user management
-    async fn init_users(&self) -> Result<(), DataError>;
-
     /// Insert new account info in database, if it not yet exist
     async fn insert_user(&self, user: &User, password: &str) -> Result<i32, DataError>;
 